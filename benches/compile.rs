@@ -0,0 +1,58 @@
+// how long compiling a big program takes, and the most memory it used. the program is 100k
+// generated lines, compiled to c like `haneul main.han` does, without running the c compiler
+//
+//   cargo bench --bench compile
+
+use std::{fmt::Write, fs, time::Instant};
+
+use haneul::Options;
+
+const LINES: usize = 100_000;
+const RUNS: usize = 7;
+
+fn main() {
+    let source = program();
+    let mut times = vec![];
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        haneul::compile(&source, &Options::default()).unwrap();
+        times.push(start.elapsed());
+    }
+    times.sort();
+
+    println!(
+        "{} lines, median of {} runs: {:?}",
+        LINES,
+        RUNS,
+        times[RUNS / 2]
+    );
+    // linux only, the peak of the whole process, the generated source is part of it
+    if let Some(peak) = fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| {
+            status
+                .lines()
+                .find(|line| line.starts_with("VmHWM:"))
+                .map(str::to_owned)
+        })
+    {
+        println!("peak memory: {}", peak["VmHWM:".len()..].trim());
+    }
+}
+
+// blocks of 8 lines with a calculation, an IF and a WHILE, on the 26 variables a to z
+fn program() -> String {
+    let mut source = String::new();
+    for i in 0..LINES / 8 {
+        let v = (b'a' + (i % 26) as u8) as char;
+        writeln!(source, "LET {} = {} * 2 + 3 - {} / 7", v, i, i).unwrap();
+        writeln!(source, "IF {} > 5 THEN", v).unwrap();
+        writeln!(source, "PRINT {} + 1", v).unwrap();
+        writeln!(source, "ENDIF").unwrap();
+        writeln!(source, "WHILE {} < 10 REPEAT", v).unwrap();
+        writeln!(source, "LET {v} = {v} + 1", v = v).unwrap();
+        writeln!(source, "ENDWHILE").unwrap();
+        writeln!(source, "PRINT \"block {}\"", i).unwrap();
+    }
+    source
+}
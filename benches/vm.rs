@@ -0,0 +1,133 @@
+// how fast the vm runs loops of calculations, like `haneul run --no-cache` does after compiling
+//
+//   cargo bench --bench vm
+
+use std::time::{Duration, Instant};
+
+use haneul::{
+    analyze,
+    builtins::Registry,
+    bytecode::{self, Chunk},
+    diagnostics::Lints,
+    lexer::Lexer,
+    parse, parser,
+    passes::{self, OptLevel},
+    source::SourceMap,
+    vm::Vm,
+};
+
+const RUNS: usize = 3;
+
+// 3 million times float arithmetic, a comparison and an IF
+const FLOATS: &str = "
+LET i = 0
+LET s = 0
+WHILE i < 3000000 REPEAT
+    LET s = s + i * 2 - i / 3
+    IF s > 100000 THEN
+        LET s = s - 100000
+    ENDIF
+    LET i = i + 1
+ENDWHILE
+PRINT s
+";
+
+// 2 million times int arithmetic in nested loops, the inner one a GOTO loop
+const INTS: &str = "
+LET n = 0
+LET t = 0
+WHILE n < 2000 REPEAT
+    LET j = 0
+    LABEL inner
+    LET t = t + j % 7 * 3
+    LET j = j + 1
+    IF j < 1000 THEN
+        GOTO inner
+    ENDIF
+    LET n = n + 1
+ENDWHILE
+PRINT t
+";
+
+// 2 million times the same product three times in a block, which -O2 works out once (see
+// cse.rs)
+const SHARED: &str = "
+LET i = 0
+LET s = 1
+LET t = 2
+WHILE i < 2000000 REPEAT
+    LET a = s * t + i
+    LET b = s * t - i
+    LET c = s * t % 7
+    LET s = a + b - c + 1
+    LET t = t % 5 + 1
+    LET i = i + 1
+ENDWHILE
+PRINT s
+";
+
+// 3 million times a calculation that's the same in every round and a multiplication of the
+// counter, which -O2 moves before the loop and makes into an addition (see loops.rs)
+const INVARIANT: &str = "
+LET n = 7
+LET i = 0
+LET s = 0
+WHILE i < 3000000 REPEAT
+    LET s = s + i * 12 + n * n * 3
+    LET i = i + 1
+ENDWHILE
+PRINT s
+";
+
+fn main() {
+    bench("floats", FLOATS, OptLevel::default());
+    bench("ints", INTS, OptLevel::default());
+    // what sharing calculations and the loop optimizations save, -O1 doesn't do them
+    for level in [OptLevel::O1, OptLevel::O2] {
+        bench(&format!["shared at {:?}", level], SHARED, level);
+        bench(&format!["invariant at {:?}", level], INVARIANT, level);
+    }
+    // the only strings a program makes are what it prints, 10 and 20 MB of it should take
+    // twice as long, not four times
+    for lines in [100_000, 200_000] {
+        let source = format![
+            "LET i = 0\nWHILE i < {} REPEAT\nPRINT \"{}\"\nLET i = i + 1\nENDWHILE\n",
+            lines,
+            "x".repeat(99)
+        ];
+        bench(
+            &format!["{} MB printed", lines / 10_000],
+            &source,
+            OptLevel::default(),
+        );
+    }
+}
+
+fn bench(name: &str, source: &str, level: OptLevel) {
+    let chunk = compile(source, level);
+    let mut best = Duration::MAX;
+    for _ in 0..RUNS {
+        let mut output = vec![];
+        let mut vm = Vm::new(&chunk);
+        vm.set_output(&mut output);
+        let start = Instant::now();
+        vm.run().unwrap();
+        best = best.min(start.elapsed());
+    }
+    println!("{}: best of {} runs: {:?}", name, RUNS, best);
+}
+
+fn compile(source: &str, level: OptLevel) -> Chunk {
+    let builtins = Registry::new();
+    let mut sources = SourceMap::new();
+    let file = sources.add(String::from("main.han"), source.to_owned());
+    let mut lexer = Lexer::new(file, sources.get(file).text());
+    let (result, warnings) = parse(&mut lexer, &builtins, parser::DEFAULT_MAX_DEPTH);
+    let (_, errors) = analyze(&result, warnings, &builtins, &Lints::default());
+    assert!(errors.is_empty(), "the program has errors: {:?}", errors);
+    let mut program = result.unwrap();
+    passes::optimize(&mut program, level);
+    let mut chunk = bytecode::compile(&program, &builtins, Default::default()).unwrap();
+    passes::optimize_chunk(&mut chunk, level);
+    chunk
+}
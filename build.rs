@@ -0,0 +1,29 @@
+// with the ffi feature, writes haneul.h with the functions of src/ffi.rs for the c programs
+// that use the cdylib. it goes in the OUT_DIR cargo gives build scripts, not in the source tree,
+// `cargo build --features ffi -vv` shows where that is
+
+fn main() {
+    #[cfg(feature = "ffi")]
+    header();
+}
+
+#[cfg(feature = "ffi")]
+fn header() {
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    let out_dir = std::env::var("OUT_DIR").expect("cargo sets OUT_DIR for build scripts");
+    cbindgen::Builder::new()
+        .with_src("src/ffi.rs")
+        .with_language(cbindgen::Language::C)
+        .with_include_guard("HANEUL_H")
+        .with_header(HEADER)
+        .with_no_includes()
+        .with_sys_include("stdint.h")
+        .generate()
+        .expect("src/ffi.rs is rust cbindgen understands")
+        .write_to_file(std::path::Path::new(&out_dir).join("haneul.h"));
+}
+
+#[cfg(feature = "ffi")]
+const HEADER: &str = "\
+/* made by build.rs from src/ffi.rs, the comments there say what the functions do.
+ * the strings are utf-8 and end with a 0. what haneul gives back is freed with haneul_free */";
@@ -0,0 +1,231 @@
+// checks on the whole tree after parsing. the parser only sees the program from top to
+// bottom, these follow the IFs, loops and GOTOs the way the program runs
+
+use crate::{
+    ast::{ExprId, ExprKind, Exprs, Program, Statement, StatementKind},
+    builtins::Registry,
+    codes,
+    diagnostics::Diagnostic,
+    intern::Symbol,
+    prelude::*,
+    suggest,
+    symbols::{SymbolTable, Variable},
+    token::Span,
+    types,
+    visit::{self, Visitor},
+};
+
+// all the errors, in the order they are in the program
+pub fn check(program: &Program, builtins: &Registry) -> Vec<Diagnostic> {
+    let symbols = SymbolTable::new(program, builtins);
+    let mut errors = labels(&symbols);
+    errors.extend(unassigned_reads(program, &symbols));
+    errors.extend(types::check(program, builtins));
+    errors.sort_by_key(|error| (error.span.line, error.span.column));
+
+    errors
+}
+
+// every GOTO has to go to a label that is declared exactly once
+fn labels(symbols: &SymbolTable) -> Vec<Diagnostic> {
+    let mut errors = vec![];
+    for label in symbols.labels() {
+        for span in label.declarations.iter().skip(1) {
+            errors.push(Diagnostic::error(
+                codes::DUPLICATE_LABEL,
+                vec![label.name.clone()],
+                *span,
+            ));
+        }
+    }
+
+    // the suggestions are in the order the labels are declared, so they're the same every time
+    let mut declared: Vec<_> = symbols
+        .labels()
+        .iter()
+        .filter_map(|label| Some((label.declarations.first()?.offset, label.name.as_str())))
+        .collect();
+    declared.sort();
+    for label in symbols.labels() {
+        if !label.declarations.is_empty() {
+            continue;
+        }
+        let names = declared.iter().map(|(_, name)| *name);
+        let suggestion = suggest::closest(&label.name, names).map(String::from);
+        for span in &label.gotos {
+            errors.push(Diagnostic {
+                suggestion: suggestion.clone(),
+                ..Diagnostic::error(codes::UNDECLARED_LABEL, vec![label.name.clone()], *span)
+            });
+        }
+    }
+
+    errors
+}
+
+// the variables that have a value on every way to get to a point of the program.
+// None when there is no way to get there at all, like right after a GOTO
+type Assigned = Option<HashSet<Symbol>>;
+
+// what is known where two ways come together, like after an IF or at a LABEL
+fn meet(a: &Assigned, b: &Assigned) -> Assigned {
+    match (a, b) {
+        (None, b) => b.clone(),
+        (a, None) => a.clone(),
+        (Some(a), Some(b)) => Some(a.intersection(b).copied().collect()),
+    }
+}
+
+// every read of a variable that doesn't have a value there on every way to get to it.
+// what a GOTO or the end of a loop body brings to a LABEL or the start of the loop is only
+// known after the rest of the program, so the program is gone over until that stops changing
+fn unassigned_reads(program: &Program, symbols: &SymbolTable) -> Vec<Diagnostic> {
+    let mut flow = Flow {
+        exprs: &program.exprs,
+        labels: HashMap::new(),
+        loops: HashMap::new(),
+        gotos: HashMap::new(),
+        loop_ends: HashMap::new(),
+        reads: vec![],
+    };
+    loop {
+        flow.statements(&program.statements, Some(HashSet::new()));
+        let gotos = core::mem::take(&mut flow.gotos);
+        let loop_ends = core::mem::take(&mut flow.loop_ends);
+        if gotos == flow.labels && loop_ends == flow.loops {
+            break;
+        }
+        flow.labels = gotos;
+        flow.loops = loop_ends;
+        flow.reads.clear();
+    }
+
+    // in the order they're first used, so the suggestion is the same every time
+    let assigned: Vec<&Variable> = symbols
+        .variables()
+        .iter()
+        .filter(|variable| !variable.assignments.is_empty())
+        .collect();
+
+    flow.reads
+        .iter()
+        .map(|(variable, span)| {
+            let name = program.names.resolve(*variable);
+            if assigned.iter().any(|assigned| assigned.symbol == *variable) {
+                return Diagnostic::error(
+                    codes::MAYBE_UNASSIGNED_VARIABLE,
+                    vec![name.to_owned()],
+                    *span,
+                );
+            }
+            let names = assigned.iter().map(|variable| variable.name.as_str());
+            Diagnostic {
+                suggestion: suggest::closest(name, names).map(String::from),
+                ..Diagnostic::error(codes::UNASSIGNED_VARIABLE, vec![name.to_owned()], *span)
+            }
+        })
+        .collect()
+}
+
+struct Flow<'a> {
+    exprs: &'a Exprs,
+    // from the previous time over the program: what the GOTOs bring to every label, and
+    // the end of every loop body to its start. a loop is known by its condition
+    labels: HashMap<Symbol, Assigned>,
+    loops: HashMap<ExprId, Assigned>,
+    // the same, filled in this time over the program
+    gotos: HashMap<Symbol, Assigned>,
+    loop_ends: HashMap<ExprId, Assigned>,
+    reads: Vec<(Symbol, Span)>,
+}
+
+impl Flow<'_> {
+    fn statements(&mut self, statements: &[Statement], mut assigned: Assigned) -> Assigned {
+        for statement in statements {
+            assigned = self.statement(statement, assigned);
+        }
+
+        assigned
+    }
+
+    // what has a value after the statement, given what has one before it
+    fn statement(&mut self, statement: &Statement, assigned: Assigned) -> Assigned {
+        match &statement.kind {
+            StatementKind::Print(value) => {
+                self.read(*value, &assigned);
+                assigned
+            }
+            StatementKind::Call { arguments, .. } => {
+                for argument in arguments {
+                    self.read(*argument, &assigned);
+                }
+                assigned
+            }
+            StatementKind::PrintString(_) => assigned,
+            StatementKind::Let { variable, value } => {
+                self.read(*value, &assigned);
+                assign(assigned, *variable)
+            }
+            StatementKind::Input(variable) => assign(assigned, *variable),
+            // the body might not run
+            StatementKind::If { condition, body } => {
+                self.read(*condition, &assigned);
+                let end = self.statements(body, assigned.clone());
+                meet(&assigned, &end)
+            }
+            // the condition is checked before every time through the body, and once more
+            // when the loop stops
+            StatementKind::While { condition, body } => {
+                let back = self.loops.get(condition).cloned().flatten();
+                let start = meet(&assigned, &back);
+                self.read(*condition, &start);
+
+                let end = self.statements(body, start.clone());
+                let loop_end = self.loop_ends.entry(*condition).or_insert(None);
+                *loop_end = meet(loop_end, &end);
+                start
+            }
+            StatementKind::Label(label) => {
+                let gotos = self.labels.get(label).cloned().flatten();
+                meet(&assigned, &gotos)
+            }
+            StatementKind::Goto(label) => {
+                let gotos = self.gotos.entry(*label).or_insert(None);
+                *gotos = meet(gotos, &assigned);
+                None
+            }
+        }
+    }
+
+    fn read(&mut self, expression: ExprId, assigned: &Assigned) {
+        let Some(assigned) = assigned else {
+            return;
+        };
+        let mut variables = Variables(vec![]);
+        variables.visit_expr(self.exprs, expression);
+        for (variable, span) in variables.0 {
+            if !assigned.contains(&variable) {
+                self.reads.push((variable, span));
+            }
+        }
+    }
+}
+
+fn assign(assigned: Assigned, variable: Symbol) -> Assigned {
+    assigned.map(|mut assigned| {
+        assigned.insert(variable);
+        assigned
+    })
+}
+
+// the variables an expression reads, with where they are
+struct Variables(Vec<(Symbol, Span)>);
+
+impl Visitor for Variables {
+    fn visit_expr(&mut self, exprs: &Exprs, expression: ExprId) {
+        if let ExprKind::Variable(variable) = exprs[expression].kind {
+            self.0.push((variable, exprs[expression].span));
+        }
+        visit::walk_expr(self, exprs, expression);
+    }
+}
@@ -0,0 +1,320 @@
+// the tree the parser makes of a program. everything after parsing (the checks, the c code)
+// works on this instead of on the tokens, so none of it has to know about the grammar
+//
+// with the serde feature the tree is serialized like it's kept: the expressions are a list and
+// point at each other by their index in it, a symbol is the index of its name in `names`
+
+#[cfg(feature = "serde")]
+use alloc::collections::BTreeMap;
+use core::ops::{Index, IndexMut};
+
+#[cfg(feature = "serde")]
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    builtins::Capability,
+    ctype::CType,
+    intern::{Interner, Symbol},
+    prelude::*,
+    token::Span,
+};
+#[cfg(feature = "serde")]
+use crate::{ctype, lexer::Lexer, source::FileId, token::TokenType};
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Program {
+    pub statements: Vec<Statement>,
+    pub exprs: Exprs,
+    // the names of the variables and labels the symbols in the tree stand for
+    pub names: Interner,
+    // the type of every variable, from ctype::infer before the passes run. they don't change
+    // it, a temporary they make gets the type of what it holds. it isn't serialized, infer
+    // works it out again when the program is deserialized
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub types: HashMap<Symbol, CType>,
+    // what the builtins the program calls need outside of it, from the Registry it was parsed
+    // with. a vm or an interpreter checks them against its ExecutionPolicy before a call
+    #[cfg_attr(feature = "serde", serde(serialize_with = "sorted"))]
+    pub needs: HashMap<String, Vec<Capability>>,
+}
+
+// a statement's span goes from its first token to its last, for an IF or a WHILE that
+// includes the body and the ENDIF or ENDWHILE
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum StatementKind {
+    Print(ExprId),
+    PrintString(String),
+    If {
+        condition: ExprId,
+        body: Vec<Statement>,
+    },
+    While {
+        condition: ExprId,
+        body: Vec<Statement>,
+    },
+    Label(Symbol),
+    Goto(Symbol),
+    Let {
+        variable: Symbol,
+        value: ExprId,
+    },
+    Input(Symbol),
+    // a builtin that doesn't return anything, like BEEP 440, 100
+    Call {
+        name: String,
+        arguments: Vec<ExprId>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ExprKind {
+    // the number as it was written, so it ends up in the output the same way
+    Number(String),
+    Variable(Symbol),
+    // a builtin that returns something, like RND(6)
+    Call {
+        name: String,
+        arguments: Vec<ExprId>,
+    },
+    Unary {
+        op: UnaryOp,
+        operand: ExprId,
+    },
+    Binary {
+        op: BinaryOp,
+        left: ExprId,
+        right: ExprId,
+    },
+}
+
+// all the expressions of a program are kept in one list and point at each other by their
+// index in it, instead of every node being its own Box. that's a few big allocations for the
+// whole tree, and dropping it doesn't have to walk it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ExprId(u32);
+
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Exprs {
+    exprs: Vec<Expr>,
+}
+
+impl Exprs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, expression: Expr) -> ExprId {
+        let id = ExprId(self.exprs.len() as u32);
+        self.exprs.push(expression);
+        id
+    }
+}
+
+impl Index<ExprId> for Exprs {
+    type Output = Expr;
+
+    fn index(&self, id: ExprId) -> &Expr {
+        &self.exprs[id.0 as usize]
+    }
+}
+
+impl IndexMut<ExprId> for Exprs {
+    fn index_mut(&mut self, id: ExprId) -> &mut Expr {
+        &mut self.exprs[id.0 as usize]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum UnaryOp {
+    Plus,
+    Minus,
+}
+
+// the comparisons are only allowed in the condition of an IF or a WHILE
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    // what's left of the division, with the sign of the left side like in c
+    Remainder,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl UnaryOp {
+    pub fn text(self) -> &'static str {
+        match self {
+            UnaryOp::Plus => "+",
+            UnaryOp::Minus => "-",
+        }
+    }
+}
+
+impl BinaryOp {
+    pub fn is_comparison(self) -> bool {
+        matches!(
+            self,
+            BinaryOp::Equal
+                | BinaryOp::NotEqual
+                | BinaryOp::Less
+                | BinaryOp::LessEqual
+                | BinaryOp::Greater
+                | BinaryOp::GreaterEqual
+        )
+    }
+
+    pub fn text(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Subtract => "-",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Remainder => "%",
+            BinaryOp::Equal => "==",
+            BinaryOp::NotEqual => "!=",
+            BinaryOp::Less => "<",
+            BinaryOp::LessEqual => "<=",
+            BinaryOp::Greater => ">",
+            BinaryOp::GreaterEqual => ">=",
+        }
+    }
+}
+
+// in the order of their names, so the same program always gives the same json
+#[cfg(feature = "serde")]
+fn sorted<S: Serializer>(
+    needs: &HashMap<String, Vec<Capability>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(needs.iter().collect::<BTreeMap<_, _>>())
+}
+
+// a deserialized tree is checked before anything uses it, the rest of the compiler counts on
+// every tree being one the parser could have made
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Program {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Serialized {
+            statements: Vec<Statement>,
+            exprs: Exprs,
+            names: Interner,
+            needs: BTreeMap<String, Vec<Capability>>,
+        }
+
+        let serialized = Serialized::deserialize(deserializer)?;
+        let mut program = Program {
+            statements: serialized.statements,
+            exprs: serialized.exprs,
+            names: serialized.names,
+            types: HashMap::new(),
+            needs: serialized.needs.into_iter().collect(),
+        };
+        program.check().map_err(D::Error::custom)?;
+        ctype::infer(&mut program);
+
+        Ok(program)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Program {
+    // every index points at something that's there. an expression only points at the ones
+    // before it, it's added after them, so there's no loop in the tree
+    fn check(&self) -> Result<(), String> {
+        let name = |symbol: Symbol| match self.names.contains(symbol) {
+            true => Ok(()),
+            false => Err(format!["there's no name {:?}", symbol]),
+        };
+        for (index, expression) in self.exprs.exprs.iter().enumerate() {
+            let before = |id: &ExprId| match (id.0 as usize) < index {
+                true => Ok(()),
+                false => Err(format![
+                    "expression {} points at expression {}",
+                    index, id.0
+                ]),
+            };
+            match &expression.kind {
+                ExprKind::Number(text) => number(text)?,
+                ExprKind::Variable(variable) => name(*variable)?,
+                ExprKind::Call { arguments, .. } => arguments.iter().try_for_each(before)?,
+                ExprKind::Unary { operand, .. } => before(operand)?,
+                ExprKind::Binary { left, right, .. } => {
+                    before(left)?;
+                    before(right)?;
+                }
+            }
+        }
+        self.check_statements(&self.statements, &name)
+    }
+
+    fn check_statements(
+        &self,
+        statements: &[Statement],
+        name: &impl Fn(Symbol) -> Result<(), String>,
+    ) -> Result<(), String> {
+        let expression = |id: &ExprId| match (id.0 as usize) < self.exprs.exprs.len() {
+            true => Ok(()),
+            false => Err(format!["there's no expression {}", id.0]),
+        };
+        for statement in statements {
+            match &statement.kind {
+                StatementKind::Print(value) => expression(value)?,
+                StatementKind::PrintString(_) => {}
+                StatementKind::If { condition, body }
+                | StatementKind::While { condition, body } => {
+                    expression(condition)?;
+                    self.check_statements(body, name)?;
+                }
+                StatementKind::Label(label) | StatementKind::Goto(label) => name(*label)?,
+                StatementKind::Let { variable, value } => {
+                    name(*variable)?;
+                    expression(value)?;
+                }
+                StatementKind::Input(variable) => name(*variable)?,
+                StatementKind::Call { arguments, .. } => {
+                    arguments.iter().try_for_each(expression)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// what the lexer reads as one number. a pass can make a negative one, like fold does of -(2)
+#[cfg(feature = "serde")]
+fn number(text: &str) -> Result<(), String> {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    match Lexer::new(FileId::default(), digits).next() {
+        Some(Ok(token)) if token.kind() == TokenType::Number && token.text() == digits => Ok(()),
+        _ => Err(format!["not a number: {}", text]),
+    }
+}
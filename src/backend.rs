@@ -0,0 +1,442 @@
+// what a program is compiled to, picked with --target. main checks and optimizes the program,
+// then hands it to a backend:
+//
+//   prepare, with the program before anything is emitted
+//   emit_program, which gives the name of a builtin it can't compile
+//   files, what it made, which haneul::compile gives without writing them
+//   finish, which writes the files and runs the tools that build them, like gcc
+//
+// the same program always gives the same bytes, nothing is emitted in the order of a hash map
+// and there are no timestamps or paths but the program's name in the output.
+// --verify-deterministic compiles it twice and compares their `files`
+//
+// a new target is a Backend and a name in the --target option, the parser and the analysis
+// don't know about them
+
+use std::fs;
+
+use crate::{
+    ast::Program,
+    builtins::Registry,
+    bytecode::{self, Chunk},
+    cc, codegen,
+    ctype::FloatDivision,
+    emitter::Emitter,
+    hbc, js, minify,
+    passes::{self, OptLevel},
+    python, rust, sourcemap, wat,
+};
+
+pub trait Backend {
+    // in the errors, like "javascript"
+    fn language(&self) -> &'static str;
+
+    fn prepare(&mut self, _program: &Program) {}
+
+    fn emit_program(&mut self, program: &Program) -> Result<(), String>;
+
+    // the names of the files finish writes and what's in them, after emit_program
+    fn files(&self) -> Vec<(String, Vec<u8>)>;
+
+    fn finish(self: Box<Self>) -> Result<(), String>;
+}
+
+fn write(files: Vec<(String, Vec<u8>)>) -> Result<(), String> {
+    for (file, contents) in files {
+        fs::write(&file, contents).map_err(|e| format!["couldn't write {}: {}", file, e])?;
+    }
+    Ok(())
+}
+
+// out.c, which the c compiler makes into the executable
+pub struct C<'a> {
+    builtins: &'a Registry,
+    // the name of the program, for the #line directives
+    source: String,
+    executable: String,
+    minify: bool,
+    float_division: FloatDivision,
+    emitter: Emitter,
+}
+
+impl<'a> C<'a> {
+    pub fn new(
+        builtins: &'a Registry,
+        source: String,
+        executable: String,
+        minify: bool,
+        float_division: FloatDivision,
+    ) -> Self {
+        Self {
+            builtins,
+            source,
+            executable,
+            minify,
+            float_division,
+            emitter: Emitter::new(),
+        }
+    }
+}
+
+impl Backend for C<'_> {
+    fn language(&self) -> &'static str {
+        "c"
+    }
+
+    fn emit_program(&mut self, program: &Program) -> Result<(), String> {
+        let source = (!self.minify).then_some(self.source.as_str());
+        codegen::generate(
+            program,
+            self.builtins,
+            source,
+            self.float_division,
+            &mut self.emitter,
+        )
+    }
+
+    fn files(&self) -> Vec<(String, Vec<u8>)> {
+        let code = self.emitter.contents();
+        let code = match self.minify {
+            true => minify::c(&code),
+            false => code,
+        };
+        vec![(String::from("out.c"), code.into_bytes())]
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), String> {
+        // an int that overflows wraps around like in the other backends, instead of being
+        // undefined in c
+        let mut args: Vec<String> = vec![
+            "out.c".into(),
+            "-fwrapv".into(),
+            "-o".into(),
+            self.executable.clone(),
+        ];
+        for library in self.emitter.libraries() {
+            args.push(format!["-l{}", library]);
+        }
+        write(self.files())?;
+        cc::compile(&args)
+    }
+}
+
+// the backends that make the source code of another language in one go
+pub struct Source<'a> {
+    file: &'static str,
+    language: &'static str,
+    generate: Generate<'a>,
+    float_division: FloatDivision,
+    minify: Option<fn(&str) -> String>,
+    code: String,
+    // the files that come with the code, like the Cargo.toml of main.rs
+    extra: Vec<(String, Vec<u8>)>,
+}
+
+// the generator of a language, with the builtins when it has any
+type Generate<'a> = Box<dyn Fn(&Program, FloatDivision) -> Result<String, String> + 'a>;
+
+impl<'a> Source<'a> {
+    pub fn rust(builtins: &'a Registry, source: &str, float_division: FloatDivision) -> Self {
+        let generate = move |program: &Program, float_division| {
+            rust::generate(program, builtins, float_division)
+        };
+        Self {
+            extra: vec![(
+                String::from("Cargo.toml"),
+                rust::manifest(source).into_bytes(),
+            )],
+            ..Self::new("main.rs", "rust", Box::new(generate), float_division)
+        }
+    }
+
+    pub fn python(builtins: &'a Registry, minify: bool, float_division: FloatDivision) -> Self {
+        let generate = move |program: &Program, float_division| {
+            python::generate(program, builtins, float_division)
+        };
+        Self {
+            minify: minify.then_some(minify::python),
+            ..Self::new("out.py", "python", Box::new(generate), float_division)
+        }
+    }
+
+    fn new(
+        file: &'static str,
+        language: &'static str,
+        generate: Generate<'a>,
+        float_division: FloatDivision,
+    ) -> Self {
+        Self {
+            file,
+            language,
+            generate,
+            float_division,
+            minify: None,
+            code: String::new(),
+            extra: vec![],
+        }
+    }
+}
+
+impl Backend for Source<'_> {
+    fn language(&self) -> &'static str {
+        self.language
+    }
+
+    fn emit_program(&mut self, program: &Program) -> Result<(), String> {
+        self.code = (self.generate)(program, self.float_division)?;
+        if let Some(minify) = self.minify {
+            self.code = minify(&self.code);
+        }
+        Ok(())
+    }
+
+    fn files(&self) -> Vec<(String, Vec<u8>)> {
+        let mut files = vec![(String::from(self.file), self.code.clone().into_bytes())];
+        files.extend(self.extra.iter().cloned());
+        files
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), String> {
+        write(self.files())
+    }
+}
+
+// out.js, and out.js.map that points its lines back at the program
+pub struct Js<'a> {
+    builtins: &'a Registry,
+    source: String,
+    minify: bool,
+    float_division: FloatDivision,
+    code: String,
+    positions: Vec<(usize, usize)>,
+}
+
+impl<'a> Js<'a> {
+    pub fn new(
+        builtins: &'a Registry,
+        source: String,
+        minify: bool,
+        float_division: FloatDivision,
+    ) -> Self {
+        Self {
+            builtins,
+            source,
+            minify,
+            float_division,
+            code: String::new(),
+            positions: vec![],
+        }
+    }
+}
+
+impl Backend for Js<'_> {
+    fn language(&self) -> &'static str {
+        "javascript"
+    }
+
+    fn emit_program(&mut self, program: &Program) -> Result<(), String> {
+        (self.code, self.positions) = js::generate(program, self.builtins, self.float_division)?;
+        Ok(())
+    }
+
+    // a minified out.js has no out.js.map
+    fn files(&self) -> Vec<(String, Vec<u8>)> {
+        if self.minify {
+            return vec![(String::from("out.js"), minify::c(&self.code).into_bytes())];
+        }
+        let map = sourcemap::encode(&self.code, &self.positions, "out.js", &self.source);
+        let code = format!["{}//# sourceMappingURL=out.js.map\n", self.code];
+        vec![
+            (String::from("out.js"), code.into_bytes()),
+            (String::from("out.js.map"), map.into_bytes()),
+        ]
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), String> {
+        write(self.files())
+    }
+}
+
+// out.wat, and out.glue.js that runs it once it is made into out.wasm
+pub struct Wat(Source<'static>);
+
+impl Wat {
+    pub fn new(float_division: FloatDivision) -> Self {
+        Self(Source::new(
+            "out.wat",
+            "webassembly",
+            Box::new(wat::generate),
+            float_division,
+        ))
+    }
+}
+
+impl Backend for Wat {
+    fn language(&self) -> &'static str {
+        self.0.language()
+    }
+
+    fn emit_program(&mut self, program: &Program) -> Result<(), String> {
+        self.0.emit_program(program)
+    }
+
+    fn files(&self) -> Vec<(String, Vec<u8>)> {
+        let mut files = self.0.files();
+        files.push((String::from("out.glue.js"), wat::glue().into_bytes()));
+        files
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), String> {
+        write(self.files())
+    }
+}
+
+// out.ll, and with `object` out.o made from it with opt and llc. with `link` the c compiler
+// links out.o into that executable
+#[cfg(feature = "llvm")]
+pub struct Llvm {
+    source: Source<'static>,
+    object: bool,
+    link: Option<String>,
+}
+
+#[cfg(feature = "llvm")]
+impl Llvm {
+    pub fn new(object: bool, link: Option<String>, float_division: FloatDivision) -> Self {
+        Self {
+            source: Source::new(
+                "out.ll",
+                "llvm ir",
+                Box::new(crate::llvm::generate),
+                float_division,
+            ),
+            object,
+            link,
+        }
+    }
+}
+
+#[cfg(feature = "llvm")]
+impl Backend for Llvm {
+    fn language(&self) -> &'static str {
+        self.source.language()
+    }
+
+    fn emit_program(&mut self, program: &Program) -> Result<(), String> {
+        self.source.emit_program(program)
+    }
+
+    fn files(&self) -> Vec<(String, Vec<u8>)> {
+        self.source.files()
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), String> {
+        write(self.files())?;
+        if !self.object {
+            return Ok(());
+        }
+        let mut flags = vec![];
+        match llvm_version("opt")? {
+            15.. => {}
+            14 => flags.push(String::from("-opaque-pointers")),
+            version => {
+                return Err(format![
+                    "--target=llvm-object needs llvm 14 or newer, opt is llvm {}",
+                    version
+                ])
+            }
+        }
+        // what opt makes is only for llc, it goes in a directory of its own
+        let directory = std::env::temp_dir().join(format!["haneul-llvm-{}", std::process::id()]);
+        fs::create_dir_all(&directory)
+            .map_err(|e| format!["couldn't make {}: {}", directory.display(), e])?;
+        let bitcode = directory.join("out.bc").to_string_lossy().into_owned();
+        let args = |args: &[&str]| -> Vec<String> {
+            flags
+                .iter()
+                .cloned()
+                .chain(args.iter().map(|arg| arg.to_string()))
+                .collect()
+        };
+        let built = cc::run("opt", &args(&["-O2", "out.ll", "-o", &bitcode])).and_then(|_| {
+            cc::run(
+                "llc",
+                &args(&[
+                    "-filetype=obj",
+                    "-relocation-model=pic",
+                    &bitcode,
+                    "-o",
+                    "out.o",
+                ]),
+            )
+        });
+        let _ = fs::remove_dir_all(&directory);
+        built?;
+        match self.link {
+            Some(executable) => {
+                cc::compile(&[String::from("out.o"), String::from("-o"), executable])
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+// the major version of an llvm tool, from `opt --version`
+#[cfg(feature = "llvm")]
+fn llvm_version(tool: &str) -> Result<u32, String> {
+    let output = std::process::Command::new(tool)
+        .arg("--version")
+        .output()
+        .map_err(|e| format!["couldn't run {}: {}", tool, e])?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    text.split_once("LLVM version ")
+        .and_then(|(_, version)| version.split('.').next()?.trim().parse().ok())
+        .ok_or_else(|| format!["{} --version doesn't say which llvm it is", tool])
+}
+
+// the bytecode the vm runs, in a .hbc file (see hbc.rs)
+pub struct Bytecode<'a> {
+    builtins: &'a Registry,
+    file: String,
+    float_division: FloatDivision,
+    // of the peephole pass, see passes::optimize_chunk
+    opt_level: OptLevel,
+    chunk: Chunk,
+}
+
+impl<'a> Bytecode<'a> {
+    pub fn new(
+        builtins: &'a Registry,
+        file: String,
+        float_division: FloatDivision,
+        opt_level: OptLevel,
+    ) -> Self {
+        Self {
+            builtins,
+            file,
+            float_division,
+            opt_level,
+            chunk: Chunk::default(),
+        }
+    }
+}
+
+impl Backend for Bytecode<'_> {
+    fn language(&self) -> &'static str {
+        "bytecode"
+    }
+
+    fn emit_program(&mut self, program: &Program) -> Result<(), String> {
+        self.chunk = bytecode::compile(program, self.builtins, self.float_division)?;
+        passes::optimize_chunk(&mut self.chunk, self.opt_level);
+        Ok(())
+    }
+
+    fn files(&self) -> Vec<(String, Vec<u8>)> {
+        vec![(self.file.clone(), hbc::encode(&self.chunk))]
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), String> {
+        write(self.files())
+    }
+}
@@ -0,0 +1,656 @@
+// builtins are the statements and functions that aren't part of the grammar itself.
+// the parser looks them up by name, so adding one (or letting a host program add one)
+// only means adding an entry here instead of a keyword, a token type and a parser branch
+//
+// a rust program that embeds haneul adds its own with `host`, before the program is parsed,
+// and gives the interpreter what they do with Interpreter::register:
+//
+//   let mut builtins = Registry::new();
+//   builtins.register(builtins::host("SPAWN", 2, false));
+//   ...parse the program with them...
+//   interpreter.register("SPAWN", |x: f32, y: f32| enemies.push((x, y)));
+//
+// a builtin that reaches outside of the program says so in `needs`, a Registry that was
+// restricted to an ExecutionPolicy doesn't let the program use it
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+#[cfg(feature = "std")]
+use crate::runtime::ExecutionPolicy;
+
+// the types the type checker knows, see types.rs
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Type {
+    Number,
+    // what a comparison gives
+    Boolean,
+}
+
+// what a builtin does outside of the program, which an ExecutionPolicy can forbid. serialized
+// as its name
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Capability {
+    // reads or writes files
+    Fs,
+    // connects to another machine, like the X server of the graphics can be
+    Net,
+    // starts another program
+    Exec,
+}
+
+impl Capability {
+    // the allow_ field of ExecutionPolicy that allows it
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::Fs => "fs",
+            Capability::Net => "net",
+            Capability::Exec => "exec",
+        }
+    }
+}
+
+// what a builtin can be compiled to. webassembly and llvm ir have no builtins yet, a program
+// that calls one can't be compiled to them
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Platform {
+    C,
+    Js,
+    Python,
+    Rust,
+    // the vm and the tree interpreter, which run it themselves
+    Run,
+}
+
+// how a platform implements a builtin: a call to `function` with the arguments, which is
+// defined in the helper of that platform named `helper` (one helper can be shared by several
+// builtins). Run has no helpers, its `function` is the name of a runtime::Native
+#[derive(Debug, Clone)]
+pub struct Implementation {
+    pub function: &'static str,
+    pub helper: &'static str,
+}
+
+#[derive(Debug, Clone)]
+pub struct Builtin {
+    pub name: &'static str,
+    pub params: &'static [Type],
+    // builtins that return something are functions used inside expressions: NAME(arg, arg),
+    // the others are statements: NAME arg, arg
+    pub returns: Option<Type>,
+    // cargo feature haneul has to be built with for this builtin to be usable
+    pub feature: Option<&'static str>,
+    // what it does outside of the program, every builtin says it even when it's nothing
+    pub needs: &'static [Capability],
+    // a host function has none, it can only be run by the interpreter. a backend whose
+    // platform isn't here can't compile a program that calls it
+    pub implementations: Vec<(Platform, Implementation)>,
+}
+
+impl Builtin {
+    pub fn arity(&self) -> usize {
+        self.params.len()
+    }
+
+    pub fn is_function(&self) -> bool {
+        self.returns.is_some()
+    }
+
+    pub fn implementation(&self, platform: Platform) -> Option<&Implementation> {
+        self.implementations
+            .iter()
+            .find(|(implemented, _)| *implemented == platform)
+            .map(|(_, implementation)| implementation)
+    }
+
+    // the builtin, with how `platform` implements it too
+    pub fn implemented(
+        mut self,
+        platform: Platform,
+        function: &'static str,
+        helper: &'static str,
+    ) -> Self {
+        self.implementations
+            .retain(|(implemented, _)| *implemented != platform);
+        self.implementations
+            .push((platform, Implementation { function, helper }));
+        self
+    }
+}
+
+// code in the language of `platform`, emitted once in a program that uses it. in c it's placed
+// before main
+#[derive(Debug, Clone)]
+pub struct Helper {
+    pub platform: Platform,
+    pub name: &'static str,
+    pub code: &'static str,
+    pub libraries: &'static [&'static str],
+}
+
+#[derive(Debug, Clone)]
+pub struct Registry {
+    builtins: Vec<Builtin>,
+    helpers: Vec<Helper>,
+    // what the policy of restrict doesn't allow
+    forbidden: Vec<Capability>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        let mut registry = Self {
+            builtins: vec![],
+            helpers: vec![],
+            forbidden: vec![],
+        };
+        registry.register_defaults();
+
+        registry
+    }
+
+    pub fn register(&mut self, builtin: Builtin) {
+        self.builtins
+            .retain(|existing| existing.name != builtin.name);
+        self.builtins.push(builtin);
+    }
+
+    // helpers are emitted in the order they are registered in,
+    // so a helper can use everything that was registered before it
+    pub fn register_helper(&mut self, helper: Helper) {
+        self.helpers.retain(|existing| {
+            existing.platform != helper.platform || existing.name != helper.name
+        });
+        self.helpers.push(helper);
+    }
+
+    #[cfg(feature = "std")]
+    // a program parsed with it can't use the builtins that need what the policy doesn't allow
+    pub fn restrict(&mut self, policy: &ExecutionPolicy) {
+        self.forbidden = [Capability::Fs, Capability::Net, Capability::Exec]
+            .into_iter()
+            .filter(|capability| !policy.allows(*capability))
+            .collect();
+    }
+
+    // the first thing the builtin needs that isn't allowed
+    pub fn forbidden(&self, builtin: &Builtin) -> Option<Capability> {
+        builtin
+            .needs
+            .iter()
+            .copied()
+            .find(|capability| self.forbidden.contains(capability))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Builtin> {
+        self.builtins.iter().find(|builtin| builtin.name == name)
+    }
+
+    pub fn builtins(&self) -> &[Builtin] {
+        &self.builtins
+    }
+
+    pub fn helpers(&self, platform: Platform) -> impl Iterator<Item = &Helper> {
+        self.helpers
+            .iter()
+            .filter(move |helper| helper.platform == platform)
+    }
+
+    fn register_defaults(&mut self) {
+        self.register_helper(Helper {
+            platform: Platform::C,
+            name: "inkey",
+            code: INKEY,
+            libraries: &[],
+        });
+        self.register_helper(Helper {
+            platform: Platform::C,
+            name: "beep",
+            code: BEEP,
+            libraries: &[],
+        });
+        self.register_helper(Helper {
+            platform: Platform::C,
+            name: "sound",
+            code: if cfg!(feature = "audio") {
+                SOUND_AUDIO
+            } else {
+                SOUND_BELL
+            },
+            libraries: &[],
+        });
+        self.register_helper(Helper {
+            platform: Platform::C,
+            name: "graphics",
+            code: GRAPHICS,
+            libraries: &["X11"],
+        });
+        self.register_helper(Helper {
+            platform: Platform::C,
+            name: "turtle",
+            code: TURTLE,
+            libraries: &["m"],
+        });
+
+        // BEEP and SOUND without audio ring the bell of the terminal in the other languages too
+        for platform in [Platform::Js, Platform::Python, Platform::Rust] {
+            let (beep, sound) = match platform {
+                Platform::Js => (JS_BEEP, JS_SOUND),
+                Platform::Python => (PYTHON_BEEP, PYTHON_SOUND),
+                _ => (RUST_BEEP, RUST_SOUND),
+            };
+            self.register_helper(Helper {
+                platform,
+                name: "beep",
+                code: beep,
+                libraries: &[],
+            });
+            self.register_helper(Helper {
+                platform,
+                name: "sound",
+                code: sound,
+                libraries: &[],
+            });
+        }
+
+        // INKEY() gives the character code of the pressed key, or 0 if no key is pressed
+        self.register(
+            function("INKEY", &[], &[])
+                .implemented(Platform::C, "haneul_inkey", "inkey")
+                .implemented(Platform::Run, "inkey", ""),
+        );
+        self.register(
+            statement("BEEP", &[], &[])
+                .implemented(Platform::C, "haneul_beep", "beep")
+                .implemented(Platform::Run, "beep", "")
+                .implemented(Platform::Js, "haneul_beep", "beep")
+                .implemented(Platform::Python, "haneul_beep", "beep")
+                .implemented(Platform::Rust, "haneul_beep", "beep"),
+        );
+        // SOUND freq, duration plays `freq` hertz for `duration` milliseconds. with audio it
+        // runs aplay
+        let sound: &[Capability] = if cfg!(feature = "audio") {
+            &[Capability::Exec]
+        } else {
+            &[]
+        };
+        self.register(
+            statement("SOUND", &[Type::Number; 2], sound)
+                .implemented(Platform::C, "haneul_sound", "sound")
+                .implemented(Platform::Js, "haneul_sound", "sound")
+                .implemented(Platform::Python, "haneul_sound", "sound")
+                .implemented(Platform::Rust, "haneul_sound", "sound"),
+        );
+
+        // drawing outside of the screen (or before SCREEN) is silently ignored, like most BASICs do
+        let graphics = [
+            ("SCREEN", 2, "haneul_screen"),
+            ("PSET", 2, "haneul_pset"),
+            ("LINE", 4, "haneul_line"),
+            ("RECT", 4, "haneul_rect"),
+            ("CIRCLE", 3, "haneul_circle"),
+            ("FLIP", 0, "haneul_flip"),
+        ];
+        // they all need the connection to the x server SCREEN makes, the others draw into
+        // memory until there's a window
+        for (name, arity, c_function) in graphics {
+            let needs = &[Capability::Net];
+            let mut builtin = statement(name, &NUMBERS[..arity], needs).implemented(
+                Platform::C,
+                c_function,
+                "graphics",
+            );
+            builtin.feature = Some("graphics");
+            self.register(builtin);
+        }
+
+        // the turtle starts in the middle facing up, TURN is clockwise in degrees.
+        // everything it draws is saved to turtle.svg when the program exits,
+        // and also drawn on the graphics screen if there is one
+        let turtle = [
+            ("FORWARD", 1, "haneul_forward"),
+            ("TURN", 1, "haneul_turn"),
+            ("PENUP", 0, "haneul_penup"),
+            ("PENDOWN", 0, "haneul_pendown"),
+        ];
+        for (name, arity, c_function) in turtle {
+            let needs = &[Capability::Fs];
+            self.register(statement(name, &NUMBERS[..arity], needs).implemented(
+                Platform::C,
+                c_function,
+                "turtle",
+            ));
+        }
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+const ENABLED_FEATURES: &[&str] = &[
+    #[cfg(feature = "audio")]
+    "audio",
+    #[cfg(feature = "graphics")]
+    "graphics",
+];
+
+pub fn feature_enabled(feature: &str) -> bool {
+    ENABLED_FEATURES.contains(&feature)
+}
+
+const NUMBERS: [Type; MAX_HOST_ARITY] = [Type::Number; MAX_HOST_ARITY];
+
+// the most numbers a host function takes, see interpreter::HostFunction
+pub const MAX_HOST_ARITY: usize = 6;
+
+// a function of the program that embeds haneul: NAME(arg, arg) when it gives a number back to
+// the haneul program, the statement NAME arg, arg when it doesn't. one that touches files, the
+// network or other programs sets `needs` too
+pub fn host(name: &'static str, arity: usize, function: bool) -> Builtin {
+    assert!(
+        arity <= MAX_HOST_ARITY,
+        "a host function takes at most {} numbers",
+        MAX_HOST_ARITY
+    );
+    Builtin {
+        name,
+        params: &NUMBERS[..arity],
+        returns: function.then_some(Type::Number),
+        feature: None,
+        needs: &[],
+        implementations: vec![],
+    }
+}
+
+fn statement(name: &'static str, params: &'static [Type], needs: &'static [Capability]) -> Builtin {
+    Builtin {
+        name,
+        params,
+        returns: None,
+        feature: None,
+        needs,
+        implementations: vec![],
+    }
+}
+
+fn function(name: &'static str, params: &'static [Type], needs: &'static [Capability]) -> Builtin {
+    Builtin {
+        name,
+        params,
+        returns: Some(Type::Number),
+        feature: None,
+        needs,
+        implementations: vec![],
+    }
+}
+
+// the terminal is only put in raw mode for the duration of the call, so INPUT keeps working
+const INKEY: &str = r#"#include <fcntl.h>
+#include <termios.h>
+#include <unistd.h>
+static float haneul_inkey(void) {
+struct termios old, raw;
+int flags, c;
+int is_tty = tcgetattr(STDIN_FILENO, &old) == 0;
+fflush(stdout);
+if(is_tty) {
+raw = old;
+raw.c_lflag &= ~(ICANON | ECHO);
+tcsetattr(STDIN_FILENO, TCSANOW, &raw);
+}
+flags = fcntl(STDIN_FILENO, F_GETFL, 0);
+fcntl(STDIN_FILENO, F_SETFL, flags | O_NONBLOCK);
+c = getchar();
+fcntl(STDIN_FILENO, F_SETFL, flags);
+if(is_tty) {
+tcsetattr(STDIN_FILENO, TCSANOW, &old);
+}
+if(c == EOF) {
+clearerr(stdin);
+return 0;
+}
+return (float)c;
+}"#;
+
+const BEEP: &str = r#"static void haneul_beep(void) {
+printf("\a");
+fflush(stdout);
+}"#;
+
+const SOUND_AUDIO: &str = r#"static void haneul_sound(float freq, float duration) {
+FILE *pipe;
+int i, period, samples = (int)(8000 * duration / 1000);
+fflush(stdout);
+pipe = popen("aplay -q -t raw -f U8 -r 8000 -c 1 2>/dev/null", "w");
+if(pipe == NULL) {
+printf("\a");
+return;
+}
+period = freq > 0 ? (int)(8000 / freq) : 0;
+for(i = 0; i < samples; i++) {
+fputc(period > 0 && i % period < period / 2 ? 192 : 64, pipe);
+}
+pclose(pipe);
+}"#;
+
+// without the audio feature SOUND rings the terminal bell and waits for the same duration,
+// so the timing of the program stays the same
+const SOUND_BELL: &str = r#"#include <unistd.h>
+static void haneul_sound(float freq, float duration) {
+(void)freq;
+printf("\a");
+fflush(stdout);
+if(duration > 0) {
+usleep((useconds_t)(duration * 1000));
+}
+}"#;
+
+// a framebuffer that FLIP copies to an X11 window
+const GRAPHICS: &str = r#"#include <stdlib.h>
+#include <X11/Xlib.h>
+#define HANEUL_GRAPHICS
+static Display *haneul_display;
+static Window haneul_window;
+static GC haneul_gc;
+static XImage *haneul_image;
+static unsigned int *haneul_pixels;
+static int haneul_width, haneul_height;
+static void haneul_screen(float w, float h) {
+int screen;
+haneul_width = (int)w;
+haneul_height = (int)h;
+haneul_display = XOpenDisplay(NULL);
+if(haneul_display == NULL || haneul_width <= 0 || haneul_height <= 0) {
+fprintf(stderr, "SCREEN: could not open a %dx%d window\n", haneul_width, haneul_height);
+exit(1);
+}
+screen = DefaultScreen(haneul_display);
+haneul_window = XCreateSimpleWindow(haneul_display, RootWindow(haneul_display, screen), 0, 0, haneul_width, haneul_height, 0, BlackPixel(haneul_display, screen), BlackPixel(haneul_display, screen));
+XStoreName(haneul_display, haneul_window, "haneul");
+XMapWindow(haneul_display, haneul_window);
+haneul_gc = XCreateGC(haneul_display, haneul_window, 0, NULL);
+haneul_pixels = calloc((size_t)haneul_width * haneul_height, sizeof(unsigned int));
+haneul_image = XCreateImage(haneul_display, DefaultVisual(haneul_display, screen), DefaultDepth(haneul_display, screen), ZPixmap, 0, (char *)haneul_pixels, haneul_width, haneul_height, 32, 0);
+}
+static void haneul_pset(float x, float y) {
+int ix = (int)x, iy = (int)y;
+if(haneul_pixels != NULL && ix >= 0 && iy >= 0 && ix < haneul_width && iy < haneul_height) {
+haneul_pixels[iy * haneul_width + ix] = 0xFFFFFF;
+}
+}
+static void haneul_line(float x1, float y1, float x2, float y2) {
+int x = (int)x1, y = (int)y1, ex = (int)x2, ey = (int)y2;
+int dx = abs(ex - x), dy = -abs(ey - y);
+int sx = x < ex ? 1 : -1, sy = y < ey ? 1 : -1;
+int err = dx + dy, e2;
+for(;;) {
+haneul_pset(x, y);
+if(x == ex && y == ey) {
+break;
+}
+e2 = 2 * err;
+if(e2 >= dy) {
+err += dy;
+x += sx;
+}
+if(e2 <= dx) {
+err += dx;
+y += sy;
+}
+}
+}
+static void haneul_rect(float x, float y, float w, float h) {
+haneul_line(x, y, x + w - 1, y);
+haneul_line(x + w - 1, y, x + w - 1, y + h - 1);
+haneul_line(x + w - 1, y + h - 1, x, y + h - 1);
+haneul_line(x, y + h - 1, x, y);
+}
+static void haneul_circle(float cx, float cy, float r) {
+int x = (int)r, y = 0, err = 1 - x;
+while(x >= y) {
+haneul_pset(cx + x, cy + y);
+haneul_pset(cx + y, cy + x);
+haneul_pset(cx - y, cy + x);
+haneul_pset(cx - x, cy + y);
+haneul_pset(cx - x, cy - y);
+haneul_pset(cx - y, cy - x);
+haneul_pset(cx + y, cy - x);
+haneul_pset(cx + x, cy - y);
+y++;
+if(err < 0) {
+err += 2 * y + 1;
+} else {
+x--;
+err += 2 * (y - x) + 1;
+}
+}
+}
+static void haneul_flip(void) {
+if(haneul_display == NULL) {
+return;
+}
+XPutImage(haneul_display, haneul_window, haneul_gc, haneul_image, 0, 0, 0, 0, haneul_width, haneul_height);
+XFlush(haneul_display);
+}"#;
+
+const TURTLE: &str = r#"#include <math.h>
+#include <stdlib.h>
+static float haneul_turtle_x, haneul_turtle_y, haneul_turtle_heading;
+static int haneul_turtle_pen_up;
+static float *haneul_turtle_segments;
+static int haneul_turtle_count, haneul_turtle_capacity;
+static void haneul_turtle_draw(float x1, float y1, float x2, float y2) {
+#ifdef HANEUL_GRAPHICS
+float cx = haneul_width / 2, cy = haneul_height / 2;
+haneul_line(cx + x1, cy + y1, cx + x2, cy + y2);
+#else
+(void)x1;
+(void)y1;
+(void)x2;
+(void)y2;
+#endif
+}
+static void haneul_turtle_save(void) {
+FILE *file = fopen("turtle.svg", "w");
+float min_x = 0, min_y = 0, max_x = 0, max_y = 0, *s;
+int i;
+if(file == NULL) {
+return;
+}
+for(i = 0; i < haneul_turtle_count * 4; i += 2) {
+min_x = fminf(min_x, haneul_turtle_segments[i]);
+max_x = fmaxf(max_x, haneul_turtle_segments[i]);
+min_y = fminf(min_y, haneul_turtle_segments[i + 1]);
+max_y = fmaxf(max_y, haneul_turtle_segments[i + 1]);
+}
+fprintf(file, "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"%g %g %g %g\">\n", min_x - 10, min_y - 10, max_x - min_x + 20, max_y - min_y + 20);
+for(i = 0; i < haneul_turtle_count; i++) {
+s = &haneul_turtle_segments[i * 4];
+fprintf(file, "<line x1=\"%g\" y1=\"%g\" x2=\"%g\" y2=\"%g\" stroke=\"black\"/>\n", s[0], s[1], s[2], s[3]);
+}
+fprintf(file, "</svg>\n");
+fclose(file);
+}
+static void haneul_forward(float distance) {
+float radians = haneul_turtle_heading * 3.14159265f / 180;
+float x = haneul_turtle_x + distance * sinf(radians);
+float y = haneul_turtle_y - distance * cosf(radians);
+if(!haneul_turtle_pen_up) {
+if(haneul_turtle_segments == NULL) {
+atexit(haneul_turtle_save);
+}
+if(haneul_turtle_count == haneul_turtle_capacity) {
+haneul_turtle_capacity = haneul_turtle_capacity == 0 ? 64 : haneul_turtle_capacity * 2;
+haneul_turtle_segments = realloc(haneul_turtle_segments, sizeof(float) * 4 * haneul_turtle_capacity);
+}
+haneul_turtle_segments[haneul_turtle_count * 4] = haneul_turtle_x;
+haneul_turtle_segments[haneul_turtle_count * 4 + 1] = haneul_turtle_y;
+haneul_turtle_segments[haneul_turtle_count * 4 + 2] = x;
+haneul_turtle_segments[haneul_turtle_count * 4 + 3] = y;
+haneul_turtle_count++;
+haneul_turtle_draw(haneul_turtle_x, haneul_turtle_y, x, y);
+}
+haneul_turtle_x = x;
+haneul_turtle_y = y;
+}
+static void haneul_turn(float degrees) {
+haneul_turtle_heading += degrees;
+}
+static void haneul_penup(void) {
+haneul_turtle_pen_up = 1;
+}
+static void haneul_pendown(void) {
+haneul_turtle_pen_up = 0;
+}"#;
+
+// node writes the bell straight to stdout, like console.log does. a browser has no bell
+const JS_BEEP: &str = r#"function haneul_beep() {
+    if (typeof process !== "undefined") {
+        process.stdout.write("\x07");
+    }
+}"#;
+
+// Atomics.wait is the only way to wait without giving up the thread, a browser doesn't allow it
+// on the page so it doesn't wait there
+const JS_SOUND: &str = r#"function haneul_sound(freq, duration) {
+    if (typeof process !== "undefined") {
+        process.stdout.write("\x07");
+        if (duration > 0) {
+            Atomics.wait(new Int32Array(new SharedArrayBuffer(4)), 0, 0, duration);
+        }
+    }
+}"#;
+
+const PYTHON_BEEP: &str = r#"def haneul_beep():
+    sys.stdout.write("\a")
+    sys.stdout.flush()"#;
+
+const PYTHON_SOUND: &str = r#"import time
+
+
+def haneul_sound(freq, duration):
+    sys.stdout.write("\a")
+    sys.stdout.flush()
+    if duration > 0:
+        time.sleep(duration / 1000)"#;
+
+const RUST_BEEP: &str = r#"fn haneul_beep() {
+    use std::io::Write;
+    print!("\x07");
+    let _ = std::io::stdout().flush();
+}"#;
+
+// a duration that isn't a number of seconds, like a negative one, doesn't wait
+const RUST_SOUND: &str = r#"fn haneul_sound(_freq: f32, duration: f32) {
+    use std::io::Write;
+    print!("\x07");
+    let _ = std::io::stdout().flush();
+    if let Ok(duration) = std::time::Duration::try_from_secs_f32(duration / 1000.0) {
+        std::thread::sleep(duration);
+    }
+}"#;
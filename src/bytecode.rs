@@ -0,0 +1,290 @@
+// the bytecode `haneul run` runs on the vm (vm.rs). a program is a chunk: the instructions, and
+// the numbers and strings they use. the instructions work on a stack of values with the types
+// c gives them (see ctype.rs), the compiler puts in a Convert wherever c converts one, so the
+// program does the same as when it's compiled to c. the values on the stack don't know their
+// type, the instructions that use them say what it is
+//
+// LABELs are gone, a GOTO, an IF or a WHILE is a jump to the index of an instruction
+
+use std::collections::HashMap;
+
+use crate::{
+    ast::{BinaryOp, ExprId, ExprKind, Program, Statement, StatementKind, UnaryOp},
+    builtins::{Platform, Registry},
+    ctype::{self, CType, FloatDivision},
+    intern::Symbol,
+    runtime::{Native, Raw, Value},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    // pushes a number of the constant pool
+    Constant(u32),
+    // pushes or pops a variable, the value has the type of the variable
+    Load(u32),
+    Store(u32),
+    // the value on top, from the first type to the second
+    Convert(CType, CType),
+    Negate(CType),
+    // these pop two values of the type and push the result, a comparison gives an int
+    Add(CType),
+    Subtract(CType),
+    Multiply(CType),
+    Divide(CType),
+    Remainder(CType),
+    Equal(CType),
+    NotEqual(CType),
+    Less(CType),
+    LessEqual(CType),
+    Greater(CType),
+    GreaterEqual(CType),
+    Jump(u32),
+    // pops an int and jumps when it's 0, the condition of an IF or a WHILE is a comparison
+    JumpIfZero(u32),
+    // pops a value of the type, an int or a long is printed as it is and a float like
+    // runtime::format_number does
+    Print(CType),
+    PrintString(u32),
+    Input(u32),
+    // runs a builtin, a function pushes the float it gives
+    Call(Native),
+    Halt,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Chunk {
+    pub code: Vec<Instruction>,
+    pub constants: Vec<Value>,
+    pub strings: Vec<String>,
+    // the name and the type of every variable, by its number
+    pub variables: Vec<(String, CType)>,
+    // the line in the program every instruction comes from
+    pub lines: Vec<usize>,
+    pub float_division: FloatDivision,
+}
+
+// gives the name of a builtin the program uses that the vm doesn't have, see runtime::Native
+pub fn compile(
+    program: &Program,
+    builtins: &Registry,
+    float_division: FloatDivision,
+) -> Result<Chunk, String> {
+    let mut compiler = Compiler {
+        program,
+        builtins,
+        chunk: Chunk {
+            float_division,
+            ..Chunk::default()
+        },
+        variables: HashMap::new(),
+        labels: HashMap::new(),
+        gotos: vec![],
+        line: 1,
+    };
+    compiler.statements(&program.statements)?;
+    compiler.emit(Instruction::Halt);
+
+    // a GOTO can come before its LABEL, so the jumps get their target at the end
+    for (jump, label) in compiler.gotos {
+        let target = compiler.labels[&label];
+        compiler.chunk.code[jump] = Instruction::Jump(target);
+    }
+
+    Ok(compiler.chunk)
+}
+
+struct Compiler<'a> {
+    program: &'a Program,
+    builtins: &'a Registry,
+    chunk: Chunk,
+    variables: HashMap<Symbol, u32>,
+    labels: HashMap<Symbol, u32>,
+    // the jumps of the GOTOs and the LABEL they go to
+    gotos: Vec<(usize, Symbol)>,
+    // of the statement that is compiled
+    line: usize,
+}
+
+impl Compiler<'_> {
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        self.chunk.code.push(instruction);
+        self.chunk.lines.push(self.line);
+        self.chunk.code.len() - 1
+    }
+
+    fn here(&self) -> u32 {
+        self.chunk.code.len() as u32
+    }
+
+    // a jump that's emitted before its target is known
+    fn patch(&mut self, jump: usize) {
+        let target = self.here();
+        self.chunk.code[jump] = match self.chunk.code[jump] {
+            Instruction::Jump(_) => Instruction::Jump(target),
+            Instruction::JumpIfZero(_) => Instruction::JumpIfZero(target),
+            instruction => unreachable!("{:?} isn't a jump", instruction),
+        };
+    }
+
+    fn statements(&mut self, statements: &[Statement]) -> Result<(), String> {
+        for statement in statements {
+            self.line = statement.span.line;
+            self.statement(statement)?;
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<(), String> {
+        match &statement.kind {
+            StatementKind::Print(value) => {
+                let ctype = match ctype::of(self.program, *value) {
+                    ctype if ctype.is_integer() => ctype,
+                    _ => CType::Float,
+                };
+                self.convert(*value, ctype)?;
+                self.emit(Instruction::Print(ctype));
+            }
+            StatementKind::PrintString(string) => {
+                let index = self.chunk.strings.len() as u32;
+                self.chunk.strings.push(string.clone());
+                self.emit(Instruction::PrintString(index));
+            }
+            StatementKind::If { condition, body } => {
+                self.expression(*condition)?;
+                let skip = self.emit(Instruction::JumpIfZero(0));
+                self.statements(body)?;
+                self.patch(skip);
+            }
+            StatementKind::While { condition, body } => {
+                let start = self.here();
+                self.line = statement.span.line;
+                self.expression(*condition)?;
+                let exit = self.emit(Instruction::JumpIfZero(0));
+                self.statements(body)?;
+                self.line = statement.span.line;
+                self.emit(Instruction::Jump(start));
+                self.patch(exit);
+            }
+            StatementKind::Label(label) => {
+                let here = self.here();
+                self.labels.insert(*label, here);
+            }
+            StatementKind::Goto(label) => {
+                let jump = self.emit(Instruction::Jump(0));
+                self.gotos.push((jump, *label));
+            }
+            StatementKind::Let { variable, value } => {
+                self.convert(*value, ctype::variable(self.program, *variable))?;
+                let slot = self.variable(*variable);
+                self.emit(Instruction::Store(slot));
+            }
+            StatementKind::Input(variable) => {
+                let slot = self.variable(*variable);
+                self.emit(Instruction::Input(slot));
+            }
+            StatementKind::Call { name, .. } => {
+                self.call(name)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn expression(&mut self, expression: ExprId) -> Result<(), String> {
+        let exprs = &self.program.exprs;
+        match &exprs[expression].kind {
+            ExprKind::Number(text) => {
+                let index = self.constant(Value::number(text));
+                self.emit(Instruction::Constant(index));
+            }
+            ExprKind::Variable(variable) => {
+                let slot = self.variable(*variable);
+                self.emit(Instruction::Load(slot));
+            }
+            ExprKind::Call { name, .. } => self.call(name)?,
+            ExprKind::Unary { op, operand } => {
+                let (op, operand) = (*op, *operand);
+                self.expression(operand)?;
+                if op == UnaryOp::Minus {
+                    let ctype = ctype::of(self.program, operand);
+                    self.emit(Instruction::Negate(ctype));
+                }
+            }
+            ExprKind::Binary { op, left, right } => {
+                let (op, left, right) = (*op, *left, *right);
+                let common = ctype::common(self.program, left, right);
+                self.convert(left, common)?;
+                self.convert(right, common)?;
+                self.emit(match op {
+                    BinaryOp::Add => Instruction::Add(common),
+                    BinaryOp::Subtract => Instruction::Subtract(common),
+                    BinaryOp::Multiply => Instruction::Multiply(common),
+                    BinaryOp::Divide => Instruction::Divide(common),
+                    BinaryOp::Remainder => Instruction::Remainder(common),
+                    BinaryOp::Equal => Instruction::Equal(common),
+                    BinaryOp::NotEqual => Instruction::NotEqual(common),
+                    BinaryOp::Less => Instruction::Less(common),
+                    BinaryOp::LessEqual => Instruction::LessEqual(common),
+                    BinaryOp::Greater => Instruction::Greater(common),
+                    BinaryOp::GreaterEqual => Instruction::GreaterEqual(common),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    // the natives take no arguments, so there's nothing to push
+    fn call(&mut self, name: &str) -> Result<(), String> {
+        let native = self
+            .builtins
+            .get(name)
+            .and_then(|builtin| builtin.implementation(Platform::Run))
+            .and_then(|implementation| Native::named(implementation.function))
+            .ok_or_else(|| name.to_owned())?;
+        self.emit(Instruction::Call(native));
+        Ok(())
+    }
+
+    fn convert(&mut self, expression: ExprId, to: CType) -> Result<(), String> {
+        // a number is put in the pool as the type it's used as
+        if let ExprKind::Number(text) = &self.program.exprs[expression].kind {
+            let index = self.constant(Value::number(text).convert(to));
+            self.emit(Instruction::Constant(index));
+            return Ok(());
+        }
+        self.expression(expression)?;
+        let from = ctype::of(self.program, expression);
+        if from != to {
+            self.emit(Instruction::Convert(from, to));
+        }
+        Ok(())
+    }
+
+    // the same number is in the pool once. they're compared by their bits, -0.0 == 0.0
+    fn constant(&mut self, value: Value) -> u32 {
+        let constants = &mut self.chunk.constants;
+        let same = |constant: &Value| {
+            constant.ctype() == value.ctype() && Raw::new(*constant) == Raw::new(value)
+        };
+        match constants.iter().position(same) {
+            Some(index) => index as u32,
+            None => {
+                constants.push(value);
+                constants.len() as u32 - 1
+            }
+        }
+    }
+
+    fn variable(&mut self, variable: Symbol) -> u32 {
+        if let Some(slot) = self.variables.get(&variable) {
+            return *slot;
+        }
+        let slot = self.chunk.variables.len() as u32;
+        let name = self.program.names.resolve(variable).to_owned();
+        let ctype = ctype::variable(self.program, variable);
+        self.chunk.variables.push((name, ctype));
+        self.variables.insert(variable, slot);
+        slot
+    }
+}
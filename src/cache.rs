@@ -0,0 +1,40 @@
+// the compilation cache of `haneul run`: the bytecode of a program is saved in .haneul-cache
+// next to it, named after a hash of the program and the options that change the bytecode. when
+// nothing changed the program runs from there without being parsed again. --no-cache skips it
+//
+// only programs without warnings are saved, so the warnings are still shown every run. a file
+// in the cache that can't be read is compiled again, it's never an error
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use haneul::{bytecode::Chunk, hbc};
+
+pub struct Cache {
+    path: PathBuf,
+}
+
+impl Cache {
+    // `options` is everything besides the program that changes what it compiles to
+    pub fn new(directory: &Path, text: &str, options: &str) -> Self {
+        let key = format!["{}\0{}\0{}", env!("CARGO_PKG_VERSION"), options, text];
+        Self {
+            path: directory
+                .join(".haneul-cache")
+                .join(format!["{:016x}.hbc", hbc::checksum(key.as_bytes())]),
+        }
+    }
+
+    pub fn load(&self) -> Option<Chunk> {
+        hbc::decode(&fs::read(&self.path).ok()?).ok()
+    }
+
+    pub fn store(&self, chunk: &Chunk) {
+        if let Some(directory) = self.path.parent() {
+            let _ = fs::create_dir_all(directory);
+        }
+        let _ = fs::write(&self.path, hbc::encode(chunk));
+    }
+}
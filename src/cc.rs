@@ -0,0 +1,42 @@
+// running the c compiler and the other tools that make the output of a backend into a program.
+// the compiler is $CC, or the first of cc, gcc and clang that is on the PATH. when a tool fails
+// its errors are passed through, the ones about out.c point at the program with #line
+
+use std::{
+    env,
+    io::{self, Write},
+    process::Command,
+};
+
+pub fn compile(args: &[String]) -> Result<(), String> {
+    let cc = find().ok_or_else(|| String::from("there is no c compiler, install one or set CC"))?;
+    // CC can have flags after the compiler, like `clang -m32`
+    let mut words = cc.split_whitespace();
+    let program = words.next().unwrap_or("cc");
+    let mut all_args: Vec<String> = words.map(String::from).collect();
+    all_args.extend_from_slice(args);
+    run(program, &all_args)
+}
+
+pub fn run(program: &str, args: &[String]) -> Result<(), String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| format!["couldn't run {}: {}", program, e])?;
+    if !output.status.success() {
+        let _ = io::stderr().write_all(&output.stderr);
+        return Err(format!["{} failed", program]);
+    }
+    Ok(())
+}
+
+fn find() -> Option<String> {
+    if let Some(cc) = env::var("CC").ok().filter(|cc| !cc.trim().is_empty()) {
+        return Some(cc);
+    }
+    let path = env::var_os("PATH")?;
+    ["cc", "gcc", "clang"]
+        .into_iter()
+        .find(|name| env::split_paths(&path).any(|directory| directory.join(name).is_file()))
+        .map(String::from)
+}
@@ -0,0 +1,273 @@
+// the control flow graph of a program: the statements cut up into basic blocks, runs of
+// statements that always run one after the other, with the jumps between them. the IFs,
+// WHILEs, LABELs and GOTOs are gone, they're the edges. passes that need to know which
+// statement can run after which one (dead code, optimizations) work on this
+
+use crate::{
+    ast::{ExprId, Program, Statement, StatementKind},
+    intern::Symbol,
+    prelude::*,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(u32);
+
+impl BlockId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    // the LABEL the block starts at, if there is one
+    pub label: Option<Symbol>,
+    // only PRINT, LET, INPUT and builtin statements, everything else is the terminator
+    pub statements: Vec<Statement>,
+    pub terminator: Terminator,
+}
+
+// how a block ends
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Terminator {
+    Jump(BlockId),
+    // the condition of an IF or a WHILE
+    Branch {
+        condition: ExprId,
+        then: BlockId,
+        otherwise: BlockId,
+    },
+    // the end of the program
+    Return,
+}
+
+impl Terminator {
+    pub fn successors(&self) -> Vec<BlockId> {
+        match *self {
+            Terminator::Jump(target) => vec![target],
+            Terminator::Branch {
+                then, otherwise, ..
+            } => vec![then, otherwise],
+            Terminator::Return => vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cfg {
+    // the program starts at the first block
+    blocks: Vec<Block>,
+    // the block every statement starts in, in the order the statements are in the program
+    // with the body of an IF or a WHILE right after it. None when no block can get to it
+    starts: Vec<Option<BlockId>>,
+}
+
+impl Cfg {
+    pub fn new(program: &Program) -> Self {
+        let mut builder = Builder {
+            blocks: vec![],
+            current: None,
+            labels: HashMap::new(),
+            starts: vec![],
+        };
+        builder.current();
+        builder.statements(&program.statements);
+
+        Self {
+            blocks: builder.blocks,
+            starts: builder.starts,
+        }
+    }
+
+    pub fn entry(&self) -> BlockId {
+        BlockId(0)
+    }
+
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    pub fn block(&self, id: BlockId) -> &Block {
+        &self.blocks[id.index()]
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = BlockId> {
+        (0..self.blocks.len() as u32).map(BlockId)
+    }
+
+    pub fn starts(&self) -> &[Option<BlockId>] {
+        &self.starts
+    }
+
+    pub fn successors(&self, id: BlockId) -> Vec<BlockId> {
+        self.block(id).terminator.successors()
+    }
+
+    // for every block, the blocks that jump to it
+    pub fn predecessors(&self) -> Vec<Vec<BlockId>> {
+        let mut predecessors = vec![vec![]; self.blocks.len()];
+        for id in self.ids() {
+            for successor in self.successors(id) {
+                if !predecessors[successor.index()].contains(&id) {
+                    predecessors[successor.index()].push(id);
+                }
+            }
+        }
+
+        predecessors
+    }
+
+    // for every block, whether any way through the program gets to it
+    pub fn reachable(&self) -> Vec<bool> {
+        self.reachable_with(|_| None)
+    }
+
+    // the same, but a branch whose condition `known` gives the value of only goes one way
+    pub fn reachable_with(&self, known: impl Fn(ExprId) -> Option<bool>) -> Vec<bool> {
+        let mut reachable = vec![false; self.blocks.len()];
+        let mut todo = vec![self.entry()];
+        while let Some(id) = todo.pop() {
+            if core::mem::replace(&mut reachable[id.index()], true) {
+                continue;
+            }
+            match self.block(id).terminator {
+                Terminator::Branch {
+                    condition,
+                    then,
+                    otherwise,
+                } => match known(condition) {
+                    Some(true) => todo.push(then),
+                    Some(false) => todo.push(otherwise),
+                    None => todo.extend([then, otherwise]),
+                },
+                terminator => todo.extend(terminator.successors()),
+            }
+        }
+
+        reachable
+    }
+}
+
+struct Builder {
+    blocks: Vec<Block>,
+    // the block statements are added to, None right after a GOTO where nothing can get to
+    current: Option<BlockId>,
+    // a GOTO can come before its LABEL, so the block is made by whichever comes first
+    labels: HashMap<Symbol, BlockId>,
+    starts: Vec<Option<BlockId>>,
+}
+
+impl Builder {
+    fn statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        // filled in below, before the statements in the body are
+        let start = self.starts.len();
+        self.starts.push(self.current);
+
+        match &statement.kind {
+            StatementKind::If { condition, body } => {
+                // the condition is in the current block, even when nothing gets there
+                self.starts[start] = Some(self.current());
+                let then = self.block(None);
+                let after = self.block(None);
+                self.terminate(Terminator::Branch {
+                    condition: *condition,
+                    then,
+                    otherwise: after,
+                });
+
+                self.current = Some(then);
+                self.statements(body);
+                self.terminate(Terminator::Jump(after));
+                self.current = Some(after);
+            }
+            // the condition gets its own block, every time through the body jumps back to it
+            StatementKind::While { condition, body } => {
+                let head = self.block(None);
+                let body_block = self.block(None);
+                let after = self.block(None);
+                self.terminate(Terminator::Jump(head));
+                self.starts[start] = Some(head);
+
+                self.current = Some(head);
+                self.terminate(Terminator::Branch {
+                    condition: *condition,
+                    then: body_block,
+                    otherwise: after,
+                });
+
+                self.current = Some(body_block);
+                self.statements(body);
+                self.terminate(Terminator::Jump(head));
+                self.current = Some(after);
+            }
+            StatementKind::Label(label) => {
+                let target = self.label(*label);
+                self.terminate(Terminator::Jump(target));
+                self.current = Some(target);
+                self.starts[start] = Some(target);
+            }
+            StatementKind::Goto(label) => {
+                let target = self.label(*label);
+                self.terminate(Terminator::Jump(target));
+            }
+            StatementKind::Print(_)
+            | StatementKind::PrintString(_)
+            | StatementKind::Let { .. }
+            | StatementKind::Input(_)
+            | StatementKind::Call { .. } => {
+                let current = self.current();
+                self.starts[start] = Some(current);
+                self.blocks[current.index()]
+                    .statements
+                    .push(statement.clone());
+            }
+        }
+    }
+
+    fn block(&mut self, label: Option<Symbol>) -> BlockId {
+        let id = BlockId(self.blocks.len() as u32);
+        self.blocks.push(Block {
+            label,
+            statements: vec![],
+            terminator: Terminator::Return,
+        });
+
+        id
+    }
+
+    // statements after a GOTO start a block of their own that nothing jumps to
+    fn current(&mut self) -> BlockId {
+        match self.current {
+            Some(current) => current,
+            None => {
+                let block = self.block(None);
+                self.current = Some(block);
+                block
+            }
+        }
+    }
+
+    fn label(&mut self, label: Symbol) -> BlockId {
+        if let Some(block) = self.labels.get(&label) {
+            return *block;
+        }
+        let block = self.block(Some(label));
+        self.labels.insert(label, block);
+
+        block
+    }
+
+    // ends the current block, whatever comes next isn't reached from it.
+    // right after a GOTO there's no block to end, a jump from there never happens
+    fn terminate(&mut self, terminator: Terminator) {
+        if let Some(current) = self.current.take() {
+            self.blocks[current.index()].terminator = terminator;
+        }
+    }
+}
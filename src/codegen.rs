@@ -0,0 +1,529 @@
+// turns the tree from the parser into c99: every variable is declared at the top of main with
+// its type (see ctype::infer), PRINT and INPUT call the functions in RUNTIME, LABEL and GOTO are c labels and gotos
+
+use std::collections::HashSet;
+
+use crate::{
+    ast::{BinaryOp, ExprId, ExprKind, Program, Statement, StatementKind},
+    builtins::{Helper, Platform, Registry},
+    ctype::{self, CType, FloatDivision},
+    emitter::Emitter,
+    intern::Symbol,
+};
+
+struct Generator<'a> {
+    program: &'a Program,
+    // the name of the program as a c string, for #line. None without them
+    source: Option<String>,
+    builtins: &'a Registry,
+    float_division: FloatDivision,
+    emitter: &'a mut Emitter,
+    declared: HashSet<Symbol>,
+    helpers_used: Vec<&'static str>,
+    unsupported: Option<String>,
+}
+
+// `source` is the name of the program, the #line directives point the c compiler's errors and
+// a debugger back at it. gives the name of a host function when the program calls one, only the
+// interpreter has them
+pub fn generate(
+    program: &Program,
+    builtins: &Registry,
+    source: Option<&str>,
+    float_division: FloatDivision,
+    emitter: &mut Emitter,
+) -> Result<(), String> {
+    let mut generator = Generator {
+        program,
+        source: source.map(string_literal),
+        builtins,
+        float_division,
+        emitter,
+        declared: HashSet::new(),
+        helpers_used: vec![],
+        unsupported: None,
+    };
+
+    generator.emitter.prelude_line("#include <stdio.h>");
+    generator.emitter.header_line("int main(void) {");
+
+    generator.statements(&program.statements);
+
+    generator.emitter.emit_line("return 0;");
+    generator.emitter.emit_line("}");
+
+    for helper in RUNTIME.iter().chain(builtins.helpers(Platform::C)) {
+        if generator.helpers_used.contains(&helper.name) {
+            for line in helper.code.lines() {
+                generator.emitter.prelude_line(line);
+            }
+            for library in helper.libraries {
+                generator.emitter.link_library(library);
+            }
+        }
+    }
+
+    match generator.unsupported {
+        Some(builtin) => Err(builtin),
+        None => Ok(()),
+    }
+}
+
+impl<'a> Generator<'a> {
+    fn statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            if let Some(source) = &self.source {
+                self.emitter
+                    .emit_line(&format!["#line {} {}", statement.span.line, source]);
+            }
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match &statement.kind {
+            StatementKind::Print(value) => {
+                let helper = match ctype::of(self.program, *value) {
+                    CType::Int => "print_int",
+                    CType::Long => "print_long",
+                    _ => "print_number",
+                };
+                self.use_helper(helper);
+                self.emitter.emit(&format!["haneul_{}(", helper]);
+                self.expression(*value);
+                self.emitter.emit_line(");");
+            }
+            StatementKind::PrintString(string) => {
+                self.use_helper("print_string");
+                self.emitter
+                    .emit_line(&format!["haneul_print_string({});", string_literal(string)]);
+            }
+            StatementKind::If { condition, body } => {
+                self.emitter.emit("if(");
+                self.expression(*condition);
+                self.emitter.emit_line("){");
+                self.statements(body);
+                self.emitter.emit_line("}");
+            }
+            StatementKind::While { condition, body } => {
+                self.emitter.emit("while(");
+                self.expression(*condition);
+                self.emitter.emit_line("){");
+                self.statements(body);
+                self.emitter.emit_line("}");
+            }
+            // a label has to be in front of a statement in c99, even at the end of a block
+            StatementKind::Label(label) => {
+                self.emitter.emit_line(&format!["{}:;", self.name(*label)]);
+            }
+            StatementKind::Goto(label) => {
+                self.emitter
+                    .emit_line(&format!["goto {};", self.name(*label)]);
+            }
+            StatementKind::Let { variable, value } => {
+                self.declare(*variable);
+                self.emitter.emit(&format!["{} = ", self.name(*variable)]);
+                self.expression(*value);
+                self.emitter.emit_line(";");
+            }
+            StatementKind::Input(variable) => {
+                self.declare(*variable);
+                self.use_helper("input");
+                self.emitter
+                    .emit_line(&format!["haneul_input(&{});", self.name(*variable)]);
+            }
+            StatementKind::Call { name, arguments } => {
+                self.call(name, arguments);
+                self.emitter.emit_line(";");
+            }
+        }
+    }
+
+    fn expression(&mut self, expression: ExprId) {
+        let program = self.program;
+        match &program.exprs[expression].kind {
+            // an int with a 0 in front is octal in c
+            ExprKind::Number(number) if !number.contains('.') => {
+                let digits = number.trim_start_matches('0');
+                self.emitter
+                    .emit(if digits.is_empty() { "0" } else { digits })
+            }
+            ExprKind::Number(number) => self.emitter.emit(number),
+            ExprKind::Variable(variable) => self.emitter.emit(&self.name(*variable)),
+            ExprKind::Call { name, arguments } => self.call(name, arguments),
+            ExprKind::Unary { op, operand } => {
+                self.emitter.emit(op.text());
+                self.expression(*operand);
+            }
+            ExprKind::Binary {
+                op: op @ (BinaryOp::Divide | BinaryOp::Remainder),
+                left,
+                right,
+            } => self.division(*op, expression, *left, *right),
+            // the tree has the same precedence as c, so it doesn't need any parentheses
+            ExprKind::Binary { op, left, right } => {
+                self.expression(*left);
+                self.emitter.emit(op.text());
+                self.expression(*right);
+            }
+        }
+    }
+
+    // an int divided by 0 crashes in c, the helper stops the program with an error instead. c
+    // has no % of floats, it's fmod
+    fn division(&mut self, op: BinaryOp, expression: ExprId, left: ExprId, right: ExprId) {
+        let program = self.program;
+        let exprs = &program.exprs;
+        let common = ctype::common(self.program, left, right);
+        let helper = match (op, common) {
+            (BinaryOp::Divide, CType::Int) => "divide_int",
+            (BinaryOp::Divide, CType::Long) => "divide_long",
+            (_, CType::Int) => "remainder_int",
+            (_, CType::Long) => "remainder_long",
+            (BinaryOp::Divide, _) if self.float_division == FloatDivision::Ieee => {
+                self.expression(left);
+                self.emitter.emit(BinaryOp::Divide.text());
+                self.expression(right);
+                return;
+            }
+            (_, _) if self.float_division == FloatDivision::Ieee => {
+                self.use_helper("math");
+                let function = match common {
+                    CType::Float => "fmodf",
+                    _ => "fmod",
+                };
+                self.emitter.emit(&format!["{}(", function]);
+                self.expression(left);
+                self.emitter.emit(", ");
+                self.expression(right);
+                self.emitter.emit(")");
+                return;
+            }
+            (BinaryOp::Divide, CType::Float) => "divide_float",
+            (BinaryOp::Divide, _) => "divide_double",
+            (_, CType::Float) => "remainder_float",
+            (_, _) => "remainder_double",
+        };
+        let line = exprs[expression].span.line;
+        self.use_helper(helper);
+        self.emitter.emit(&format!["haneul_{}(", helper]);
+        self.expression(left);
+        self.emitter.emit(", ");
+        self.expression(right);
+        self.emitter.emit(&format![", {})", line]);
+    }
+
+    fn call(&mut self, name: &str, arguments: &[ExprId]) {
+        let builtin = self
+            .builtins
+            .get(name)
+            .expect("the parser only accepts known builtins");
+        let Some(implementation) = builtin.implementation(Platform::C) else {
+            self.unsupported.get_or_insert_with(|| name.to_owned());
+            return;
+        };
+        self.use_helper(implementation.helper);
+
+        self.emitter.emit(&format!["{}(", implementation.function]);
+        for (i, argument) in arguments.iter().enumerate() {
+            if i > 0 {
+                self.emitter.emit(", ");
+            }
+            self.expression(*argument);
+        }
+        self.emitter.emit(")");
+    }
+
+    // the helper is emitted before main, once
+    fn use_helper(&mut self, helper: &'static str) {
+        if !self.helpers_used.contains(&helper) {
+            self.helpers_used.push(helper);
+        }
+    }
+
+    // the first LET or INPUT of a variable declares it at the top of main
+    fn declare(&mut self, variable: Symbol) {
+        if self.declared.insert(variable) {
+            self.emitter.header_line(&format![
+                "{} {};",
+                c_type(ctype::variable(self.program, variable)),
+                self.name(variable)
+            ]);
+        }
+    }
+
+    // a name c already uses gets a _ after it, no name in a program can end in one
+    fn name(&self, symbol: Symbol) -> String {
+        let name = self.program.names.resolve(symbol);
+        if RESERVED.contains(&name) {
+            format!["{}_", name]
+        } else {
+            name.to_owned()
+        }
+    }
+}
+
+// what PRINT and INPUT do, in functions instead of a printf or scanf at every one of them. the
+// other backends have a runtime that does the same
+const RUNTIME: &[Helper] = &[
+    // the fewest digits that read back as the same float, without an exponent: the first of 1
+    // to 9 digits in the e notation that strtof reads back as the float, written out with its
+    // zeros. a precision of 0 writes no digits of the 0 in %.*d
+    Helper {
+        platform: Platform::C,
+        name: "print_number",
+        code: "#include <stdlib.h>
+#include <string.h>
+static void haneul_print_number(float value) {
+    char text[32];
+    int precision, exponent, sign;
+    if (value != value) {
+        puts(\"nan\");
+        return;
+    }
+    if (value > 3.40282347e38f || value < -3.40282347e38f) {
+        puts(value > 0 ? \"inf\" : \"-inf\");
+        return;
+    }
+    for (precision = 1;; precision++) {
+        snprintf(text, sizeof text, \"%.*e\", precision - 1, value);
+        if (precision == 9 || strtof(text, NULL) == value) {
+            break;
+        }
+    }
+    exponent = atoi(strchr(text, 'e') + 1);
+    if (exponent < precision - 1) {
+        printf(\"%.*f\\n\", precision - 1 - exponent, value);
+        return;
+    }
+    sign = text[0] == '-';
+    printf(\"%.*s%.*s%.*d\\n\", sign + 1, text, precision - 1, text + sign + 2,
+        exponent - precision + 1, 0);
+}",
+        libraries: &[],
+    },
+    Helper {
+        platform: Platform::C,
+        name: "print_int",
+        code: "static void haneul_print_int(int value) {
+    printf(\"%d\\n\", value);
+}",
+        libraries: &[],
+    },
+    Helper {
+        platform: Platform::C,
+        name: "print_long",
+        code: "static void haneul_print_long(long value) {
+    printf(\"%ld\\n\", value);
+}",
+        libraries: &[],
+    },
+    Helper {
+        platform: Platform::C,
+        name: "print_string",
+        code: "static void haneul_print_string(const char *string) {
+    puts(string);
+}",
+        libraries: &[],
+    },
+    // the smallest int divided by -1 doesn't fit, it wraps around like in the other backends
+    Helper {
+        platform: Platform::C,
+        name: "divide_int",
+        code: "#include <stdlib.h>
+static int haneul_divide_int(int left, int right, int line) {
+    if (right == 0) {
+        fflush(stdout);
+        fprintf(stderr, \"Error: an int is divided by 0 on line %d\\n\", line);
+        exit(1);
+    }
+    return right == -1 ? (int)(0u - (unsigned)left) : left / right;
+}",
+        libraries: &[],
+    },
+    Helper {
+        platform: Platform::C,
+        name: "divide_long",
+        code: "#include <stdlib.h>
+static long haneul_divide_long(long left, long right, int line) {
+    if (right == 0) {
+        fflush(stdout);
+        fprintf(stderr, \"Error: an int is divided by 0 on line %d\\n\", line);
+        exit(1);
+    }
+    return right == -1 ? (long)(0ul - (unsigned long)left) : left / right;
+}",
+        libraries: &[],
+    },
+    // the sign of the left side, like % in c. the smallest int % -1 is 0
+    Helper {
+        platform: Platform::C,
+        name: "remainder_int",
+        code: "#include <stdlib.h>
+static int haneul_remainder_int(int left, int right, int line) {
+    if (right == 0) {
+        fflush(stdout);
+        fprintf(stderr, \"Error: an int is divided by 0 on line %d\\n\", line);
+        exit(1);
+    }
+    return right == -1 ? 0 : left % right;
+}",
+        libraries: &[],
+    },
+    Helper {
+        platform: Platform::C,
+        name: "remainder_long",
+        code: "#include <stdlib.h>
+static long haneul_remainder_long(long left, long right, int line) {
+    if (right == 0) {
+        fflush(stdout);
+        fprintf(stderr, \"Error: an int is divided by 0 on line %d\\n\", line);
+        exit(1);
+    }
+    return right == -1 ? 0 : left % right;
+}",
+        libraries: &[],
+    },
+    // fmod and fmodf for the % of floats
+    Helper {
+        platform: Platform::C,
+        name: "math",
+        code: "#include <math.h>",
+        libraries: &["m"],
+    },
+    // only with --float-division=error
+    Helper {
+        platform: Platform::C,
+        name: "divide_float",
+        code: "#include <stdlib.h>
+static float haneul_divide_float(float left, float right, int line) {
+    if (right == 0) {
+        fflush(stdout);
+        fprintf(stderr, \"Error: a float is divided by 0 on line %d\\n\", line);
+        exit(1);
+    }
+    return left / right;
+}",
+        libraries: &[],
+    },
+    Helper {
+        platform: Platform::C,
+        name: "divide_double",
+        code: "#include <stdlib.h>
+static double haneul_divide_double(double left, double right, int line) {
+    if (right == 0) {
+        fflush(stdout);
+        fprintf(stderr, \"Error: a float is divided by 0 on line %d\\n\", line);
+        exit(1);
+    }
+    return left / right;
+}",
+        libraries: &[],
+    },
+    Helper {
+        platform: Platform::C,
+        name: "remainder_float",
+        code: "#include <math.h>
+#include <stdlib.h>
+static float haneul_remainder_float(float left, float right, int line) {
+    if (right == 0) {
+        fflush(stdout);
+        fprintf(stderr, \"Error: a float is divided by 0 on line %d\\n\", line);
+        exit(1);
+    }
+    return fmodf(left, right);
+}",
+        libraries: &["m"],
+    },
+    Helper {
+        platform: Platform::C,
+        name: "remainder_double",
+        code: "#include <math.h>
+#include <stdlib.h>
+static double haneul_remainder_double(double left, double right, int line) {
+    if (right == 0) {
+        fflush(stdout);
+        fprintf(stderr, \"Error: a float is divided by 0 on line %d\\n\", line);
+        exit(1);
+    }
+    return fmod(left, right);
+}",
+        libraries: &["m"],
+    },
+    // like scanf(\"%f\"): a word that doesn't start with a number gives 0 and is skipped, at the
+    // end of the input the variable keeps the value it had
+    Helper {
+        platform: Platform::C,
+        name: "input",
+        code: "static void haneul_input(float *variable) {
+    if (scanf(\"%f\", variable) == 0) {
+        *variable = 0;
+        scanf(\"%*s\");
+    }
+}",
+        libraries: &[],
+    },
+];
+
+fn c_type(ty: CType) -> &'static str {
+    match ty {
+        CType::Int => "int",
+        CType::Long => "long",
+        CType::Float => "float",
+        CType::Double => "double",
+    }
+}
+
+fn string_literal(string: &str) -> String {
+    format!["\"{}\"", string.replace('\\', "\\\\").replace('"', "\\\"")]
+}
+
+// the c99 keywords, and the names the code in main uses. a variable with one of these names
+// would be a syntax error or would hide the function
+const RESERVED: &[&str] = &[
+    "auto",
+    "break",
+    "case",
+    "char",
+    "const",
+    "continue",
+    "default",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extern",
+    "float",
+    "for",
+    "goto",
+    "if",
+    "inline",
+    "int",
+    "long",
+    "register",
+    "restrict",
+    "return",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "struct",
+    "switch",
+    "typedef",
+    "union",
+    "unsigned",
+    "void",
+    "volatile",
+    "while",
+    "_Bool",
+    "_Complex",
+    "_Imaginary",
+    "main",
+    "printf",
+    "scanf",
+    "stdin",
+    "stdout",
+    "stderr",
+    "EOF",
+    "NULL",
+];
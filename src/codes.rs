@@ -0,0 +1,277 @@
+// every diagnostic has a code that never changes meaning, so it can be looked up with
+// `haneul explain <code>` and searched for online even when the message text changes
+
+// lexing
+pub const UNKNOWN_CHARACTER: &str = "E0001";
+pub const EXPECTED_NOT_EQ: &str = "E0002";
+pub const UNTERMINATED_STRING: &str = "E0012";
+pub const NUMBER_WITHOUT_DIGITS: &str = "E0013";
+pub const MALFORMED_NUMBER: &str = "E0014";
+pub const NUMBER_TOO_LARGE: &str = "E0016";
+// parsing
+pub const UNEXPECTED_TOKEN: &str = "E0003";
+pub const EXPECTED_EXPRESSION: &str = "E0004";
+pub const EXPECTED_COMPARISON: &str = "E0005";
+pub const MISSING_FEATURE: &str = "E0009";
+pub const INVALID_STATEMENT: &str = "E0010";
+pub const UNKNOWN_BUILTIN: &str = "E0011";
+pub const NESTED_TOO_DEEPLY: &str = "E0015";
+pub const NOT_ALLOWED: &str = "E0020";
+// analysis
+pub const UNASSIGNED_VARIABLE: &str = "E0006";
+pub const MAYBE_UNASSIGNED_VARIABLE: &str = "E0017";
+pub const DUPLICATE_LABEL: &str = "E0007";
+pub const UNDECLARED_LABEL: &str = "E0008";
+pub const COMPARISON_AS_NUMBER: &str = "E0018";
+pub const NUMBER_AS_CONDITION: &str = "E0019";
+// warnings
+pub const UNUSED_VARIABLE: &str = "W0001";
+pub const UNUSED_LABEL: &str = "W0002";
+pub const UNREACHABLE_STATEMENT: &str = "W0003";
+
+pub fn explain(code: &str) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|(explained, _)| explained.eq_ignore_ascii_case(code))
+        .map(|(_, explanation)| *explanation)
+}
+
+const EXPLANATIONS: &[(&str, &str)] = &[
+    (
+        UNKNOWN_CHARACTER,
+        r#"A character was found that isn't part of the language.
+
+Only letters, digits, the operators + - * / = == != < <= > >=, parentheses,
+commas, strings between double quotes and comments starting with # can be used.
+
+    LET price = 10$     # error: `$` isn't part of haneul
+    LET price = 10      # ok"#,
+    ),
+    (
+        EXPECTED_NOT_EQ,
+        r#"A `!` was found that isn't followed by `=`.
+
+haneul has no `!` operator on its own, it is only used in `!=` (not equal).
+
+    IF a ! 3 THEN       # error
+    IF a != 3 THEN      # ok"#,
+    ),
+    (
+        UNEXPECTED_TOKEN,
+        r#"The parser expected a specific token, like a newline after a statement
+or THEN after the comparison of an IF, but found something else.
+
+    IF a > 3            # error: THEN is missing
+        PRINT a
+    ENDIF
+
+    IF a > 3 THEN       # ok
+        PRINT a
+    ENDIF"#,
+    ),
+    (
+        EXPECTED_EXPRESSION,
+        r#"A value was expected: a number, a variable or a function call.
+
+    LET a =             # error: there is nothing after the =
+    LET a = 5           # ok
+    LET b = a * 2       # ok"#,
+    ),
+    (
+        EXPECTED_COMPARISON,
+        r#"IF and WHILE need a comparison to decide what to do, using one of
+== != < <= > >=.
+
+    WHILE a REPEAT      # error
+    WHILE a > 0 REPEAT  # ok"#,
+    ),
+    (
+        UNASSIGNED_VARIABLE,
+        r#"A variable is used before it got a value.
+
+Variables are created by giving them a value with LET or INPUT. Using one
+before that would mean using a value that doesn't exist yet.
+
+    PRINT total         # error: total has no value yet
+
+    LET total = 0       # ok
+    PRINT total"#,
+    ),
+    (
+        MAYBE_UNASSIGNED_VARIABLE,
+        r#"A variable is used at a point where it only has a value on some of the
+ways the program can get there, for example when it is only given a value
+inside an IF, or only after the GOTO that jumps back to this line.
+
+    INPUT score
+    IF score > 50 THEN
+        LET grade = 1
+    ENDIF
+    PRINT grade         # error: grade has no value when score <= 50
+
+    LET grade = 0       # ok
+    IF score > 50 THEN
+        LET grade = 1
+    ENDIF
+    PRINT grade"#,
+    ),
+    (
+        DUPLICATE_LABEL,
+        r#"The same label was declared twice, so a GOTO to it would be ambiguous.
+
+    LABEL again
+    LABEL again         # error: use a different name"#,
+    ),
+    (
+        UNDECLARED_LABEL,
+        r#"A GOTO jumps to a label that doesn't exist anywhere in the program.
+
+    GOTO finish         # error: there is no LABEL finish
+
+    GOTO finish         # ok
+    LABEL finish"#,
+    ),
+    (
+        COMPARISON_AS_NUMBER,
+        r#"A comparison is used where a number is needed. A comparison is only true
+or false, it can only be the condition of an IF or a WHILE.
+
+This nearly always comes from a chain like `a < b < c`, which compares the
+result of `a < b` with c instead of checking that b is between a and c.
+Use two IFs instead:
+
+    IF 0 < a < 10 THEN      # error
+        PRINT a
+    ENDIF
+
+    IF 0 < a THEN           # ok
+        IF a < 10 THEN
+            PRINT a
+        ENDIF
+    ENDIF"#,
+    ),
+    (
+        NUMBER_AS_CONDITION,
+        r#"The condition of an IF or a WHILE is a number instead of a comparison.
+Compare the number with something to decide what to do.
+
+    IF a THEN           # error
+    IF a != 0 THEN      # ok"#,
+    ),
+    (
+        MISSING_FEATURE,
+        r#"A builtin was used that needs haneul to be built with an optional cargo
+feature, like the graphics statements.
+
+Rebuild haneul with the feature turned on:
+
+    cargo build --release --features graphics"#,
+    ),
+    (
+        INVALID_STATEMENT,
+        r#"A line doesn't start with a statement.
+
+Every line starts with a keyword like PRINT, LET, IF, WHILE, GOTO, LABEL or
+INPUT, or with the name of a builtin statement like BEEP.
+
+    a = 5               # error: assignments need LET
+    LET a = 5           # ok"#,
+    ),
+    (
+        UNKNOWN_BUILTIN,
+        r#"A builtin was called that isn't registered, or a builtin statement was
+used as a function (or the other way around).
+
+    LET a = BEEP()      # error: BEEP is a statement
+    BEEP                # ok"#,
+    ),
+    (
+        NESTED_TOO_DEEPLY,
+        r#"IF and WHILE blocks or function calls are nested deeper than the limit,
+which is 256 levels unless it is changed with --max-depth=N.
+
+Programs this deep are almost always generated. Flatten the nesting, for
+example by jumping out with GOTO, or raise the limit."#,
+    ),
+    (
+        NOT_ALLOWED,
+        r#"A builtin was used that reaches outside of the program, and the program
+that runs it doesn't allow that. A server that runs programs it doesn't trust
+can forbid the builtins that use files (fs), the network (net) or other
+programs (exec), like FORWARD, which saves turtle.svg.
+
+The program has to do without the builtin where it runs:
+
+    FORWARD 10      # error when files aren't allowed"#,
+    ),
+    (
+        UNTERMINATED_STRING,
+        r#"A string was started with `"` but the line ended before the closing `"`.
+Strings can't span multiple lines.
+
+    PRINT "hello        # error: the closing quote is missing
+    PRINT "hello"       # ok"#,
+    ),
+    (
+        NUMBER_WITHOUT_DIGITS,
+        r#"A `.` was found on its own. A number needs digits on at least one side
+of the dot.
+
+    LET half = .        # error
+    LET half = .5       # ok
+    LET half = 0.5      # ok"#,
+    ),
+    (
+        MALFORMED_NUMBER,
+        r#"A number is directly followed by letters or a second dot. Names can't
+start with a digit, and a number has at most one dot.
+
+    LET a = 2b          # error: did you mean 2 * b?
+    LET a = 2 * b       # ok
+    LET v = 1.2.3       # error"#,
+    ),
+    (
+        NUMBER_TOO_LARGE,
+        r#"A number is too large to be stored. Numbers with a dot are 32 bit floats,
+so they go up to about 3.4e38 (a 3 with 38 digits after it). Bigger ones would
+silently become infinity. Whole numbers are 64 bit ints, they go up to
+9223372036854775807. Bigger ones would silently wrap around or lose digits.
+
+    LET big = 1000000000000000000000000000000000000000.0   # error
+    LET big = 1000000000000000000000000000000000000.0      # ok
+    LET big = 99999999999999999999                         # error
+    LET big = 9999999999999999999.0                        # ok, a float"#,
+    ),
+    (
+        UNUSED_VARIABLE,
+        r#"A variable gets a value but is never used afterwards.
+
+This is often a typo in the name where the variable is read.
+
+    LET count = 0
+    LET cuont = count + 1   # warning: cuont is never read"#,
+    ),
+    (
+        UNUSED_LABEL,
+        r#"A label is declared, but no GOTO ever jumps to it.
+
+    LABEL start         # warning: nothing jumps to start
+    PRINT "hello""#,
+    ),
+    (
+        UNREACHABLE_STATEMENT,
+        r#"A statement can never run, because no way through the program gets to it:
+a GOTO always jumps away before it, or it is inside an IF whose condition is
+never true. Put a LABEL in front of it if something should jump there.
+
+Statements that can never run are left out of the compiled program.
+
+    GOTO finish
+    PRINT "skipped"     # warning: this never runs
+    LABEL finish
+
+    IF 1 > 2 THEN
+        PRINT "never"   # warning: 1 is never more than 2
+    ENDIF"#,
+    ),
+];
@@ -0,0 +1,66 @@
+// haneul.toml, read from the directory of the program that is compiled. only the small part
+// of toml that's needed is understood: [sections], `key = "value"` and # comments
+//
+// [lints]
+// warnings = "deny"
+// W0002 = "allow"
+
+use std::{fs, path::Path};
+
+pub const FILE_NAME: &str = "haneul.toml";
+
+#[derive(Debug, Default)]
+pub struct Config {
+    // (lint, level) in the order they're written
+    pub lints: Vec<(String, String)>,
+}
+
+impl Config {
+    // a missing config file is the same as an empty one
+    pub fn load(directory: &Path) -> Result<Self, String> {
+        let path = directory.join(FILE_NAME);
+        match fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text).map_err(|e| format!["{}: {}", path.display(), e]),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut config = Self::default();
+        let mut section = String::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = match line.find('#') {
+                Some(comment) => &line[..comment],
+                None => line,
+            }
+            .trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_owned();
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!["line {}: expected `key = \"value\"`", i + 1])?;
+            let (key, value) = (key.trim(), value.trim());
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .ok_or_else(|| {
+                    format!["line {}: the value of {} should be a string", i + 1, key]
+                })?;
+
+            // unknown sections are left alone so newer config files still work
+            if section == "lints" {
+                config.lints.push((key.to_owned(), value.to_owned()));
+            }
+        }
+
+        Ok(config)
+    }
+}
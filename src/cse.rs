@@ -0,0 +1,226 @@
+// common subexpression elimination: when the same calculation is done more than once in a
+// basic block (statements that always run one after the other), it is done once into a
+// temporary variable and the other places use that
+//
+//     LET x = a * b + 1            LET haneul_cse0 = a * b
+//     LET y = a * b - 1     ->     LET x = haneul_cse0 + 1
+//                                  LET y = haneul_cse0 - 1
+//
+// a calculation stops being the same when one of its variables gets a new value. calls are
+// never shared, INKEY() gives something else every time. a temporary has the type of its
+// calculation (see ctype::infer), but a variable is never a double so one with a number with a
+// dot in it isn't shared. the temporaries can't clash with a variable, a name can't have a _
+// after its first letter
+
+use std::{cmp::Reverse, collections::HashMap};
+
+use crate::{
+    ast::{Expr, ExprId, ExprKind, Exprs, Program, Statement, StatementKind},
+    ctype,
+    intern::Symbol,
+};
+
+pub fn eliminate(program: &mut Program) {
+    let statements = std::mem::take(&mut program.statements);
+    let mut eliminator = Eliminator {
+        program,
+        temporaries: 0,
+    };
+    let statements = eliminator.statements(statements);
+    program.statements = statements;
+}
+
+struct Eliminator<'a> {
+    program: &'a mut Program,
+    temporaries: usize,
+}
+
+// the calculations done so far in the block, by what they calculate
+#[derive(Default)]
+struct Block {
+    available: HashMap<String, Available>,
+    // the LETs of the temporaries: the statement they go in front of, the first place the
+    // calculation was done, and the temporary with the calculation
+    lets: Vec<(usize, ExprId, Symbol, ExprId)>,
+}
+
+struct Available {
+    // where it was done first, and the statement that is in
+    first: ExprId,
+    statement: usize,
+    reads: Vec<Symbol>,
+    temporary: Option<Symbol>,
+}
+
+impl Eliminator<'_> {
+    fn statements(&mut self, statements: Vec<Statement>) -> Vec<Statement> {
+        let mut eliminated = Vec::with_capacity(statements.len());
+        let mut block = Block::default();
+        for mut statement in statements {
+            let index = eliminated.len();
+            match &mut statement.kind {
+                StatementKind::Print(value) => self.expression(*value, index, &mut block),
+                StatementKind::Call { arguments, .. } => {
+                    for argument in arguments.iter() {
+                        self.expression(*argument, index, &mut block);
+                    }
+                }
+                StatementKind::Let { variable, value } => {
+                    self.expression(*value, index, &mut block);
+                    block.assign(*variable);
+                }
+                StatementKind::Input(variable) => block.assign(*variable),
+                StatementKind::PrintString(_) => {}
+                // the condition of an IF is the end of the block, the body is a block of its own
+                StatementKind::If { condition, body } => {
+                    self.expression(*condition, index, &mut block);
+                    *body = self.statements(std::mem::take(body));
+                }
+                StatementKind::While { body, .. } => {
+                    *body = self.statements(std::mem::take(body));
+                }
+                StatementKind::Label(_) | StatementKind::Goto(_) => {}
+            }
+
+            // a block ends where something can jump to, or where it jumps away. a LABEL
+            // starts the next one, so it is the only one that ends the block before it
+            if matches!(statement.kind, StatementKind::Label(_)) {
+                insert_lets(&mut eliminated, std::mem::take(&mut block));
+            }
+            let ends_block = matches!(
+                statement.kind,
+                StatementKind::If { .. } | StatementKind::While { .. } | StatementKind::Goto(_)
+            );
+            eliminated.push(statement);
+            if ends_block {
+                insert_lets(&mut eliminated, std::mem::take(&mut block));
+            }
+        }
+        insert_lets(&mut eliminated, block);
+
+        eliminated
+    }
+
+    // the biggest calculations are looked at first, when one is shared the parts in it are too
+    fn expression(&mut self, expression: ExprId, statement: usize, block: &mut Block) {
+        let exprs = &self.program.exprs;
+        if let (ExprKind::Binary { .. }, Some(key)) =
+            (&exprs[expression].kind, key(exprs, expression))
+        {
+            if let Some(available) = block.available.get_mut(&key) {
+                let temporary = match available.temporary {
+                    Some(temporary) => temporary,
+                    None => {
+                        let name = format!["haneul_cse{}", self.temporaries];
+                        let temporary = self.program.names.intern(&name);
+                        self.temporaries += 1;
+                        let ty = ctype::of(self.program, available.first);
+                        self.program.types.insert(temporary, ty);
+                        // the first one moves into the LET, its place gets the temporary too
+                        let exprs = &mut self.program.exprs;
+                        let moved = exprs.add(exprs[available.first].clone());
+                        replace(exprs, available.first, temporary);
+                        block
+                            .lets
+                            .push((available.statement, available.first, temporary, moved));
+                        available.temporary = Some(temporary);
+                        temporary
+                    }
+                };
+                replace(&mut self.program.exprs, expression, temporary);
+                return;
+            }
+
+            let reads = reads(exprs, expression);
+            // only numbers is left to fold.rs
+            if !reads.is_empty() {
+                block.available.insert(
+                    key,
+                    Available {
+                        first: expression,
+                        statement,
+                        reads,
+                        temporary: None,
+                    },
+                );
+            }
+        }
+
+        let children = match &self.program.exprs[expression].kind {
+            ExprKind::Binary { left, right, .. } => vec![*left, *right],
+            ExprKind::Unary { operand, .. } => vec![*operand],
+            ExprKind::Call { arguments, .. } => arguments.clone(),
+            ExprKind::Number(_) | ExprKind::Variable(_) => vec![],
+        };
+        for child in children {
+            self.expression(child, statement, block);
+        }
+    }
+}
+
+impl Block {
+    // the calculations that read the variable aren't the same anymore
+    fn assign(&mut self, variable: Symbol) {
+        self.available
+            .retain(|_, available| !available.reads.contains(&variable));
+    }
+}
+
+// from the last statement to the first, so the places of the ones before don't move.
+// a temporary for a part of another one has to come first, the parser makes the parts of
+// an expression before the expression so they have lower ids
+fn insert_lets(statements: &mut Vec<Statement>, mut block: Block) {
+    block
+        .lets
+        .sort_by_key(|(statement, first, _, _)| Reverse((*statement, *first)));
+    for (index, _, variable, value) in block.lets {
+        let span = statements[index].span;
+        statements.insert(
+            index,
+            Statement {
+                kind: StatementKind::Let { variable, value },
+                span,
+            },
+        );
+    }
+}
+
+fn replace(exprs: &mut Exprs, expression: ExprId, temporary: Symbol) {
+    exprs[expression] = Expr {
+        kind: ExprKind::Variable(temporary),
+        span: exprs[expression].span,
+    };
+}
+
+// what an expression calculates, the same text for the same calculation. None when it
+// calls a builtin or has a number with a dot, which is a double in c
+fn key(exprs: &Exprs, expression: ExprId) -> Option<String> {
+    match &exprs[expression].kind {
+        ExprKind::Number(number) if number.contains('.') => None,
+        ExprKind::Number(number) => Some(number.clone()),
+        ExprKind::Variable(variable) => Some(format!["{:?}", variable]),
+        ExprKind::Call { .. } => None,
+        ExprKind::Unary { op, operand } => {
+            Some(format!["({} {})", op.text(), key(exprs, *operand)?])
+        }
+        ExprKind::Binary { op, left, right } => Some(format![
+            "({} {} {})",
+            op.text(),
+            key(exprs, *left)?,
+            key(exprs, *right)?
+        ]),
+    }
+}
+
+fn reads(exprs: &Exprs, expression: ExprId) -> Vec<Symbol> {
+    match &exprs[expression].kind {
+        ExprKind::Variable(variable) => vec![*variable],
+        ExprKind::Unary { operand, .. } => reads(exprs, *operand),
+        ExprKind::Binary { left, right, .. } => {
+            let mut variables = reads(exprs, *left);
+            variables.extend(reads(exprs, *right));
+            variables
+        }
+        ExprKind::Number(_) | ExprKind::Call { .. } => vec![],
+    }
+}
@@ -0,0 +1,136 @@
+// the type an expression has in the c code codegen.rs makes. the backends that don't make c
+// follow the same types, so a program does the same everywhere: `7 / 2` is 3 and `7 / 2.0`
+// is 3.5, like in c
+//
+// a variable has a type too, worked out from the values it gets (see infer). an int stays an
+// int: a counter counts exactly, `i % 2` is what's left of an int division and `i == 10`
+// compares ints. a calculation with an int and a float is a float one, like in c
+
+use crate::{
+    ast::{ExprId, ExprKind, Exprs, Program, Statement, StatementKind},
+    intern::Symbol,
+    prelude::*,
+};
+
+// in the order c converts them in: an int and a float calculate as floats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CType {
+    Int,
+    // an int that doesn't fit in an int is a long in c
+    Long,
+    Float,
+    Double,
+}
+
+impl CType {
+    pub fn is_integer(self) -> bool {
+        matches!(self, CType::Int | CType::Long)
+    }
+}
+
+// what dividing a float by 0 does, picked with --float-division. dividing an int by 0 always
+// stops the program with "an int is divided by 0 on line N", in every backend. a % by 0 is a
+// division by 0 too, of floats it gives nan with Ieee
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FloatDivision {
+    // inf, -inf or nan, like ieee 754 says
+    #[default]
+    Ieee,
+    // stops the program like dividing an int by 0
+    Error,
+}
+
+// the error of a division by 0, without the line
+pub fn division_by_zero(ctype: CType) -> &'static str {
+    match ctype.is_integer() {
+        true => "an int is divided by 0",
+        false => "a float is divided by 0",
+    }
+}
+
+pub fn number(text: &str) -> CType {
+    if text.contains('.') {
+        CType::Double
+    } else if text.parse::<i32>().is_ok() {
+        CType::Int
+    } else {
+        CType::Long
+    }
+}
+
+// builtins are floats, a comparison is an int
+pub fn of(program: &Program, expression: ExprId) -> CType {
+    expression_type(&program.exprs, &program.types, expression)
+}
+
+// the type both sides of a calculation or a comparison are converted to
+pub fn common(program: &Program, left: ExprId, right: ExprId) -> CType {
+    of(program, left).max(of(program, right))
+}
+
+// a variable without a type was never given a value, the analysis doesn't let a program use
+// one. as a float it's what it was before variables had types
+pub fn variable(program: &Program, variable: Symbol) -> CType {
+    program
+        .types
+        .get(&variable)
+        .copied()
+        .unwrap_or(CType::Float)
+}
+
+// the type of every variable: an int when every LET of it gives an int, a long when one gives a
+// long, and a float when one gives a float or a double, or it has an INPUT. a variable is never
+// a double, they were all floats before. a LET of a variable can depend on another one, or on
+// itself like `LET i = i + 1`, so they all start as ints and go up until nothing changes
+pub fn infer(program: &mut Program) {
+    let mut assignments = vec![];
+    assignments_in(&program.statements, &mut assignments);
+    let mut types = HashMap::new();
+    for (variable, _) in &assignments {
+        types.insert(*variable, CType::Int);
+    }
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (variable, value) in &assignments {
+            let ty = match value {
+                Some(value) => expression_type(&program.exprs, &types, *value).min(CType::Float),
+                None => CType::Float,
+            };
+            if ty > types[variable] {
+                types.insert(*variable, ty);
+                changed = true;
+            }
+        }
+    }
+
+    program.types = types;
+}
+
+fn expression_type(exprs: &Exprs, types: &HashMap<Symbol, CType>, expression: ExprId) -> CType {
+    match &exprs[expression].kind {
+        ExprKind::Number(text) => number(text),
+        ExprKind::Variable(variable) => types.get(variable).copied().unwrap_or(CType::Float),
+        ExprKind::Call { .. } => CType::Float,
+        ExprKind::Unary { operand, .. } => expression_type(exprs, types, *operand),
+        ExprKind::Binary { op, .. } if op.is_comparison() => CType::Int,
+        ExprKind::Binary { left, right, .. } => {
+            expression_type(exprs, types, *left).max(expression_type(exprs, types, *right))
+        }
+    }
+}
+
+// the value of every LET, an INPUT has None
+fn assignments_in(statements: &[Statement], assignments: &mut Vec<(Symbol, Option<ExprId>)>) {
+    for statement in statements {
+        match &statement.kind {
+            StatementKind::Let { variable, value } => assignments.push((*variable, Some(*value))),
+            StatementKind::Input(variable) => assignments.push((*variable, None)),
+            StatementKind::If { body, .. } | StatementKind::While { body, .. } => {
+                assignments_in(body, assignments)
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,81 @@
+// dead code: statements no way through the program gets to, because a GOTO always jumps away
+// before them or they're inside an IF whose condition is never true. they're found with the
+// control flow graph, reported as warnings and left out of the c code
+
+use crate::{
+    ast::{Program, Statement, StatementKind},
+    cfg::Cfg,
+    codes,
+    diagnostics::Diagnostic,
+    fold,
+    prelude::*,
+};
+
+// one warning for every run of dead statements, at the first one
+pub fn warnings(program: &Program) -> Vec<Diagnostic> {
+    let dead = dead(program);
+    let mut warnings = vec![];
+    report(&program.statements, &dead, &mut 0, false, &mut warnings);
+
+    warnings
+}
+
+pub fn eliminate(program: &mut Program) {
+    let dead = dead(program);
+    eliminate_in(&mut program.statements, &dead, &mut 0);
+}
+
+// for every statement, in the order of Cfg::starts
+fn dead(program: &Program) -> Vec<bool> {
+    let cfg = Cfg::new(program);
+    let reachable =
+        cfg.reachable_with(|condition| fold::known_condition(&program.exprs, condition));
+
+    cfg.starts()
+        .iter()
+        .map(|start| start.is_none_or(|block| !reachable[block.index()]))
+        .collect()
+}
+
+// the body of a dead IF or WHILE continues the run of the statement itself
+fn report(
+    statements: &[Statement],
+    dead: &[bool],
+    next: &mut usize,
+    mut previous_dead: bool,
+    warnings: &mut Vec<Diagnostic>,
+) {
+    for statement in statements {
+        let is_dead = dead[*next];
+        *next += 1;
+        if is_dead && !previous_dead {
+            warnings.push(Diagnostic::warning(
+                codes::UNREACHABLE_STATEMENT,
+                vec![],
+                statement.span,
+            ));
+        }
+        previous_dead = is_dead;
+
+        // a LABEL inside a dead block can still be jumped to
+        if let StatementKind::If { body, .. } | StatementKind::While { body, .. } = &statement.kind
+        {
+            report(body, dead, next, is_dead, warnings);
+        }
+    }
+}
+
+// an IF or a WHILE that is dead itself stays when something in it can still run
+fn eliminate_in(statements: &mut Vec<Statement>, dead: &[bool], next: &mut usize) {
+    statements.retain_mut(|statement| {
+        let is_dead = dead[*next];
+        *next += 1;
+        match &mut statement.kind {
+            StatementKind::If { body, .. } | StatementKind::While { body, .. } => {
+                eliminate_in(body, dead, next);
+                !is_dead || !body.is_empty()
+            }
+            _ => !is_dead,
+        }
+    });
+}
@@ -0,0 +1,295 @@
+use core::fmt::Write;
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use crate::{
+    codes,
+    i18n::{self, Lang, Text},
+    parser::ParseError,
+    prelude::*,
+    source::SourceMap,
+    token::Span,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageFormat {
+    Human,
+    Json,
+}
+
+// what to do with a warning, set with -A, -W and -D or in the [lints] section of haneul.toml
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl Level {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "allow" => Some(Level::Allow),
+            "warn" => Some(Level::Warn),
+            "deny" => Some(Level::Deny),
+            _ => None,
+        }
+    }
+}
+
+// a lint is the code of a warning, or `warnings` for all of them.
+// later settings win, so the command line can override the config file
+#[derive(Debug, Default, Clone)]
+pub struct Lints {
+    levels: Vec<(String, Level)>,
+}
+
+impl Lints {
+    pub fn set(&mut self, lint: &str, level: Level) -> Result<(), String> {
+        let is_warning = lint.starts_with(['W', 'w']) && codes::explain(lint).is_some();
+        if !is_warning && lint != "warnings" {
+            return Err(format!["unknown lint: {}", lint]);
+        }
+        self.levels.push((lint.to_uppercase(), level));
+
+        Ok(())
+    }
+
+    pub fn level(&self, code: &str) -> Level {
+        self.levels
+            .iter()
+            .rev()
+            .find(|(lint, _)| lint == code || lint == "WARNINGS")
+            .map_or(Level::Warn, |(_, level)| *level)
+    }
+
+    // splits the warnings into the ones that are still warnings and the ones that became errors
+    pub fn apply(&self, warnings: &[Diagnostic]) -> (Vec<Diagnostic>, Vec<Diagnostic>) {
+        let mut kept = vec![];
+        let mut denied = vec![];
+        for warning in warnings {
+            match self.level(warning.code) {
+                Level::Allow => {}
+                Level::Warn => kept.push(warning.clone()),
+                Level::Deny => denied.push(Diagnostic {
+                    severity: Severity::Error,
+                    ..warning.clone()
+                }),
+            }
+        }
+
+        (kept, denied)
+    }
+}
+
+// --terse is a single line per diagnostic, --teach writes out full sentences with an example
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verbosity {
+    Terse,
+    Normal,
+    Teach,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    // the text of a diagnostic is only made when it is shown, see i18n.rs
+    pub args: Vec<String>,
+    pub span: Span,
+    // a "did you mean"
+    pub suggestion: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(code: &'static str, args: Vec<String>, span: Span) -> Self {
+        Self {
+            severity: Severity::Error,
+            code,
+            args,
+            span,
+            suggestion: None,
+        }
+    }
+
+    pub fn warning(code: &'static str, args: Vec<String>, span: Span) -> Self {
+        Self {
+            severity: Severity::Warning,
+            code,
+            args,
+            span,
+            suggestion: None,
+        }
+    }
+
+    pub fn message(&self, lang: Lang) -> String {
+        i18n::message(lang, self.code, &self.args)
+    }
+
+    pub fn hint(&self, lang: Lang) -> Option<String> {
+        i18n::hint(lang, self.code, &self.args, self.suggestion.as_deref())
+    }
+
+    // {"code":"E0006","severity":"error","file":"scores.han",
+    //  "span":{"line":5,"column":11,"length":1,"offset":42,"byte_length":1},"message":"...",
+    //  "suggestion":"score","hint":"..."}
+    //
+    // "suggestion" is what to write instead, like the name a typo was meant to be, or null.
+    // "hint" is the hint the terminal shows, or null when the diagnostic has none
+    #[cfg(feature = "std")]
+    pub fn to_json(&self, sources: &SourceMap, lang: Lang) -> String {
+        #[derive(Serialize)]
+        struct Json<'a> {
+            code: &'a str,
+            severity: Severity,
+            file: &'a str,
+            span: Span,
+            message: String,
+            suggestion: Option<&'a str>,
+            hint: Option<String>,
+        }
+
+        let json = Json {
+            code: self.code,
+            severity: self.severity,
+            file: sources.get(self.span.file).name(),
+            span: self.span,
+            message: self.message(lang),
+            suggestion: self.suggestion.as_deref(),
+            hint: self.hint(lang),
+        };
+        serde_json::to_string(&json).expect("a diagnostic is always json")
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: error.code(),
+            args: error.args(),
+            span: error.span(),
+            suggestion: error.suggestion(),
+        }
+    }
+}
+
+// what haneul::compile found in a program, with the program they point into
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    pub list: Vec<Diagnostic>,
+    pub sources: SourceMap,
+}
+
+impl Diagnostics {
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    // every one of them rendered, like the command line shows them
+    pub fn render(&self, lang: Lang, verbosity: Verbosity) -> String {
+        self.list
+            .iter()
+            .map(|diagnostic| render(diagnostic, &self.sources, lang, verbosity) + "\n")
+            .collect()
+    }
+}
+
+// renders a diagnostic like this:
+//
+// error[E0006]: Referencing unassigned variable: c
+//  --> scores.han:5:11
+//   |
+// 5 |     PRINT c
+//   |           ^
+//   = hint: give the variable a value with LET or INPUT before using it
+//
+// --terse makes that `scores.han:5:11: error[E0006]: Referencing unassigned variable: c`
+pub fn render(
+    diagnostic: &Diagnostic,
+    sources: &SourceMap,
+    lang: Lang,
+    verbosity: Verbosity,
+) -> String {
+    let span = diagnostic.span;
+    let file = sources.get(span.file);
+    let severity = match diagnostic.severity {
+        Severity::Error => i18n::text(lang, Text::Error),
+        Severity::Warning => i18n::text(lang, Text::Warning),
+    };
+    let line = file.line(span.line);
+    let gutter = " ".repeat(span.line.to_string().len());
+
+    let message = match verbosity {
+        Verbosity::Teach => i18n::lesson(lang, diagnostic.code, &diagnostic.args)
+            .unwrap_or_else(|| diagnostic.message(lang)),
+        _ => diagnostic.message(lang),
+    };
+
+    if verbosity == Verbosity::Terse {
+        return format![
+            "{}:{}:{}: {}[{}]: {}",
+            file.name(),
+            span.line,
+            span.column,
+            severity,
+            diagnostic.code,
+            message
+        ];
+    }
+
+    let mut output = String::new();
+    let _ = writeln!(output, "{}[{}]: {}", severity, diagnostic.code, message);
+    let _ = writeln!(
+        output,
+        "{}--> {}:{}:{}",
+        gutter,
+        file.name(),
+        span.line,
+        span.column
+    );
+    // a streamed file has no lines to show
+    if let Some(line) = line {
+        // keep the tabs in front of the caret, so it lines up however wide the tabs are shown
+        let padding: String = line
+            .chars()
+            .take(span.column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let carets = "^".repeat(span.length.max(1));
+
+        let _ = writeln!(output, "{} |", gutter);
+        let _ = writeln!(output, "{} | {}", span.line, line);
+        let _ = writeln!(output, "{} | {}{}", gutter, padding, carets);
+    }
+    if let Some(hint) = diagnostic.hint(lang) {
+        let _ = writeln!(
+            output,
+            "{} = {}: {}",
+            gutter,
+            i18n::text(lang, Text::Hint),
+            hint
+        );
+    }
+    if verbosity == Verbosity::Teach {
+        if let Some(example) = i18n::example(lang, diagnostic.code, &diagnostic.args) {
+            let _ = writeln!(
+                output,
+                "{} = {}: {}",
+                gutter,
+                i18n::text(lang, Text::Example),
+                example
+            );
+        }
+    }
+
+    output
+}
@@ -0,0 +1,142 @@
+// `haneul dot file.han` writes the tree of a program as a graphviz graph, render it with
+// `haneul dot file.han | dot -Tsvg > tree.svg`. --emit-cfg writes the control flow graph
+// the same way
+
+use std::fmt::Write;
+
+use crate::{
+    ast::{ExprId, Program, Statement, StatementKind},
+    cfg::{Cfg, Terminator},
+    pretty::{block_lines, expr_node, statement_node},
+};
+
+struct Graph<'a> {
+    output: String,
+    program: &'a Program,
+    nodes: usize,
+}
+
+pub fn ast(program: &Program) -> String {
+    let mut graph = Graph {
+        output: String::new(),
+        program,
+        nodes: 0,
+    };
+
+    graph.output.push_str("digraph ast {\n");
+    graph
+        .output
+        .push_str("  node [shape=box, fontname=monospace];\n");
+    let root = graph.node("Program");
+    for statement in &program.statements {
+        let child = graph.statement(statement);
+        graph.edge(root, child, None);
+    }
+    graph.output.push_str("}\n");
+
+    graph.output
+}
+
+// every block is a node with its statements in it, the blocks nothing can get to are dashed
+pub fn cfg(program: &Program, cfg: &Cfg) -> String {
+    let mut output = String::new();
+    output.push_str("digraph cfg {\n");
+    output.push_str("  node [shape=box, fontname=monospace];\n");
+
+    let reachable = cfg.reachable();
+    for (id, block) in cfg.ids().zip(cfg.blocks()) {
+        let label: String = block_lines(id, block, program)
+            .iter()
+            .map(|line| format!["{}\\l", escape(line)])
+            .collect();
+        let style = if reachable[id.index()] {
+            ""
+        } else {
+            ", style=dashed, fontcolor=gray"
+        };
+        let _ = writeln!(output, "  b{} [label=\"{}\"{}];", id.index(), label, style);
+
+        match block.terminator {
+            Terminator::Jump(target) => {
+                let _ = writeln!(output, "  b{} -> b{};", id.index(), target.index());
+            }
+            Terminator::Branch {
+                then, otherwise, ..
+            } => {
+                let _ = writeln!(
+                    output,
+                    "  b{} -> b{} [label=\"true\"];",
+                    id.index(),
+                    then.index()
+                );
+                let _ = writeln!(
+                    output,
+                    "  b{} -> b{} [label=\"false\"];",
+                    id.index(),
+                    otherwise.index()
+                );
+            }
+            Terminator::Return => {}
+        }
+    }
+    output.push_str("}\n");
+
+    output
+}
+
+impl Graph<'_> {
+    fn statement(&mut self, statement: &Statement) -> usize {
+        let (label, expressions, body) = statement_node(statement, &self.program.names);
+        let node = self.node(&label);
+
+        // the one expression of an IF or a WHILE is its condition, the rest is the body
+        let edge = match statement.kind {
+            StatementKind::If { .. } | StatementKind::While { .. } => Some("condition"),
+            _ => None,
+        };
+        for expression in expressions {
+            let child = self.expr(expression);
+            self.edge(node, child, edge);
+        }
+        for statement in body {
+            let child = self.statement(statement);
+            self.edge(node, child, None);
+        }
+
+        node
+    }
+
+    fn expr(&mut self, expression: ExprId) -> usize {
+        let (label, children) = expr_node(&self.program.exprs[expression], &self.program.names);
+        let node = self.node(&label);
+        for child in children {
+            let child = self.expr(child);
+            self.edge(node, child, None);
+        }
+
+        node
+    }
+
+    fn node(&mut self, label: &str) -> usize {
+        let node = self.nodes;
+        self.nodes += 1;
+        let _ = writeln!(self.output, "  n{} [label=\"{}\"];", node, escape(label));
+
+        node
+    }
+
+    fn edge(&mut self, from: usize, to: usize, label: Option<&str>) {
+        match label {
+            Some(label) => {
+                let _ = writeln!(self.output, "  n{} -> n{} [label=\"{}\"];", from, to, label);
+            }
+            None => {
+                let _ = writeln!(self.output, "  n{} -> n{};", from, to);
+            }
+        }
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,318 @@
+// compiling or running a whole program in one call, see lib.rs. it's the part of the library
+// that needs std
+
+use std::{
+    fmt,
+    io::{self, Cursor, Write},
+};
+
+use crate::{
+    analyze,
+    backend::{self, Backend},
+    builtins::Registry,
+    bytecode,
+    ctype::FloatDivision,
+    diagnostics::{Diagnostics, Lints, Verbosity},
+    i18n::{self, Lang},
+    lexer, parse, parser,
+    passes::{self, OptLevel},
+    runtime::{ExecutionPolicy, Input},
+    source::SourceMap,
+    vm::Vm,
+};
+
+// what the program is compiled to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Target {
+    // out.c, which gcc makes into out
+    #[default]
+    C,
+    // main.rs
+    Rust,
+    // out.js
+    Js,
+    // out.py
+    Python,
+    // out.wat, and out.glue.js that runs it once it is made into out.wasm
+    Wat,
+    // out.ll
+    #[cfg(feature = "llvm")]
+    Llvm,
+    // out.ll made into out.o
+    #[cfg(feature = "llvm")]
+    LlvmObject,
+    // the bytecode the vm runs, in a .hbc file
+    Bytecode,
+}
+
+impl Target {
+    // the name --target gives it
+    pub fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "c" => Target::C,
+            "rust" => Target::Rust,
+            "js" => Target::Js,
+            "python" => Target::Python,
+            "wat" => Target::Wat,
+            "bytecode" => Target::Bytecode,
+            #[cfg(feature = "llvm")]
+            "llvm" => Target::Llvm,
+            #[cfg(feature = "llvm")]
+            "llvm-object" => Target::LlvmObject,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub target: Target,
+    pub opt_level: OptLevel,
+    // how deep blocks and expressions can be nested, see Parser::set_max_depth
+    pub max_depth: usize,
+    pub float_division: FloatDivision,
+    // for the c, javascript and python code
+    pub minify: bool,
+    pub lints: Lints,
+    // the name of the program in the diagnostics, the #line directives and the source map
+    pub name: String,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            target: Target::default(),
+            opt_level: OptLevel::default(),
+            max_depth: parser::DEFAULT_MAX_DEPTH,
+            float_division: FloatDivision::default(),
+            minify: false,
+            lints: Lints::default(),
+            name: String::from("main.han"),
+        }
+    }
+}
+
+// a program that compiled
+#[derive(Debug)]
+pub struct Output {
+    // the files the target makes and what's in them, like ("out.c", the c code). they're not
+    // written, and the tools that build them (gcc, llc) don't run
+    pub files: Vec<(String, Vec<u8>)>,
+    pub warnings: Diagnostics,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    // the program has errors, they come after its warnings
+    Program(Diagnostics),
+    // a builtin the target can't compile yet, and the language of the target
+    Unsupported(String, &'static str),
+    // the file of the program couldn't be read, with its name
+    Read(String, io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Program(diagnostics) => {
+                write!(
+                    f,
+                    "{}",
+                    diagnostics.render(Lang::English, Verbosity::Normal)
+                )
+            }
+            Error::Unsupported(builtin, language) => {
+                write!(f, "{} can't be compiled to {} yet", builtin, language)
+            }
+            Error::Read(file, error) => write!(f, "couldn't read {}: {}", file, error),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// what `haneul run` would have printed and exited with
+#[derive(Debug)]
+pub struct RunResult {
+    // what the program printed
+    pub stdout: String,
+    // the warnings and the errors, rendered, and the error it stopped with like "Error: ... on
+    // line 3", like `haneul run` prints them
+    pub stderr: String,
+    // 0, or 1 when the program has errors or went wrong
+    pub exit_code: i32,
+    pub diagnostics: Diagnostics,
+}
+
+pub fn compile(source: &str, options: &Options) -> Result<Output, Error> {
+    let builtins = Registry::new();
+    let mut sources = SourceMap::new();
+    let file = sources.add(options.name.clone(), source.to_owned());
+    let mut lexer = lexer::Lexer::new(file, sources.get(file).text());
+    let (result, warnings) = parse(&mut lexer, &builtins, options.max_depth);
+    let (mut warnings, errors) = analyze(&result, warnings, &builtins, &options.lints);
+    if !errors.is_empty() {
+        warnings.extend(errors);
+        return Err(Error::Program(Diagnostics {
+            list: warnings,
+            sources,
+        }));
+    }
+
+    let mut program = result.expect("parse errors are found by analyze");
+    passes::optimize(&mut program, options.opt_level);
+    let mut backend = backend(options, &builtins, options.name.clone(), None);
+    backend.prepare(&program);
+    backend
+        .emit_program(&program)
+        .map_err(|builtin| Error::Unsupported(builtin, backend.language()))?;
+    Ok(Output {
+        files: backend.files(),
+        warnings: Diagnostics {
+            list: warnings,
+            sources,
+        },
+    })
+}
+
+// the text of the program in another language, the target named like --target names it. for
+// the playground and the c interface, which only give text back
+#[cfg(any(feature = "wasm", feature = "ffi", feature = "python"))]
+pub(crate) fn compile_text(source: &str, target: &str) -> Result<String, String> {
+    let options = Options {
+        target: match Target::parse(target) {
+            // they aren't text
+            Some(Target::Bytecode) | None => return Err(format!["can't compile to {}", target]),
+            #[cfg(feature = "llvm")]
+            Some(Target::LlvmObject) => return Err(format!["can't compile to {}", target]),
+            Some(target) => target,
+        },
+        ..Options::default()
+    };
+    let output = compile(source, &options).map_err(|error| error.to_string())?;
+    Ok(String::from_utf8_lossy(&output.files[0].1).into_owned())
+}
+
+// runs the program on the vm with `stdin` as its input, like `haneul run main.han`, for a grader
+// or a playground that wants all of its output at once
+//
+//   let result = haneul::run_source("INPUT a\nPRINT a * 2\n", "21\n");
+//   assert_eq!(result.stdout, "42\n");
+pub fn run_source(source: &str, stdin: &str) -> RunResult {
+    run_source_with_policy(source, stdin, &ExecutionPolicy::default())
+}
+
+// run_source for a program that isn't trusted: `policy` says what it may use and stops it when
+// it runs too long, ExecutionPolicy::default() is what `haneul run` does
+pub fn run_source_with_policy(source: &str, stdin: &str, policy: &ExecutionPolicy) -> RunResult {
+    let mut output = vec![];
+    let mut result = run_source_to(source, stdin, policy, &mut output);
+    result.stdout = String::from_utf8_lossy(&output).into_owned();
+    result
+}
+
+// run_source_with_policy that writes what the program prints to `output` while it runs, like
+// the playground shows it. stdout of the result is empty
+pub(crate) fn run_source_to(
+    source: &str,
+    stdin: &str,
+    policy: &ExecutionPolicy,
+    output: impl Write + Send,
+) -> RunResult {
+    let options = Options::default();
+    let mut builtins = Registry::new();
+    builtins.restrict(policy);
+    let mut sources = SourceMap::new();
+    let file = sources.add(options.name.clone(), source.to_owned());
+    let mut lexer = lexer::Lexer::new(file, sources.get(file).text());
+    let (result, warnings) = parse(&mut lexer, &builtins, options.max_depth);
+    let (mut list, errors) = analyze(&result, warnings, &builtins, &options.lints);
+    let error_count = errors.len();
+    list.extend(errors);
+    let diagnostics = Diagnostics { list, sources };
+    let mut stderr = diagnostics.render(Lang::English, Verbosity::Normal);
+    if error_count > 0 {
+        stderr.push_str(&i18n::errors_found(Lang::English, error_count));
+        stderr.push('\n');
+        return RunResult {
+            stdout: String::new(),
+            stderr,
+            exit_code: 1,
+            diagnostics,
+        };
+    }
+
+    let mut program = result.expect("parse errors are found by analyze");
+    passes::optimize(&mut program, options.opt_level);
+    let failure = match bytecode::compile(&program, &builtins, options.float_division) {
+        Ok(mut chunk) => {
+            passes::optimize_chunk(&mut chunk, options.opt_level);
+            let mut vm = Vm::new(&chunk);
+            vm.set_policy(*policy);
+            vm.set_input(Input::from_reader(Cursor::new(stdin.to_owned())));
+            vm.set_output(output);
+            vm.run()
+                .err()
+                .map(|error| format!["{} on line {}", error.message, error.line])
+        }
+        Err(builtin) => Some(format!["{} can't be run yet", builtin]),
+    };
+    if let Some(failure) = &failure {
+        stderr.push_str(&format!["Error: {}\n", failure]);
+    }
+    RunResult {
+        stdout: String::new(),
+        stderr,
+        exit_code: failure.map_or(0, |_| 1),
+        diagnostics,
+    }
+}
+
+// the backend of a target. `source` is the name of the program. `executable` is what finish
+// builds: the program the c compiler makes (out without one), the .hbc file (out.hbc) or the
+// program out.o is linked into (without one there's only out.o)
+pub fn backend<'a>(
+    options: &Options,
+    builtins: &'a Registry,
+    source: String,
+    executable: Option<String>,
+) -> Box<dyn Backend + 'a> {
+    match options.target {
+        Target::C => Box::new(backend::C::new(
+            builtins,
+            source,
+            executable.unwrap_or_else(|| String::from("out")),
+            options.minify,
+            options.float_division,
+        )),
+        Target::Rust => Box::new(backend::Source::rust(
+            builtins,
+            &source,
+            options.float_division,
+        )),
+        Target::Js => Box::new(backend::Js::new(
+            builtins,
+            source,
+            options.minify,
+            options.float_division,
+        )),
+        Target::Python => Box::new(backend::Source::python(
+            builtins,
+            options.minify,
+            options.float_division,
+        )),
+        Target::Wat => Box::new(backend::Wat::new(options.float_division)),
+        #[cfg(feature = "llvm")]
+        Target::Llvm => Box::new(backend::Llvm::new(false, None, options.float_division)),
+        #[cfg(feature = "llvm")]
+        Target::LlvmObject => {
+            Box::new(backend::Llvm::new(true, executable, options.float_division))
+        }
+        Target::Bytecode => Box::new(backend::Bytecode::new(
+            builtins,
+            executable.unwrap_or_else(|| String::from("out.hbc")),
+            options.float_division,
+            options.opt_level,
+        )),
+    }
+}
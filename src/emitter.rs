@@ -1,17 +1,17 @@
-use std::{fs::File, io::Write};
-
 pub struct Emitter {
-    full_path: String,
+    prelude: String,
     header: String,
     code: String,
+    libraries: Vec<String>,
 }
 
 impl Emitter {
-    pub fn new(path: String) -> Self {
+    pub fn new() -> Self {
         Self {
-            full_path: path,
+            prelude: String::new(),
             header: String::new(),
             code: String::new(),
+            libraries: vec![],
         }
     }
 
@@ -23,17 +23,28 @@ impl Emitter {
         self.code.push_str(&format!["{}\n", code]);
     }
 
+    // includes and helper functions, these have to go before main
+    pub fn prelude_line(&mut self, code: &str) {
+        self.prelude.push_str(&format!["{}\n", code])
+    }
+
     pub fn header_line(&mut self, code: &str) {
         self.header.push_str(&format!["{}\n", code])
     }
 
-    // this function only needs to be called once, so it consumes self
-    // else I would have to clone `self.header` and `self.code`
-    pub fn write_file(self) -> std::io::Result<()> {
-        let mut file = File::create(&self.full_path)?;
-        file.write_all(&self.header.into_bytes())?;
-        file.write_all(&self.code.into_bytes())?;
+    // libraries the C compiler has to link the program with
+    pub fn link_library(&mut self, name: &str) {
+        if !self.libraries.iter().any(|library| library == name) {
+            self.libraries.push(name.to_owned());
+        }
+    }
+
+    pub fn libraries(&self) -> &[String] {
+        &self.libraries
+    }
 
-        Ok(())
+    // all of the file
+    pub fn contents(&self) -> String {
+        format!["{}{}{}", self.prelude, self.header, self.code]
     }
 }
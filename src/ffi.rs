@@ -0,0 +1,156 @@
+// the c interface, with the ffi feature: the cdylib exports these for a c or c++ program, or
+// anything else that can call c, and build.rs writes haneul.h with them to its OUT_DIR
+//
+//   char *code = haneul_compile("LET a = 2\nPRINT a * 21\n", "c");
+//   if (code == NULL) {
+//       fprintf(stderr, "%s", haneul_last_error());
+//   }
+//   haneul_free(code);
+//
+// the strings are utf-8 and end with a 0, a NULL one is an error. what haneul gives back is
+// freed with haneul_free, never with free(), rust allocated it
+// the safety of every function is in the comment above it
+#![allow(clippy::missing_safety_doc)]
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, c_int, CStr, CString},
+    ptr,
+    time::Duration,
+};
+
+use crate::runtime::{ExecutionPolicy, Limits};
+
+thread_local! {
+    // of the last call on this thread that went wrong
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+// the program in another language, a target like --target names it ("c", "python", ...). NULL
+// when it has errors or the target doesn't give text, haneul_last_error says why. `source` and
+// `target` are NULL or strings
+#[no_mangle]
+pub unsafe extern "C" fn haneul_compile(
+    source: *const c_char,
+    target: *const c_char,
+) -> *mut c_char {
+    let code = string(source, "source")
+        .and_then(|source| crate::compile_text(source, string(target, "target")?))
+        .and_then(c_string);
+    match code {
+        Ok(code) => code.into_raw(),
+        Err(error) => {
+            set_last_error(error);
+            ptr::null_mut()
+        }
+    }
+}
+
+// what a program haneul_run_with runs may do, see ExecutionPolicy. the allow_ ones are 0 or 1,
+// and a limit of 0 is no limit
+#[repr(C)]
+pub struct HaneulPolicy {
+    pub allow_fs: c_int,
+    pub allow_net: c_int,
+    pub allow_exec: c_int,
+    // instructions on the vm
+    pub max_steps: u64,
+    pub timeout_ms: u64,
+    // about how many bytes
+    pub max_memory: usize,
+}
+
+impl From<&HaneulPolicy> for ExecutionPolicy {
+    fn from(policy: &HaneulPolicy) -> Self {
+        Self {
+            allow_fs: policy.allow_fs != 0,
+            allow_net: policy.allow_net != 0,
+            allow_exec: policy.allow_exec != 0,
+            limits: Limits {
+                steps: Some(policy.max_steps).filter(|steps| *steps != 0),
+                timeout: Some(policy.timeout_ms)
+                    .filter(|timeout| *timeout != 0)
+                    .map(Duration::from_millis),
+            },
+            memory: Some(policy.max_memory).filter(|memory| *memory != 0),
+        }
+    }
+}
+
+// runs the program with `input` as what its INPUTs read, and gives the exit code of `haneul
+// run`: 0, or 1 when the program has errors or went wrong. what it printed is put in `*output`
+// when `output` isn't NULL, and its warnings and errors and the error it stopped with are in
+// haneul_last_error. -1 when
+// `source` or `input` isn't a string. `source` and `input` are NULL or strings, `output` is
+// NULL or a place a pointer can be written to
+#[no_mangle]
+pub unsafe extern "C" fn haneul_run(
+    source: *const c_char,
+    input: *const c_char,
+    output: *mut *mut c_char,
+) -> c_int {
+    haneul_run_with(source, input, ptr::null(), output)
+}
+
+// haneul_run with what the program may do, like a grader that runs programs it doesn't trust
+// wants. NULL lets it do everything, like haneul_run. `policy` is NULL or points to a
+// HaneulPolicy, the rest is like haneul_run
+#[no_mangle]
+pub unsafe extern "C" fn haneul_run_with(
+    source: *const c_char,
+    input: *const c_char,
+    policy: *const HaneulPolicy,
+    output: *mut *mut c_char,
+) -> c_int {
+    let policy = policy
+        .as_ref()
+        .map_or_else(ExecutionPolicy::default, |policy| policy.into());
+    let (source, input) = match (string(source, "source"), string(input, "input")) {
+        (Ok(source), Ok(input)) => (source, input),
+        (Err(error), _) | (_, Err(error)) => {
+            set_last_error(error);
+            return -1;
+        }
+    };
+    let result = crate::run_source_with_policy(source, input, &policy);
+    set_last_error(result.stderr);
+    if !output.is_null() {
+        // a 0 the program printed ends the string early
+        let printed = result.stdout.split('\0').next().unwrap_or_default();
+        *output = CString::new(printed).unwrap_or_default().into_raw();
+    }
+    result.exit_code
+}
+
+// why the last call on this thread went wrong, or the warnings of the last haneul_run. it's
+// empty when there's nothing, and stays valid until the next call on this thread
+#[no_mangle]
+pub extern "C" fn haneul_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| error.borrow().as_ptr())
+}
+
+// `string` is NULL or something haneul gave back, which isn't used after this
+#[no_mangle]
+pub unsafe extern "C" fn haneul_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+unsafe fn string<'a>(string: *const c_char, name: &str) -> Result<&'a str, String> {
+    if string.is_null() {
+        return Err(format!["{} is NULL", name]);
+    }
+    CStr::from_ptr(string)
+        .to_str()
+        .map_err(|_| format!["{} isn't utf-8", name])
+}
+
+fn c_string(text: String) -> Result<CString, String> {
+    CString::new(text).map_err(|_| String::from("the code has a 0 in it"))
+}
+
+fn set_last_error(error: String) {
+    let error = CString::new(error.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = error);
+}
@@ -0,0 +1,194 @@
+// constant folding: the parts of expressions that only use numbers are worked out while
+// compiling, so `LET seconds = 60 * 60 * 24` is just `86400` in the c code. an IF or a WHILE
+// whose condition is then known never has to check it at all
+//
+// the numbers are put in the c code as they're written, so they follow c's rules: a number
+// without a dot is an int and dividing two ints rounds towards zero. folding follows the same
+// rules, and leaves anything alone where c wouldn't give a plain number (dividing by zero,
+// overflowing an int), so the program does exactly what it did before
+
+use crate::{
+    ast::{BinaryOp, Expr, ExprId, ExprKind, Exprs, Program, Statement, StatementKind, UnaryOp},
+    prelude::*,
+    visit::{self, VisitorMut},
+};
+
+pub fn fold(program: &mut Program) {
+    visit::walk_program_mut(&mut Folder, program);
+    simplify(&mut program.statements, &program.exprs);
+}
+
+// whether a condition is always true or always false, without changing the tree.
+// None when it depends on something that is only known when the program runs
+pub fn known_condition(exprs: &Exprs, condition: ExprId) -> Option<bool> {
+    evaluate(exprs, condition).map(Constant::is_true)
+}
+
+fn evaluate(exprs: &Exprs, expression: ExprId) -> Option<Constant> {
+    match &exprs[expression].kind {
+        ExprKind::Number(number) => Constant::parse(number),
+        ExprKind::Unary { op, operand } => unary(*op, evaluate(exprs, *operand)?),
+        ExprKind::Binary { op, left, right } => {
+            binary(*op, evaluate(exprs, *left)?, evaluate(exprs, *right)?)
+        }
+        ExprKind::Variable(_) | ExprKind::Call { .. } => None,
+    }
+}
+
+// a number like c sees it
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Constant {
+    Int(i32),
+    Double(f64),
+}
+
+impl Constant {
+    fn parse(text: &str) -> Option<Self> {
+        if text.contains('.') {
+            text.parse().ok().map(Constant::Double)
+        } else {
+            text.parse().ok().map(Constant::Int)
+        }
+    }
+
+    fn of(exprs: &Exprs, expression: ExprId) -> Option<Self> {
+        match &exprs[expression].kind {
+            ExprKind::Number(number) => Self::parse(number),
+            _ => None,
+        }
+    }
+
+    fn as_double(self) -> f64 {
+        match self {
+            Constant::Int(value) => value as f64,
+            Constant::Double(value) => value,
+        }
+    }
+
+    fn is_true(self) -> bool {
+        self.as_double() != 0.0
+    }
+
+    // the way the number is written in the c code. a double always gets a dot, or c would
+    // read it as an int
+    fn text(self) -> String {
+        match self {
+            Constant::Int(value) => value.to_string(),
+            Constant::Double(value) => {
+                let text = value.to_string();
+                if text.contains('.') {
+                    text
+                } else {
+                    format!["{}.0", text]
+                }
+            }
+        }
+    }
+}
+
+fn unary(op: UnaryOp, operand: Constant) -> Option<Constant> {
+    match (op, operand) {
+        (UnaryOp::Plus, operand) => Some(operand),
+        (UnaryOp::Minus, Constant::Int(value)) => value.checked_neg().map(Constant::Int),
+        (UnaryOp::Minus, Constant::Double(value)) => Some(Constant::Double(-value)),
+    }
+}
+
+fn binary(op: BinaryOp, left: Constant, right: Constant) -> Option<Constant> {
+    if op.is_comparison() {
+        let (left, right) = (left.as_double(), right.as_double());
+        let result = match op {
+            BinaryOp::Equal => left == right,
+            BinaryOp::NotEqual => left != right,
+            BinaryOp::Less => left < right,
+            BinaryOp::LessEqual => left <= right,
+            BinaryOp::Greater => left > right,
+            _ => left >= right,
+        };
+        return Some(Constant::Int(result.into()));
+    }
+
+    match (left, right) {
+        (Constant::Int(left), Constant::Int(right)) => match op {
+            BinaryOp::Add => left.checked_add(right),
+            BinaryOp::Subtract => left.checked_sub(right),
+            BinaryOp::Multiply => left.checked_mul(right),
+            BinaryOp::Remainder => left.checked_rem(right),
+            _ => left.checked_div(right),
+        }
+        .map(Constant::Int),
+        (left, right) => {
+            let (left, right) = (left.as_double(), right.as_double());
+            let result = match op {
+                BinaryOp::Add => left + right,
+                BinaryOp::Subtract => left - right,
+                BinaryOp::Multiply => left * right,
+                // fmod in c
+                BinaryOp::Remainder => left % right,
+                _ => left / right,
+            };
+            result.is_finite().then_some(Constant::Double(result))
+        }
+    }
+}
+
+struct Folder;
+
+impl VisitorMut for Folder {
+    // the children are folded first, so a whole constant expression folds from the bottom up
+    fn visit_expr_mut(&mut self, exprs: &mut Exprs, expression: ExprId) {
+        visit::walk_expr_mut(self, exprs, expression);
+
+        let folded = match exprs[expression].kind {
+            ExprKind::Unary { op, operand } => {
+                Constant::of(exprs, operand).and_then(|operand| unary(op, operand))
+            }
+            ExprKind::Binary { op, left, right } => {
+                match (Constant::of(exprs, left), Constant::of(exprs, right)) {
+                    (Some(left), Some(right)) => binary(op, left, right),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        if let Some(folded) = folded {
+            exprs[expression] = Expr {
+                kind: ExprKind::Number(folded.text()),
+                span: exprs[expression].span,
+            };
+        }
+    }
+}
+
+// an IF that is always true is replaced by its body, an IF or a WHILE that is always false
+// is left out. unless a GOTO could jump into it, then it stays
+fn simplify(statements: &mut Vec<Statement>, exprs: &Exprs) {
+    let mut simplified = Vec::with_capacity(statements.len());
+    for mut statement in statements.drain(..) {
+        let (condition, body, is_if) = match &mut statement.kind {
+            StatementKind::If { condition, body } => (*condition, body, true),
+            StatementKind::While { condition, body } => (*condition, body, false),
+            _ => {
+                simplified.push(statement);
+                continue;
+            }
+        };
+
+        simplify(body, exprs);
+        match Constant::of(exprs, condition).map(Constant::is_true) {
+            Some(true) if is_if => simplified.append(body),
+            Some(false) if !has_label(body) => {}
+            _ => simplified.push(statement),
+        }
+    }
+
+    *statements = simplified;
+}
+
+fn has_label(statements: &[Statement]) -> bool {
+    statements.iter().any(|statement| match &statement.kind {
+        StatementKind::Label(_) => true,
+        StatementKind::If { body, .. } | StatementKind::While { body, .. } => has_label(body),
+        _ => false,
+    })
+}
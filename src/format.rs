@@ -0,0 +1,31 @@
+// formats the text of a program for the playground (see wasm.rs): the body of every IF and
+// WHILE is indented by four spaces, like testprogram.han, and the spaces at the end of the lines
+// are removed. only the start of a line is looked at, so comments and even lines with errors
+// stay what they are
+
+use crate::{keywords, prelude::*, token::TokenType};
+
+const INDENT: &str = "    ";
+
+pub fn format(source: &str) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut depth: usize = 0;
+    for line in source.lines() {
+        let line = line.trim();
+        let first = line.split(|c: char| !c.is_ascii_alphanumeric()).next();
+        let keyword = first.and_then(keywords::keyword);
+        if matches!(keyword, Some(TokenType::Endif | TokenType::EndWhile)) {
+            depth = depth.saturating_sub(1);
+        }
+        if !line.is_empty() {
+            output.push_str(&INDENT.repeat(depth));
+            output.push_str(line);
+        }
+        output.push('\n');
+        if matches!(keyword, Some(TokenType::If | TokenType::While)) {
+            depth += 1;
+        }
+    }
+
+    output
+}
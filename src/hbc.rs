@@ -0,0 +1,362 @@
+// .hbc files, the bytecode of a program (see bytecode.rs) saved by `haneul build` so `haneul run`
+// can run it without the source. all numbers are little endian:
+//
+//   "HBC\0", the version (u32), a checksum of the rest (u64, fnv-1a)
+//   what dividing a float by 0 does (u8): 0 gives inf or nan, 1 is an error
+//   the constants: their count (u32), then a tag (u8) and the value (8 bytes) for every one
+//   the strings: their count, then the length and the bytes of every one
+//   the variables: their count, then the name like a string and the type (u8) of every one
+//   the code: its length, then an opcode (u8) and an operand (u32) for every instruction, the
+//   operand of an instruction that works on a type is the type, of a Call the index of the
+//   builtin in runtime::Native::ALL
+//   the line of every instruction (u32)
+
+use crate::{
+    bytecode::{Chunk, Instruction},
+    ctype::{CType, FloatDivision},
+    runtime::{Native, Value},
+};
+
+const MAGIC: &[u8; 4] = b"HBC\0";
+// goes up when the format or the meaning of an instruction changes
+const VERSION: u32 = 5;
+
+pub fn encode(chunk: &Chunk) -> Vec<u8> {
+    let mut body = Writer { bytes: vec![] };
+
+    body.bytes.push(match chunk.float_division {
+        FloatDivision::Ieee => 0,
+        FloatDivision::Error => 1,
+    });
+    body.u32(chunk.constants.len() as u32);
+    for constant in &chunk.constants {
+        let (tag, bits) = match *constant {
+            Value::Int(value) => (0, value as i64 as u64),
+            Value::Long(value) => (1, value as u64),
+            Value::Float(value) => (2, value.to_bits() as u64),
+            Value::Double(value) => (3, value.to_bits()),
+        };
+        body.bytes.push(tag);
+        body.bytes.extend(bits.to_le_bytes());
+    }
+    body.u32(chunk.strings.len() as u32);
+    for string in &chunk.strings {
+        body.string(string);
+    }
+    body.u32(chunk.variables.len() as u32);
+    for (name, ctype) in &chunk.variables {
+        body.string(name);
+        body.bytes.push(type_code(*ctype) as u8);
+    }
+    body.u32(chunk.code.len() as u32);
+    for instruction in &chunk.code {
+        let (opcode, operand) = opcode(*instruction);
+        body.bytes.push(opcode);
+        body.u32(operand);
+    }
+    for line in &chunk.lines {
+        body.u32(*line as u32);
+    }
+
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend(VERSION.to_le_bytes());
+    bytes.extend(checksum(&body.bytes).to_le_bytes());
+    bytes.extend(body.bytes);
+    bytes
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Chunk, String> {
+    if bytes.get(..4) != Some(MAGIC) {
+        return Err(String::from("isn't a haneul bytecode file"));
+    }
+    let mut header = Reader { bytes, at: 4 };
+    let version = header.u32()?;
+    if version != VERSION {
+        return Err(format![
+            "is bytecode version {}, this haneul runs version {}",
+            version, VERSION
+        ]);
+    }
+    let sum = header.u64()?;
+    let body = &bytes[header.at..];
+    if checksum(body) != sum {
+        return Err(String::from("is damaged, its checksum is wrong"));
+    }
+
+    let mut reader = Reader { bytes: body, at: 0 };
+    let float_division = match reader.u8()? {
+        0 => FloatDivision::Ieee,
+        1 => FloatDivision::Error,
+        division => return Err(format!["has an unknown float division {}", division]),
+    };
+    let mut chunk = Chunk {
+        float_division,
+        ..Chunk::default()
+    };
+    for _ in 0..reader.u32()? {
+        let tag = reader.u8()?;
+        let bits = reader.u64()?;
+        chunk.constants.push(match tag {
+            0 => Value::Int(bits as i32),
+            1 => Value::Long(bits as i64),
+            2 => Value::Float(f32::from_bits(bits as u32)),
+            3 => Value::Double(f64::from_bits(bits)),
+            _ => return Err(format!["has a constant with an unknown tag {}", tag]),
+        });
+    }
+    for _ in 0..reader.u32()? {
+        chunk.strings.push(reader.string()?);
+    }
+    for _ in 0..reader.u32()? {
+        let name = reader.string()?;
+        let ctype = code_type(reader.u8()? as u32)?;
+        chunk.variables.push((name, ctype));
+    }
+    let length = reader.u32()?;
+    for _ in 0..length {
+        let opcode = reader.u8()?;
+        let operand = reader.u32()?;
+        chunk.code.push(instruction(opcode, operand)?);
+    }
+    for _ in 0..length {
+        chunk.lines.push(reader.u32()? as usize);
+    }
+    if reader.at != body.len() {
+        return Err(String::from("has bytes after the end of the program"));
+    }
+
+    check(&chunk)?;
+    Ok(chunk)
+}
+
+// the vm trusts the bytecode it runs, so everything an instruction points to has to be there,
+// and no instruction may pop more than there is on the stack. a file that was changed by hand
+// and given a new checksum is turned away instead of making the vm panic
+fn check(chunk: &Chunk) -> Result<(), String> {
+    if chunk.code.last() != Some(&Instruction::Halt) {
+        return Err(String::from("doesn't end with a halt"));
+    }
+    for instruction in &chunk.code {
+        let fits = match *instruction {
+            Instruction::Constant(index) => (index as usize) < chunk.constants.len(),
+            Instruction::Load(slot) | Instruction::Store(slot) | Instruction::Input(slot) => {
+                (slot as usize) < chunk.variables.len()
+            }
+            Instruction::PrintString(index) => (index as usize) < chunk.strings.len(),
+            Instruction::Jump(target) | Instruction::JumpIfZero(target) => {
+                (target as usize) < chunk.code.len()
+            }
+            _ => true,
+        };
+        if !fits {
+            return Err(format![
+                "has an instruction out of bounds: {:?}",
+                instruction
+            ]);
+        }
+    }
+    depths(chunk).map(|_| ())
+}
+
+// how many values are on the stack before every instruction, None for one that's never run.
+// every way to an instruction has to leave the same number there, like the compiler does it
+pub fn depths(chunk: &Chunk) -> Result<Vec<Option<usize>>, String> {
+    let mut depths = vec![None; chunk.code.len()];
+    let mut next = vec![(0, 0_usize)];
+    while let Some((pc, depth)) = next.pop() {
+        match depths[pc] {
+            Some(known) if known == depth => continue,
+            Some(known) => {
+                return Err(format![
+                    "has {} or {} values on the stack at instruction {}",
+                    known, depth, pc
+                ])
+            }
+            None => depths[pc] = Some(depth),
+        }
+        let instruction = chunk.code[pc];
+        let (pops, pushes) = effect(instruction);
+        let Some(left) = depth.checked_sub(pops) else {
+            return Err(format![
+                "pops more than there is on the stack at instruction {}: {:?}",
+                pc, instruction
+            ]);
+        };
+        let depth = left + pushes;
+        match instruction {
+            Instruction::Halt => {}
+            Instruction::Jump(target) => next.push((target as usize, depth)),
+            Instruction::JumpIfZero(target) => {
+                next.push((target as usize, depth));
+                next.push((pc + 1, depth));
+            }
+            // it ends with a halt, so there is a next one
+            _ => next.push((pc + 1, depth)),
+        }
+    }
+    Ok(depths)
+}
+
+// how many values an instruction pops and pushes
+fn effect(instruction: Instruction) -> (usize, usize) {
+    match instruction {
+        Instruction::Constant(_) | Instruction::Load(_) => (0, 1),
+        Instruction::Store(_) | Instruction::JumpIfZero(_) | Instruction::Print(_) => (1, 0),
+        Instruction::Convert(..) | Instruction::Negate(_) => (1, 1),
+        Instruction::Add(_)
+        | Instruction::Subtract(_)
+        | Instruction::Multiply(_)
+        | Instruction::Divide(_)
+        | Instruction::Remainder(_)
+        | Instruction::Equal(_)
+        | Instruction::NotEqual(_)
+        | Instruction::Less(_)
+        | Instruction::LessEqual(_)
+        | Instruction::Greater(_)
+        | Instruction::GreaterEqual(_) => (2, 1),
+        Instruction::Call(native) => (0, native.returns() as usize),
+        Instruction::Jump(_)
+        | Instruction::PrintString(_)
+        | Instruction::Input(_)
+        | Instruction::Halt => (0, 0),
+    }
+}
+
+// the operand of a typed instruction is its type, a Convert has both types in it
+fn opcode(instruction: Instruction) -> (u8, u32) {
+    match instruction {
+        Instruction::Constant(index) => (0, index),
+        Instruction::Load(slot) => (1, slot),
+        Instruction::Store(slot) => (2, slot),
+        Instruction::Convert(from, to) => (3, type_code(from) * 4 + type_code(to)),
+        Instruction::Negate(ctype) => (4, type_code(ctype)),
+        Instruction::Add(ctype) => (5, type_code(ctype)),
+        Instruction::Subtract(ctype) => (6, type_code(ctype)),
+        Instruction::Multiply(ctype) => (7, type_code(ctype)),
+        Instruction::Divide(ctype) => (8, type_code(ctype)),
+        Instruction::Equal(ctype) => (9, type_code(ctype)),
+        Instruction::NotEqual(ctype) => (10, type_code(ctype)),
+        Instruction::Less(ctype) => (11, type_code(ctype)),
+        Instruction::LessEqual(ctype) => (12, type_code(ctype)),
+        Instruction::Greater(ctype) => (13, type_code(ctype)),
+        Instruction::GreaterEqual(ctype) => (14, type_code(ctype)),
+        Instruction::Jump(target) => (15, target),
+        Instruction::JumpIfZero(target) => (16, target),
+        Instruction::Print(ctype) => (17, type_code(ctype)),
+        Instruction::PrintString(index) => (18, index),
+        Instruction::Input(slot) => (19, slot),
+        Instruction::Halt => (20, 0),
+        Instruction::Remainder(ctype) => (21, type_code(ctype)),
+        Instruction::Call(native) => (22, native as u32),
+    }
+}
+
+fn instruction(opcode: u8, operand: u32) -> Result<Instruction, String> {
+    let ctype = || code_type(operand);
+    Ok(match opcode {
+        0 => Instruction::Constant(operand),
+        1 => Instruction::Load(operand),
+        2 => Instruction::Store(operand),
+        3 => Instruction::Convert(code_type(operand / 4)?, code_type(operand % 4)?),
+        4 => Instruction::Negate(ctype()?),
+        5 => Instruction::Add(ctype()?),
+        6 => Instruction::Subtract(ctype()?),
+        7 => Instruction::Multiply(ctype()?),
+        8 => Instruction::Divide(ctype()?),
+        9 => Instruction::Equal(ctype()?),
+        10 => Instruction::NotEqual(ctype()?),
+        11 => Instruction::Less(ctype()?),
+        12 => Instruction::LessEqual(ctype()?),
+        13 => Instruction::Greater(ctype()?),
+        14 => Instruction::GreaterEqual(ctype()?),
+        15 => Instruction::Jump(operand),
+        16 => Instruction::JumpIfZero(operand),
+        17 => Instruction::Print(ctype()?),
+        18 => Instruction::PrintString(operand),
+        19 => Instruction::Input(operand),
+        20 => Instruction::Halt,
+        21 => Instruction::Remainder(ctype()?),
+        22 => match Native::ALL.get(operand as usize) {
+            Some(native) => Instruction::Call(*native),
+            None => return Err(format!["has an unknown builtin {}", operand]),
+        },
+        _ => return Err(format!["has an unknown opcode {}", opcode]),
+    })
+}
+
+fn type_code(ctype: CType) -> u32 {
+    match ctype {
+        CType::Int => 0,
+        CType::Long => 1,
+        CType::Float => 2,
+        CType::Double => 3,
+    }
+}
+
+fn code_type(code: u32) -> Result<CType, String> {
+    Ok(match code {
+        0 => CType::Int,
+        1 => CType::Long,
+        2 => CType::Float,
+        3 => CType::Double,
+        _ => return Err(format!["has an unknown type {}", code]),
+    })
+}
+
+pub fn checksum(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf29ce484222325_u64;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend(value.to_le_bytes());
+    }
+
+    fn string(&mut self, string: &str) {
+        self.u32(string.len() as u32);
+        self.bytes.extend(string.as_bytes());
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, length: usize) -> Result<&[u8], String> {
+        let bytes = self
+            .bytes
+            .get(self.at..self.at + length)
+            .ok_or_else(|| String::from("ends too early"))?;
+        self.at += length;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let length = self.u32()? as usize;
+        String::from_utf8(self.take(length)?.to_vec())
+            .map_err(|_| String::from("has a string that isn't utf-8"))
+    }
+}
@@ -0,0 +1,607 @@
+// message catalogs for diagnostics. diagnostics only store their code and arguments,
+// the text is looked up here when they are shown, so they can be shown in any language.
+// {0}, {1}, ... in a message are replaced with the arguments
+
+use crate::{codes, prelude::*};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    English,
+    Korean,
+}
+
+impl Lang {
+    // accepts both `ko` and locale names like `ko_KR.UTF-8`
+    pub fn parse(name: &str) -> Option<Self> {
+        let language = name.split(['_', '-', '.']).next().unwrap_or("");
+        match language.to_lowercase().as_str() {
+            "en" | "c" | "posix" => Some(Lang::English),
+            "ko" | "kr" => Some(Lang::Korean),
+            _ => None,
+        }
+    }
+
+    // the same variables gettext looks at, in the same order
+    #[cfg(feature = "std")]
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|variable| std::env::var(variable).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Self::parse(&value))
+            .unwrap_or(Lang::English)
+    }
+
+    fn catalog(self) -> &'static [Entry] {
+        match self {
+            Lang::English => ENGLISH,
+            Lang::Korean => KOREAN,
+        }
+    }
+
+    fn lessons(self) -> &'static [Lesson] {
+        match self {
+            Lang::English => ENGLISH_LESSONS,
+            Lang::Korean => KOREAN_LESSONS,
+        }
+    }
+}
+
+// a piece of text that isn't a diagnostic message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Text {
+    Error,
+    Warning,
+    Hint,
+    DidYouMean,
+    EndOfLine,
+    EndOfFile,
+    Example,
+}
+
+pub fn text(lang: Lang, text: Text) -> &'static str {
+    match (lang, text) {
+        (Lang::English, Text::Error) => "error",
+        (Lang::English, Text::Warning) => "warning",
+        (Lang::English, Text::Hint) => "hint",
+        (Lang::English, Text::DidYouMean) => "did you mean `{0}`?",
+        (Lang::English, Text::EndOfLine) => "end of line",
+        (Lang::English, Text::EndOfFile) => "end of file",
+        (Lang::English, Text::Example) => "example",
+        (Lang::Korean, Text::Error) => "오류",
+        (Lang::Korean, Text::Warning) => "경고",
+        (Lang::Korean, Text::Hint) => "도움말",
+        (Lang::Korean, Text::DidYouMean) => "`{0}`을(를) 뜻한 건가요?",
+        (Lang::Korean, Text::EndOfLine) => "줄 끝",
+        (Lang::Korean, Text::EndOfFile) => "파일 끝",
+        (Lang::Korean, Text::Example) => "예시",
+    }
+}
+
+pub fn errors_found(lang: Lang, count: usize) -> String {
+    match (lang, count) {
+        (Lang::English, 1) => String::from("1 error found"),
+        (Lang::English, count) => format!["{} errors found", count],
+        (Lang::Korean, count) => format!["오류 {}개를 찾았습니다", count],
+    }
+}
+
+pub fn message(lang: Lang, code: &str, args: &[String]) -> String {
+    match entry(lang, code) {
+        Some(entry) => fill(lang, entry.message, args),
+        None => format!["{} {}", code, args.join(" ")],
+    }
+}
+
+// a "did you mean" suggestion replaces the usual hint of a diagnostic
+pub fn hint(lang: Lang, code: &str, args: &[String], suggestion: Option<&str>) -> Option<String> {
+    if let Some(suggestion) = suggestion {
+        return Some(fill(
+            lang,
+            text(lang, Text::DidYouMean),
+            &[suggestion.to_owned()],
+        ));
+    }
+    entry(lang, code)
+        .and_then(|entry| entry.hint)
+        .map(|hint| fill(lang, hint, args))
+}
+
+// the full sentence explaining a diagnostic for --teach, it replaces the short message
+pub fn lesson(lang: Lang, code: &str, args: &[String]) -> Option<String> {
+    lang.lessons()
+        .iter()
+        .chain(ENGLISH_LESSONS)
+        .find(|lesson| lesson.code == code)
+        .map(|lesson| fill(lang, lesson.sentence, args))
+}
+
+// a line of correct code for --teach, the same in every language
+pub fn example(lang: Lang, code: &str, args: &[String]) -> Option<String> {
+    EXAMPLES
+        .iter()
+        .find(|(example_code, _)| *example_code == code)
+        .map(|(_, example)| fill(lang, example, args))
+}
+
+struct Entry {
+    code: &'static str,
+    message: &'static str,
+    hint: Option<&'static str>,
+}
+
+// falls back to english when a message isn't translated yet
+fn entry(lang: Lang, code: &str) -> Option<&'static Entry> {
+    lang.catalog()
+        .iter()
+        .chain(ENGLISH)
+        .find(|entry| entry.code == code)
+}
+
+// arguments are often token texts, newlines and the end of the file are described instead
+fn fill(lang: Lang, template: &str, args: &[String]) -> String {
+    let mut output = template.to_owned();
+    for (i, arg) in args.iter().enumerate() {
+        let arg = match arg.as_str() {
+            "\n" => text(lang, Text::EndOfLine),
+            "\0" => text(lang, Text::EndOfFile),
+            arg => arg,
+        };
+        output = output.replace(&format!["{{{}}}", i], arg);
+    }
+
+    output
+}
+
+const ENGLISH: &[Entry] = &[
+    Entry {
+        code: codes::UNKNOWN_CHARACTER,
+        message: "unknown token: {0}",
+        hint: None,
+    },
+    Entry {
+        code: codes::EXPECTED_NOT_EQ,
+        message: "Expected !=, got !{0}",
+        hint: Some("use != to check if two values are different"),
+    },
+    Entry {
+        code: codes::UNEXPECTED_TOKEN,
+        message: "Expected {0}, got {1}",
+        hint: None,
+    },
+    Entry {
+        code: codes::EXPECTED_EXPRESSION,
+        message: "Unexpected token at {0}",
+        hint: Some("expected a number, a variable or a function call"),
+    },
+    Entry {
+        code: codes::EXPECTED_COMPARISON,
+        message: "Expected comparison at: {0}",
+        hint: Some("IF and WHILE need a comparison, like `a < 10`"),
+    },
+    Entry {
+        code: codes::UNASSIGNED_VARIABLE,
+        message: "Referencing unassigned variable: {0}",
+        hint: Some("give the variable a value with LET or INPUT before using it"),
+    },
+    Entry {
+        code: codes::MAYBE_UNASSIGNED_VARIABLE,
+        message: "Variable might not have a value here: {0}",
+        hint: Some("not every way to get here gives `{0}` a value, give it one before the IF or the loop"),
+    },
+    Entry {
+        code: codes::DUPLICATE_LABEL,
+        message: "Label already exists: {0}",
+        hint: Some("every label name can only be declared once"),
+    },
+    Entry {
+        code: codes::UNDECLARED_LABEL,
+        message: "Attempting to GOTO to undeclared label: {0}",
+        hint: Some("declare the label with `LABEL {0}`"),
+    },
+    Entry {
+        code: codes::COMPARISON_AS_NUMBER,
+        message: "A comparison can't be used as a number",
+        hint: Some("comparisons can't be chained, nest two IFs instead"),
+    },
+    Entry {
+        code: codes::NUMBER_AS_CONDITION,
+        message: "Expected a comparison, found a number",
+        hint: Some("IF and WHILE need a comparison, like `a != 0`"),
+    },
+    Entry {
+        code: codes::MISSING_FEATURE,
+        message: "{0} needs haneul to be built with the {1} feature",
+        hint: Some("rebuild haneul with `cargo build --features {1}`"),
+    },
+    Entry {
+        code: codes::INVALID_STATEMENT,
+        message: "Invalid statement at: {0} ({1})",
+        hint: None,
+    },
+    Entry {
+        code: codes::UNKNOWN_BUILTIN,
+        message: "Unknown builtin: {0}",
+        hint: None,
+    },
+    Entry {
+        code: codes::NESTED_TOO_DEEPLY,
+        message: "nested more than {0} levels deep",
+        hint: Some("flatten the nesting or raise the limit with --max-depth"),
+    },
+    Entry {
+        code: codes::NOT_ALLOWED,
+        message: "{0} isn't allowed here, it needs allow_{1}",
+        hint: Some("the program that runs this one doesn't let it use {0}"),
+    },
+    Entry {
+        code: codes::UNTERMINATED_STRING,
+        message: "unterminated string literal starting at line {0}",
+        hint: Some("add a `\"` at the end of the string"),
+    },
+    Entry {
+        code: codes::NUMBER_WITHOUT_DIGITS,
+        message: "a number needs at least one digit, found a lone `.`",
+        hint: Some("write 0.5 or .5 instead of ."),
+    },
+    Entry {
+        code: codes::MALFORMED_NUMBER,
+        message: "malformed number literal: {0}",
+        hint: Some("names can't start with a digit, put an operator or a space between a number and a name"),
+    },
+    Entry {
+        code: codes::NUMBER_TOO_LARGE,
+        message: "number literal is too large: {0}",
+        hint: Some("numbers go from -{1} to {1}"),
+    },
+    Entry {
+        code: codes::UNUSED_VARIABLE,
+        message: "Variable is never read: {0}",
+        hint: Some("remove `{0}` or use its value"),
+    },
+    Entry {
+        code: codes::UNUSED_LABEL,
+        message: "Label is never used: {0}",
+        hint: None,
+    },
+    Entry {
+        code: codes::UNREACHABLE_STATEMENT,
+        message: "Unreachable statement",
+        hint: Some("a GOTO always jumps away before this or a condition is never true, put a LABEL here if it should run"),
+    },
+];
+
+const KOREAN: &[Entry] = &[
+    Entry {
+        code: codes::UNKNOWN_CHARACTER,
+        message: "알 수 없는 문자입니다: {0}",
+        hint: None,
+    },
+    Entry {
+        code: codes::EXPECTED_NOT_EQ,
+        message: "!= 이(가) 와야 하는데 !{0} 이(가) 있습니다",
+        hint: Some("두 값이 다른지 확인하려면 != 를 사용하세요"),
+    },
+    Entry {
+        code: codes::UNEXPECTED_TOKEN,
+        message: "{0} 이(가) 와야 하는데 {1} 이(가) 있습니다",
+        hint: None,
+    },
+    Entry {
+        code: codes::EXPECTED_EXPRESSION,
+        message: "예상하지 못한 토큰입니다: {0}",
+        hint: Some("숫자, 변수 또는 함수 호출이 와야 합니다"),
+    },
+    Entry {
+        code: codes::EXPECTED_COMPARISON,
+        message: "비교식이 와야 합니다: {0}",
+        hint: Some("IF 와 WHILE 에는 `a < 10` 같은 비교식이 필요합니다"),
+    },
+    Entry {
+        code: codes::UNASSIGNED_VARIABLE,
+        message: "값이 없는 변수를 사용했습니다: {0}",
+        hint: Some("변수를 사용하기 전에 LET 이나 INPUT 으로 값을 넣어 주세요"),
+    },
+    Entry {
+        code: codes::MAYBE_UNASSIGNED_VARIABLE,
+        message: "여기에서 변수에 값이 없을 수도 있습니다: {0}",
+        hint: Some("여기로 오는 모든 경우에 `{0}` 에 값이 들어가지는 않습니다. IF 나 반복문 앞에서 값을 넣어 주세요"),
+    },
+    Entry {
+        code: codes::DUPLICATE_LABEL,
+        message: "이미 있는 레이블입니다: {0}",
+        hint: Some("레이블 이름은 한 번만 선언할 수 있습니다"),
+    },
+    Entry {
+        code: codes::UNDECLARED_LABEL,
+        message: "선언되지 않은 레이블로 GOTO 하려고 합니다: {0}",
+        hint: Some("`LABEL {0}` 으로 레이블을 선언하세요"),
+    },
+    Entry {
+        code: codes::COMPARISON_AS_NUMBER,
+        message: "비교식은 숫자로 쓸 수 없습니다",
+        hint: Some("비교식은 이어서 쓸 수 없습니다. IF 를 두 번 겹쳐서 쓰세요"),
+    },
+    Entry {
+        code: codes::NUMBER_AS_CONDITION,
+        message: "비교식이 와야 하는데 숫자가 있습니다",
+        hint: Some("IF 와 WHILE 에는 `a != 0` 같은 비교식이 필요합니다"),
+    },
+    Entry {
+        code: codes::MISSING_FEATURE,
+        message: "{0} 을(를) 쓰려면 haneul 을 {1} 기능과 함께 빌드해야 합니다",
+        hint: Some("`cargo build --features {1}` 로 haneul 을 다시 빌드하세요"),
+    },
+    Entry {
+        code: codes::INVALID_STATEMENT,
+        message: "올바르지 않은 문장입니다: {0} ({1})",
+        hint: None,
+    },
+    Entry {
+        code: codes::UNKNOWN_BUILTIN,
+        message: "알 수 없는 내장 함수입니다: {0}",
+        hint: None,
+    },
+    Entry {
+        code: codes::NESTED_TOO_DEEPLY,
+        message: "{0}단계보다 깊게 중첩되었습니다",
+        hint: Some("중첩을 줄이거나 --max-depth 로 한도를 높이세요"),
+    },
+    Entry {
+        code: codes::NOT_ALLOWED,
+        message: "{0} 은(는) 여기에서 쓸 수 없습니다, allow_{1} 이 필요합니다",
+        hint: Some("이 프로그램을 실행하는 프로그램이 {0} 을(를) 허용하지 않습니다"),
+    },
+    Entry {
+        code: codes::UNTERMINATED_STRING,
+        message: "{0}번째 줄에서 시작한 문자열이 끝나지 않았습니다",
+        hint: Some("문자열 끝에 `\"` 를 붙이세요"),
+    },
+    Entry {
+        code: codes::NUMBER_WITHOUT_DIGITS,
+        message: "숫자에는 숫자가 하나 이상 있어야 하는데 `.` 만 있습니다",
+        hint: Some(". 대신 0.5 나 .5 처럼 쓰세요"),
+    },
+    Entry {
+        code: codes::MALFORMED_NUMBER,
+        message: "잘못된 숫자입니다: {0}",
+        hint: Some("이름은 숫자로 시작할 수 없습니다. 숫자와 이름 사이에 연산자나 공백을 넣으세요"),
+    },
+    Entry {
+        code: codes::NUMBER_TOO_LARGE,
+        message: "숫자가 너무 큽니다: {0}",
+        hint: Some("숫자는 -{1} 부터 {1} 까지 쓸 수 있습니다"),
+    },
+    Entry {
+        code: codes::UNUSED_VARIABLE,
+        message: "한 번도 읽지 않는 변수입니다: {0}",
+        hint: Some("`{0}` 을(를) 지우거나 그 값을 사용하세요"),
+    },
+    Entry {
+        code: codes::UNUSED_LABEL,
+        message: "한 번도 쓰이지 않는 레이블입니다: {0}",
+        hint: None,
+    },
+    Entry {
+        code: codes::UNREACHABLE_STATEMENT,
+        message: "실행될 수 없는 문장입니다",
+        hint: Some(
+            "앞의 GOTO 가 항상 다른 곳으로 이동하거나 조건이 절대 참이 되지 않습니다. 실행되어야 한다면 여기에 LABEL 을 두세요",
+        ),
+    },
+];
+
+struct Lesson {
+    code: &'static str,
+    sentence: &'static str,
+}
+
+const ENGLISH_LESSONS: &[Lesson] = &[
+    Lesson {
+        code: codes::UNKNOWN_CHARACTER,
+        sentence: "The character `{0}` isn't part of haneul, so this line can't be read.",
+    },
+    Lesson {
+        code: codes::EXPECTED_NOT_EQ,
+        sentence: "A `!` has to be followed by `=` to mean \"not equal\", but here it is followed by `{0}`.",
+    },
+    Lesson {
+        code: codes::UNEXPECTED_TOKEN,
+        sentence: "The program needs {0} here, but it found `{1}` instead.",
+    },
+    Lesson {
+        code: codes::EXPECTED_EXPRESSION,
+        sentence: "A value (a number, a variable or a function call) should come here, but it found `{0}`.",
+    },
+    Lesson {
+        code: codes::EXPECTED_COMPARISON,
+        sentence: "IF and WHILE need a comparison like `a < 10`, but it found `{0}`.",
+    },
+    Lesson {
+        code: codes::UNASSIGNED_VARIABLE,
+        sentence: "The variable `{0}` is used before it was given a value.",
+    },
+    Lesson {
+        code: codes::MAYBE_UNASSIGNED_VARIABLE,
+        sentence: "The variable `{0}` only has a value here on some of the ways the program can get to this line.",
+    },
+    Lesson {
+        code: codes::DUPLICATE_LABEL,
+        sentence: "The label `{0}` is declared twice, but every label needs its own name.",
+    },
+    Lesson {
+        code: codes::UNDECLARED_LABEL,
+        sentence: "GOTO jumps to the label `{0}`, but there is no `LABEL {0}` in the program.",
+    },
+    Lesson {
+        code: codes::COMPARISON_AS_NUMBER,
+        sentence: "A comparison is only true or false, so it can't be used as a number here.",
+    },
+    Lesson {
+        code: codes::NUMBER_AS_CONDITION,
+        sentence: "IF and WHILE decide what to do with a comparison, but this is a number.",
+    },
+    Lesson {
+        code: codes::MISSING_FEATURE,
+        sentence: "`{0}` only works when haneul is built with the `{1}` feature.",
+    },
+    Lesson {
+        code: codes::INVALID_STATEMENT,
+        sentence: "Every line has to start with a statement like PRINT or LET, but this one starts with `{0}`.",
+    },
+    Lesson {
+        code: codes::UNKNOWN_BUILTIN,
+        sentence: "`{0}` isn't a builtin that can be used here.",
+    },
+    Lesson {
+        code: codes::NESTED_TOO_DEEPLY,
+        sentence: "The program is nested more than {0} levels deep.",
+    },
+    Lesson {
+        code: codes::NOT_ALLOWED,
+        sentence: "`{0}` reaches outside of the program, and where it runs that isn't allowed.",
+    },
+    Lesson {
+        code: codes::UNTERMINATED_STRING,
+        sentence: "The string that starts on line {0} has no closing `\"`.",
+    },
+    Lesson {
+        code: codes::NUMBER_WITHOUT_DIGITS,
+        sentence: "A number needs at least one digit, a `.` on its own isn't a number.",
+    },
+    Lesson {
+        code: codes::MALFORMED_NUMBER,
+        sentence: "`{0}` isn't a number: names can't start with a digit, and a number has only one dot.",
+    },
+    Lesson {
+        code: codes::NUMBER_TOO_LARGE,
+        sentence: "The number `{0}` is too large, numbers can be at most {1}.",
+    },
+    Lesson {
+        code: codes::UNUSED_VARIABLE,
+        sentence: "The variable `{0}` gets a value, but that value is never used.",
+    },
+    Lesson {
+        code: codes::UNUSED_LABEL,
+        sentence: "The label `{0}` is declared, but no GOTO jumps to it.",
+    },
+    Lesson {
+        code: codes::UNREACHABLE_STATEMENT,
+        sentence: "No way through the program gets to this statement, so it can never run.",
+    },
+];
+
+const KOREAN_LESSONS: &[Lesson] = &[
+    Lesson {
+        code: codes::UNKNOWN_CHARACTER,
+        sentence: "`{0}` 문자는 haneul 에서 쓸 수 없어서 이 줄을 읽을 수 없습니다.",
+    },
+    Lesson {
+        code: codes::EXPECTED_NOT_EQ,
+        sentence: "`!` 뒤에는 \"다르다\"는 뜻이 되도록 `=` 가 와야 하는데 `{0}` 이(가) 있습니다.",
+    },
+    Lesson {
+        code: codes::UNEXPECTED_TOKEN,
+        sentence: "여기에는 {0} 이(가) 와야 하는데 `{1}` 이(가) 있습니다.",
+    },
+    Lesson {
+        code: codes::EXPECTED_EXPRESSION,
+        sentence: "여기에는 값(숫자, 변수 또는 함수 호출)이 와야 하는데 `{0}` 이(가) 있습니다.",
+    },
+    Lesson {
+        code: codes::EXPECTED_COMPARISON,
+        sentence: "IF 와 WHILE 에는 `a < 10` 같은 비교식이 필요한데 `{0}` 이(가) 있습니다.",
+    },
+    Lesson {
+        code: codes::UNASSIGNED_VARIABLE,
+        sentence: "변수 `{0}` 에 값을 넣기 전에 사용했습니다.",
+    },
+    Lesson {
+        code: codes::MAYBE_UNASSIGNED_VARIABLE,
+        sentence: "프로그램이 이 줄에 오는 경우 중 일부에서만 변수 `{0}` 에 값이 있습니다.",
+    },
+    Lesson {
+        code: codes::DUPLICATE_LABEL,
+        sentence: "레이블 `{0}` 이(가) 두 번 선언되었습니다. 레이블마다 다른 이름이 필요합니다.",
+    },
+    Lesson {
+        code: codes::UNDECLARED_LABEL,
+        sentence: "GOTO 가 레이블 `{0}` 로 이동하려고 하지만 프로그램에 `LABEL {0}` 이(가) 없습니다.",
+    },
+    Lesson {
+        code: codes::COMPARISON_AS_NUMBER,
+        sentence: "비교식은 참 아니면 거짓이라서 여기에서 숫자로 쓸 수 없습니다.",
+    },
+    Lesson {
+        code: codes::NUMBER_AS_CONDITION,
+        sentence: "IF 와 WHILE 은 비교식으로 할 일을 정하는데 여기에는 숫자가 있습니다.",
+    },
+    Lesson {
+        code: codes::MISSING_FEATURE,
+        sentence: "`{0}` 은(는) haneul 을 `{1}` 기능과 함께 빌드했을 때만 쓸 수 있습니다.",
+    },
+    Lesson {
+        code: codes::INVALID_STATEMENT,
+        sentence: "모든 줄은 PRINT 나 LET 같은 문장으로 시작해야 하는데 이 줄은 `{0}` 으로 시작합니다.",
+    },
+    Lesson {
+        code: codes::UNKNOWN_BUILTIN,
+        sentence: "`{0}` 은(는) 여기에서 쓸 수 있는 내장 함수가 아닙니다.",
+    },
+    Lesson {
+        code: codes::NESTED_TOO_DEEPLY,
+        sentence: "프로그램이 {0}단계보다 깊게 중첩되었습니다.",
+    },
+    Lesson {
+        code: codes::NOT_ALLOWED,
+        sentence: "`{0}` 은(는) 프로그램 밖에 접근하는데, 이 프로그램이 실행되는 곳에서는 허용되지 않습니다.",
+    },
+    Lesson {
+        code: codes::UNTERMINATED_STRING,
+        sentence: "{0}번째 줄에서 시작한 문자열에 닫는 `\"` 가 없습니다.",
+    },
+    Lesson {
+        code: codes::NUMBER_WITHOUT_DIGITS,
+        sentence: "숫자에는 숫자가 하나 이상 있어야 합니다. `.` 하나만으로는 숫자가 아닙니다.",
+    },
+    Lesson {
+        code: codes::MALFORMED_NUMBER,
+        sentence: "`{0}` 은(는) 숫자가 아닙니다. 이름은 숫자로 시작할 수 없고 숫자에는 점이 하나만 있습니다.",
+    },
+    Lesson {
+        code: codes::NUMBER_TOO_LARGE,
+        sentence: "숫자 `{0}` 이(가) 너무 큽니다. 숫자는 {1} 까지 쓸 수 있습니다.",
+    },
+    Lesson {
+        code: codes::UNUSED_VARIABLE,
+        sentence: "변수 `{0}` 에 값을 넣었지만 그 값을 한 번도 쓰지 않습니다.",
+    },
+    Lesson {
+        code: codes::UNUSED_LABEL,
+        sentence: "레이블 `{0}` 을(를) 선언했지만 그곳으로 GOTO 하는 곳이 없습니다.",
+    },
+    Lesson {
+        code: codes::UNREACHABLE_STATEMENT,
+        sentence: "프로그램이 이 문장에 도달하는 경우가 없어서 절대 실행되지 않습니다.",
+    },
+];
+
+const EXAMPLES: &[(&str, &str)] = &[
+    (codes::UNKNOWN_CHARACTER, "LET price = 10"),
+    (codes::EXPECTED_NOT_EQ, "IF a != 3 THEN"),
+    (codes::UNEXPECTED_TOKEN, "IF a > 3 THEN"),
+    (codes::EXPECTED_EXPRESSION, "LET b = a * 2"),
+    (codes::EXPECTED_COMPARISON, "WHILE a > 0 REPEAT"),
+    (codes::UNASSIGNED_VARIABLE, "LET {0} = 0"),
+    (codes::MAYBE_UNASSIGNED_VARIABLE, "LET {0} = 0"),
+    (codes::UNDECLARED_LABEL, "LABEL {0}"),
+    (codes::COMPARISON_AS_NUMBER, "IF a < b THEN"),
+    (codes::NUMBER_AS_CONDITION, "IF a != 0 THEN"),
+    (codes::MISSING_FEATURE, "cargo build --features {1}"),
+    (codes::INVALID_STATEMENT, "LET a = 5"),
+    (codes::UNTERMINATED_STRING, "PRINT \"hello\""),
+    (codes::NUMBER_WITHOUT_DIGITS, "LET half = 0.5"),
+    (codes::MALFORMED_NUMBER, "LET a = 2 * b"),
+    (codes::UNUSED_VARIABLE, "PRINT {0}"),
+    (codes::UNUSED_LABEL, "GOTO {0}"),
+];
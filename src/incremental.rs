@@ -0,0 +1,113 @@
+// re-lexing after an edit, for editors that lex on every keystroke. no token goes past the end
+// of a line, so only the lines the edit touches are lexed again, the tokens after them are moved
+
+use crate::{
+    lexer::{self, LexError, Lexer},
+    source::FileId,
+    token::{Span, Token, TokenType},
+};
+
+// replaces `length` bytes at `offset` with `text`, offsets are in bytes like in spans
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub offset: usize,
+    pub length: usize,
+    pub text: String,
+}
+
+// `tokens` are the tokens from before the edit and `source` is the source after it.
+// gives the same tokens as lexing all of `source` again would
+pub fn relex<'src>(
+    file: FileId,
+    source: &'src str,
+    tokens: &[Result<Token, LexError>],
+    edit: &TextEdit,
+) -> Vec<Result<Token<'src>, LexError>> {
+    // the whole lines the edit is on, after the edit
+    let region_start = source[..edit.offset].rfind('\n').map_or(0, |i| i + 1);
+    let edit_end = edit.offset + edit.text.len();
+    let region_end = source[edit_end..]
+        .find('\n')
+        .map_or(source.len(), |i| edit_end + i + 1);
+    let old_region_end = region_end - edit.text.len() + edit.length;
+    // on the last line the newline and Eof the Lexer adds have to be lexed again too
+    let to_end = region_end == source.len();
+
+    let mut relexed: Vec<Result<Token, LexError>> = tokens
+        .iter()
+        .take_while(|token| span_of(token).offset < region_start && !added(token))
+        .map(|token| moved(token, source, 0, 0))
+        .collect();
+
+    let first_line = source[..region_start].matches('\n').count() + 1;
+    let mut new_lines = 0;
+    for token in Lexer::new(file, &source[region_start..region_end]) {
+        if !to_end && added(&token) {
+            break;
+        }
+        let mut span = span_of(&token);
+        span.line += first_line - 1;
+        span.offset += region_start;
+
+        relexed.push(match token {
+            Ok(token) => {
+                if token.kind() == TokenType::Newline {
+                    new_lines += 1;
+                }
+                Ok(token.with_span(span))
+            }
+            Err(error) => Err(error.with_span(span)),
+        });
+    }
+    if to_end {
+        return relexed;
+    }
+
+    let old_lines = tokens
+        .iter()
+        .filter(|token| (region_start..old_region_end).contains(&span_of(token).offset))
+        .filter(|token| matches!(token, Ok(token) if token.kind() == TokenType::Newline))
+        .count();
+    let line_shift = new_lines as isize - old_lines as isize;
+    let offset_shift = edit.text.len() as isize - edit.length as isize;
+    relexed.extend(
+        tokens
+            .iter()
+            .filter(|token| span_of(token).offset >= old_region_end)
+            .map(|token| moved(token, source, offset_shift, line_shift)),
+    );
+
+    relexed
+}
+
+// the newline the Lexer adds and the Eof, they're where the last line ends
+fn added(token: &Result<Token, LexError>) -> bool {
+    matches!(token, Ok(token) if token.span().byte_length == 0)
+}
+
+fn span_of(token: &Result<Token, LexError>) -> Span {
+    match token {
+        Ok(token) => token.span(),
+        Err(error) => error.span(),
+    }
+}
+
+// a token that didn't change, with its text taken from the new source
+fn moved<'src>(
+    token: &Result<Token, LexError>,
+    source: &'src str,
+    offset_shift: isize,
+    line_shift: isize,
+) -> Result<Token<'src>, LexError> {
+    let mut span = span_of(token);
+    span.offset = span.offset.saturating_add_signed(offset_shift);
+    span.line = span.line.saturating_add_signed(line_shift);
+
+    match token {
+        Ok(token) => {
+            let text = &source[span.offset..span.offset + span.byte_length];
+            Ok(Token::new(lexer::token_text(token.kind(), text), token.kind()).with_span(span))
+        }
+        Err(error) => Err(error.clone().with_span(span)),
+    }
+}
@@ -0,0 +1,75 @@
+// names of variables and labels are interned: every name is stored once and the rest of the
+// compiler passes around a Symbol, which is cheap to copy, compare and hash. the names are
+// in an Arc, so a parsed Program can be shared between threads
+
+use crate::prelude::*;
+
+use alloc::sync::Arc;
+#[cfg(feature = "serde")]
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+// serialized as its index in the names of the Interner
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Symbol(u32);
+
+#[derive(Debug, Default)]
+pub struct Interner {
+    // the map and the list share the same allocation for a name
+    symbols: HashMap<Arc<str>, Symbol>,
+    names: Vec<Arc<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn intern(&mut self, name: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(name) {
+            return *symbol;
+        }
+
+        let symbol = Symbol(self.names.len() as u32);
+        let name: Arc<str> = Arc::from(name);
+        self.names.push(Arc::clone(&name));
+        self.symbols.insert(name, symbol);
+        symbol
+    }
+
+    // the symbol of a name, without interning it when it isn't there
+    pub fn get(&self, name: &str) -> Option<Symbol> {
+        self.symbols.get(name).copied()
+    }
+
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.names[symbol.0 as usize]
+    }
+
+    // a symbol that was deserialized can be one this never gave out
+    pub fn contains(&self, symbol: Symbol) -> bool {
+        (symbol.0 as usize) < self.names.len()
+    }
+}
+
+// the names in the order of their symbols
+#[cfg(feature = "serde")]
+impl Serialize for Interner {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.names.iter().map(|name| &**name))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Interner {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut interner = Interner::new();
+        for name in Vec::<String>::deserialize(deserializer)? {
+            if interner.get(&name).is_some() {
+                return Err(D::Error::custom(format!["{} is in the names twice", name]));
+            }
+            interner.intern(&name);
+        }
+        Ok(interner)
+    }
+}
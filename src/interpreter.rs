@@ -0,0 +1,579 @@
+// runs a program straight from its tree, for `haneul run --engine=tree`. it runs the same as on
+// the vm, the numbers and what is done with them are in runtime.rs
+//
+// a GOTO can go into the body of an IF or a WHILE, so every LABEL has a path: the index of the
+// statement in every body on the way to it. a GOTO goes back up to the program, which starts
+// again at the path of the LABEL, going into the bodies without checking their condition
+//
+// the builtins it runs are the ones of the vm, see runtime::Native. a program that embeds haneul
+// gives the interpreter its own too, see register and builtins::host
+
+use std::{
+    collections::HashMap,
+    io::{BufWriter, Write},
+};
+
+use crate::{
+    ast::{ExprId, ExprKind, Exprs, Program, Statement, StatementKind, UnaryOp},
+    builtins::{Platform, Registry},
+    ctype::{self, CType, FloatDivision},
+    intern::Symbol,
+    runtime::{
+        self, CancellationToken, ErrorKind, ExecutionPolicy, Guard, Input, Limits, Native, Output,
+        RuntimeError, Value,
+    },
+    visit::{self, Visitor},
+};
+
+pub struct Interpreter<'a> {
+    program: &'a Program,
+    // with the type of the variable, see ctype::infer
+    variables: HashMap<Symbol, Value>,
+    labels: HashMap<Symbol, Vec<usize>>,
+    // of the statement that runs
+    line: usize,
+    policy: ExecutionPolicy,
+    cancellation: CancellationToken,
+    guard: Guard,
+    // the steps that weren't given to the guard yet, see count
+    steps: u64,
+    float_division: FloatDivision,
+    input: Input,
+    output: Output<'a>,
+    // the builtins the program calls, with how many arguments and on which line
+    calls: Vec<(String, usize, usize)>,
+    // about how many bytes the tree of the program takes up, see Footprint
+    footprint: usize,
+    hosts: HashMap<String, Host<'a>>,
+    // the builtins of the program that the interpreter runs itself, a host function with the
+    // same name comes first
+    natives: HashMap<String, Native>,
+}
+
+// a registered host function, with the numbers it takes made into Values
+struct Host<'a> {
+    arity: usize,
+    function: Function<'a>,
+}
+
+// Send like the rest of the interpreter, for a server that runs it on another thread
+type Function<'a> = Box<dyn FnMut(&[Value]) -> Result<Value, String> + Send + 'a>;
+
+// what happens after a statement
+enum Flow {
+    Next,
+    Goto(Symbol),
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(program: &'a Program) -> Self {
+        let mut builtins = Builtins { calls: vec![] };
+        visit::walk_program(&mut builtins, program);
+        let mut footprint = Footprint { memory: 0 };
+        visit::walk_program(&mut footprint, program);
+        let mut labels = HashMap::new();
+        find_labels(&program.statements, &mut vec![], &mut labels);
+        let registry = Registry::new();
+        let natives = builtins
+            .calls
+            .iter()
+            .filter_map(|(name, ..)| {
+                let implementation = registry.get(name)?.implementation(Platform::Run)?;
+                Some((name.clone(), Native::named(implementation.function)?))
+            })
+            .collect();
+        Self {
+            program,
+            variables: HashMap::new(),
+            labels,
+            line: 1,
+            policy: ExecutionPolicy::default(),
+            cancellation: CancellationToken::new(),
+            guard: Guard::new(&Limits::default(), &CancellationToken::new()),
+            steps: 0,
+            float_division: FloatDivision::default(),
+            input: Input::new(),
+            output: runtime::stdout(),
+            calls: builtins.calls,
+            footprint: footprint.memory,
+            hosts: HashMap::new(),
+            natives,
+        }
+    }
+
+    // what the builtin `name` does, for a builtin the program was parsed with that was made with
+    // builtins::host. the function can be a closure that borrows the state of the host:
+    //
+    //   interpreter.register("SPAWN", |x: f32, y: f32| enemies.push((x, y)));
+    //   interpreter.register("HEALTH", || player.health);
+    //
+    // the arguments are converted to the types the function takes, see HostFunction. a
+    // function registered again replaces the old one. it has to be Send, so state it shares
+    // with the host goes in a Mutex or an atomic instead of a RefCell or a Cell
+    pub fn register<Args>(
+        &mut self,
+        name: &str,
+        mut function: impl HostFunction<Args> + Send + 'a,
+    ) {
+        self.hosts.insert(
+            name.to_owned(),
+            Host {
+                arity: function.arity(),
+                function: Box::new(move |arguments| function.call(arguments)),
+            },
+        );
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.policy.limits = limits;
+    }
+
+    // for a program that isn't trusted: its limits, how much memory it may take up and what the
+    // builtins it calls may reach outside of it, see Program::needs
+    pub fn set_policy(&mut self, policy: ExecutionPolicy) {
+        self.policy = policy;
+    }
+
+    // cancelling it from another thread stops the run, see CancellationToken
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
+    pub fn set_input(&mut self, input: Input) {
+        self.input = input;
+    }
+
+    // what the program PRINTs goes to `output` instead of stdout, like a Vec<u8> that a test
+    // looks at after the run. it gets every write when it's made, it isn't buffered like stdout
+    pub fn set_output(&mut self, output: impl Write + Send + 'a) {
+        self.output = BufWriter::with_capacity(0, Box::new(output));
+    }
+
+    // after a run, what the INPUTs gave with --record
+    pub fn recorded(&self) -> Option<&[Option<f32>]> {
+        self.input.recorded()
+    }
+
+    // the value a variable has after a run, for a test or a grader that checks what a program
+    // worked out. None when the program never gives it a value
+    pub fn get(&self, name: &str) -> Option<Value> {
+        let variable = self.program.names.get(name)?;
+        let ty = self.program.types.get(&variable)?;
+        Some(match self.variables.get(&variable) {
+            Some(value) => *value,
+            None => Value::Int(0).convert(*ty),
+        })
+    }
+
+    pub fn set_float_division(&mut self, float_division: FloatDivision) {
+        self.float_division = float_division;
+    }
+
+    pub fn run(&mut self) -> Result<(), RuntimeError> {
+        // the clock of the timeout starts here
+        self.guard = Guard::new(&self.policy.limits, &self.cancellation);
+        self.steps = 0;
+        self.check()?;
+        let program = self.program;
+        let mut path = vec![];
+        let result = loop {
+            match self.statements(&program.statements, &path) {
+                Ok(Flow::Next) => break Ok(()),
+                Ok(Flow::Goto(label)) => path = self.labels[&label].clone(),
+                Err(error) => break Err(error),
+            }
+        };
+        let _ = self.output.flush();
+        result
+    }
+
+    // starts at the path, or at the first statement when it's empty
+    fn statements(
+        &mut self,
+        statements: &'a [Statement],
+        path: &[usize],
+    ) -> Result<Flow, RuntimeError> {
+        let (start, inside) = path.split_first().unwrap_or((&0, &[]));
+        for (index, statement) in statements.iter().enumerate().skip(*start) {
+            let inside = if index == *start { inside } else { &[] };
+            if let Flow::Goto(label) = self.statement(statement, inside)? {
+                return Ok(Flow::Goto(label));
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    // the path goes into the body of an IF or a WHILE
+    fn statement(
+        &mut self,
+        statement: &'a Statement,
+        path: &[usize],
+    ) -> Result<Flow, RuntimeError> {
+        self.line = statement.span.line;
+        self.step()?;
+        match &statement.kind {
+            StatementKind::Print(value) => {
+                let value = self.expression(*value)?;
+                let _ = writeln!(self.output, "{}", runtime::format_value(value));
+            }
+            StatementKind::PrintString(string) => {
+                let _ = writeln!(self.output, "{}", string);
+            }
+            StatementKind::If { condition, body } => {
+                if !path.is_empty() || !self.expression(*condition)?.is_zero() {
+                    return self.statements(body, path);
+                }
+            }
+            StatementKind::While { condition, body } => {
+                let mut path = path;
+                while !path.is_empty() || !self.expression(*condition)?.is_zero() {
+                    if let Flow::Goto(label) = self.statements(body, path)? {
+                        return Ok(Flow::Goto(label));
+                    }
+                    path = &[];
+                    self.line = statement.span.line;
+                    // a loop with nothing in it still takes a step every time round
+                    self.step()?;
+                    self.count()?;
+                }
+            }
+            StatementKind::Label(_) => {}
+            StatementKind::Goto(label) => {
+                self.count()?;
+                return Ok(Flow::Goto(*label));
+            }
+            StatementKind::Let { variable, value } => {
+                let value = self.convert(*value, ctype::variable(self.program, *variable))?;
+                self.variables.insert(*variable, value);
+            }
+            StatementKind::Input(variable) => {
+                // so the program's question is there before it waits
+                let _ = self.output.flush();
+                let mut value = self
+                    .variables
+                    .get(variable)
+                    .map_or(0.0, |value| value.float());
+                self.input.read(&mut value);
+                self.variables.insert(*variable, Value::Float(value));
+            }
+            StatementKind::Call { name, arguments } => {
+                self.call(name, arguments)?;
+            }
+        }
+
+        Ok(Flow::Next)
+    }
+
+    // every statement is a step, see Limits. the memory is checked there too
+    fn step(&mut self) -> Result<(), RuntimeError> {
+        self.steps += 1;
+        self.policy.check_memory(self.memory(), self.line)
+    }
+
+    // the steps are given to the guard where the vm counts its instructions, at the end of a
+    // WHILE and at a GOTO, so the two stop a program that runs too long on the same line
+    fn count(&mut self) -> Result<(), RuntimeError> {
+        match self.guard.take(core::mem::take(&mut self.steps)) {
+            true => Ok(()),
+            false => Err(self.guard.exceeded(self.line)),
+        }
+    }
+
+    // about how many bytes the program takes up now: its tree, the variables that have a value
+    // and the line of input it's reading
+    fn memory(&self) -> usize {
+        self.footprint
+            + self.variables.len() * size_of::<(Symbol, Value)>()
+            + self.input.pending().len()
+    }
+
+    fn expression(&mut self, expression: ExprId) -> Result<Value, RuntimeError> {
+        let exprs = &self.program.exprs;
+        Ok(match &exprs[expression].kind {
+            ExprKind::Number(text) => Value::number(text),
+            ExprKind::Variable(variable) => match self.variables.get(variable) {
+                Some(value) => *value,
+                None => Value::Int(0).convert(ctype::variable(self.program, *variable)),
+            },
+            // a call is a float, see ctype::of
+            ExprKind::Call { name, arguments } => self.call(name, arguments)?.convert(CType::Float),
+            ExprKind::Unary { op, operand } => {
+                let value = self.expression(*operand)?;
+                match op {
+                    UnaryOp::Plus => value,
+                    UnaryOp::Minus => value.negate(),
+                }
+            }
+            ExprKind::Binary { op, left, right } => {
+                let (op, left, right) = (*op, *left, *right);
+                let common = ctype::common(self.program, left, right);
+                let left = self.convert(left, common)?;
+                let right = self.convert(right, common)?;
+                runtime::binary(op, left, right, self.float_division).ok_or_else(|| {
+                    RuntimeError {
+                        kind: ErrorKind::Failed,
+                        message: String::from(ctype::division_by_zero(common)),
+                        line: self.line,
+                    }
+                })?
+            }
+        })
+    }
+
+    // that the interpreter has every builtin the program calls, with the number of arguments it
+    // calls it with. run checks it before anything runs, so a program doesn't go wrong halfway
+    // through, a host checks it before the run to turn the program away like the vm does
+    pub fn check(&self) -> Result<(), RuntimeError> {
+        for (name, count, line) in &self.calls {
+            let message = match self.hosts.get(name) {
+                None if self.natives.contains_key(name) => continue,
+                None => format!["{} can't be run yet", name],
+                Some(host) if host.arity != *count => {
+                    let numbers = if host.arity == 1 { "number" } else { "numbers" };
+                    format!["{} takes {} {}, not {}", name, host.arity, numbers, count]
+                }
+                Some(_) => continue,
+            };
+            return Err(RuntimeError {
+                kind: ErrorKind::Failed,
+                message,
+                line: *line,
+            });
+        }
+        Ok(())
+    }
+
+    fn call(&mut self, name: &str, arguments: &[ExprId]) -> Result<Value, RuntimeError> {
+        let mut values = Vec::with_capacity(arguments.len());
+        for argument in arguments {
+            values.push(self.expression(*argument)?);
+        }
+        let needs = self.program.needs.get(name).map_or(&[][..], Vec::as_slice);
+        self.policy.check_builtin(name, needs, self.line)?;
+        let Some(host) = self.hosts.get_mut(name) else {
+            let native = self.natives[name];
+            let value = native.call(&mut self.input, &mut self.output);
+            return Ok(Value::Float(value));
+        };
+        // the output of the program comes before what the host prints
+        let _ = self.output.flush();
+        (host.function)(&values).map_err(|message| RuntimeError {
+            kind: ErrorKind::Failed,
+            message,
+            line: self.line,
+        })
+    }
+
+    // the expression as another type, like c converts it for a calculation or a LET
+    fn convert(&mut self, expression: ExprId, to: CType) -> Result<Value, RuntimeError> {
+        Ok(self.expression(expression)?.convert(to))
+    }
+}
+
+fn find_labels(
+    statements: &[Statement],
+    path: &mut Vec<usize>,
+    labels: &mut HashMap<Symbol, Vec<usize>>,
+) {
+    for (index, statement) in statements.iter().enumerate() {
+        path.push(index);
+        match &statement.kind {
+            StatementKind::Label(label) => {
+                labels.insert(*label, path.clone());
+            }
+            StatementKind::If { body, .. } | StatementKind::While { body, .. } => {
+                find_labels(body, path, labels)
+            }
+            _ => {}
+        }
+        path.pop();
+    }
+}
+
+// every call of a builtin in the program
+struct Builtins {
+    calls: Vec<(String, usize, usize)>,
+}
+
+impl Visitor for Builtins {
+    fn visit_stmt(&mut self, exprs: &Exprs, statement: &Statement) {
+        if let StatementKind::Call { name, arguments } = &statement.kind {
+            self.calls
+                .push((name.clone(), arguments.len(), statement.span.line));
+        }
+        visit::walk_stmt(self, exprs, statement);
+    }
+
+    fn visit_expr(&mut self, exprs: &Exprs, expression: ExprId) {
+        if let ExprKind::Call { name, arguments } = &exprs[expression].kind {
+            let line = exprs[expression].span.line;
+            self.calls.push((name.clone(), arguments.len(), line));
+        }
+        visit::walk_expr(self, exprs, expression);
+    }
+}
+
+// the statements and the expressions of the program, with the strings it prints. the variables
+// come on top of it
+struct Footprint {
+    memory: usize,
+}
+
+impl Visitor for Footprint {
+    fn visit_stmt(&mut self, exprs: &Exprs, statement: &Statement) {
+        self.memory += size_of::<Statement>();
+        if let StatementKind::PrintString(string) = &statement.kind {
+            self.memory += string.len();
+        }
+        visit::walk_stmt(self, exprs, statement);
+    }
+
+    fn visit_expr(&mut self, exprs: &Exprs, expression: ExprId) {
+        self.memory += size_of_val(&exprs[expression]);
+        visit::walk_expr(self, exprs, expression);
+    }
+}
+
+// a rust function or closure that can be registered as a host function. it takes up to
+// builtins::MAX_HOST_ARITY arguments of types that are Arguments and gives back a Returned. `Args` is
+// only there to tell the impls for the different arities apart
+pub trait HostFunction<Args> {
+    fn arity(&self) -> usize;
+    fn call(&mut self, arguments: &[Value]) -> Result<Value, String>;
+}
+
+// what a number of the program is made into for a host function. an int the program gives to
+// an f32 is converted like a LET converts it, a float given to an int is truncated like c does
+pub trait Argument {
+    fn from_value(value: Value) -> Self;
+}
+
+impl Argument for Value {
+    fn from_value(value: Value) -> Self {
+        value
+    }
+}
+
+impl Argument for f32 {
+    fn from_value(value: Value) -> Self {
+        match value {
+            Value::Int(value) => value as f32,
+            Value::Long(value) => value as f32,
+            Value::Float(value) => value,
+            Value::Double(value) => value as f32,
+        }
+    }
+}
+
+impl Argument for f64 {
+    fn from_value(value: Value) -> Self {
+        match value.convert(CType::Double) {
+            Value::Double(value) => value,
+            _ => unreachable!("it was converted to a double"),
+        }
+    }
+}
+
+impl Argument for i32 {
+    fn from_value(value: Value) -> Self {
+        i64::from_value(value) as i32
+    }
+}
+
+impl Argument for i64 {
+    fn from_value(value: Value) -> Self {
+        match value {
+            Value::Int(value) => value as i64,
+            Value::Long(value) => value,
+            Value::Float(value) => value as i64,
+            Value::Double(value) => value as i64,
+        }
+    }
+}
+
+// what a host function gives back to the program. nothing is 0, true and false are 1 and 0
+// like a comparison, an Err stops the program with its message
+pub trait Returned {
+    fn into_value(self) -> Result<Value, String>;
+}
+
+impl Returned for () {
+    fn into_value(self) -> Result<Value, String> {
+        Ok(Value::Int(0))
+    }
+}
+
+impl Returned for bool {
+    fn into_value(self) -> Result<Value, String> {
+        Ok(Value::Int(self as i32))
+    }
+}
+
+impl Returned for Value {
+    fn into_value(self) -> Result<Value, String> {
+        Ok(self)
+    }
+}
+
+impl Returned for f32 {
+    fn into_value(self) -> Result<Value, String> {
+        Ok(Value::Float(self))
+    }
+}
+
+impl Returned for f64 {
+    fn into_value(self) -> Result<Value, String> {
+        Ok(Value::Double(self))
+    }
+}
+
+impl Returned for i32 {
+    fn into_value(self) -> Result<Value, String> {
+        Ok(Value::Int(self))
+    }
+}
+
+impl Returned for i64 {
+    fn into_value(self) -> Result<Value, String> {
+        Ok(Value::Long(self))
+    }
+}
+
+impl<T: Returned, E: ToString> Returned for Result<T, E> {
+    fn into_value(self) -> Result<Value, String> {
+        self.map_err(|error| error.to_string())?.into_value()
+    }
+}
+
+macro_rules! host_function {
+    ($($argument:ident),*) => {
+        impl<F, R, $($argument),*> HostFunction<($($argument,)*)> for F
+        where
+            F: FnMut($($argument),*) -> R,
+            R: Returned,
+            $($argument: Argument),*
+        {
+            fn arity(&self) -> usize {
+                <[&str]>::len(&[$(stringify!($argument)),*])
+            }
+
+            #[allow(non_snake_case, unused_variables, unused_mut)]
+            fn call(&mut self, arguments: &[Value]) -> Result<Value, String> {
+                let mut arguments = arguments.iter();
+                $(let $argument = $argument::from_value(
+                    *arguments.next().expect("run checks the arity"),
+                );)*
+                self($($argument),*).into_value()
+            }
+        }
+    };
+}
+
+host_function!();
+host_function!(A);
+host_function!(A, B);
+host_function!(A, B, C);
+host_function!(A, B, C, D);
+host_function!(A, B, C, D, E);
+// as many as builtins::MAX_HOST_ARITY
+host_function!(A, B, C, D, E, G);
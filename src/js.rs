@@ -0,0 +1,592 @@
+// --target=js, the program as an out.js that runs in node and in a browser. like the rust
+// backend it is the control flow graph of the program: a switch in a loop, on the block that
+// runs next. every number in javascript is a double, so the types c gives them (see ctype.rs)
+// are made by hand: a float calculation is rounded with Math.fround, an int one with | 0.
+// a long is a BigInt, cut back to 64 bits after every calculation like c wraps it around
+//
+// INPUT reads a line with prompt() in a browser and from stdin in node
+
+use std::fmt::Write;
+
+use crate::{
+    ast::{BinaryOp, ExprId, ExprKind, Program, Statement, StatementKind, UnaryOp},
+    builtins::{Platform, Registry},
+    cfg::{Cfg, Terminator},
+    ctype::{self, CType, FloatDivision},
+    intern::Symbol,
+};
+
+// gives the name of a builtin the program uses that has no javascript implementation (see
+// builtins::Platform). with the code come the positions of the statements in it for the source
+// map, the byte where one starts and its line
+pub fn generate(
+    program: &Program,
+    builtins: &Registry,
+    float_division: FloatDivision,
+) -> Result<(String, Vec<(usize, usize)>), String> {
+    let cfg = Cfg::new(program);
+    let reachable = cfg.reachable();
+    let mut generator = Generator {
+        program,
+        builtins,
+        float_division,
+        helpers_used: vec![],
+        unsupported: None,
+    };
+
+    let mut code = String::new();
+    let mut positions = vec![];
+    code.push_str("// made by haneul\n\"use strict\";\n");
+    code.push_str(RUNTIME);
+    code.push_str("\nfunction haneul_main() {\n");
+    for variable in variables(&program.statements) {
+        let zero = match ctype::variable(program, variable) {
+            CType::Long => "0n",
+            _ => "0",
+        };
+        let _ = writeln!(code, "    let {} = {};", generator.name(variable), zero);
+    }
+    code.push_str("    let haneul_block = 0;\n");
+    code.push_str("    for (;;) {\n");
+    code.push_str("        switch (haneul_block) {\n");
+    for id in cfg.ids().filter(|id| reachable[id.index()]) {
+        let block = cfg.block(id);
+        let _ = writeln!(code, "            case {}:", id.index());
+        for statement in &block.statements {
+            positions.push((code.len(), statement.span.line));
+            let statement = generator.statement(statement);
+            let _ = writeln!(code, "                {}", statement);
+        }
+        if let Terminator::Branch { condition, .. } = block.terminator {
+            positions.push((code.len(), program.exprs[condition].span.line));
+        }
+        let next = match block.terminator {
+            Terminator::Jump(target) => {
+                format!["haneul_block = {};\n                break;", target.index()]
+            }
+            Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            } => format![
+                "haneul_block = {} ? {} : {};\n                break;",
+                generator.condition(condition),
+                then.index(),
+                otherwise.index()
+            ],
+            Terminator::Return => String::from("return;"),
+        };
+        let _ = writeln!(code, "                {}", next);
+    }
+    code.push_str("        }\n");
+    code.push_str("    }\n");
+    code.push_str("}\n");
+    for helper in builtins.helpers(Platform::Js) {
+        if generator.helpers_used.contains(&helper.name) {
+            let _ = write!(code, "\n{}\n", helper.code);
+        }
+    }
+    code.push_str("\nhaneul_main();\n");
+
+    match generator.unsupported {
+        Some(builtin) => Err(builtin),
+        None => Ok((code, positions)),
+    }
+}
+
+struct Generator<'a> {
+    program: &'a Program,
+    builtins: &'a Registry,
+    float_division: FloatDivision,
+    helpers_used: Vec<&'static str>,
+    unsupported: Option<String>,
+}
+
+impl Generator<'_> {
+    // only the statements that stay in a block of the control flow graph
+    fn statement(&mut self, statement: &Statement) -> String {
+        match &statement.kind {
+            StatementKind::Print(value) => match ctype::of(self.program, *value) {
+                CType::Int | CType::Long => {
+                    format!["haneul_print_integer({});", self.expression(*value)]
+                }
+                _ => format![
+                    "haneul_print_number({});",
+                    self.convert(*value, CType::Float)
+                ],
+            },
+            StatementKind::PrintString(string) => {
+                format!["haneul_print_string({});", string_literal(string)]
+            }
+            StatementKind::Let { variable, value } => format![
+                "{} = {};",
+                self.name(*variable),
+                self.convert(*value, ctype::variable(self.program, *variable))
+            ],
+            StatementKind::Input(variable) => {
+                let name = self.name(*variable);
+                format!["{} = haneul_input({});", name, name]
+            }
+            StatementKind::Call { name, arguments } => format!["{};", self.call(name, arguments)],
+            StatementKind::If { .. }
+            | StatementKind::While { .. }
+            | StatementKind::Label(_)
+            | StatementKind::Goto(_) => unreachable!("these are the edges of the graph"),
+        }
+    }
+
+    // a boolean, where c checks if the number isn't 0
+    fn condition(&mut self, condition: ExprId) -> String {
+        let exprs = &self.program.exprs;
+        match exprs[condition].kind {
+            ExprKind::Binary { op, left, right } if op.is_comparison() => {
+                let common = ctype::common(self.program, left, right);
+                format![
+                    "({} {} {})",
+                    self.convert(left, common),
+                    js_op(op),
+                    self.convert(right, common)
+                ]
+            }
+            _ => match ctype::of(self.program, condition) {
+                CType::Long => format!["({} !== 0n)", self.expression(condition)],
+                _ => format!["({} !== 0)", self.expression(condition)],
+            },
+        }
+    }
+
+    fn expression(&mut self, expression: ExprId) -> String {
+        let exprs = &self.program.exprs;
+        match &exprs[expression].kind {
+            // written again, a leading 0 is an error in strict javascript
+            ExprKind::Number(text) => match (text.parse::<i64>(), ctype::number(text)) {
+                (Ok(value), CType::Int) => value.to_string(),
+                (Ok(value), CType::Long) => format!["{}n", value],
+                _ => format!["{:?}", text.parse::<f64>().unwrap_or_default()],
+            },
+            ExprKind::Variable(variable) => self.name(*variable),
+            ExprKind::Call { name, arguments } => self.call(name, arguments),
+            ExprKind::Unary { op, operand } => {
+                let ty = ctype::of(self.program, *operand);
+                let operand = self.expression(*operand);
+                match op {
+                    UnaryOp::Plus => operand,
+                    UnaryOp::Minus if ty == CType::Int => format!["(-{} | 0)", operand],
+                    UnaryOp::Minus if ty == CType::Long => {
+                        format!["haneul_wrap_long(-{})", operand]
+                    }
+                    UnaryOp::Minus => format!["(-{})", operand],
+                }
+            }
+            ExprKind::Binary { op, left, right } => {
+                let (op, left, right) = (*op, *left, *right);
+                let common = ctype::common(self.program, left, right);
+                let left = self.convert(left, common);
+                let right = self.convert(right, common);
+                let calculation = format!["{} {} {}", left, js_op(op), right];
+                let checked = common.is_integer() || self.float_division == FloatDivision::Error;
+                match (common, op) {
+                    _ if op.is_comparison() => format!["({} ? 1 : 0)", calculation],
+                    (_, BinaryOp::Divide | BinaryOp::Remainder) if checked => format![
+                        "haneul_{}_{}({}, {}, {})",
+                        if op == BinaryOp::Divide {
+                            "divide"
+                        } else {
+                            "remainder"
+                        },
+                        c_name(common),
+                        left,
+                        right,
+                        self.program.exprs[expression].span.line
+                    ],
+                    (CType::Int, BinaryOp::Multiply) => format!["haneul_imul({}, {})", left, right],
+                    (CType::Int, _) => format!["(({}) | 0)", calculation],
+                    (CType::Float, _) => format!["haneul_fround({})", calculation],
+                    (CType::Long, _) => format!["haneul_wrap_long({})", calculation],
+                    _ => format!["({})", calculation],
+                }
+            }
+        }
+    }
+
+    // the function of the builtin with the arguments as floats, its helper comes after main
+    fn call(&mut self, name: &str, arguments: &[ExprId]) -> String {
+        let builtin = self
+            .builtins
+            .get(name)
+            .expect("the parser only accepts known builtins");
+        let Some(implementation) = builtin.implementation(Platform::Js) else {
+            self.unsupported.get_or_insert_with(|| name.to_owned());
+            return String::new();
+        };
+        if !self.helpers_used.contains(&implementation.helper) {
+            self.helpers_used.push(implementation.helper);
+        }
+        let arguments: Vec<String> = arguments
+            .iter()
+            .map(|argument| self.convert(*argument, CType::Float))
+            .collect();
+        format!["{}({})", implementation.function, arguments.join(", ")]
+    }
+
+    // the expression as another type, like c converts it for a calculation or a LET. making
+    // something a float changes the number, a long is a BigInt and the rest are numbers
+    fn convert(&mut self, expression: ExprId, to: CType) -> String {
+        let from = ctype::of(self.program, expression);
+        if let (ExprKind::Number(text), CType::Int, CType::Long) =
+            (&self.program.exprs[expression].kind, from, to)
+        {
+            return format!["{}n", text.parse::<i64>().unwrap()];
+        }
+        let code = self.expression(expression);
+        match (from, to) {
+            _ if from == to => code,
+            (CType::Long, CType::Float) => format!["haneul_fround(haneul_from_long({}))", code],
+            (CType::Long, _) => format!["haneul_from_long({})", code],
+            (_, CType::Long) => format!["haneul_to_long({})", code],
+            (_, CType::Float) => format!["haneul_fround({})", code],
+            _ => code,
+        }
+    }
+
+    // a name javascript already uses gets a _ after it, no name in a program can end in one
+    fn name(&self, symbol: Symbol) -> String {
+        let name = self.program.names.resolve(symbol);
+        if RESERVED.contains(&name) {
+            format!["{}_", name]
+        } else {
+            name.to_owned()
+        }
+    }
+}
+
+fn js_op(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Equal => "===",
+        BinaryOp::NotEqual => "!==",
+        op => op.text(),
+    }
+}
+
+fn string_literal(string: &str) -> String {
+    let mut literal = String::from("\"");
+    for c in string.chars() {
+        match c {
+            '"' | '\\' => {
+                literal.push('\\');
+                literal.push(c);
+            }
+            c if c < ' ' || c == '\u{2028}' || c == '\u{2029}' => {
+                let _ = write!(literal, "\\u{:04x}", c as u32);
+            }
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+// every variable, in the order they first get a value
+fn variables(statements: &[Statement]) -> Vec<Symbol> {
+    fn collect(statements: &[Statement], variables: &mut Vec<Symbol>) {
+        for statement in statements {
+            match &statement.kind {
+                StatementKind::Let { variable, .. } | StatementKind::Input(variable)
+                    if !variables.contains(variable) =>
+                {
+                    variables.push(*variable)
+                }
+                StatementKind::If { body, .. } | StatementKind::While { body, .. } => {
+                    collect(body, variables)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut variables = vec![];
+    collect(statements, &mut variables);
+    variables
+}
+
+// the keywords and the names strict javascript doesn't let a variable have
+const RESERVED: &[&str] = &[
+    "arguments",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "eval",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+// the program only uses these names, so a variable called Math can't get in the way. the
+// glue of the wasm backend uses PRINT and INPUT from here too
+pub const RUNTIME: &str = r#"
+const haneul_fround = Math.fround;
+const haneul_imul = Math.imul;
+const haneul_to_long = BigInt;
+const haneul_from_long = Number;
+
+function haneul_wrap_long(value) {
+    return BigInt.asIntN(64, value);
+}
+
+// the fewest digits that read back as the same float, without an exponent, like the c code:
+// the float rounded to 1 to 9 digits, the first one that reads back as the float is written
+// out with its zeros. toExponential rounds a tie up where printf goes to the even one, so the
+// rounding is done on the digits of the float, which are exact in a BigInt
+function haneul_print_number(value) {
+    if (Number.isNaN(value)) {
+        console.log("nan");
+        return;
+    }
+    if (!Number.isFinite(value)) {
+        console.log(value > 0 ? "inf" : "-inf");
+        return;
+    }
+    const sign = value < 0 || Object.is(value, -0) ? "-" : "";
+    const magnitude = Math.abs(value);
+    if (magnitude === 0) {
+        console.log(sign + "0");
+        return;
+    }
+
+    // the float is digits × 10^-scale
+    const view = new DataView(new ArrayBuffer(4));
+    view.setFloat32(0, magnitude);
+    const bits = view.getUint32(0);
+    const biased = bits >>> 23;
+    const mantissa = BigInt(bits & 0x7fffff) + (biased === 0 ? 0n : 0x800000n);
+    const power = Math.max(biased, 1) - 150;
+    const digits = power >= 0 ? mantissa << BigInt(power) : mantissa * 5n ** BigInt(-power);
+    const scale = Math.max(-power, 0);
+    const length = digits.toString().length;
+
+    let kept;
+    let exponent;
+    for (let precision = 1; precision <= 9; precision++) {
+        const cut = Math.max(length - precision, 0);
+        const unit = 10n ** BigInt(cut);
+        kept = digits / unit;
+        const rest = (digits % unit) * 2n;
+        if (rest > unit || (rest === unit && kept % 2n === 1n)) {
+            kept += 1n;
+        }
+        exponent = cut - scale;
+        if (Math.fround(parseFloat(kept + "e" + exponent)) === magnitude) {
+            break;
+        }
+    }
+
+    let text = kept.toString();
+    while (text.endsWith("0")) {
+        text = text.slice(0, -1);
+        exponent += 1;
+    }
+    if (exponent >= 0) {
+        console.log(sign + text + "0".repeat(exponent));
+        return;
+    }
+    text = text.padStart(1 - exponent, "0");
+    console.log(sign + text.slice(0, exponent) + "." + text.slice(exponent));
+}
+
+// an int or a long, which is a BigInt
+function haneul_print_integer(value) {
+    console.log(String(value));
+}
+
+function haneul_print_string(string) {
+    console.log(string);
+}
+
+// stops the program, like a c program compiled by haneul that divides an int by 0
+function haneul_fail(message, line) {
+    const error = "Error: " + message + " on line " + line;
+    if (typeof process === "object") {
+        console.error(error);
+        process.exit(1);
+    }
+    throw new Error(error);
+}
+
+// the smallest int divided by -1 wraps around with | 0, like in c
+function haneul_divide_int(left, right, line) {
+    if (right === 0) {
+        haneul_fail("an int is divided by 0", line);
+    }
+    return (left / right) | 0;
+}
+
+// a BigInt / rounds toward 0 like c, the smallest long divided by -1 wraps around
+function haneul_divide_long(left, right, line) {
+    if (right === 0n) {
+        haneul_fail("an int is divided by 0", line);
+    }
+    return BigInt.asIntN(64, left / right);
+}
+
+// these two only with --float-division=error
+function haneul_divide_float(left, right, line) {
+    if (right === 0) {
+        haneul_fail("a float is divided by 0", line);
+    }
+    return Math.fround(left / right);
+}
+
+function haneul_divide_double(left, right, line) {
+    if (right === 0) {
+        haneul_fail("a float is divided by 0", line);
+    }
+    return left / right;
+}
+
+// a % has the sign of the left side, like in c. the smallest int % -1 is 0 | 0
+function haneul_remainder_int(left, right, line) {
+    if (right === 0) {
+        haneul_fail("an int is divided by 0", line);
+    }
+    return (left % right) | 0;
+}
+
+function haneul_remainder_long(left, right, line) {
+    if (right === 0n) {
+        haneul_fail("an int is divided by 0", line);
+    }
+    return left % right;
+}
+
+// these two only with --float-division=error, % of numbers is fmod
+function haneul_remainder_float(left, right, line) {
+    if (right === 0) {
+        haneul_fail("a float is divided by 0", line);
+    }
+    return Math.fround(left % right);
+}
+
+function haneul_remainder_double(left, right, line) {
+    if (right === 0) {
+        haneul_fail("a float is divided by 0", line);
+    }
+    return left % right;
+}
+
+// a line, or null at the end of the input
+function haneul_read_line() {
+    if (typeof prompt === "function") {
+        const line = prompt("");
+        return line === null ? null : line + "\n";
+    }
+
+    const fs = require("fs");
+    const byte = Buffer.alloc(1);
+    const bytes = [];
+    for (;;) {
+        let count;
+        try {
+            count = fs.readSync(0, byte, 0, 1, null);
+        } catch (error) {
+            if (error.code === "EAGAIN") {
+                continue;
+            }
+            if (error.code !== "EOF") {
+                throw error;
+            }
+            count = 0;
+        }
+        if (count === 0) {
+            return bytes.length === 0 ? null : Buffer.from(bytes).toString();
+        }
+        bytes.push(byte[0]);
+        if (byte[0] === 10) {
+            return Buffer.from(bytes).toString();
+        }
+    }
+}
+
+// like scanf("%f") in c: the number at the start of the next word, the rest of the word is
+// read by the next INPUT. a word that doesn't start with a number gives 0, at the end of the
+// input the variable keeps the value it had
+let haneul_pending = "";
+const haneul_number = /^[+-]?(infinity|inf|nan|(\d+\.?\d*|\.\d+)(e[+-]?\d+)?)/i;
+
+function haneul_input(value) {
+    while (haneul_pending.trim() === "") {
+        const line = haneul_read_line();
+        if (line === null) {
+            haneul_pending = "";
+            return value;
+        }
+        haneul_pending = line;
+    }
+
+    haneul_pending = haneul_pending.trimStart();
+    const match = haneul_number.exec(haneul_pending);
+    if (match === null) {
+        haneul_pending = haneul_pending.replace(/^\S*/, "");
+        return 0;
+    }
+    haneul_pending = haneul_pending.slice(match[0].length);
+    const text = match[0].toLowerCase();
+    const negative = text.startsWith("-");
+    if (text.includes("inf")) {
+        return negative ? -Infinity : Infinity;
+    }
+    if (text.includes("nan")) {
+        return NaN;
+    }
+    return Math.fround(parseFloat(text));
+}
+"#;
+
+// the name of a type in the functions of RUNTIME
+fn c_name(ctype: CType) -> &'static str {
+    match ctype {
+        CType::Int => "int",
+        CType::Long => "long",
+        CType::Float => "float",
+        CType::Double => "double",
+    }
+}
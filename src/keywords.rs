@@ -0,0 +1,50 @@
+// every keyword of the language, the lexer, the suggestions and anything else that needs to
+// know the keywords (a formatter or a highlighter) should use this table
+
+use crate::token::TokenType;
+
+pub const KEYWORDS: &[(&str, TokenType)] = &[
+    ("LABEL", TokenType::Label),
+    ("GOTO", TokenType::Goto),
+    ("PRINT", TokenType::Print),
+    ("INPUT", TokenType::Input),
+    ("LET", TokenType::Let),
+    ("IF", TokenType::If),
+    ("THEN", TokenType::Then),
+    ("ENDIF", TokenType::Endif),
+    ("WHILE", TokenType::While),
+    ("REPEAT", TokenType::Repeat),
+    ("ENDWHILE", TokenType::EndWhile),
+];
+
+// a perfect hash: every keyword gets its own slot in TABLE, which is checked while compiling.
+// if a new keyword collides with another one the build fails, then change the hash or make
+// TABLE_SIZE bigger
+const TABLE_SIZE: usize = 64;
+const TABLE: [Option<usize>; TABLE_SIZE] = build_table();
+
+const fn hash(text: &[u8]) -> usize {
+    (text.len() + text[0] as usize + text[text.len() - 1] as usize) % TABLE_SIZE
+}
+
+const fn build_table() -> [Option<usize>; TABLE_SIZE] {
+    let mut table = [None; TABLE_SIZE];
+    let mut i = 0;
+    while i < KEYWORDS.len() {
+        let slot = hash(KEYWORDS[i].0.as_bytes());
+        if table[slot].is_some() {
+            panic!("two keywords have the same hash");
+        }
+        table[slot] = Some(i);
+        i += 1;
+    }
+    table
+}
+
+pub fn keyword(text: &str) -> Option<TokenType> {
+    if text.is_empty() {
+        return None;
+    }
+    let (keyword, kind) = KEYWORDS[TABLE[hash(text.as_bytes())]?];
+    (keyword == text).then_some(kind)
+}
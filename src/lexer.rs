@@ -1,180 +1,616 @@
-use crate::token::{Token, TokenType};
+use core::fmt;
+#[cfg(feature = "std")]
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, BufReader, Read},
+};
 
-pub struct Lexer {
-    source: Vec<char>,
+use crate::{
+    codes,
+    i18n::{self, Lang},
+    keywords,
+    prelude::*,
+    source::FileId,
+    token::{Span, Token, TokenType},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexError {
+    // a '!' that isn't followed by '='
+    ExpectedNotEq { found: char, span: Span },
+    UnknownCharacter { found: char, span: Span },
+    // a string without a closing quote on the line it starts on
+    UnterminatedString { span: Span },
+    // a '.' without digits on either side
+    NumberWithoutDigits { span: Span },
+    // a number directly followed by letters or another '.', like 1abc or 1.2.3
+    MalformedNumber { text: String, span: Span },
+    // a number with a dot past the largest float, it would become infinity in the generated c,
+    // or one without a dot past the largest long
+    NumberTooLarge { text: String, span: Span },
+}
+
+impl LexError {
+    pub fn span(&self) -> Span {
+        match self {
+            LexError::ExpectedNotEq { span, .. }
+            | LexError::UnknownCharacter { span, .. }
+            | LexError::UnterminatedString { span }
+            | LexError::NumberWithoutDigits { span }
+            | LexError::MalformedNumber { span, .. }
+            | LexError::NumberTooLarge { span, .. } => *span,
+        }
+    }
+
+    pub fn with_span(mut self, new_span: Span) -> Self {
+        match &mut self {
+            LexError::ExpectedNotEq { span, .. }
+            | LexError::UnknownCharacter { span, .. }
+            | LexError::UnterminatedString { span }
+            | LexError::NumberWithoutDigits { span }
+            | LexError::MalformedNumber { span, .. }
+            | LexError::NumberTooLarge { span, .. } => *span = new_span,
+        }
+        self
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            LexError::ExpectedNotEq { .. } => codes::EXPECTED_NOT_EQ,
+            LexError::UnknownCharacter { .. } => codes::UNKNOWN_CHARACTER,
+            LexError::UnterminatedString { .. } => codes::UNTERMINATED_STRING,
+            LexError::NumberWithoutDigits { .. } => codes::NUMBER_WITHOUT_DIGITS,
+            LexError::MalformedNumber { .. } => codes::MALFORMED_NUMBER,
+            LexError::NumberTooLarge { .. } => codes::NUMBER_TOO_LARGE,
+        }
+    }
+
+    pub fn args(&self) -> Vec<String> {
+        match self {
+            LexError::ExpectedNotEq { found, .. } | LexError::UnknownCharacter { found, .. } => {
+                vec![found.to_string()]
+            }
+            LexError::UnterminatedString { span } => vec![span.line.to_string()],
+            LexError::NumberWithoutDigits { .. } => vec![],
+            LexError::MalformedNumber { text, .. } => vec![text.clone()],
+            LexError::NumberTooLarge { text, .. } if text.contains('.') => {
+                vec![text.clone(), format!["{:e}", f32::MAX]]
+            }
+            LexError::NumberTooLarge { text, .. } => vec![text.clone(), i64::MAX.to_string()],
+        }
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            i18n::message(Lang::English, self.code(), &self.args())
+        )
+    }
+}
+
+impl core::error::Error for LexError {}
+
+// the text of a token from the source it covers. newlines always read "\n", even a \r\n or
+// the one the lexer adds, and the end of the file "\0". strings leave out their quotes and are
+// kept as they're written, every backend escapes them the way it needs to
+pub fn token_text(kind: TokenType, source: &str) -> &str {
+    match kind {
+        TokenType::Newline => "\n",
+        TokenType::Eof => "\0",
+        TokenType::String => &source[1..source.len() - 1],
+        _ => source,
+    }
+}
+
+// where a lexer is, to go back there later with restore
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
     current_char: char,
     current_pos: usize,
+    line: usize,
+    column: usize,
+    offset: usize,
+    done: bool,
+    trivia_start: usize,
+    end: Option<(usize, usize, usize)>,
 }
 
-impl Lexer {
-    pub fn new(mut source: String) -> Self {
-        source.push('\n');
-        let source: Vec<char> = source.chars().collect();
-        Self {
-            // should be fine since we just appended a newline to source
-            current_char: source[0],
-            source,
+pub struct Lexer<'src> {
+    file: FileId,
+    // the original source, tokens borrow their text from it. it's scanned byte by byte,
+    // chars are only decoded one at a time where the lexer is
+    text: &'src str,
+    current_char: char,
+    // how many chars came before current_char, for the length of spans
+    current_pos: usize,
+    line: usize,
+    column: usize,
+    // byte offset of current_char in the original source
+    offset: usize,
+    // set once the Eof token is given out, the iterator ends after it
+    done: bool,
+    // a lossless lexer gives every token the text in front of it as trivia
+    lossless: bool,
+    trivia_start: usize,
+    // the line, column and offset where the last line of the file ends, once it's lexed. the
+    // newline the lexer adds and the end of the file are put there
+    end: Option<(usize, usize, usize)>,
+}
+
+impl<'src> Lexer<'src> {
+    pub fn new(file: FileId, text: &'src str) -> Self {
+        // editors on windows like to start files with a byte order mark
+        let offset = if text.starts_with('\u{feff}') {
+            '\u{feff}'.len_utf8()
+        } else {
+            0
+        };
+        Self::starting_at(file, text, offset)
+    }
+
+    // a line in the middle of a file, where a byte order mark isn't one
+    #[cfg(feature = "std")]
+    fn line(file: FileId, text: &'src str) -> Self {
+        Self::starting_at(file, text, 0)
+    }
+
+    fn starting_at(file: FileId, text: &'src str, offset: usize) -> Self {
+        let mut lexer = Self {
+            file,
+            text,
+            current_char: '\0',
             current_pos: 0,
+            line: 1,
+            column: 1,
+            offset,
+            done: false,
+            lossless: false,
+            // the byte order mark is trivia too
+            trivia_start: 0,
+            end: None,
+        };
+        lexer.current_char = lexer.char_at(offset);
+
+        lexer
+    }
+
+    // keeps whitespace, comments and text that couldn't be lexed as trivia on the tokens,
+    // for a formatter that has to give back the file exactly
+    pub fn lossless(self) -> Self {
+        Self {
+            lossless: true,
+            ..self
         }
     }
 
-    pub fn next_char(&mut self) {
-        self.current_pos += 1;
+    // for a parser that tries one way to parse something and backtracks if it doesn't work out
+    pub fn save(&self) -> Checkpoint {
+        Checkpoint {
+            current_char: self.current_char,
+            current_pos: self.current_pos,
+            line: self.line,
+            column: self.column,
+            offset: self.offset,
+            done: self.done,
+            trivia_start: self.trivia_start,
+            end: self.end,
+        }
+    }
 
-        if self.current_pos >= self.source.len() {
-            self.current_char = '\0';
-        } else {
-            self.current_char = self.source[self.current_pos];
+    // the tokens after this are the same as the ones after the save
+    pub fn restore(&mut self, checkpoint: Checkpoint) {
+        self.current_char = checkpoint.current_char;
+        self.current_pos = checkpoint.current_pos;
+        self.line = checkpoint.line;
+        self.column = checkpoint.column;
+        self.offset = checkpoint.offset;
+        self.done = checkpoint.done;
+        self.trivia_start = checkpoint.trivia_start;
+        self.end = checkpoint.end;
+    }
+
+    // the source acts like it ends with a newline, so the last statement always ends with one.
+    // after that comes '\0'
+    fn char_at(&self, offset: usize) -> char {
+        match self.text.get(offset..).and_then(|rest| rest.chars().next()) {
+            Some(c) => c,
+            None if offset == self.text.len() => '\n',
+            None => '\0',
         }
     }
 
-    fn peek(&self) -> char {
-        if self.current_pos + 1 >= self.source.len() {
-            '\0'
+    fn next_char(&mut self) {
+        if self.current_char == '\n' {
+            self.line += 1;
+            self.column = 1;
         } else {
-            self.source[self.current_pos + 1]
+            self.column += 1;
         }
+        self.offset += self.current_char.len_utf8();
+        self.current_pos += 1;
+        self.current_char = self.char_at(self.offset);
     }
 
-    pub fn get_token(&mut self) -> Token {
+    fn peek(&self) -> char {
+        self.char_at(self.offset + self.current_char.len_utf8())
+    }
+
+    fn get_token(&mut self) -> Result<Token<'src>, LexError> {
         self.skip_whitespace();
         self.skip_comment();
 
-        let mut current_str: String = self.current_char.into();
+        let (line, column, start_pos, start_offset) =
+            (self.line, self.column, self.current_pos, self.offset);
 
-        match self.current_char {
-            '+' => Token::new(current_str, TokenType::Plus),
-            '-' => Token::new(current_str, TokenType::Minus),
-            '*' => Token::new(current_str, TokenType::Asterisk),
-            '/' => Token::new(current_str, TokenType::Slash),
-            '\n' => Token::new(current_str, TokenType::Newline),
-            '\0' => Token::new(current_str, TokenType::Eof),
-            '=' => {
-                if self.peek() == '=' {
-                    self.next_char();
-                    current_str.push(self.current_char);
-                    Token::new(current_str, TokenType::EqEq)
-                } else {
-                    Token::new(current_str, TokenType::Eq)
-                }
-            }
-            '>' => {
-                if self.peek() == '=' {
-                    self.next_char();
-                    current_str.push(self.current_char);
-                    Token::new(current_str, TokenType::GtEq)
-                } else {
-                    Token::new(current_str, TokenType::Gt)
-                }
-            }
-            '<' => {
-                if self.peek() == '=' {
-                    self.next_char();
-                    current_str.push(self.current_char);
-                    Token::new(current_str, TokenType::LtEq)
-                } else {
-                    Token::new(current_str, TokenType::Lt)
-                }
+        let kind = match self.current_char {
+            '+' => TokenType::Plus,
+            '-' => TokenType::Minus,
+            '*' => TokenType::Asterisk,
+            '/' => TokenType::Slash,
+            '%' => TokenType::Percent,
+            '(' => TokenType::LParen,
+            ')' => TokenType::RParen,
+            ',' => TokenType::Comma,
+            '\n' => TokenType::Newline,
+            // \r\n is one newline
+            '\r' if self.at_crlf() => {
+                self.next_char();
+                TokenType::Newline
             }
+            '\0' => TokenType::Eof,
+            '=' => self.one_or_two('=', TokenType::Eq, TokenType::EqEq),
+            '>' => self.one_or_two('=', TokenType::Gt, TokenType::GtEq),
+            '<' => self.one_or_two('=', TokenType::Lt, TokenType::LtEq),
             '!' => {
                 if self.peek() == '=' {
                     self.next_char();
-                    current_str.push(self.current_char);
-                    Token::new(current_str, TokenType::NotEq)
+                    TokenType::NotEq
                 } else {
-                    Self::die(format!["Expected !=, got !{}", self.peek()]);
+                    return Err(LexError::ExpectedNotEq {
+                        found: self.peek(),
+                        span: self.char_span(),
+                    });
                 }
             }
             '"' => {
-                self.next_char();
-                let mut string = String::new();
-
-                while self.current_char != '"' {
-                    match self.current_char {
-                        '%' => string.push_str("\\%"),
-                        '\\' => string.push_str("\\\\"),
-                        _ => string.push(self.current_char),
+                // peek instead of moving on, so the newline is still lexed after the error
+                while self.peek() != '"' {
+                    if self.peek() == '\n' || self.peek() == '\r' || self.peek() == '\0' {
+                        return Err(LexError::UnterminatedString {
+                            span: self.span_since(line, column, start_pos, start_offset),
+                        });
                     }
                     self.next_char();
                 }
+                self.next_char();
 
-                Token::new(string, TokenType::String)
+                TokenType::String
             }
             '0'..='9' | '.' => {
-                let mut raw_num = String::new();
                 let mut is_float = self.current_char == '.';
-                raw_num.push(self.current_char);
 
                 while self.peek().is_ascii_digit() || (self.peek() == '.' && !is_float) {
                     self.next_char();
-                    raw_num.push(self.current_char);
 
                     if self.current_char == '.' {
                         is_float = true;
                     }
                 }
 
-                Token::new(raw_num, TokenType::Number)
+                // the rest of the word is part of the error, so it doesn't turn into an identifier
+                if self.peek().is_alphanumeric() || self.peek() == '_' || self.peek() == '.' {
+                    while self.peek().is_alphanumeric() || self.peek() == '_' || self.peek() == '.'
+                    {
+                        self.next_char();
+                    }
+                    let span = self.span_since(line, column, start_pos, start_offset);
+                    return Err(LexError::MalformedNumber {
+                        text: self.slice(span).to_owned(),
+                        span,
+                    });
+                }
+                let span = self.span_since(line, column, start_pos, start_offset);
+                let raw_num = self.slice(span);
+                if raw_num == "." {
+                    return Err(LexError::NumberWithoutDigits {
+                        span: self.char_span(),
+                    });
+                }
+                let too_large = if raw_num.contains('.') {
+                    raw_num.parse::<f32>().map_or(true, f32::is_infinite)
+                } else {
+                    raw_num.parse::<i64>().is_err()
+                };
+                if too_large {
+                    return Err(LexError::NumberTooLarge {
+                        text: raw_num.to_owned(),
+                        span,
+                    });
+                }
+
+                TokenType::Number
             }
             'a'..='z' | 'A'..='Z' | '_' => {
-                let mut ident = String::new();
-                ident.push(self.current_char);
-
                 while self.peek().is_alphanumeric() {
                     self.next_char();
-                    ident.push(self.current_char);
                 }
 
-                if let Some(tokentype) = Self::is_keyword(&ident) {
-                    Token::new(ident, tokentype)
-                } else {
-                    Token::new(ident, TokenType::Ident)
-                }
+                let span = self.span_since(line, column, start_pos, start_offset);
+                keywords::keyword(self.slice(span)).unwrap_or(TokenType::Ident)
             }
-            _ => Self::die(format!("unknown token: {}", self.current_char)),
-        }
-    }
-
-    fn is_keyword(token_text: &str) -> Option<TokenType> {
-        // Could be replaced with a hashmap, but it doesn't have enough keywords to be efficient
-        let keywords = [
-            ("LABEL", TokenType::Label),
-            ("GOTO", TokenType::Goto),
-            ("PRINT", TokenType::Print),
-            ("INPUT", TokenType::Input),
-            ("LET", TokenType::Let),
-            ("IF", TokenType::If),
-            ("THEN", TokenType::Then),
-            ("ENDIF", TokenType::Endif),
-            ("WHILE", TokenType::While),
-            ("REPEAT", TokenType::Repeat),
-            ("ENDWHILE", TokenType::EndWhile),
-        ];
-
-        for (keyword, tokentype) in keywords {
-            if token_text == keyword {
-                return Some(tokentype);
+            _ => {
+                return Err(LexError::UnknownCharacter {
+                    found: self.current_char,
+                    span: self.char_span(),
+                })
             }
+        };
+
+        let mut span = self.span_since(line, column, start_pos, start_offset);
+        if matches!(kind, TokenType::Newline | TokenType::Eof) {
+            span = self.at_end(span);
+        }
+        Ok(Token::new(token_text(kind, self.slice(span)), kind).with_span(span))
+    }
+
+    // for operators like = and ==
+    fn one_or_two(&mut self, second: char, one: TokenType, two: TokenType) -> TokenType {
+        if self.peek() == second {
+            self.next_char();
+            two
+        } else {
+            one
         }
-        None
+    }
+
+    // the text of a span in the original source
+    fn slice(&self, span: Span) -> &'src str {
+        &self.text[span.offset..span.offset + span.byte_length]
     }
 
     fn skip_comment(&mut self) {
         if self.current_char == '#' {
-            while self.current_char != '\n' {
+            while self.current_char != '\n' && !self.at_crlf() {
                 self.next_char();
             }
         }
     }
 
+    // only a real \n makes a \r\n, not the one the source acts like it ends with
+    fn at_crlf(&self) -> bool {
+        self.current_char == '\r' && self.text[self.offset + 1..].starts_with('\n')
+    }
+
     fn skip_whitespace(&mut self) {
-        while self.current_char == ' ' || self.current_char == '\t' || self.current_char == '\r' {
+        // a \r on its own is whitespace, one in front of a \n is part of the newline
+        while self.current_char == ' '
+            || self.current_char == '\t'
+            || (self.current_char == '\r' && !self.at_crlf())
+        {
             self.next_char();
         }
     }
 
-    fn die(message: String) -> ! {
-        println!("Error while lexing: {}", message);
-        std::process::exit(1);
+    // span from the given start up to and including the current character
+    fn span_since(
+        &self,
+        line: usize,
+        column: usize,
+        start_pos: usize,
+        start_offset: usize,
+    ) -> Span {
+        let offset = start_offset.min(self.text.len());
+        Span {
+            file: self.file,
+            line,
+            column,
+            length: self.current_pos - start_pos + 1,
+            offset,
+            byte_length: self.end_offset() - offset,
+        }
+    }
+
+    // span of the current character
+    fn char_span(&self) -> Span {
+        let offset = self.offset.min(self.text.len());
+        Span {
+            file: self.file,
+            line: self.line,
+            column: self.column,
+            length: 1,
+            offset,
+            byte_length: self.end_offset() - offset,
+        }
+    }
+
+    // the newline the lexer adds and the end of the file aren't in the file. after a file that
+    // ends with a newline they'd be on a line it doesn't have, so they go where the last line
+    // ends, like the newlines at the end of the file
+    fn at_end(&mut self, span: Span) -> Span {
+        if span.offset + span.byte_length == self.text.len() && self.end.is_none() {
+            self.end = Some((span.line, span.column, span.offset));
+        }
+        match self.end {
+            Some((line, column, offset)) if span.byte_length == 0 => Span {
+                line,
+                column,
+                offset,
+                ..span
+            },
+            _ => span,
+        }
+    }
+
+    // byte offset just past current_char, the newline we appended and the eof don't exist
+    // in the original source so they get clamped to its end
+    fn end_offset(&self) -> usize {
+        (self.offset + self.current_char.len_utf8()).min(self.text.len())
+    }
+}
+
+// gives every token up to and including Eof. a token that can't be lexed is an Err,
+// lexing goes on after it with the next token
+impl<'src> Iterator for Lexer<'src> {
+    type Item = Result<Token<'src>, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut token = self.get_token();
+        self.next_char();
+        if let Ok(lexed) = token {
+            self.done = lexed.kind() == TokenType::Eof;
+            token = Ok(if self.lossless {
+                // text that couldn't be lexed stays in the trivia of the next token
+                // the newline the lexer adds and the end of the file can be before it
+                let trivia =
+                    &self.text[self.trivia_start..lexed.span().offset.max(self.trivia_start)];
+                self.trivia_start = self.offset.min(self.text.len());
+                lexed.with_trivia(trivia)
+            } else {
+                lexed
+            });
+        }
+
+        Some(token)
+    }
+}
+
+// lexes anything that can be read, a line at a time, so the whole program never has to be in
+// memory. no token goes past the end of a line, so every line is lexed on its own
+#[cfg(feature = "std")]
+pub struct StreamLexer<R> {
+    file: FileId,
+    reader: BufReader<R>,
+    line: String,
+    // the tokens of the last line that weren't given out yet
+    pending: VecDeque<Result<Token<'static>, LexError>>,
+    line_number: usize,
+    line_offset: usize,
+    // the Lexer adds a newline when the input already ended with one, we need to know if it
+    // did to give the same tokens
+    ended_with_newline: bool,
+    // the line, column and offset of the last newline, where the Lexer puts the newline it
+    // adds and the Eof
+    end: (usize, usize, usize),
+    error: Option<io::Error>,
+    done: bool,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> StreamLexer<R> {
+    pub fn new(file: FileId, reader: R) -> Self {
+        Self {
+            file,
+            reader: BufReader::new(reader),
+            line: String::new(),
+            pending: VecDeque::new(),
+            line_number: 1,
+            line_offset: 0,
+            ended_with_newline: true,
+            end: (1, 1, 0),
+            error: None,
+            done: false,
+        }
+    }
+
+    // reading stops at the first error, the tokens end with Eof like at the end of the input
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    fn lex_line(&mut self) {
+        self.line.clear();
+        let read = self
+            .reader
+            .read_line(&mut self.line)
+            .unwrap_or_else(|error| {
+                self.error = Some(error);
+                0
+            });
+
+        if read == 0 {
+            self.done = true;
+            let (line, column, offset) = self.end;
+            let span = Span {
+                file: self.file,
+                line,
+                column,
+                length: 1,
+                offset,
+                byte_length: 0,
+            };
+            if self.ended_with_newline {
+                self.pending
+                    .push_back(Ok(Token::new("\n", TokenType::Newline).with_span(span)));
+            }
+            self.pending
+                .push_back(Ok(Token::new("\0", TokenType::Eof).with_span(span)));
+            return;
+        }
+
+        // without its line ending the Lexer adds the newline itself
+        let text = match self.line.strip_suffix('\n') {
+            Some(text) => text.strip_suffix('\r').unwrap_or(text),
+            None => &self.line,
+        };
+        let newline_length = self.line.len() - text.len();
+        self.ended_with_newline = newline_length > 0;
+        let (line_number, line_offset) = (self.line_number, self.line_offset);
+        let place = |mut span: Span| {
+            span.line = line_number;
+            span.offset += line_offset;
+            span
+        };
+
+        // only the file starts with a byte order mark
+        let lexer = match line_number {
+            1 => Lexer::new(self.file, text),
+            _ => Lexer::line(self.file, text),
+        };
+        for token in lexer {
+            self.pending.push_back(match token {
+                Ok(token) if token.kind() == TokenType::Eof => break,
+                Ok(token) => {
+                    let mut span = place(token.span());
+                    // a \r\n is one newline two long, like the Lexer has it
+                    if token.kind() == TokenType::Newline {
+                        span.length = newline_length.max(1);
+                        span.byte_length = newline_length;
+                        self.end = (span.line, span.column, span.offset);
+                    }
+                    Ok(token.into_owned().with_span(span))
+                }
+                Err(error) => {
+                    let span = place(error.span());
+                    Err(error.with_span(span))
+                }
+            });
+        }
+
+        self.line_number += 1;
+        self.line_offset += self.line.len();
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> Iterator for StreamLexer<R> {
+    type Item = Result<Token<'static>, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(token) = self.pending.pop_front() {
+                return Some(token);
+            }
+            if self.done {
+                return None;
+            }
+            self.lex_line();
+        }
     }
 }
@@ -0,0 +1,191 @@
+// haneul as a library, for a rust program that compiles or runs haneul programs. the command
+// line in main.rs is built on it too
+//
+//   let options = haneul::Options {
+//       target: haneul::Target::Python,
+//       ..Default::default()
+//   };
+//   match haneul::compile("LET a = 2\nPRINT a * 21\n", &options) {
+//       Ok(output) => fs::write("out.py", &output.files[0].1)?,
+//       Err(error) => eprintln!("{}", error),
+//   }
+//
+// compile goes through all of it in one go, run_source does the same for `haneul run` and gives
+// what the program printed. the steps are here too, for a tool that wants only
+// some of them: lexer::Lexer, parse, analyze, passes::optimize, then backend, or
+// bytecode::compile and vm::Vm to run it. the modules that are private are the inside of a
+// step, like the code of one backend
+//
+// without the std feature only the front end is there: the lexer, the parser and the analysis,
+// with parse and analyze. it needs alloc, for a program on a microcontroller or in a wasm
+// runtime without wasi that checks haneul programs
+#![cfg_attr(not(feature = "std"), no_std)]
+// the optimizations of fold.rs and dce.rs are only used by the back end
+#![cfg_attr(not(feature = "std"), allow(dead_code))]
+
+extern crate alloc;
+
+pub mod analysis;
+pub mod ast;
+#[cfg(feature = "std")]
+pub mod backend;
+pub mod builtins;
+#[cfg(feature = "std")]
+pub mod bytecode;
+#[cfg(feature = "std")]
+mod cc;
+pub mod cfg;
+#[cfg(feature = "std")]
+mod codegen;
+pub mod codes;
+#[cfg(feature = "std")]
+mod cse;
+pub mod ctype;
+mod dce;
+pub mod diagnostics;
+#[cfg(feature = "std")]
+pub mod dot;
+#[cfg(feature = "std")]
+mod driver;
+#[cfg(feature = "std")]
+mod emitter;
+#[cfg(feature = "ffi")]
+mod ffi;
+mod fold;
+pub mod format;
+#[cfg(feature = "std")]
+pub mod hbc;
+pub mod i18n;
+// not used by the compiler itself, it's for editors
+#[cfg(feature = "std")]
+pub mod incremental;
+pub mod intern;
+#[cfg(feature = "std")]
+pub mod interpreter;
+#[cfg(feature = "std")]
+mod js;
+mod keywords;
+pub mod lexer;
+#[cfg(feature = "llvm")]
+mod llvm;
+#[cfg(feature = "std")]
+mod loops;
+#[cfg(feature = "std")]
+pub mod mermaid;
+#[cfg(feature = "std")]
+mod minify;
+pub mod parser;
+#[cfg(feature = "std")]
+pub mod passes;
+#[cfg(feature = "std")]
+mod peephole;
+#[cfg(feature = "std")]
+pub mod pretty;
+#[cfg(feature = "python")]
+mod py;
+#[cfg(feature = "std")]
+mod python;
+#[cfg(feature = "std")]
+pub mod runtime;
+#[cfg(feature = "std")]
+mod rust;
+#[cfg(feature = "std")]
+pub mod snapshot;
+pub mod source;
+#[cfg(feature = "std")]
+mod sourcemap;
+mod suggest;
+pub mod symbols;
+pub mod token;
+mod types;
+pub mod visit;
+#[cfg(feature = "std")]
+pub mod vm;
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "std")]
+mod wat;
+
+// the names of the std prelude the modules of the front end use, from alloc. the maps are
+// hashbrown's without std, it's what std's are made of
+mod prelude {
+    pub use alloc::{
+        borrow::ToOwned,
+        format,
+        string::{String, ToString},
+        vec,
+        vec::Vec,
+    };
+    #[cfg(not(feature = "std"))]
+    pub use hashbrown::{HashMap, HashSet};
+    #[cfg(feature = "std")]
+    pub use std::collections::{HashMap, HashSet};
+}
+
+#[cfg(any(feature = "wasm", feature = "ffi", feature = "python"))]
+use driver::compile_text;
+#[cfg(feature = "wasm")]
+use driver::run_source_to;
+#[cfg(feature = "std")]
+pub use driver::{
+    backend, compile, run_source, run_source_with_policy, Error, Options, Output, RunResult, Target,
+};
+
+use ast::Program;
+use builtins::Registry;
+use diagnostics::{Diagnostic, Lints};
+use parser::ParseError;
+use prelude::*;
+
+// a server runs many programs at once on a thread pool: a parsed Program and a compiled Chunk
+// are Sync, so the threads share them, and every thread makes its own Vm or Interpreter, which
+// are Send. this stops compiling when one of them isn't anymore
+#[cfg(feature = "std")]
+const _: fn() = || {
+    fn send<T: Send>() {}
+    fn sync<T: Send + Sync>() {}
+    sync::<Program>();
+    sync::<Registry>();
+    sync::<bytecode::Chunk>();
+    sync::<RunResult>();
+    send::<vm::Vm>();
+    send::<interpreter::Interpreter>();
+};
+
+// the program and the warnings of the parser, or the errors it found
+pub fn parse(
+    tokens: &mut parser::Tokens,
+    builtins: &Registry,
+    max_depth: usize,
+) -> (Result<Program, Vec<ParseError>>, Vec<Diagnostic>) {
+    let mut parser = parser::Parser::new(tokens, builtins);
+    parser.set_max_depth(max_depth);
+
+    let result = parser.program();
+    (result, parser.warnings().to_vec())
+}
+
+// the warnings and the errors of a parsed program, after the lints. the flow of the program is
+// only worked out once the analysis found nothing wrong with it
+pub fn analyze(
+    result: &Result<Program, Vec<ParseError>>,
+    mut warnings: Vec<Diagnostic>,
+    builtins: &Registry,
+    lints: &Lints,
+) -> (Vec<Diagnostic>, Vec<Diagnostic>) {
+    let program_errors = match result {
+        Ok(program) => {
+            let errors = analysis::check(program, builtins);
+            if errors.is_empty() {
+                warnings.extend(dce::warnings(program));
+                warnings.sort_by_key(|warning| (warning.span.line, warning.span.column));
+            }
+            errors
+        }
+        Err(parse_errors) => parse_errors.iter().map(Diagnostic::from).collect(),
+    };
+
+    let (warnings, mut errors) = lints.apply(&warnings);
+    errors.extend(program_errors);
+    (warnings, errors)
+}
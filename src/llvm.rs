@@ -0,0 +1,548 @@
+// --target=llvm, the program as llvm ir in out.ll, with the cargo feature "llvm". it is written
+// as text so haneul doesn't need llvm to build, and run with the llvm tools: `lli out.ll`, or
+// --target=llvm-object which has opt and llc make it into out.o. the pointers are opaque, like
+// llvm 15 and newer read them. llvm 14 reads them with -opaque-pointers
+//
+// the blocks of the control flow graph are the basic blocks, every variable is on the stack
+// with its type (see ctype::infer) and opt puts it in registers. the numbers have the types c gives them (see
+// ctype.rs), and PRINT and INPUT call printf and scanf like the c code does
+//
+// sdiv and srem are undefined for a 0 and for the smallest int divided by -1, a division that's
+// checked calls a function after main that stops the program or wraps around, like the c helpers
+
+use std::fmt::Write;
+
+use crate::{
+    ast::{BinaryOp, ExprId, ExprKind, Program, Statement, StatementKind, UnaryOp},
+    cfg::{Cfg, Terminator},
+    ctype::{self, CType, FloatDivision},
+    intern::Symbol,
+};
+
+// gives the name of the builtin when the program uses one
+pub fn generate(program: &Program, float_division: FloatDivision) -> Result<String, String> {
+    let cfg = Cfg::new(program);
+    let reachable = cfg.reachable();
+    let variables = variables(&program.statements);
+    let mut generator = Generator {
+        program,
+        float_division,
+        variables: &variables,
+        code: String::new(),
+        strings: vec![],
+        divisions: vec![],
+        values: 0,
+        labels: 0,
+        unsupported: None,
+    };
+
+    for id in cfg.ids().filter(|id| reachable[id.index()]) {
+        let block = cfg.block(id);
+        let _ = writeln!(generator.code, "block{}:", id.index());
+        for statement in &block.statements {
+            generator.statement(statement);
+        }
+        match block.terminator {
+            Terminator::Jump(target) => {
+                generator.emit(format!["br label %block{}", target.index()]);
+            }
+            Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let condition = generator.condition(condition);
+                generator.emit(format![
+                    "br i1 {}, label %block{}, label %block{}",
+                    condition,
+                    then.index(),
+                    otherwise.index()
+                ]);
+            }
+            Terminator::Return => generator.emit(String::from("ret i32 0")),
+        }
+    }
+
+    let mut module = String::from("; made by haneul\n\n");
+    module.push_str(CONSTANTS);
+    for (i, string) in generator.strings.iter().enumerate() {
+        let _ = writeln!(
+            module,
+            "@.string.{} = private unnamed_addr constant [{} x i8] c\"{}\\00\"",
+            i,
+            string.len() + 1,
+            escape(string)
+        );
+    }
+    module.push_str("\ndeclare i32 @printf(ptr, ...)\ndeclare i32 @scanf(ptr, ...)\n");
+    if !generator.divisions.is_empty() {
+        module.push_str(FAIL);
+    }
+    module.push('\n');
+    module.push_str("define i32 @main() {\nentry:\n");
+    for variable in &variables {
+        let ty = llvm_type(ctype::variable(program, *variable));
+        let _ = writeln!(module, "  {} = alloca {}", generator.address(*variable), ty);
+    }
+    module.push_str("  br label %block0\n");
+    module.push_str(&generator.code);
+    module.push_str("}\n");
+    module.push_str(PRINT_NUMBER);
+    for (op, ty) in &generator.divisions {
+        module.push_str(&division(*op, *ty));
+    }
+
+    match generator.unsupported {
+        Some(builtin) => Err(builtin),
+        None => Ok(module),
+    }
+}
+
+struct Generator<'a> {
+    program: &'a Program,
+    float_division: FloatDivision,
+    variables: &'a [Symbol],
+    code: String,
+    // the strings of PRINT "...", they're constants in the module
+    strings: Vec<String>,
+    // the numbers of the next %value and the next label INPUT makes
+    values: usize,
+    labels: usize,
+    // the checked divisions and remainders, their functions are put after main
+    divisions: Vec<(BinaryOp, CType)>,
+    unsupported: Option<String>,
+}
+
+impl Generator<'_> {
+    fn emit(&mut self, instruction: String) {
+        let _ = writeln!(self.code, "  {}", instruction);
+    }
+
+    // emits an instruction that gives a value, gives the name of the value
+    fn value(&mut self, instruction: String) -> String {
+        let value = format!["%v{}", self.values];
+        self.values += 1;
+        self.emit(format!["{} = {}", value, instruction]);
+        value
+    }
+
+    // only the statements that stay in a block of the control flow graph
+    fn statement(&mut self, statement: &Statement) {
+        match &statement.kind {
+            StatementKind::Print(value) => match ctype::of(self.program, *value) {
+                ty @ (CType::Int | CType::Long) => {
+                    let (format, name) = match ty {
+                        CType::Int => (constant(".int"), "i32"),
+                        _ => (constant(".long"), "i64"),
+                    };
+                    let value = self.expression(*value);
+                    self.value(format![
+                        "call i32 (ptr, ...) @printf(ptr {}, {} {})",
+                        format, name, value
+                    ]);
+                }
+                _ => {
+                    let value = self.convert(*value, CType::Float);
+                    self.emit(format!["call void @haneul.print_number(float {})", value]);
+                }
+            },
+            StatementKind::PrintString(string) => {
+                let string_constant = constant(&format![".string.{}", self.strings.len()]);
+                self.strings.push(string.clone());
+                self.value(format![
+                    "call i32 (ptr, ...) @printf(ptr {}, ptr {})",
+                    constant(".string"),
+                    string_constant
+                ]);
+            }
+            StatementKind::Let { variable, value } => {
+                let ty = llvm_type(ctype::variable(self.program, *variable));
+                let value = self.convert(*value, ctype::variable(self.program, *variable));
+                self.emit(format![
+                    "store {ty} {}, ptr {}",
+                    value,
+                    self.address(*variable)
+                ]);
+            }
+            // like the c code: a word that isn't a number gives 0 and is skipped. the variable
+            // is a float, see ctype::infer
+            StatementKind::Input(variable) => {
+                let address = self.address(*variable);
+                let read = self.value(format![
+                    "call i32 (ptr, ...) @scanf(ptr {}, ptr {})",
+                    constant(".input"),
+                    address
+                ]);
+                let failed = self.value(format!["icmp eq i32 {}, 0", read]);
+                let label = self.labels;
+                self.labels += 1;
+                self.emit(format![
+                    "br i1 {}, label %input{}.failed, label %input{}.done",
+                    failed, label, label
+                ]);
+                let _ = writeln!(self.code, "input{}.failed:", label);
+                self.emit(format!["store float 0.0, ptr {}", address]);
+                self.value(format![
+                    "call i32 (ptr, ...) @scanf(ptr {})",
+                    constant(".skip")
+                ]);
+                self.emit(format!["br label %input{}.done", label]);
+                let _ = writeln!(self.code, "input{}.done:", label);
+            }
+            StatementKind::Call { name, .. } => {
+                self.unsupported.get_or_insert_with(|| name.clone());
+            }
+            StatementKind::If { .. }
+            | StatementKind::While { .. }
+            | StatementKind::Label(_)
+            | StatementKind::Goto(_) => unreachable!("these are the edges of the graph"),
+        }
+    }
+
+    // an i1, where c checks if the number isn't 0
+    fn condition(&mut self, condition: ExprId) -> String {
+        let exprs = &self.program.exprs;
+        if let ExprKind::Binary { op, left, right } = exprs[condition].kind {
+            if op.is_comparison() {
+                return self.compare(op, left, right);
+            }
+        }
+
+        let ty = ctype::of(self.program, condition);
+        let value = self.expression(condition);
+        if ty.is_integer() {
+            self.value(format!["icmp ne {} {}, 0", llvm_type(ty), value])
+        } else {
+            self.value(format!["fcmp une {} {}, 0.0", llvm_type(ty), value])
+        }
+    }
+
+    // an i1. != is also true when one of them is nan, like in c
+    fn compare(&mut self, op: BinaryOp, left: ExprId, right: ExprId) -> String {
+        let common = ctype::common(self.program, left, right);
+        let left = self.convert(left, common);
+        let right = self.convert(right, common);
+        let (instruction, predicate) = match (common.is_integer(), op) {
+            (true, BinaryOp::Equal) => ("icmp", "eq"),
+            (true, BinaryOp::NotEqual) => ("icmp", "ne"),
+            (true, BinaryOp::Less) => ("icmp", "slt"),
+            (true, BinaryOp::LessEqual) => ("icmp", "sle"),
+            (true, BinaryOp::Greater) => ("icmp", "sgt"),
+            (true, _) => ("icmp", "sge"),
+            (false, BinaryOp::Equal) => ("fcmp", "oeq"),
+            (false, BinaryOp::NotEqual) => ("fcmp", "une"),
+            (false, BinaryOp::Less) => ("fcmp", "olt"),
+            (false, BinaryOp::LessEqual) => ("fcmp", "ole"),
+            (false, BinaryOp::Greater) => ("fcmp", "ogt"),
+            (false, _) => ("fcmp", "oge"),
+        };
+        self.value(format![
+            "{} {} {} {}, {}",
+            instruction,
+            predicate,
+            llvm_type(common),
+            left,
+            right
+        ])
+    }
+
+    fn expression(&mut self, expression: ExprId) -> String {
+        let exprs = &self.program.exprs;
+        match &exprs[expression].kind {
+            ExprKind::Number(text) => match ctype::number(text) {
+                CType::Int | CType::Long => text.parse::<i64>().unwrap().to_string(),
+                // the exact bits, llvm doesn't round a decimal that a double doesn't have
+                _ => format![
+                    "0x{:016X}",
+                    text.parse::<f64>().unwrap_or_default().to_bits()
+                ],
+            },
+            ExprKind::Variable(variable) => {
+                let ty = llvm_type(ctype::variable(self.program, *variable));
+                let address = self.address(*variable);
+                self.value(format!["load {ty}, ptr {}", address])
+            }
+            ExprKind::Call { name, .. } => {
+                self.unsupported.get_or_insert_with(|| name.clone());
+                String::from("0.0")
+            }
+            ExprKind::Unary { op, operand } => {
+                let (op, operand) = (*op, *operand);
+                let ty = ctype::of(self.program, operand);
+                let value = self.expression(operand);
+                match op {
+                    UnaryOp::Plus => value,
+                    UnaryOp::Minus if ty.is_integer() => {
+                        self.value(format!["sub {} 0, {}", llvm_type(ty), value])
+                    }
+                    UnaryOp::Minus => self.value(format!["fneg {} {}", llvm_type(ty), value]),
+                }
+            }
+            ExprKind::Binary { op, left, right } => {
+                let (op, left, right) = (*op, *left, *right);
+                if op.is_comparison() {
+                    let compared = self.compare(op, left, right);
+                    return self.value(format!["zext i1 {} to i32", compared]);
+                }
+
+                let common = ctype::common(self.program, left, right);
+                let left = self.convert(left, common);
+                let right = self.convert(right, common);
+                let checked = common.is_integer() || self.float_division == FloatDivision::Error;
+                if matches!(op, BinaryOp::Divide | BinaryOp::Remainder) && checked {
+                    if !self.divisions.contains(&(op, common)) {
+                        self.divisions.push((op, common));
+                    }
+                    let ty = llvm_type(common);
+                    let name = function(op);
+                    let line = self.program.exprs[expression].span.line;
+                    return self.value(format![
+                        "call {ty} @haneul.{name}.{ty}({ty} {left}, {ty} {right}, i32 {line})"
+                    ]);
+                }
+                let instruction = match (common.is_integer(), op) {
+                    (true, BinaryOp::Add) => "add",
+                    (true, BinaryOp::Subtract) => "sub",
+                    (true, BinaryOp::Multiply) => "mul",
+                    (true, _) => "sdiv",
+                    (false, BinaryOp::Add) => "fadd",
+                    (false, BinaryOp::Subtract) => "fsub",
+                    (false, BinaryOp::Multiply) => "fmul",
+                    // the same as fmod in c
+                    (false, BinaryOp::Remainder) => "frem",
+                    (false, _) => "fdiv",
+                };
+                self.value(format![
+                    "{} {} {}, {}",
+                    instruction,
+                    llvm_type(common),
+                    left,
+                    right
+                ])
+            }
+        }
+    }
+
+    // the expression as another type, like c converts it for a calculation or a LET
+    fn convert(&mut self, expression: ExprId, to: CType) -> String {
+        let from = ctype::of(self.program, expression);
+        let value = self.expression(expression);
+        let conversion = match (from, to) {
+            _ if from == to => return value,
+            (CType::Int, CType::Long) => "sext",
+            (CType::Int | CType::Long, _) => "sitofp",
+            (CType::Float, CType::Double) => "fpext",
+            (CType::Double, CType::Float) => "fptrunc",
+            _ => unreachable!("nothing is made into a smaller type but a float"),
+        };
+        self.value(format![
+            "{} {} {} to {}",
+            conversion,
+            llvm_type(from),
+            value,
+            llvm_type(to)
+        ])
+    }
+
+    // where a variable is on the stack, they're numbered so any name works
+    fn address(&self, variable: Symbol) -> String {
+        let index = self
+            .variables
+            .iter()
+            .position(|known| *known == variable)
+            .expect("every variable is given a value somewhere");
+        format!["%var{}", index]
+    }
+}
+
+fn function(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Divide => "divide",
+        _ => "remainder",
+    }
+}
+
+// the division or remainder of a type that stops the program when it divides by 0. the
+// smallest int divided by -1 wraps around like in c, its remainder is 0
+fn division(op: BinaryOp, ty: CType) -> String {
+    let name = llvm_type(ty);
+    let function = function(op);
+    let (zero, message) = match ty.is_integer() {
+        true => (format!["icmp eq {name} %right, 0"], constant(".int_zero")),
+        false => (
+            format!["fcmp oeq {name} %right, 0.0"],
+            constant(".float_zero"),
+        ),
+    };
+    let divide = match (ty.is_integer(), op) {
+        (true, BinaryOp::Divide) => "sdiv",
+        (true, _) => "srem",
+        (false, BinaryOp::Divide) => "fdiv",
+        (false, _) => "frem",
+    };
+    let minus = match op {
+        BinaryOp::Divide => format!["  %negated = sub {name} 0, %left\n  ret {name} %negated\n"],
+        _ => format!["  ret {name} 0\n"],
+    };
+    let wrap = match ty.is_integer() {
+        true => format![
+            "  %minus = icmp eq {name} %right, -1\n  br i1 %minus, label %negate, label %divide\n\
+             negate:\n{minus}"
+        ],
+        false => String::from("  br label %divide\n"),
+    };
+    format![
+        "\ndefine internal {name} @haneul.{function}.{name}({name} %left, {name} %right, i32 %line) {{\n\
+         entry:\n  %zero = {zero}\n  br i1 %zero, label %fail, label %check\n\
+         fail:\n  call void @haneul.fail(ptr {message}, i32 %line)\n  unreachable\n\
+         check:\n{wrap}divide:\n  %quotient = {divide} {name} %left, %right\n  ret {name} %quotient\n}}\n"
+    ]
+}
+
+fn llvm_type(ty: CType) -> &'static str {
+    match ty {
+        CType::Int => "i32",
+        CType::Long => "i64",
+        CType::Float => "float",
+        CType::Double => "double",
+    }
+}
+
+// a pointer to the start of a constant string, which is the constant itself
+fn constant(name: &str) -> String {
+    format!["@{}", name]
+}
+
+fn escape(string: &str) -> String {
+    let mut escaped = String::new();
+    for byte in string.bytes() {
+        match byte {
+            b'"' | b'\\' => {
+                let _ = write!(escaped, "\\{:02X}", byte);
+            }
+            b' '..=b'~' => escaped.push(byte as char),
+            _ => {
+                let _ = write!(escaped, "\\{:02X}", byte);
+            }
+        }
+    }
+    escaped
+}
+
+// every variable, in the order they first get a value
+fn variables(statements: &[Statement]) -> Vec<Symbol> {
+    fn collect(statements: &[Statement], variables: &mut Vec<Symbol>) {
+        for statement in statements {
+            match &statement.kind {
+                StatementKind::Let { variable, .. } | StatementKind::Input(variable)
+                    if !variables.contains(variable) =>
+                {
+                    variables.push(*variable)
+                }
+                StatementKind::If { body, .. } | StatementKind::While { body, .. } => {
+                    collect(body, variables)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut variables = vec![];
+    collect(statements, &mut variables);
+    variables
+}
+
+const CONSTANTS: &str = r#"@.exponent = private unnamed_addr constant [5 x i8] c"%.*e\00"
+@.fixed = private unnamed_addr constant [6 x i8] c"%.*f\0A\00"
+@.digits = private unnamed_addr constant [14 x i8] c"%.*s%.*s%.*d\0A\00"
+@.nan = private unnamed_addr constant [4 x i8] c"nan\00"
+@.inf = private unnamed_addr constant [4 x i8] c"inf\00"
+@.minus_inf = private unnamed_addr constant [5 x i8] c"-inf\00"
+@.string = private unnamed_addr constant [4 x i8] c"%s\0A\00"
+@.int = private unnamed_addr constant [4 x i8] c"%d\0A\00"
+@.long = private unnamed_addr constant [5 x i8] c"%ld\0A\00"
+@.input = private unnamed_addr constant [3 x i8] c"%f\00"
+@.skip = private unnamed_addr constant [4 x i8] c"%*s\00"
+"#;
+
+// PRINT, like the c helper: the fewest digits that read back as the same float, without an
+// exponent. the first of 1 to 9 digits in the e notation that strtof reads back as the float
+// is written out with its zeros
+const PRINT_NUMBER: &str = r#"
+declare i32 @snprintf(ptr, i64, ptr, ...)
+declare float @strtof(ptr, ptr)
+declare ptr @strchr(ptr, i32)
+declare i32 @atoi(ptr)
+declare i32 @puts(ptr)
+declare float @llvm.fabs.f32(float)
+
+define internal void @haneul.print_number(float %value) {
+entry:
+  %buffer = alloca [32 x i8]
+  %double = fpext float %value to double
+  %nan = fcmp uno float %value, %value
+  br i1 %nan, label %print_nan, label %finite
+print_nan:
+  call i32 @puts(ptr @.nan)
+  ret void
+finite:
+  %magnitude = call float @llvm.fabs.f32(float %value)
+  %infinite = fcmp oeq float %magnitude, 0x7FF0000000000000
+  br i1 %infinite, label %print_infinity, label %try
+print_infinity:
+  %negative = fcmp olt float %value, 0.0
+  %infinity = select i1 %negative, ptr @.minus_inf, ptr @.inf
+  call i32 @puts(ptr %infinity)
+  ret void
+try:
+  %precision = phi i32 [ 1, %finite ], [ %next, %longer ]
+  %decimals = sub i32 %precision, 1
+  call i32 (ptr, i64, ptr, ...) @snprintf(ptr %buffer, i64 32, ptr @.exponent, i32 %decimals, double %double)
+  %read = call float @strtof(ptr %buffer, ptr null)
+  %same = fcmp oeq float %read, %value
+  %last = icmp eq i32 %precision, 9
+  %done = or i1 %same, %last
+  br i1 %done, label %found, label %longer
+longer:
+  %next = add i32 %precision, 1
+  br label %try
+found:
+  %e = call ptr @strchr(ptr %buffer, i32 101)
+  %after = getelementptr inbounds i8, ptr %e, i64 1
+  %exponent = call i32 @atoi(ptr %after)
+  %fraction = icmp slt i32 %exponent, %decimals
+  br i1 %fraction, label %print_fixed, label %print_digits
+print_fixed:
+  %places = sub i32 %decimals, %exponent
+  call i32 (ptr, ...) @printf(ptr @.fixed, i32 %places, double %double)
+  ret void
+print_digits:
+  %first = load i8, ptr %buffer
+  %minus = icmp eq i8 %first, 45
+  %sign = zext i1 %minus to i32
+  %lead = add i32 %sign, 1
+  %offset = add i32 %sign, 2
+  %offset64 = sext i32 %offset to i64
+  %rest = getelementptr inbounds i8, ptr %buffer, i64 %offset64
+  %zeros = sub i32 %exponent, %decimals
+  call i32 (ptr, ...) @printf(ptr @.digits, i32 %lead, ptr %buffer, i32 %decimals, ptr %rest, i32 %zeros, i32 0)
+  ret void
+}
+"#;
+
+// prints the error like the c helpers do and stops the program
+const FAIL: &str = r#"@.int_zero = private unnamed_addr constant [42 x i8] c"Error: an int is divided by 0 on line %d\0A\00"
+@.float_zero = private unnamed_addr constant [43 x i8] c"Error: a float is divided by 0 on line %d\0A\00"
+
+declare i32 @fflush(ptr)
+declare i32 @dprintf(i32, ptr, ...)
+declare void @exit(i32)
+
+define internal void @haneul.fail(ptr %message, i32 %line) {
+entry:
+  call i32 @fflush(ptr null)
+  call i32 (i32, ptr, ...) @dprintf(i32 2, ptr %message, i32 %line)
+  call void @exit(i32 1)
+  unreachable
+}
+"#;
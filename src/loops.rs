@@ -0,0 +1,504 @@
+// loop optimizations, the body of a WHILE is where a program spends its time
+//
+// calculations in the body that don't change while the loop runs are done once before it, when
+// the loop runs at all:
+//
+//     WHILE i < n REPEAT               IF i < n THEN
+//         PRINT i * (w * h)      ->        LET haneul_loop0 = w * h
+//         LET i = i + 1                ENDIF
+//     ENDWHILE                         WHILE i < n REPEAT
+//                                          PRINT i * haneul_loop0
+//                                          LET i = i + 1
+//                                      ENDWHILE
+//
+// a calculation that can go wrong isn't moved, the IF it was in might have kept it from running:
+// only a / or a % by a number that isn't 0 is. a condition with a call isn't checked twice, a
+// loop like that keeps its calculations
+//
+// and a counter that goes up by the same step every time through the loop, times a number,
+// is kept in a variable that goes up by the step times that number instead of multiplying:
+//
+//     LET i = 0                        LET i = 0
+//     WHILE i < 10 REPEAT              LET haneul_loop0 = 0
+//         PRINT i * 4          ->      WHILE i < 10 REPEAT
+//         LET i = i + 1                    PRINT haneul_loop0
+//     ENDWHILE                             LET i = i + 1
+//                                          LET haneul_loop0 = haneul_loop0 + 4
+//                                      ENDWHILE
+//
+// a loop with a LABEL in it is left alone, a GOTO into it would skip what is done before it.
+// like in cse.rs a temporary has the type of its calculation, and one with a double isn't
+// moved. the counter has to start at an int, go up by an int and stop at an int, small enough
+// that a float has every number the temporary goes through, so adding gives exactly what
+// multiplying would
+
+use crate::{
+    ast::{BinaryOp, Expr, ExprId, ExprKind, Exprs, Program, Statement, StatementKind},
+    ctype,
+    intern::Symbol,
+    token::Span,
+};
+
+pub fn optimize(program: &mut Program) {
+    let statements = std::mem::take(&mut program.statements);
+    let mut optimizer = Optimizer {
+        program,
+        temporaries: 0,
+    };
+    let statements = optimizer.statements(statements);
+    program.statements = statements;
+}
+
+struct Optimizer<'a> {
+    program: &'a mut Program,
+    temporaries: usize,
+}
+
+impl Optimizer<'_> {
+    fn statements(&mut self, statements: Vec<Statement>) -> Vec<Statement> {
+        let mut optimized = Vec::with_capacity(statements.len());
+        for mut statement in statements {
+            match &mut statement.kind {
+                StatementKind::If { body, .. } => *body = self.statements(std::mem::take(body)),
+                // the loops inside are done first, what they move out can then move further
+                StatementKind::While { condition, body } => {
+                    *body = self.statements(std::mem::take(body));
+                    if !has_label(body) {
+                        let span = statement.span;
+                        let guard = self.copy(*condition);
+                        optimized.extend(self.reduce(*condition, body, &optimized, span));
+                        if !has_call(&self.program.exprs, guard) {
+                            let hoisted = self.hoist(*condition, body, span);
+                            if !hoisted.is_empty() {
+                                optimized.push(Statement {
+                                    kind: StatementKind::If {
+                                        condition: guard,
+                                        body: hoisted,
+                                    },
+                                    span,
+                                });
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+            optimized.push(statement);
+        }
+
+        optimized
+    }
+
+    // moves the calculations that don't change out of the loop, gives the LETs that go in
+    // front of it
+    fn hoist(&mut self, condition: ExprId, body: &[Statement], span: Span) -> Vec<Statement> {
+        let mut assigned = vec![];
+        assigned_in(body, &mut assigned);
+
+        let mut roots = vec![condition];
+        expressions_in(body, &mut roots);
+        let mut before = vec![];
+        for root in roots {
+            self.hoist_expression(root, &assigned, &mut before, span);
+        }
+
+        before
+    }
+
+    fn hoist_expression(
+        &mut self,
+        expression: ExprId,
+        assigned: &[Symbol],
+        before: &mut Vec<Statement>,
+        span: Span,
+    ) {
+        let exprs = &self.program.exprs;
+        let invariant = matches!(exprs[expression].kind, ExprKind::Binary { .. })
+            && is_simple(exprs, expression)
+            && reads(exprs, expression)
+                .iter()
+                .all(|variable| !assigned.contains(variable));
+        if invariant {
+            let temporary = self.temporary(expression);
+            let exprs = &mut self.program.exprs;
+            let moved = exprs.add(exprs[expression].clone());
+            replace(exprs, expression, ExprKind::Variable(temporary));
+            before.push(let_statement(temporary, moved, span));
+            return;
+        }
+
+        for child in children(&self.program.exprs, expression) {
+            self.hoist_expression(child, assigned, before, span);
+        }
+    }
+
+    // strength reduction for every counter of the loop, gives the LETs that go in front of it
+    fn reduce(
+        &mut self,
+        condition: ExprId,
+        body: &mut Vec<Statement>,
+        previous: &[Statement],
+        span: Span,
+    ) -> Vec<Statement> {
+        let mut before = vec![];
+        let mut index = 0;
+        while index < body.len() {
+            if let Some(counter) = counter(&self.program.exprs, condition, body, index, previous) {
+                let updates = self.reduce_counter(&counter, condition, body, &mut before, span);
+                let count = updates.len();
+                body.splice(index + 1..index + 1, updates);
+                index += count;
+            }
+            index += 1;
+        }
+
+        before
+    }
+
+    // every `counter * number` in the loop becomes a temporary that goes up with the counter.
+    // gives the LETs that go right after the counter goes up
+    fn reduce_counter(
+        &mut self,
+        counter: &Counter,
+        condition: ExprId,
+        body: &[Statement],
+        before: &mut Vec<Statement>,
+        span: Span,
+    ) -> Vec<Statement> {
+        let mut roots = vec![condition];
+        expressions_in(body, &mut roots);
+        let mut products = vec![];
+        for root in roots {
+            products_in(&self.program.exprs, root, counter.variable, &mut products);
+        }
+
+        let mut temporaries: Vec<(i32, Symbol)> = vec![];
+        let mut updates = vec![];
+        for (product, factor) in products {
+            if !counter.exact(factor) {
+                continue;
+            }
+            let temporary = match temporaries.iter().find(|(known, _)| *known == factor) {
+                Some((_, temporary)) => *temporary,
+                None => {
+                    let temporary = self.temporary(product);
+                    temporaries.push((factor, temporary));
+
+                    let initial = self.number(counter.start * factor, span);
+                    before.push(let_statement(temporary, initial, span));
+                    let read = self.program.exprs.add(Expr {
+                        kind: ExprKind::Variable(temporary),
+                        span,
+                    });
+                    let increase = self.number(counter.step * factor, span);
+                    let sum = self.program.exprs.add(Expr {
+                        kind: ExprKind::Binary {
+                            op: BinaryOp::Add,
+                            left: read,
+                            right: increase,
+                        },
+                        span,
+                    });
+                    updates.push(let_statement(temporary, sum, span));
+                    temporary
+                }
+            };
+            replace(
+                &mut self.program.exprs,
+                product,
+                ExprKind::Variable(temporary),
+            );
+        }
+
+        updates
+    }
+
+    // a copy of the expression that doesn't share its parts, what's moved out of the original
+    // stays in the copy
+    fn copy(&mut self, expression: ExprId) -> ExprId {
+        let mut copy = self.program.exprs[expression].clone();
+        match &mut copy.kind {
+            ExprKind::Binary { left, right, .. } => {
+                *left = self.copy(*left);
+                *right = self.copy(*right);
+            }
+            ExprKind::Unary { operand, .. } => *operand = self.copy(*operand),
+            ExprKind::Call { arguments, .. } => {
+                for argument in arguments {
+                    *argument = self.copy(*argument);
+                }
+            }
+            ExprKind::Number(_) | ExprKind::Variable(_) => {}
+        }
+        self.program.exprs.add(copy)
+    }
+
+    fn number(&mut self, value: i32, span: Span) -> ExprId {
+        self.program.exprs.add(Expr {
+            kind: ExprKind::Number(value.to_string()),
+            span,
+        })
+    }
+
+    // the temporaries can't clash with a variable, a name can't have a _ after its first letter.
+    // it has the type of the calculation it holds
+    fn temporary(&mut self, calculation: ExprId) -> Symbol {
+        let name = format!["haneul_loop{}", self.temporaries];
+        self.temporaries += 1;
+        let temporary = self.program.names.intern(&name);
+        let ty = ctype::of(self.program, calculation);
+        self.program.types.insert(temporary, ty);
+        temporary
+    }
+}
+
+// a variable that goes up (or down) by the same int every time through the loop
+struct Counter {
+    variable: Symbol,
+    start: i32,
+    step: i32,
+    // the loop stops before the counter gets past this
+    limit: i32,
+}
+
+// a float has every int up to this, after it adding and multiplying can round differently
+const EXACT: i64 = 1 << 24;
+
+impl Counter {
+    // whether every `counter * factor` the loop can see is an int a float has. the counter
+    // goes from its start to one step past the limit
+    fn exact(&self, factor: i32) -> bool {
+        let end = self.limit as i64 + self.step as i64;
+        [self.start as i64, end]
+            .iter()
+            .all(|value| (value * factor as i64).abs() <= EXACT)
+    }
+}
+
+// the statement at `index` is `LET i = i + step` or `LET i = i - step` with an int step, and
+// nothing else in the loop gives i a value. the condition is `i < limit` going up or `i > limit`
+// going down, and the last statement before the loop that gives i a value is `LET i = start`
+fn counter(
+    exprs: &Exprs,
+    condition: ExprId,
+    body: &[Statement],
+    index: usize,
+    previous: &[Statement],
+) -> Option<Counter> {
+    let StatementKind::Let { variable, value } = body[index].kind else {
+        return None;
+    };
+    let ExprKind::Binary { op, left, right } = exprs[value].kind else {
+        return None;
+    };
+    let step = match (op, &exprs[left].kind, &exprs[right].kind) {
+        (BinaryOp::Add, ExprKind::Variable(read), _) if *read == variable => int(exprs, right)?,
+        (BinaryOp::Add, _, ExprKind::Variable(read)) if *read == variable => int(exprs, left)?,
+        (BinaryOp::Subtract, ExprKind::Variable(read), _) if *read == variable => {
+            int(exprs, right)?.checked_neg()?
+        }
+        _ => return None,
+    };
+    if assignments(body, variable) != 1 {
+        return None;
+    }
+
+    let ExprKind::Binary { op, left, right } = exprs[condition].kind else {
+        return None;
+    };
+    if !matches!(exprs[left].kind, ExprKind::Variable(read) if read == variable) {
+        return None;
+    }
+    let limit = int(exprs, right)?;
+    let bounded = match op {
+        BinaryOp::Less | BinaryOp::LessEqual => step > 0,
+        BinaryOp::Greater | BinaryOp::GreaterEqual => step < 0,
+        _ => false,
+    };
+    if !bounded {
+        return None;
+    }
+
+    Some(Counter {
+        variable,
+        start: start(exprs, previous, variable)?,
+        step,
+        limit,
+    })
+}
+
+// the int the counter starts at. no LABEL can come after its LET, another value could come
+// from there
+fn start(exprs: &Exprs, previous: &[Statement], counter: Symbol) -> Option<i32> {
+    for statement in previous.iter().rev() {
+        match &statement.kind {
+            StatementKind::Let { variable, value } if *variable == counter => {
+                return int(exprs, *value)
+            }
+            _ => {
+                let statement = std::slice::from_ref(statement);
+                if assignments(statement, counter) > 0 || has_label(statement) {
+                    return None;
+                }
+            }
+        }
+    }
+
+    None
+}
+
+fn int(exprs: &Exprs, expression: ExprId) -> Option<i32> {
+    match &exprs[expression].kind {
+        ExprKind::Number(number) => number.parse().ok(),
+        _ => None,
+    }
+}
+
+// the `counter * number` and `number * counter` in an expression, with the number
+fn products_in(
+    exprs: &Exprs,
+    expression: ExprId,
+    counter: Symbol,
+    products: &mut Vec<(ExprId, i32)>,
+) {
+    if let ExprKind::Binary {
+        op: BinaryOp::Multiply,
+        left,
+        right,
+    } = exprs[expression].kind
+    {
+        let factor = match (&exprs[left].kind, &exprs[right].kind) {
+            (ExprKind::Variable(variable), _) if *variable == counter => int(exprs, right),
+            (_, ExprKind::Variable(variable)) if *variable == counter => int(exprs, left),
+            _ => None,
+        };
+        if let Some(factor) = factor {
+            products.push((expression, factor));
+            return;
+        }
+    }
+
+    for child in children(exprs, expression) {
+        products_in(exprs, child, counter, products);
+    }
+}
+
+fn has_label(statements: &[Statement]) -> bool {
+    statements.iter().any(|statement| match &statement.kind {
+        StatementKind::Label(_) => true,
+        StatementKind::If { body, .. } | StatementKind::While { body, .. } => has_label(body),
+        _ => false,
+    })
+}
+
+// the variables the statements give a value, in them or in the statements inside them
+fn assigned_in(statements: &[Statement], assigned: &mut Vec<Symbol>) {
+    for statement in statements {
+        match &statement.kind {
+            StatementKind::Let { variable, .. } | StatementKind::Input(variable) => {
+                assigned.push(*variable)
+            }
+            StatementKind::If { body, .. } | StatementKind::While { body, .. } => {
+                assigned_in(body, assigned)
+            }
+            _ => {}
+        }
+    }
+}
+
+fn assignments(statements: &[Statement], variable: Symbol) -> usize {
+    let mut assigned = vec![];
+    assigned_in(statements, &mut assigned);
+    assigned
+        .iter()
+        .filter(|assigned| **assigned == variable)
+        .count()
+}
+
+// the expressions the statements use, the conditions of the IFs and WHILEs in them too
+fn expressions_in(statements: &[Statement], expressions: &mut Vec<ExprId>) {
+    for statement in statements {
+        match &statement.kind {
+            StatementKind::Print(value) | StatementKind::Let { value, .. } => {
+                expressions.push(*value)
+            }
+            StatementKind::Call { arguments, .. } => expressions.extend(arguments),
+            StatementKind::If { condition, body } | StatementKind::While { condition, body } => {
+                expressions.push(*condition);
+                expressions_in(body, expressions);
+            }
+            StatementKind::PrintString(_)
+            | StatementKind::Label(_)
+            | StatementKind::Goto(_)
+            | StatementKind::Input(_) => {}
+        }
+    }
+}
+
+fn children(exprs: &Exprs, expression: ExprId) -> Vec<ExprId> {
+    match &exprs[expression].kind {
+        ExprKind::Binary { left, right, .. } => vec![*left, *right],
+        ExprKind::Unary { operand, .. } => vec![*operand],
+        ExprKind::Call { arguments, .. } => arguments.clone(),
+        ExprKind::Number(_) | ExprKind::Variable(_) => vec![],
+    }
+}
+
+fn has_call(exprs: &Exprs, expression: ExprId) -> bool {
+    matches!(exprs[expression].kind, ExprKind::Call { .. })
+        || children(exprs, expression)
+            .iter()
+            .any(|child| has_call(exprs, *child))
+}
+
+// no calls, which can give something else every time, and no numbers with a dot, which are
+// doubles in c. nothing that can go wrong either, a / or a % only by a number that isn't 0.
+// and at least one variable, only numbers is left to fold.rs
+fn is_simple(exprs: &Exprs, expression: ExprId) -> bool {
+    fn pure(exprs: &Exprs, expression: ExprId) -> bool {
+        match &exprs[expression].kind {
+            ExprKind::Number(number) => !number.contains('.'),
+            ExprKind::Variable(_) => true,
+            ExprKind::Call { .. } => false,
+            ExprKind::Binary {
+                op: BinaryOp::Divide | BinaryOp::Remainder,
+                left,
+                right,
+            } => pure(exprs, *left) && nonzero(exprs, *right),
+            _ => children(exprs, expression)
+                .iter()
+                .all(|child| pure(exprs, *child)),
+        }
+    }
+
+    pure(exprs, expression) && !reads(exprs, expression).is_empty()
+}
+
+fn nonzero(exprs: &Exprs, expression: ExprId) -> bool {
+    matches!(&exprs[expression].kind,
+        ExprKind::Number(number) if !number.contains('.') && number.bytes().any(|digit| digit != b'0'))
+}
+
+fn reads(exprs: &Exprs, expression: ExprId) -> Vec<Symbol> {
+    match &exprs[expression].kind {
+        ExprKind::Variable(variable) => vec![*variable],
+        _ => children(exprs, expression)
+            .iter()
+            .flat_map(|child| reads(exprs, *child))
+            .collect(),
+    }
+}
+
+fn replace(exprs: &mut Exprs, expression: ExprId, kind: ExprKind) {
+    exprs[expression] = Expr {
+        kind,
+        span: exprs[expression].span,
+    };
+}
+
+fn let_statement(variable: Symbol, value: ExprId, span: Span) -> Statement {
+    Statement {
+        kind: StatementKind::Let { variable, value },
+        span,
+    }
+}
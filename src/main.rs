@@ -1,34 +1,773 @@
-use std::{env, fs, process::Command};
+use std::{env, fs, io, path::Path, time::Duration};
 
-mod emitter;
-mod lexer;
-mod parser;
-mod token;
+// what only the command line does, the rest is in lib.rs
+mod cache;
+mod config;
+mod session;
+mod timings;
+mod watch;
 
-fn main() {
-    if !true {
-        panic!("What?");
+use haneul::{
+    ast::Program,
+    builtins, bytecode, cfg, codes,
+    ctype::FloatDivision,
+    diagnostics::{self, Diagnostic, Level, Lints, MessageFormat, Verbosity},
+    dot, hbc,
+    i18n::{self, Lang},
+    interpreter, lexer, mermaid,
+    parser::{self, ParseError},
+    passes::{self, OptLevel},
+    pretty, runtime,
+    snapshot::Snapshot,
+    source::SourceMap,
+    vm, Target,
+};
+
+use config::Config;
+use session::Session;
+use timings::Timings;
+
+// what to print instead of compiling the program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Emit {
+    Tokens,
+    Ast,
+    AstJson,
+    Dot,
+    CfgDot,
+    CfgMermaid,
+}
+
+// what `haneul run` runs the program with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Engine {
+    // the bytecode on the vm
+    Vm,
+    // the tree, with the interpreter
+    Tree,
+}
+
+struct Options {
+    input: String,
+    // --target, -O, --max-depth, --float-division, --minify and the lints
+    compile: haneul::Options,
+    emit: Option<Emit>,
+    message_format: MessageFormat,
+    verbosity: Verbosity,
+    lang: Lang,
+    timings: bool,
+    // `haneul run`, the program is run instead of compiled
+    run: bool,
+    engine: Engine,
+    // compile the program twice and check that both give the same output
+    verify_deterministic: bool,
+    // --max-steps, `haneul run` stops a program that takes more
+    max_steps: Option<u64>,
+    // --timeout, in seconds
+    timeout: Option<Duration>,
+    // --record and --replay, the file with the INPUTs of `haneul run`
+    record: Option<String>,
+    replay: Option<String>,
+    // --snapshot saves a program the limits stopped, --resume runs it on
+    snapshot: Option<String>,
+    resume: Option<String>,
+    // --watch, `haneul run` swaps in the new version of the program when its file changes
+    watch: bool,
+    // --no-cache, `haneul run` compiles the program even if it's in .haneul-cache
+    no_cache: bool,
+    // --peephole-stats, how many instructions the bytecode has before and after the peephole
+    // pass
+    peephole_stats: bool,
+    // `haneul build`, the program is named after the input and an llvm object is linked
+    build: bool,
+    // -o, the program that is built, or the .hbc file of --target=bytecode
+    output: Option<String>,
+    // from the command line, applied after the ones in haneul.toml
+    lints: Vec<(String, Level)>,
+}
+
+impl Options {
+    fn limits(&self) -> runtime::Limits {
+        runtime::Limits {
+            steps: self.max_steps,
+            timeout: self.timeout,
+        }
     }
 
+    fn parse(args: &[String]) -> Self {
+        let mut input = None;
+        let mut emit = None;
+        let mut target = Target::C;
+        let mut message_format = MessageFormat::Human;
+        let mut verbosity = Verbosity::Normal;
+        let mut lang = Lang::from_env();
+        let mut max_depth = parser::DEFAULT_MAX_DEPTH;
+        let mut opt_level = OptLevel::default();
+        let mut timings = false;
+        let mut engine = Engine::Vm;
+        let mut output = None;
+        let mut minify = false;
+        let mut verify_deterministic = false;
+        let mut no_cache = false;
+        let mut peephole_stats = false;
+        let mut max_steps = None;
+        let mut timeout = None;
+        let mut float_division = FloatDivision::default();
+        let mut record = None;
+        let mut replay = None;
+        let mut snapshot = None;
+        let mut resume = None;
+        let mut watch = false;
+        let mut lints = vec![];
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            // -D W0001 and -DW0001 both work, like in rustc
+            let level = match arg.get(..2) {
+                Some("-A") => Some(Level::Allow),
+                Some("-W") => Some(Level::Warn),
+                Some("-D") => Some(Level::Deny),
+                _ => None,
+            };
+            if let Some(level) = level {
+                let lint = match &arg[2..] {
+                    "" => args
+                        .next()
+                        .unwrap_or_else(|| die(format!["{} needs a lint", arg])),
+                    lint => lint,
+                };
+                lints.push((lint.to_owned(), level));
+            } else if let Some(level) = arg.strip_prefix("-O") {
+                opt_level = OptLevel::parse(level)
+                    .unwrap_or_else(|| die(format!["unknown optimization level: {}", arg]));
+            } else if let Some(format) = arg.strip_prefix("--message-format=") {
+                message_format = match format {
+                    "human" => MessageFormat::Human,
+                    "json" => MessageFormat::Json,
+                    _ => die(format!["unknown message format: {}", format]),
+                };
+            } else if arg == "--emit-tokens" {
+                emit = Some(Emit::Tokens);
+            } else if arg == "--emit-ast" {
+                emit = Some(Emit::Ast);
+            } else if arg == "--emit-ast=json" {
+                emit = Some(Emit::AstJson);
+            } else if arg == "--emit-cfg" || arg == "--emit-cfg=dot" {
+                emit = Some(Emit::CfgDot);
+            } else if arg == "--emit-cfg=mermaid" {
+                emit = Some(Emit::CfgMermaid);
+            } else if let Some(name) = arg.strip_prefix("--target=") {
+                target = match Target::parse(name) {
+                    Some(target) => target,
+                    None if matches!(name, "llvm" | "llvm-object") => die(format![
+                        "--target={} needs haneul to be built with the llvm feature",
+                        name
+                    ]),
+                    None => die(format!["unknown target: {}", name]),
+                };
+            } else if let Some(name) = arg.strip_prefix("--engine=") {
+                engine = match name {
+                    "vm" => Engine::Vm,
+                    "tree" => Engine::Tree,
+                    _ => die(format!["unknown engine: {}", name]),
+                };
+            } else if arg == "-o" {
+                output = Some(
+                    args.next()
+                        .unwrap_or_else(|| die(String::from("-o needs a file")))
+                        .clone(),
+                );
+            } else if arg == "--minify" {
+                minify = true;
+            } else if arg == "--verify-deterministic" {
+                verify_deterministic = true;
+            } else if let Some(steps) = arg.strip_prefix("--max-steps=") {
+                max_steps = Some(
+                    steps
+                        .parse()
+                        .unwrap_or_else(|_| die(format!["not a number of steps: {}", steps])),
+                );
+            } else if let Some(seconds) = arg.strip_prefix("--timeout=") {
+                timeout = Some(
+                    seconds
+                        .parse()
+                        .ok()
+                        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                        .unwrap_or_else(|| die(format!["not a number of seconds: {}", seconds])),
+                );
+            } else if let Some(name) = arg.strip_prefix("--float-division=") {
+                float_division = match name {
+                    "ieee" => FloatDivision::Ieee,
+                    "error" => FloatDivision::Error,
+                    _ => die(format!["unknown float division: {}", name]),
+                };
+            } else if let Some(file) = arg.strip_prefix("--record=") {
+                record = Some(file.to_owned());
+            } else if let Some(file) = arg.strip_prefix("--replay=") {
+                replay = Some(file.to_owned());
+            } else if let Some(file) = arg.strip_prefix("--snapshot=") {
+                snapshot = Some(file.to_owned());
+            } else if let Some(file) = arg.strip_prefix("--resume=") {
+                resume = Some(file.to_owned());
+            } else if arg == "--watch" {
+                watch = true;
+            } else if arg == "--no-cache" {
+                no_cache = true;
+            } else if arg == "--peephole-stats" {
+                peephole_stats = true;
+            } else if arg == "--timings" {
+                timings::count_memory();
+                timings = true;
+            } else if arg == "--teach" {
+                verbosity = Verbosity::Teach;
+            } else if arg == "--terse" {
+                verbosity = Verbosity::Terse;
+            } else if let Some(name) = arg.strip_prefix("--lang=") {
+                lang =
+                    Lang::parse(name).unwrap_or_else(|| die(format!["unknown language: {}", name]));
+            } else if let Some(depth) = arg.strip_prefix("--max-depth=") {
+                max_depth = depth
+                    .parse()
+                    .unwrap_or_else(|_| die(format!["not a valid depth: {}", depth]));
+            } else if arg.starts_with("--") {
+                die(format!["unknown option: {}", arg]);
+            } else if input.is_none() {
+                input = Some(arg.clone());
+            } else {
+                die(String::from("give one input file please"));
+            }
+        }
+
+        let input = input.unwrap_or_else(|| die(String::from("give one input file please")));
+        Self {
+            compile: haneul::Options {
+                target,
+                opt_level,
+                max_depth,
+                float_division,
+                minify,
+                lints: Lints::default(),
+                name: input.clone(),
+            },
+            input,
+            emit,
+            message_format,
+            verbosity,
+            lang,
+            timings,
+            verify_deterministic,
+            no_cache,
+            peephole_stats,
+            max_steps,
+            timeout,
+            record,
+            replay,
+            snapshot,
+            resume,
+            watch,
+            run: false,
+            build: false,
+            engine,
+            output,
+            lints,
+        }
+    }
+}
+
+fn main() {
     let args: Vec<String> = env::args().collect();
 
-    if args.len() != 2 {
-        println!("Error: give one input file please");
-        std::process::exit(1);
+    if args.len() == 3 && args[1] == "explain" {
+        match codes::explain(&args[2]) {
+            Some(explanation) => println!("{}", explanation),
+            None => die(format!["there is no diagnostic with code {}", args[2]]),
+        }
+        return;
+    }
+
+    // `haneul dot file.han` parses the program like compiling it does, then prints it as a graph
+    let mut options = match args.get(1).map(String::as_str) {
+        Some("dot") => Options {
+            emit: Some(Emit::Dot),
+            ..Options::parse(&args[2..])
+        },
+        Some("run") => Options {
+            run: true,
+            ..Options::parse(&args[2..])
+        },
+        // `haneul build file.han -o file` makes a program, `-o file.hbc` saves the bytecode
+        Some("build") => {
+            let mut options = Options::parse(&args[2..]);
+            let bytecode = options
+                .output
+                .as_ref()
+                .is_some_and(|output| output.ends_with(".hbc"));
+            if bytecode {
+                options.compile.target = Target::Bytecode;
+            }
+            Options {
+                build: true,
+                ..options
+            }
+        }
+        _ => Options::parse(&args[1..]),
+    };
+
+    if !options.run && (options.record.is_some() || options.replay.is_some()) {
+        die(String::from(
+            "--record and --replay only work with haneul run",
+        ));
+    }
+    if options.snapshot.is_some() || options.resume.is_some() {
+        if !options.run {
+            die(String::from(
+                "--snapshot and --resume only work with haneul run",
+            ));
+        }
+        if options.engine == Engine::Tree {
+            die(String::from("--snapshot and --resume only work on the vm"));
+        }
+    }
+
+    if options.peephole_stats && (!options.run || options.engine == Engine::Tree) {
+        die(String::from(
+            "--peephole-stats only works with haneul run on the vm",
+        ));
+    }
+
+    if options.watch {
+        if !options.run {
+            die(String::from("--watch only works with haneul run"));
+        }
+        if options.engine == Engine::Tree {
+            die(String::from("--watch only works on the vm"));
+        }
+        if options.input == "-" || options.input.ends_with(".hbc") {
+            die(String::from("--watch needs the file of the program"));
+        }
+        if options.record.is_some()
+            || options.replay.is_some()
+            || options.snapshot.is_some()
+            || options.resume.is_some()
+        {
+            die(String::from(
+                "--watch doesn't work with --record, --replay, --snapshot and --resume",
+            ));
+        }
+        // the loop pass and cse keep calculations in temporaries that are set before the line
+        // that uses them, going on at that line after a reload would skip them or set them
+        // again. the new version can also number them differently
+        options.compile.opt_level = options.compile.opt_level.min(OptLevel::O1);
+    }
+
+    // a .hbc file is already compiled, it is only checked and run
+    if options.run && options.input.ends_with(".hbc") {
+        if options.engine == Engine::Tree {
+            die(String::from("a .hbc file can only be run on the vm"));
+        }
+        let bytes = fs::read(&options.input)
+            .unwrap_or_else(|e| die(format!["couldn't read {}: {}", options.input, e]));
+        let chunk = hbc::decode(&bytes).unwrap_or_else(|e| die(format!["{} {}", options.input, e]));
+        finish(run_chunk(&chunk, &options));
+        return;
+    }
+
+    let directory = Path::new(&options.input).parent().unwrap_or(Path::new(""));
+    let config = Config::load(directory).unwrap_or_else(|e| die(e));
+    let mut lints = Lints::default();
+    for (lint, level) in &config.lints {
+        let level = Level::parse(level)
+            .unwrap_or_else(|| die(format!["unknown lint level for {}: {}", lint, level]));
+        lints.set(lint, level).unwrap_or_else(|e| die(e));
+    }
+    for (lint, level) in &options.lints {
+        lints.set(lint, *level).unwrap_or_else(|e| die(e));
+    }
+    options.compile.lints = lints;
+
+    // `-` reads the program from stdin while it is compiled, without keeping all of it around
+    let streamed = options.input == "-";
+    let mut sources = SourceMap::new();
+    let file = if streamed {
+        sources.add_unread(String::from("<stdin>"))
+    } else {
+        let text = fs::read_to_string(&options.input)
+            .map_err(|error| haneul::Error::Read(options.input.clone(), error))
+            .unwrap_or_else(|e| die(e.to_string()));
+        sources.add(options.input.clone(), text)
+    };
+
+    if options.emit == Some(Emit::Tokens) {
+        let errors = if streamed {
+            let mut stream = lexer::StreamLexer::new(file, io::stdin());
+            let errors = print_tokens(&mut stream);
+            if let Some(error) = stream.error() {
+                die(format!["couldn't read the program: {}", error]);
+            }
+            errors
+        } else {
+            print_tokens(&mut lexer::Lexer::new(file, sources.get(file).text()))
+        };
+        if !errors.is_empty() {
+            fail(&errors, &options, &sources);
+        }
+        return;
+    }
+
+    let mut timings = Timings::new(options.timings);
+    // `haneul run` on a program that didn't change since it was last run
+    let cache = (options.run
+        && options.engine == Engine::Vm
+        && options.emit.is_none()
+        && !options.no_cache
+        && !options.peephole_stats
+        && !options.watch
+        && !streamed)
+        .then(|| {
+            let key = format![
+                "{:?} {} {:?}",
+                options.compile.opt_level,
+                options.compile.max_depth,
+                options.compile.float_division
+            ];
+            cache::Cache::new(directory, sources.get(file).text(), &key)
+        });
+    if let Some(chunk) = cache.as_ref().and_then(cache::Cache::load) {
+        let result = timings.time("run", || run_chunk(&chunk, &options));
+        timings.report();
+        finish(result);
+        return;
+    }
+    let builtins = builtins::Registry::new();
+    let (result, warnings) = if streamed {
+        let mut stream = lexer::StreamLexer::new(file, io::stdin());
+        let parsed = parse(&mut stream, &builtins, &options, &mut timings);
+        if let Some(error) = stream.error() {
+            die(format!["couldn't read the program: {}", error]);
+        }
+        parsed
+    } else {
+        let mut lexer = lexer::Lexer::new(file, sources.get(file).text());
+        parse(&mut lexer, &builtins, &options, &mut timings)
+    };
+
+    let (warnings, errors) = timings.time("analyze", || {
+        haneul::analyze(&result, warnings, &builtins, &options.compile.lints)
+    });
+    report(&warnings, &options, &sources);
+    if !errors.is_empty() {
+        fail(&errors, &options, &sources);
+    }
+    let mut program = result.expect("parse errors are reported above");
+
+    match options.emit {
+        Some(Emit::Ast) => print!("{}", pretty::ast(&program)),
+        Some(Emit::AstJson) => println!(
+            "{}",
+            serde_json::to_string(&program).expect("a program is always json")
+        ),
+        Some(Emit::Dot) => print!("{}", dot::ast(&program)),
+        Some(Emit::CfgDot) => print!("{}", dot::cfg(&program, &cfg::Cfg::new(&program))),
+        Some(Emit::CfgMermaid) => print!("{}", mermaid::cfg(&program, &cfg::Cfg::new(&program))),
+        _ => {}
+    }
+    if options.emit.is_some() {
+        return;
+    }
+
+    timings.time("optimize", || {
+        passes::optimize(&mut program, options.compile.opt_level)
+    });
+
+    if options.run {
+        let result = match options.engine {
+            Engine::Tree => {
+                let mut interpreter = interpreter::Interpreter::new(&program);
+                if let Err(error) = interpreter.check() {
+                    die(error.message);
+                }
+                interpreter.set_limits(options.limits());
+                interpreter.set_float_division(options.compile.float_division);
+                interpreter.set_input(input(&options));
+                let result = timings.time("run", || interpreter.run());
+                record(&options, interpreter.recorded());
+                result
+            }
+            Engine::Vm => {
+                let chunk = timings
+                    .time("compile", || compile_chunk(&program, &builtins, &options))
+                    .unwrap_or_else(|builtin| die(format!["{} can't be run yet", builtin]));
+                if let Some(cache) = cache.as_ref().filter(|_| warnings.is_empty()) {
+                    cache.store(&chunk);
+                }
+                if options.watch {
+                    let text = sources.get(file).text().to_owned();
+                    let recompile = |text: &str| recompile(text, &builtins, &options);
+                    watch::run(&options.input, text, chunk, options.limits(), &recompile);
+                }
+                timings.time("run", || run_chunk(&chunk, &options))
+            }
+        };
+        timings.report();
+        finish(result);
+        return;
     }
-    let source = fs::read_to_string(&args[1]).unwrap();
 
-    let mut lexer = lexer::Lexer::new(source);
-    let mut emitter = emitter::Emitter::new(String::from("out.c"));
-    let mut parser = parser::Parser::new(&mut lexer, &mut emitter);
+    if options.compile.minify
+        && !matches!(
+            options.compile.target,
+            Target::C | Target::Js | Target::Python
+        )
+    {
+        die(String::from(
+            "--minify only works with --target=c, --target=js and --target=python",
+        ));
+    }
+    if options.verify_deterministic && streamed {
+        die(String::from(
+            "--verify-deterministic compiles the program twice, so it can't be read from stdin",
+        ));
+    }
+    let source = sources.get(file).name().to_owned();
+    // what the backend builds, see haneul::backend
+    let executable = match options.compile.target {
+        Target::Bytecode => options.output.clone(),
+        // `haneul build prog.han` makes prog, and links an llvm object
+        _ if options.build => Some(match &options.output {
+            Some(output) => output.clone(),
+            None if !streamed => Path::new(&options.input)
+                .file_stem()
+                .map_or(String::from("out"), |stem| {
+                    stem.to_string_lossy().into_owned()
+                }),
+            None => String::from("out"),
+        }),
+        #[cfg(feature = "llvm")]
+        Target::LlvmObject => None,
+        _ => options.output.clone(),
+    };
+    let mut backend = haneul::backend(
+        &options.compile,
+        &builtins,
+        source.clone(),
+        executable.clone(),
+    );
+    timings
+        .time("emit", || {
+            backend.prepare(&program);
+            backend.emit_program(&program)
+        })
+        .unwrap_or_else(|builtin| {
+            die(format![
+                "{} can't be compiled to {} yet",
+                builtin,
+                backend.language()
+            ])
+        });
+
+    if options.verify_deterministic {
+        let files = backend.files();
+        timings.time("verify", || {
+            let text = sources.get(file).text();
+            let mut lexer = lexer::Lexer::new(file, text);
+            let mut again = haneul::parse(&mut lexer, &builtins, options.compile.max_depth)
+                .0
+                .expect("it parsed the first time");
+            passes::optimize(&mut again, options.compile.opt_level);
+            let mut backend = haneul::backend(&options.compile, &builtins, source, executable);
+            backend.prepare(&again);
+            backend
+                .emit_program(&again)
+                .expect("it was emitted the first time");
+            let different = files.iter().zip(backend.files()).find_map(
+                |((name, first), (_, second))| Some((name, difference(first, &second)?)),
+            );
+            if let Some((name, byte)) = different {
+                die(format![
+                    "the output isn't deterministic, compiling it again made a different {} from byte {}",
+                    name, byte
+                ]);
+            }
+        });
+    }
 
-    parser.program();
-    emitter.write_file().unwrap();
+    timings
+        .time("build", || backend.finish())
+        .unwrap_or_else(|e| die(e));
+    if options.message_format == MessageFormat::Human {
+        println!("compiling complete!");
+    }
+    timings.report();
+}
+
+// where two compiles of the same program stop being the same, for --verify-deterministic
+fn difference(first: &[u8], second: &[u8]) -> Option<usize> {
+    first
+        .iter()
+        .zip(second)
+        .position(|(a, b)| a != b)
+        .or_else(|| (first.len() != second.len()).then(|| first.len().min(second.len())))
+}
+
+// bytecode::compile and the peephole pass
+fn compile_chunk(
+    program: &Program,
+    builtins: &builtins::Registry,
+    options: &Options,
+) -> Result<bytecode::Chunk, String> {
+    let mut chunk = bytecode::compile(program, builtins, options.compile.float_division)?;
+    let before = chunk.code.len();
+    passes::optimize_chunk(&mut chunk, options.compile.opt_level);
+    if options.peephole_stats {
+        eprintln!(
+            "peephole: {} instructions before, {} after",
+            before,
+            chunk.code.len()
+        );
+    }
+    Ok(chunk)
+}
 
-    println!("compiling complete!");
+fn run_chunk(chunk: &bytecode::Chunk, options: &Options) -> Result<(), runtime::RuntimeError> {
+    let mut vm = vm::Vm::new(chunk);
+    vm.set_limits(options.limits());
+    vm.set_input(input(options));
+    if let Some(file) = &options.resume {
+        let snapshot = Snapshot::load(file).unwrap_or_else(|e| die(e));
+        vm.resume(&snapshot)
+            .unwrap_or_else(|e| die(format!["{} can't be resumed: {}", file, e]));
+    }
+    let result = vm.run();
+    record(options, vm.recorded());
+    if let Some(file) = options.snapshot.as_ref().filter(|_| vm.stopped()) {
+        vm.snapshot().save(file).unwrap_or_else(|e| die(e));
+        eprintln!("the program is saved in {0}, --resume={0} runs it on", file);
+    }
+    result
+}
+
+// the INPUTs of `haneul run`, from the file of --replay
+fn input(options: &Options) -> runtime::Input {
+    let mut input = runtime::Input::new();
+    if let Some(file) = &options.replay {
+        input.replay(Session::load(file).unwrap_or_else(|e| die(e)).inputs);
+    }
+    if options.record.is_some() {
+        input.record();
+    }
+    input
+}
+
+// after the run, also when the program went wrong
+fn record(options: &Options, recorded: Option<&[Option<f32>]>) {
+    if let (Some(file), Some(inputs)) = (&options.record, recorded) {
+        let session = Session {
+            inputs: inputs.to_vec(),
+        };
+        session.save(file).unwrap_or_else(|e| die(e));
+    }
+}
+
+// after `haneul run`, a program that went wrong exits like one compiled to c that crashed
+fn finish(result: Result<(), runtime::RuntimeError>) {
+    if let Err(error) = result {
+        die(format!["{} on line {}", error.message, error.line]);
+    }
+}
+
+// a new version of the program for --watch. its warnings and errors are reported, with errors
+// it gives None and the old version runs on
+fn recompile(
+    text: &str,
+    builtins: &builtins::Registry,
+    options: &Options,
+) -> Option<bytecode::Chunk> {
+    let mut sources = SourceMap::new();
+    let file = sources.add(options.input.clone(), text.to_owned());
+    let mut lexer = lexer::Lexer::new(file, sources.get(file).text());
+    let (result, warnings) = haneul::parse(&mut lexer, builtins, options.compile.max_depth);
+    let (warnings, errors) = haneul::analyze(&result, warnings, builtins, &options.compile.lints);
+    report(&warnings, options, &sources);
+    if !errors.is_empty() {
+        report(&errors, options, &sources);
+        if options.message_format == MessageFormat::Human {
+            eprintln!("{}", i18n::errors_found(options.lang, errors.len()));
+        }
+        return None;
+    }
+    let mut program = result.ok()?;
+    passes::optimize(&mut program, options.compile.opt_level);
+    compile_chunk(&program, builtins, options)
+        .map_err(|builtin| eprintln!("Error: {} can't be run yet", builtin))
+        .ok()
+}
+
+// gives the errors and the warnings. the parser lexes while it goes, for --timings all of the
+// program is lexed first so the two are timed apart
+fn parse(
+    tokens: &mut parser::Tokens,
+    builtins: &builtins::Registry,
+    options: &Options,
+    timings: &mut Timings,
+) -> (Result<Program, Vec<ParseError>>, Vec<Diagnostic>) {
+    if timings.enabled() {
+        let lexed: Vec<_> = timings.time("lex", || tokens.collect());
+        return timings.time("parse", || {
+            haneul::parse(&mut lexed.into_iter(), builtins, options.compile.max_depth)
+        });
+    }
+
+    haneul::parse(tokens, builtins, options.compile.max_depth)
+}
+
+// one token per line: its type, its text and where it is, like `Ident "a" 1:5+1`
+fn print_tokens(tokens: &mut parser::Tokens) -> Vec<Diagnostic> {
+    let mut errors = vec![];
+    for token in tokens {
+        match token {
+            Ok(token) => {
+                let span = token.span();
+                println!(
+                    "{:?} {:?} {}:{}+{}",
+                    token.kind(),
+                    token.text(),
+                    span.line,
+                    span.column,
+                    span.length
+                );
+            }
+            Err(error) => errors.push(Diagnostic::from(&ParseError::from(error))),
+        }
+    }
+
+    errors
+}
+
+// human readable diagnostics go to stderr, json ones to stdout (one object per line)
+// so tools can read them without the rest of the output getting in the way
+fn report(diagnostics: &[Diagnostic], options: &Options, sources: &SourceMap) {
+    for diagnostic in diagnostics {
+        match options.message_format {
+            MessageFormat::Human => {
+                eprintln!(
+                    "{}",
+                    diagnostics::render(diagnostic, sources, options.lang, options.verbosity)
+                )
+            }
+            MessageFormat::Json => println!("{}", diagnostic.to_json(sources, options.lang)),
+        }
+    }
+}
+
+fn fail(errors: &[Diagnostic], options: &Options, sources: &SourceMap) -> ! {
+    report(errors, options, sources);
+    if options.message_format == MessageFormat::Human {
+        eprintln!("{}", i18n::errors_found(options.lang, errors.len()));
+    }
+    std::process::exit(1);
+}
 
-    Command::new("gcc")
-        .args(["out.c", "-o", "out"])
-        .output()
-        .unwrap();
+fn die(message: String) -> ! {
+    eprintln!("Error: {}", message);
+    std::process::exit(1);
 }
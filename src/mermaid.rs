@@ -0,0 +1,62 @@
+// --emit-cfg=mermaid writes the control flow graph as a mermaid flowchart, which github and
+// a lot of markdown editors draw by themselves. it shows the same as the graphviz one
+
+use std::fmt::Write;
+
+use crate::{
+    ast::Program,
+    cfg::{Cfg, Terminator},
+    pretty::block_lines,
+};
+
+pub fn cfg(program: &Program, cfg: &Cfg) -> String {
+    let mut output = String::from("flowchart TD\n");
+
+    let reachable = cfg.reachable();
+    for (id, block) in cfg.ids().zip(cfg.blocks()) {
+        let label = block_lines(id, block, program)
+            .iter()
+            .map(|line| escape(line))
+            .collect::<Vec<_>>()
+            .join("<br/>");
+        let _ = writeln!(output, "  b{}[\"{}\"]", id.index(), label);
+
+        match block.terminator {
+            Terminator::Jump(target) => {
+                let _ = writeln!(output, "  b{} --> b{}", id.index(), target.index());
+            }
+            Terminator::Branch {
+                then, otherwise, ..
+            } => {
+                let _ = writeln!(output, "  b{} -->|true| b{}", id.index(), then.index());
+                let _ = writeln!(
+                    output,
+                    "  b{} -->|false| b{}",
+                    id.index(),
+                    otherwise.index()
+                );
+            }
+            Terminator::Return => {}
+        }
+    }
+
+    let unreachable: Vec<String> = cfg
+        .ids()
+        .filter(|id| !reachable[id.index()])
+        .map(|id| format!["b{}", id.index()])
+        .collect();
+    if !unreachable.is_empty() {
+        output.push_str("  classDef unreachable stroke-dasharray: 5 5, color: gray\n");
+        let _ = writeln!(output, "  class {} unreachable", unreachable.join(","));
+    }
+
+    output
+}
+
+// mermaid reads html in labels, so the comparisons have to be written as entities
+fn escape(line: &str) -> String {
+    line.replace('&', "#amp;")
+        .replace('"', "#quot;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;")
+}
@@ -0,0 +1,48 @@
+// --minify, the output of the c, javascript and python backends without comments, blank lines
+// and indentation (but python's, that's part of the program), for when the code is embedded
+// somewhere or only has to be small. the backends leave out their #line directives and source
+// maps too
+
+// c and javascript both have // comments. the generated code has no /* */ comments and no
+// regular expressions with a // in them, so only strings have to be skipped
+pub fn c(code: &str) -> String {
+    let mut minified = String::new();
+    for line in code.lines() {
+        let line = strip_comment(line.trim(), "//");
+        if !line.is_empty() {
+            minified.push_str(line);
+            minified.push('\n');
+        }
+    }
+    minified
+}
+
+pub fn python(code: &str) -> String {
+    let mut minified = String::new();
+    for line in code.lines() {
+        let line = strip_comment(line, "#").trim_end();
+        if !line.trim_start().is_empty() {
+            minified.push_str(line);
+            minified.push('\n');
+        }
+    }
+    minified
+}
+
+// the line up to the comment that isn't in a string
+fn strip_comment<'a>(line: &'a str, comment: &str) -> &'a str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if line[i..].starts_with(comment) => return line[..i].trim_end(),
+            None => {}
+        }
+    }
+    line
+}
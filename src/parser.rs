@@ -9,63 +9,200 @@ statement ::= "PRINT" (expression | string) nl
     | "GOTO" ident nl
     | "LET" ident "=" expression nl
     | "INPUT" ident nl
+    | builtin [arguments] nl
 comparison ::= expression (("==" | "!=" | ">" | ">=" | "<" | "<=") expression)+
 expression ::= term {( "-" | "+" ) term}
-term ::= unary {( "/" | "*" ) unary}
+term ::= unary {( "/" | "*" | "%" ) unary}
 unary ::= ["+" | "-"] primary
-primary ::= number | ident
+primary ::= number | ident | builtin "(" [arguments] ")"
+arguments ::= expression {"," expression}
 nl ::= '\n'+
 */
 
+use core::fmt;
+
 use crate::{
-    emitter::Emitter,
-    lexer::Lexer,
-    token::{Token, TokenType},
+    ast::{BinaryOp, Expr, ExprId, ExprKind, Exprs, Program, Statement, StatementKind, UnaryOp},
+    builtins::{self, Capability, Registry},
+    codes, ctype,
+    diagnostics::Diagnostic,
+    i18n::{self, Lang},
+    intern::{Interner, Symbol},
+    keywords::KEYWORDS,
+    lexer::LexError,
+    prelude::*,
+    suggest,
+    token::{Span, Token, TokenType},
 };
 
-pub struct Parser<'a> {
-    lexer: &'a mut Lexer,
-    emitter: &'a mut Emitter,
-    current_token: Token,
-    peek_token: Token,
-    symbols: Vec<String>,
-    labels_declared: Vec<String>,
-    labels_gotoed: Vec<String>,
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    Lex(LexError),
+    Syntax {
+        code: &'static str,
+        args: Vec<String>,
+        span: Span,
+        // a "did you mean"
+        suggestion: Option<String>,
+    },
+}
+
+impl ParseError {
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::Lex(error) => error.span(),
+            ParseError::Syntax { span, .. } => *span,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseError::Lex(error) => error.code(),
+            ParseError::Syntax { code, .. } => code,
+        }
+    }
+
+    pub fn args(&self) -> Vec<String> {
+        match self {
+            ParseError::Lex(error) => error.args(),
+            ParseError::Syntax { args, .. } => args.clone(),
+        }
+    }
+
+    pub fn suggestion(&self) -> Option<String> {
+        match self {
+            ParseError::Lex(_) => None,
+            ParseError::Syntax { suggestion, .. } => suggestion.clone(),
+        }
+    }
+
+    fn with_suggestion(self, new_suggestion: Option<&str>) -> Self {
+        match self {
+            ParseError::Syntax {
+                code, args, span, ..
+            } => ParseError::Syntax {
+                code,
+                args,
+                span,
+                suggestion: new_suggestion.map(String::from),
+            },
+            error => error,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            i18n::message(Lang::English, self.code(), &self.args())
+        )
+    }
+}
+
+impl core::error::Error for ParseError {}
+
+impl From<LexError> for ParseError {
+    fn from(error: LexError) -> Self {
+        ParseError::Lex(error)
+    }
+}
+
+// anything that gives tokens, like a Lexer or a StreamLexer
+pub type Tokens<'a, 'src> = dyn Iterator<Item = Result<Token<'src>, LexError>> + 'a;
+
+pub struct Parser<'a, 'src> {
+    lexer: &'a mut Tokens<'a, 'src>,
+    current_token: Token<'src>,
+    peek_token: Token<'src>,
+    // of the token before the current one, where the node that was just parsed ends
+    previous_span: Span,
+    interner: Interner,
+    exprs: Exprs,
+    labels_declared: HashMap<Symbol, Span>,
+    labels_gotoed: Vec<(Symbol, Span)>,
+    builtins: &'a Registry,
+    // of the builtins that were called, see Program::needs
+    needs: HashMap<String, Vec<Capability>>,
+    errors: Vec<ParseError>,
+    // for the warnings
+    warnings: Vec<Diagnostic>,
+    // in the order they were first assigned
+    variables_assigned: Vec<(Symbol, Span)>,
+    variables_read: HashSet<Symbol>,
+    // how many IF/WHILE blocks and function calls we're inside of, every level is a few
+    // rust stack frames so there's a limit to keep deep nesting from overflowing the stack
+    depth: usize,
+    max_depth: usize,
 }
 
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
 // what the fuck are those lifetimes
-impl<'a> Parser<'a> {
-    pub fn new<'b>(lexer: &'a mut Lexer, emitter: &'a mut Emitter) -> Parser<'a> {
-        let mut parser = Self {
+impl<'a, 'src> Parser<'a, 'src> {
+    pub fn new(lexer: &'a mut Tokens<'a, 'src>, builtins: &'a Registry) -> Parser<'a, 'src> {
+        Self {
             lexer,
-            emitter,
             current_token: Token::default(),
             peek_token: Token::default(),
-            symbols: vec![],
-            labels_declared: vec![],
+            previous_span: Span::default(),
+            interner: Interner::new(),
+            exprs: Exprs::new(),
+            labels_declared: HashMap::new(),
             labels_gotoed: vec![],
-        };
-        parser.next_token();
-        parser.next_token(); // call twice to set the current and the peek token
+            builtins,
+            needs: HashMap::new(),
+            errors: vec![],
+            warnings: vec![],
+            variables_assigned: vec![],
+            variables_read: HashSet::new(),
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
 
-        parser
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
     }
 
+    // tokens the lexer can't make sense of are reported and skipped
     fn next_token(&mut self) {
+        self.previous_span = self.current_token.span();
         self.current_token = self.peek_token.clone();
-        self.peek_token = self.lexer.get_token();
-        self.lexer.next_char();
+        loop {
+            match self.lexer.next() {
+                Some(Ok(token)) => {
+                    self.peek_token = token;
+                    break;
+                }
+                Some(Err(error)) => self.errors.push(error.into()),
+                // past the end the peek token stays Eof
+                None => break,
+            }
+        }
     }
 
-    fn match_token(&mut self, kind: TokenType) {
+    fn match_token(&mut self, kind: TokenType) -> Result<(), ParseError> {
         if !self.check_token(kind) {
-            Self::die(format![
-                "Expected {:?}, got {}",
-                kind,
-                self.current_token.text()
-            ])
+            let error = self.error(
+                codes::UNEXPECTED_TOKEN,
+                vec![format!["{:?}", kind], self.current_token.text().to_owned()],
+            );
+
+            // a misspelled keyword is lexed as an identifier
+            let keyword = KEYWORDS
+                .iter()
+                .find(|(_, keyword_kind)| *keyword_kind == kind);
+            if let (Some((keyword, _)), TokenType::Ident) = (keyword, self.current_token.kind()) {
+                let suggestion = suggest::closest(self.current_token.text(), [*keyword]);
+                return Err(error.with_suggestion(suggestion));
+            }
+            return Err(error);
         }
         self.next_token();
+
+        Ok(())
     }
 
     fn check_token(&self, kind: TokenType) -> bool {
@@ -74,267 +211,485 @@ impl<'a> Parser<'a> {
 
     // grammar
     // program ::= {statement}
-    pub fn program(&mut self) {
+    // all errors in the program are collected, if there are any nothing should be done with the output
+    pub fn program(&mut self) -> Result<Program, Vec<ParseError>> {
         // println!("PROGRAM");
-        self.emitter.header_line("#include <stdio.h>");
-        self.emitter.header_line("int main(void) {");
+        self.next_token();
+        self.next_token(); // call twice to set the current and the peek token
 
         while self.check_token(TokenType::Newline) {
             self.next_token();
         }
 
+        let mut statements = vec![];
         while !self.check_token(TokenType::Eof) {
-            self.statement();
+            statements.extend(self.recovering_statement());
         }
 
-        self.emitter.emit_line("return 0;");
-        self.emitter.emit_line("}");
-
-        for label in self.labels_gotoed.iter() {
-            if !self.labels_declared.contains(&label) {
-                Self::die(format!["Attempting to GOTO to undeclared label: {}", label]);
+        for (variable, span) in self.variables_assigned.iter() {
+            if !self.variables_read.contains(variable) {
+                self.warnings.push(Diagnostic::warning(
+                    codes::UNUSED_VARIABLE,
+                    vec![self.interner.resolve(*variable).to_owned()],
+                    *span,
+                ));
+            }
+        }
+        let gotoed: HashSet<Symbol> = self.labels_gotoed.iter().map(|(label, _)| *label).collect();
+        for (label, span) in self.labels_declared.iter() {
+            if !gotoed.contains(label) {
+                self.warnings.push(Diagnostic::warning(
+                    codes::UNUSED_LABEL,
+                    vec![self.interner.resolve(*label).to_owned()],
+                    *span,
+                ));
             }
         }
+        self.warnings
+            .sort_by_key(|warning| (warning.span.line, warning.span.column));
+
+        if self.errors.is_empty() {
+            let mut program = Program {
+                statements,
+                exprs: core::mem::take(&mut self.exprs),
+                names: core::mem::take(&mut self.interner),
+                types: HashMap::new(),
+                needs: core::mem::take(&mut self.needs),
+            };
+            ctype::infer(&mut program);
+            Ok(program)
+        } else {
+            // lexing errors are found one token ahead of the parser, so sort them back in place
+            self.errors
+                .sort_by_key(|error| (error.span().line, error.span().column));
+            Err(core::mem::take(&mut self.errors))
+        }
     }
 
-    fn statement(&mut self) {
-        match self.current_token.kind() {
+    // parses a statement, and if that fails reports the error and skips to the next line,
+    // so the errors in the rest of the program can be found too
+    fn recovering_statement(&mut self) -> Option<Statement> {
+        let error = match self.statement() {
+            Ok(statement) => return Some(statement),
+            Err(error) => error,
+        };
+
+        // a token the lexer couldn't read is left out, which nearly always breaks
+        // the statement too. that error would only be noise
+        let line = error.span().line;
+        let after_lex_error = self
+            .errors
+            .iter()
+            .any(|e| matches!(e, ParseError::Lex(_)) && e.span().line == line);
+        if !after_lex_error {
+            self.errors.push(error);
+        }
+
+        while !self.check_token(TokenType::Newline) && !self.check_token(TokenType::Eof) {
+            self.next_token();
+        }
+        while self.check_token(TokenType::Newline) {
+            self.next_token();
+        }
+
+        None
+    }
+
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
+
+    fn statement(&mut self) -> Result<Statement, ParseError> {
+        let start = self.current_token.span();
+        let kind = match self.current_token.kind() {
             // "PRINT" (expression | string) nl
             TokenType::Print => {
                 // println!("STATEMENT-PRINT");
                 self.next_token();
 
                 if self.check_token(TokenType::String) {
-                    // printf("{string}");
-                    self.emitter
-                        .emit_line(&format!["printf(\"{}\\n\");", self.current_token.text()]);
+                    let string = self.current_token.text().to_owned();
                     self.next_token();
+                    StatementKind::PrintString(string)
                 } else {
-                    // printf("%.2f", (float)({val}));
-                    self.emitter.emit("printf(\"%.2f\\n\", (float)(");
-                    self.expression();
-                    self.emitter.emit_line("));");
+                    StatementKind::Print(self.expression()?)
                 }
             }
             // "IF" comparison "THEN" nl {statement} "ENDIF" nl
             TokenType::If => {
                 // println!("STATEMENT-IF");
-                self.emitter.emit("if(");
+                self.check_depth()?;
                 self.next_token();
-                self.comparison();
+                let condition = self.comparison()?;
 
-                self.match_token(TokenType::Then);
-                self.nl();
-                self.emitter.emit_line("){");
+                self.match_token(TokenType::Then)?;
+                self.nl()?;
 
-                while !self.check_token(TokenType::Endif) {
-                    self.statement();
-                }
+                let body = self.block(TokenType::Endif);
 
-                self.match_token(TokenType::Endif);
-                self.emitter.emit_line("}");
+                self.match_token(TokenType::Endif)?;
+                StatementKind::If { condition, body }
             }
             // "WHILE" comparison "REPEAT" nl {statement} "ENDWHILE" nl
             TokenType::While => {
                 // println!("STATEMENT-WHILE");
-                self.emitter.emit("while(");
+                self.check_depth()?;
                 self.next_token();
-                self.comparison();
+                let condition = self.comparison()?;
 
-                self.match_token(TokenType::Repeat);
-                self.nl();
-                self.emitter.emit_line("){");
+                self.match_token(TokenType::Repeat)?;
+                self.nl()?;
 
-                while !self.check_token(TokenType::EndWhile) {
-                    self.statement();
-                }
+                let body = self.block(TokenType::EndWhile);
 
-                self.match_token(TokenType::EndWhile);
-                self.emitter.emit_line("}");
+                self.match_token(TokenType::EndWhile)?;
+                StatementKind::While { condition, body }
             }
             // "LABEL" ident nl
             TokenType::Label => {
                 // println!("STATEMENT-LABEL");
                 self.next_token();
 
-                let token_text = self.current_token.text().to_owned();
-
-                if self.labels_declared.contains(&token_text) {
-                    Self::die(format![
-                        "Label already exists: {}",
-                        self.current_token.text()
-                    ]);
-                }
-                self.labels_declared.push(token_text);
+                // a label declared twice is reported after parsing, see analysis.rs
+                let label = self.interner.intern(self.current_token.text());
+                self.labels_declared
+                    .entry(label)
+                    .or_insert(self.current_token.span());
 
-                self.emitter
-                    .emit_line(&format!["{}:", self.current_token.text()]);
-                self.match_token(TokenType::Ident);
+                self.match_token(TokenType::Ident)?;
+                StatementKind::Label(label)
             }
             // "GOTO" ident nl
             TokenType::Goto => {
                 // println!("STATEMENT-GOTO");
                 self.next_token();
 
-                self.labels_gotoed
-                    .push(self.current_token.text().to_owned());
+                let label = self.interner.intern(self.current_token.text());
+                self.labels_gotoed.push((label, self.current_token.span()));
 
-                self.emitter
-                    .emit_line(&format!["goto {};", self.current_token.text()]);
-
-                self.match_token(TokenType::Ident);
+                self.match_token(TokenType::Ident)?;
+                StatementKind::Goto(label)
             }
             // "LET" ident "=" expression nl
             TokenType::Let => {
                 // println!("STATEMENT-LET");
                 self.next_token();
 
-                let token_text = self.current_token.text().to_owned();
-
-                if !self.symbols.contains(&token_text) {
-                    self.symbols.push(token_text);
-                    self.emitter
-                        .header_line(&format!["float {};", self.current_token.text()]);
-                }
+                let variable = self.declare_variable()?;
+                self.match_token(TokenType::Eq)?;
 
-                self.emitter
-                    .emit(&format!["{} = ", self.current_token.text()]);
-                self.match_token(TokenType::Ident);
-                self.match_token(TokenType::Eq);
-
-                self.expression();
-                self.emitter.emit_line(";");
+                let value = self.expression()?;
+                StatementKind::Let { variable, value }
             }
             // "INPUT" ident nl
             TokenType::Input => {
                 // println!("STATEMENT-INPUT");
                 self.next_token();
 
-                let token_text = self.current_token.text().to_owned();
-
-                if !self.symbols.contains(&token_text) {
-                    self.symbols.push(token_text);
-                    self.emitter
-                        .header_line(&format!["float {};", self.current_token.text()]);
+                StatementKind::Input(self.declare_variable()?)
+            }
+            // builtin [arguments] nl
+            TokenType::Ident if self.is_builtin(false) => {
+                // println!("STATEMENT-BUILTIN");
+                let (name, arguments) = self.builtin(false)?;
+                StatementKind::Call { name, arguments }
+            }
+            _ => {
+                let error = self.error(
+                    codes::INVALID_STATEMENT,
+                    vec![
+                        self.current_token.text().to_owned(),
+                        format!["{:?}", self.current_token.kind()],
+                    ],
+                );
+                if !self.check_token(TokenType::Ident) {
+                    return Err(error);
                 }
 
-                self.emitter.emit_line(&format![
-                    "if(0==scanf(\"%f\", &{})) {{",
-                    self.current_token.text()
-                ]);
-                self.emitter
-                    .emit_line(&format!["{} = 0;", self.current_token.text()]);
-                self.emitter.emit_line("scanf(\"%*s\");");
-                self.emitter.emit_line("}");
-
-                self.match_token(TokenType::Ident);
+                let keywords = KEYWORDS.iter().map(|(keyword, _)| *keyword);
+                let statements = self
+                    .builtins
+                    .builtins()
+                    .iter()
+                    .filter(|builtin| !builtin.is_function())
+                    .map(|builtin| builtin.name);
+                let suggestion =
+                    suggest::closest(self.current_token.text(), keywords.chain(statements));
+                return Err(error.with_suggestion(suggestion));
             }
-            _ => Self::die(format![
-                "Invalid statement at: {} ({:?})",
-                self.current_token.text(),
-                self.current_token.kind()
-            ]),
         };
 
-        self.nl();
+        let statement = Statement {
+            kind,
+            span: start.to(self.previous_span),
+        };
+        self.nl()?;
+
+        Ok(statement)
+    }
+
+    // {statement} until `end`, which is left for the caller to match
+    fn block(&mut self, end: TokenType) -> Vec<Statement> {
+        let mut body = vec![];
+        self.depth += 1;
+        while !self.check_token(end) && !self.check_token(TokenType::Eof) {
+            body.extend(self.recovering_statement());
+        }
+        self.depth -= 1;
+
+        body
     }
 
-    fn nl(&mut self) {
+    fn nl(&mut self) -> Result<(), ParseError> {
         // println!("NEWLINE");
 
-        self.match_token(TokenType::Newline);
+        self.match_token(TokenType::Newline)?;
         while self.check_token(TokenType::Newline) {
             self.next_token();
         }
+
+        Ok(())
+    }
+
+    // arguments ::= expression {"," expression}, with exactly `count` expressions
+    fn arguments(&mut self, count: usize) -> Result<Vec<ExprId>, ParseError> {
+        let mut arguments = vec![];
+        for i in 0..count {
+            if i > 0 {
+                self.match_token(TokenType::Comma)?;
+            }
+            arguments.push(self.expression()?);
+        }
+
+        Ok(arguments)
     }
 
     // comparison ::= expression (("==" | "!=" | ">" | ">=" | "<" | "<=") expression)+
-    fn comparison(&mut self) {
+    // a chain like a < b < c is parsed as ((a < b) < c), the type checker rejects it
+    fn comparison(&mut self) -> Result<ExprId, ParseError> {
         // println!("COMPARISON");
 
-        self.expression();
-        if self.is_comparison_operator() {
-            self.emitter.emit(self.current_token.text());
-            self.next_token();
-            self.expression();
-        } else {
-            Self::die(format![
-                "Expected comparison at: {}",
-                self.current_token.text()
-            ]);
+        let mut left = self.expression()?;
+        if self.comparison_operator().is_none() {
+            return Err(self.error(
+                codes::EXPECTED_COMPARISON,
+                vec![self.current_token.text().to_owned()],
+            ));
         }
 
-        while self.is_comparison_operator() {
-            self.emitter.emit(self.current_token.text());
+        while let Some(op) = self.comparison_operator() {
             self.next_token();
-            self.expression();
+            let right = self.expression()?;
+            left = self.binary(op, left, right);
         }
+
+        Ok(left)
     }
 
-    fn is_comparison_operator(&self) -> bool {
-        self.check_token(TokenType::Gt)
-            || self.check_token(TokenType::GtEq)
-            || self.check_token(TokenType::Lt)
-            || self.check_token(TokenType::LtEq)
-            || self.check_token(TokenType::EqEq)
-            || self.check_token(TokenType::NotEq)
+    fn comparison_operator(&self) -> Option<BinaryOp> {
+        match self.current_token.kind() {
+            TokenType::Gt => Some(BinaryOp::Greater),
+            TokenType::GtEq => Some(BinaryOp::GreaterEqual),
+            TokenType::Lt => Some(BinaryOp::Less),
+            TokenType::LtEq => Some(BinaryOp::LessEqual),
+            TokenType::EqEq => Some(BinaryOp::Equal),
+            TokenType::NotEq => Some(BinaryOp::NotEqual),
+            _ => None,
+        }
     }
 
     // expression ::= term {( "-" | "+" ) term}
-    fn expression(&mut self) {
+    fn expression(&mut self) -> Result<ExprId, ParseError> {
         // println!("EXPRESSION");
 
-        self.term();
-        while self.check_token(TokenType::Plus) || self.check_token(TokenType::Minus) {
-            self.emitter.emit(self.current_token.text());
+        let mut left = self.term()?;
+        loop {
+            let op = match self.current_token.kind() {
+                TokenType::Plus => BinaryOp::Add,
+                TokenType::Minus => BinaryOp::Subtract,
+                _ => break,
+            };
             self.next_token();
-            self.term();
+            let right = self.term()?;
+            left = self.binary(op, left, right);
         }
+
+        Ok(left)
     }
 
-    // term ::= unary {( "/" | "*" ) unary}
-    fn term(&mut self) {
+    // term ::= unary {( "/" | "*" | "%" ) unary}
+    fn term(&mut self) -> Result<ExprId, ParseError> {
         // println!("TERM");
 
-        self.unary();
-        while self.check_token(TokenType::Asterisk) || self.check_token(TokenType::Slash) {
-            self.emitter.emit(self.current_token.text());
+        let mut left = self.unary()?;
+        loop {
+            let op = match self.current_token.kind() {
+                TokenType::Asterisk => BinaryOp::Multiply,
+                TokenType::Slash => BinaryOp::Divide,
+                TokenType::Percent => BinaryOp::Remainder,
+                _ => break,
+            };
             self.next_token();
-            self.unary();
+            let right = self.unary()?;
+            left = self.binary(op, left, right);
         }
+
+        Ok(left)
     }
     // unary ::= ["+" | "-"] primary
-    fn unary(&mut self) {
+    fn unary(&mut self) -> Result<ExprId, ParseError> {
         // println!("UNARY");
-        if self.check_token(TokenType::Plus) || self.check_token(TokenType::Minus) {
-            self.emitter.emit(self.current_token.text());
+        let start = self.current_token.span();
+        let op = match self.current_token.kind() {
+            TokenType::Plus => Some(UnaryOp::Plus),
+            TokenType::Minus => Some(UnaryOp::Minus),
+            _ => None,
+        };
+        if op.is_some() {
             self.next_token();
         }
-        self.primary();
+        let operand = self.primary()?;
+
+        Ok(match op {
+            Some(op) => {
+                let span = start.to(self.exprs[operand].span);
+                self.expr(ExprKind::Unary { op, operand }, span)
+            }
+            None => operand,
+        })
     }
-    // primary ::= number | ident
-    fn primary(&mut self) {
+    // primary ::= number | ident | builtin "(" [arguments] ")"
+    fn primary(&mut self) -> Result<ExprId, ParseError> {
         // println!("PRIMARY ({})", self.current_token.text());
 
+        let start = self.current_token.span();
         if self.check_token(TokenType::Number) {
-            self.emitter.emit(self.current_token.text());
+            let number = ExprKind::Number(self.current_token.text().to_owned());
             self.next_token();
-        } else if self.check_token(TokenType::Ident) {
-            if !self.symbols.contains(&self.current_token.text().to_owned()) {
-                Self::die(format![
-                    "Referencing unassigned variable: {}",
-                    self.current_token.text()
-                ]);
+            Ok(self.expr(number, start))
+        } else if self.check_token(TokenType::Ident) && self.is_builtin(true) {
+            if self.depth >= self.max_depth {
+                return Err(self.error(codes::NESTED_TOO_DEEPLY, vec![self.max_depth.to_string()]));
             }
-            self.emitter.emit(self.current_token.text());
+            self.depth += 1;
+            let result = self.builtin(true);
+            self.depth -= 1;
+            let (name, arguments) = result?;
+            let span = start.to(self.previous_span);
+            Ok(self.expr(ExprKind::Call { name, arguments }, span))
+        } else if self.check_token(TokenType::Ident) {
+            // whether it has a value here is checked after parsing, see analysis.rs
+            let variable = self.interner.intern(self.current_token.text());
+            self.variables_read.insert(variable);
             self.next_token();
+            Ok(self.expr(ExprKind::Variable(variable), start))
         } else {
-            Self::die(format!["Unexpected token at {}", self.current_token.text()]);
+            Err(self.error(
+                codes::EXPECTED_EXPRESSION,
+                vec![self.current_token.text().to_owned()],
+            ))
+        }
+    }
+
+    // a block that is nested too deeply is skipped as a whole, counting the nesting instead of
+    // recursing into it, so it is reported once and its ENDIF doesn't end up as an error too
+    fn check_depth(&mut self) -> Result<(), ParseError> {
+        if self.depth < self.max_depth {
+            return Ok(());
+        }
+        let error = self.error(codes::NESTED_TOO_DEEPLY, vec![self.max_depth.to_string()]);
+
+        let mut nesting = 0;
+        loop {
+            match self.current_token.kind() {
+                TokenType::If | TokenType::While => nesting += 1,
+                TokenType::Endif | TokenType::EndWhile => nesting -= 1,
+                TokenType::Eof => break,
+                _ => {}
+            }
+            self.next_token();
+            if nesting == 0 {
+                break;
+            }
+        }
+
+        Err(error)
+    }
+
+    // the variable a LET or INPUT assigns to
+    fn declare_variable(&mut self) -> Result<Symbol, ParseError> {
+        let variable = self.interner.intern(self.current_token.text());
+        let span = self.current_token.span();
+        self.match_token(TokenType::Ident)?;
+
+        if !self
+            .variables_assigned
+            .iter()
+            .any(|(assigned, _)| *assigned == variable)
+        {
+            self.variables_assigned.push((variable, span));
         }
+
+        Ok(variable)
+    }
+
+    fn is_builtin(&self, function: bool) -> bool {
+        self.builtins
+            .get(self.current_token.text())
+            .is_some_and(|builtin| builtin.is_function() == function)
     }
 
-    fn die(message: String) -> ! {
-        println!("Error while parsing: {}", message);
-        std::process::exit(1);
+    // NAME arguments for statements, NAME(arguments) for functions.
+    // the current token is the name of the builtin
+    fn builtin(&mut self, function: bool) -> Result<(String, Vec<ExprId>), ParseError> {
+        let name = self.current_token.text().to_owned();
+        let builtin = match self.builtins.get(&name) {
+            Some(builtin) if builtin.is_function() == function => builtin.clone(),
+            _ => return Err(self.error(codes::UNKNOWN_BUILTIN, vec![name])),
+        };
+
+        if let Some(feature) = builtin.feature {
+            if !builtins::feature_enabled(feature) {
+                return Err(self.error(codes::MISSING_FEATURE, vec![name, feature.to_owned()]));
+            }
+        }
+        if let Some(capability) = self.builtins.forbidden(&builtin) {
+            let capability = capability.name().to_owned();
+            return Err(self.error(codes::NOT_ALLOWED, vec![name, capability]));
+        }
+        if !builtin.needs.is_empty() {
+            self.needs.insert(name.clone(), builtin.needs.to_vec());
+        }
+
+        self.next_token();
+
+        let arguments = if function {
+            self.match_token(TokenType::LParen)?;
+            let arguments = self.arguments(builtin.arity())?;
+            self.match_token(TokenType::RParen)?;
+            arguments
+        } else {
+            self.arguments(builtin.arity())?
+        };
+
+        Ok((name, arguments))
+    }
+
+    fn expr(&mut self, kind: ExprKind, span: Span) -> ExprId {
+        self.exprs.add(Expr { kind, span })
+    }
+
+    fn binary(&mut self, op: BinaryOp, left: ExprId, right: ExprId) -> ExprId {
+        let span = self.exprs[left].span.to(self.exprs[right].span);
+        self.expr(ExprKind::Binary { op, left, right }, span)
+    }
+
+    fn error(&self, code: &'static str, args: Vec<String>) -> ParseError {
+        ParseError::Syntax {
+            code,
+            args,
+            span: self.current_token.span(),
+            suggestion: None,
+        }
     }
 }
@@ -0,0 +1,69 @@
+// the optimizer passes, in the order they run, and which ones an -O level runs
+//
+// -O0  nothing, the c code is the program like it was written
+// -O1  constant folding and leaving out code that never runs, they make the c code smaller
+// -O2  everything, also the loop optimizations and sharing repeated calculations (default)
+//
+// the bytecode the vm runs goes through the peephole pass from -O1 on, see optimize_chunk
+
+use crate::{ast::Program, bytecode::Chunk, cse, dce, fold, loops, peephole};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OptLevel {
+    O0,
+    O1,
+    #[default]
+    O2,
+}
+
+impl OptLevel {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "0" => Some(OptLevel::O0),
+            "1" => Some(OptLevel::O1),
+            "2" => Some(OptLevel::O2),
+            _ => None,
+        }
+    }
+}
+
+struct Pass {
+    run: fn(&mut Program),
+    level: OptLevel,
+}
+
+// folding goes first, the conditions it works out are what dce needs to find dead code.
+// the loop pass makes temporaries cse can share
+const PASSES: &[Pass] = &[
+    Pass {
+        run: fold::fold,
+        level: OptLevel::O1,
+    },
+    Pass {
+        run: dce::eliminate,
+        level: OptLevel::O1,
+    },
+    Pass {
+        run: loops::optimize,
+        level: OptLevel::O2,
+    },
+    Pass {
+        run: cse::eliminate,
+        level: OptLevel::O2,
+    },
+];
+
+pub fn optimize(program: &mut Program, level: OptLevel) {
+    for pass in PASSES {
+        if pass.level <= level {
+            (pass.run)(program);
+        }
+    }
+}
+
+// after bytecode::compile, the chunk of a program optimize ran on with the same level
+pub fn optimize_chunk(chunk: &mut Chunk, level: OptLevel) {
+    if level >= OptLevel::O1 {
+        peephole::optimize(chunk);
+    }
+}
@@ -0,0 +1,202 @@
+// the peephole pass on the bytecode: it looks at a few instructions in a row and puts in fewer
+// that do the same. it runs until nothing changes, one change can make another one possible
+//
+//   Constant 0, Add int            nothing, and the same for - 0, * 1 and / 1 of ints and longs
+//   Constant 2, Constant 3, Less   Constant 1, a calculation of two constants that can't fail
+//   Constant 1, JumpIfZero         nothing, a condition that's always true
+//   Constant 0, JumpIfZero 9       Jump 9, one that's never true
+//   Jump 5 where 5 is Jump 9       Jump 9, also for a JumpIfZero
+//   Jump to the next instruction   nothing
+//   what no way gets to            nothing, like the body of an IF whose condition was 0
+//
+// not for floats, -0.0 + 0 is 0.0. an instruction that's jumped to starts a new row, a jump
+// that went to an instruction that's left out goes to the one after it
+
+use crate::{
+    ast::BinaryOp,
+    bytecode::{Chunk, Instruction},
+    ctype::CType,
+    hbc,
+    runtime::{self, Raw, Value},
+};
+
+pub fn optimize(chunk: &mut Chunk) {
+    loop {
+        let threaded = thread(chunk);
+        let removed = shorten(chunk);
+        if !threaded && !removed {
+            break;
+        }
+    }
+}
+
+// a jump to a Jump goes where that one goes
+fn thread(chunk: &mut Chunk) -> bool {
+    let mut changed = false;
+    for pc in 0..chunk.code.len() {
+        let (Instruction::Jump(target) | Instruction::JumpIfZero(target)) = chunk.code[pc] else {
+            continue;
+        };
+        // a loop of jumps that never ends is left like it is
+        let mut to = target;
+        for _ in 0..chunk.code.len() {
+            match chunk.code[to as usize] {
+                Instruction::Jump(next) if next != to => to = next,
+                _ => break,
+            }
+        }
+        if to != target {
+            chunk.code[pc] = match chunk.code[pc] {
+                Instruction::Jump(_) => Instruction::Jump(to),
+                _ => Instruction::JumpIfZero(to),
+            };
+            changed = true;
+        }
+    }
+    changed
+}
+
+// the rows of the table at the top
+fn shorten(chunk: &mut Chunk) -> bool {
+    let mut targets = vec![false; chunk.code.len()];
+    for instruction in &chunk.code {
+        if let Instruction::Jump(target) | Instruction::JumpIfZero(target) = instruction {
+            targets[*target as usize] = true;
+        }
+    }
+    let depths = hbc::depths(chunk).expect("bytecode::compile makes code the vm can run");
+    // the Halt at the end stays, a chunk ends with one
+    let last = chunk.code.len() - 1;
+    let mut keep: Vec<_> = (0..chunk.code.len())
+        .map(|pc| pc == last || depths[pc].is_some())
+        .collect();
+    let mut pc = 0;
+    while pc < chunk.code.len() {
+        let Some((length, first)) = row(chunk, &targets, pc) else {
+            pc += 1;
+            continue;
+        };
+        match first {
+            Some(instruction) => chunk.code[pc] = instruction,
+            None => keep[pc] = false,
+        }
+        for skipped in &mut keep[pc + 1..pc + length] {
+            *skipped = false;
+        }
+        pc += length;
+    }
+
+    if !keep.contains(&false) {
+        return false;
+    }
+    // where every instruction ends up, a left out one where the next one that's kept does
+    let mut moved = vec![];
+    let mut kept = 0;
+    for keep in &keep {
+        moved.push(kept as u32);
+        kept += *keep as usize;
+    }
+    let mut index = 0;
+    chunk.code.retain(|_| {
+        index += 1;
+        keep[index - 1]
+    });
+    let mut index = 0;
+    chunk.lines.retain(|_| {
+        index += 1;
+        keep[index - 1]
+    });
+    for instruction in &mut chunk.code {
+        if let Instruction::Jump(target) | Instruction::JumpIfZero(target) = instruction {
+            *target = moved[*target as usize];
+        }
+    }
+    true
+}
+
+// the row of the table that starts at `pc`: how long it is, and what its first instruction
+// becomes, None when it's left out too. the others are left out
+fn row(chunk: &mut Chunk, targets: &[bool], pc: usize) -> Option<(usize, Option<Instruction>)> {
+    let code = &chunk.code;
+    // the rest of a row can't be jumped to
+    let fits =
+        |length: usize| pc + length <= code.len() && !targets[pc + 1..pc + length].contains(&true);
+    let constant = |pc: usize| match code.get(pc) {
+        Some(Instruction::Constant(index)) => Some(chunk.constants[*index as usize]),
+        _ => None,
+    };
+
+    let Some(value) = constant(pc) else {
+        return (code[pc] == Instruction::Jump(pc as u32 + 1)).then_some((1, None));
+    };
+    let next = *code.get(pc + 1)?;
+    if let (Some(right), Some((op, ctype))) = (
+        constant(pc + 1),
+        code.get(pc + 2).and_then(|i| calculation(*i)),
+    ) {
+        if !fits(3) || value.ctype() != ctype || right.ctype() != ctype {
+            return None;
+        }
+        let result = runtime::binary(op, value, right, chunk.float_division)?;
+        let index = constant_index(chunk, result);
+        return Some((3, Some(Instruction::Constant(index))));
+    }
+    if !fits(2) {
+        return None;
+    }
+    match next {
+        Instruction::JumpIfZero(target) if value.is_zero() => {
+            Some((2, Some(Instruction::Jump(target))))
+        }
+        Instruction::JumpIfZero(_) => Some((2, None)),
+        _ => identity(value, next).then_some((2, None)),
+    }
+}
+
+// the op of an instruction that pops two values and pushes one, and their type
+fn calculation(instruction: Instruction) -> Option<(BinaryOp, CType)> {
+    Some(match instruction {
+        Instruction::Add(ctype) => (BinaryOp::Add, ctype),
+        Instruction::Subtract(ctype) => (BinaryOp::Subtract, ctype),
+        Instruction::Multiply(ctype) => (BinaryOp::Multiply, ctype),
+        Instruction::Divide(ctype) => (BinaryOp::Divide, ctype),
+        Instruction::Remainder(ctype) => (BinaryOp::Remainder, ctype),
+        Instruction::Equal(ctype) => (BinaryOp::Equal, ctype),
+        Instruction::NotEqual(ctype) => (BinaryOp::NotEqual, ctype),
+        Instruction::Less(ctype) => (BinaryOp::Less, ctype),
+        Instruction::LessEqual(ctype) => (BinaryOp::LessEqual, ctype),
+        Instruction::Greater(ctype) => (BinaryOp::Greater, ctype),
+        Instruction::GreaterEqual(ctype) => (BinaryOp::GreaterEqual, ctype),
+        _ => return None,
+    })
+}
+
+// the value on the stack stays the same when `instruction` uses `value` on it
+fn identity(value: Value, instruction: Instruction) -> bool {
+    let one = match value {
+        Value::Int(value) => value == 1,
+        Value::Long(value) => value == 1,
+        _ => return false,
+    };
+    match instruction {
+        Instruction::Add(ctype) | Instruction::Subtract(ctype) => {
+            ctype == value.ctype() && value.is_zero()
+        }
+        Instruction::Multiply(ctype) | Instruction::Divide(ctype) => ctype == value.ctype() && one,
+        _ => false,
+    }
+}
+
+// the one that's there already, with the same type and bits
+fn constant_index(chunk: &mut Chunk, value: Value) -> u32 {
+    let same = |constant: &Value| {
+        constant.ctype() == value.ctype() && Raw::new(*constant).bits() == Raw::new(value).bits()
+    };
+    match chunk.constants.iter().position(same) {
+        Some(index) => index as u32,
+        None => {
+            chunk.constants.push(value);
+            chunk.constants.len() as u32 - 1
+        }
+    }
+}
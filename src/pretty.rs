@@ -0,0 +1,168 @@
+// --emit-ast, the tree the parser made of a program written out so you can see how it was
+// understood. every node is a line with its children indented under it:
+//
+// If 2:1
+//   Binary < 2:4
+//     Variable a 2:4
+//     Number 3 2:8
+//   Print 3:5
+//     Variable a 3:11
+//
+// the first child of an IF or a WHILE is the condition, the rest is the body
+
+use std::fmt::Write;
+
+use crate::{
+    ast::{Expr, ExprId, ExprKind, Program, Statement, StatementKind},
+    cfg::{Block, BlockId, Terminator},
+    intern::Interner,
+};
+
+pub fn ast(program: &Program) -> String {
+    let mut output = String::new();
+    for statement in &program.statements {
+        write_statement(&mut output, statement, program, 0);
+    }
+
+    output
+}
+
+// what a statement is called when it's shown, and its children
+pub fn statement_node<'a>(
+    statement: &'a Statement,
+    names: &Interner,
+) -> (String, Vec<ExprId>, &'a [Statement]) {
+    match &statement.kind {
+        StatementKind::Print(value) => (String::from("Print"), vec![*value], &[]),
+        StatementKind::PrintString(string) => (format!["PrintString {:?}", string], vec![], &[]),
+        StatementKind::If { condition, body } => (String::from("If"), vec![*condition], body),
+        StatementKind::While { condition, body } => (String::from("While"), vec![*condition], body),
+        StatementKind::Label(label) => (format!["Label {}", names.resolve(*label)], vec![], &[]),
+        StatementKind::Goto(label) => (format!["Goto {}", names.resolve(*label)], vec![], &[]),
+        StatementKind::Let { variable, value } => (
+            format!["Let {}", names.resolve(*variable)],
+            vec![*value],
+            &[],
+        ),
+        StatementKind::Input(variable) => {
+            (format!["Input {}", names.resolve(*variable)], vec![], &[])
+        }
+        StatementKind::Call { name, arguments } => {
+            (format!["Call {}", name], arguments.clone(), &[])
+        }
+    }
+}
+
+pub fn expr_node(expression: &Expr, names: &Interner) -> (String, Vec<ExprId>) {
+    match &expression.kind {
+        ExprKind::Number(number) => (format!["Number {}", number], vec![]),
+        ExprKind::Variable(variable) => (format!["Variable {}", names.resolve(*variable)], vec![]),
+        ExprKind::Call { name, arguments } => (format!["Call {}", name], arguments.clone()),
+        ExprKind::Unary { op, operand } => (format!["Unary {}", op.text()], vec![*operand]),
+        ExprKind::Binary { op, left, right } => {
+            (format!["Binary {}", op.text()], vec![*left, *right])
+        }
+    }
+}
+
+fn write_statement(output: &mut String, statement: &Statement, program: &Program, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let span = statement.span;
+    let (node, expressions, body) = statement_node(statement, &program.names);
+
+    let _ = writeln!(output, "{}{} {}:{}", indent, node, span.line, span.column);
+    for expression in expressions {
+        write_expr(output, expression, program, depth + 1);
+    }
+    for statement in body {
+        write_statement(output, statement, program, depth + 1);
+    }
+}
+
+fn write_expr(output: &mut String, expression: ExprId, program: &Program, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let expression = &program.exprs[expression];
+    let span = expression.span;
+    let (node, children) = expr_node(expression, &program.names);
+
+    let _ = writeln!(output, "{}{} {}:{}", indent, node, span.line, span.column);
+    for child in children {
+        write_expr(output, child, program, depth + 1);
+    }
+}
+
+// a statement written back the way it looks in a program, without the body of an IF or a WHILE
+pub fn statement_source(statement: &Statement, program: &Program) -> String {
+    let names = &program.names;
+    match &statement.kind {
+        StatementKind::Print(value) => format!["PRINT {}", expr_source(*value, program)],
+        StatementKind::PrintString(string) => format!["PRINT \"{}\"", string],
+        StatementKind::If { condition, .. } => {
+            format!["IF {} THEN", expr_source(*condition, program)]
+        }
+        StatementKind::While { condition, .. } => {
+            format!["WHILE {} REPEAT", expr_source(*condition, program)]
+        }
+        StatementKind::Label(label) => format!["LABEL {}", names.resolve(*label)],
+        StatementKind::Goto(label) => format!["GOTO {}", names.resolve(*label)],
+        StatementKind::Let { variable, value } => format![
+            "LET {} = {}",
+            names.resolve(*variable),
+            expr_source(*value, program)
+        ],
+        StatementKind::Input(variable) => format!["INPUT {}", names.resolve(*variable)],
+        StatementKind::Call { name, arguments } if arguments.is_empty() => name.clone(),
+        StatementKind::Call { name, arguments } => {
+            format!["{} {}", name, arguments_source(arguments, program)]
+        }
+    }
+}
+
+// the tree has the same precedence as the grammar, so it doesn't need any parentheses
+pub fn expr_source(expression: ExprId, program: &Program) -> String {
+    match &program.exprs[expression].kind {
+        ExprKind::Number(number) => number.clone(),
+        ExprKind::Variable(variable) => program.names.resolve(*variable).to_owned(),
+        ExprKind::Call { name, arguments } => {
+            format!["{}({})", name, arguments_source(arguments, program)]
+        }
+        ExprKind::Unary { op, operand } => {
+            format!["{}{}", op.text(), expr_source(*operand, program)]
+        }
+        ExprKind::Binary { op, left, right } => format![
+            "{} {} {}",
+            expr_source(*left, program),
+            op.text(),
+            expr_source(*right, program)
+        ],
+    }
+}
+
+fn arguments_source(arguments: &[ExprId], program: &Program) -> String {
+    arguments
+        .iter()
+        .map(|argument| expr_source(*argument, program))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// what a block of the control flow graph shows: its name and the LABEL it starts at, its
+// statements and the condition it ends with. the jumps are the edges of the graph
+pub fn block_lines(id: BlockId, block: &Block, program: &Program) -> Vec<String> {
+    let mut lines = vec![match block.label {
+        Some(label) => format!["B{} (LABEL {})", id.index(), program.names.resolve(label)],
+        None => format!["B{}", id.index()],
+    }];
+    for statement in &block.statements {
+        lines.push(statement_source(statement, program));
+    }
+    match block.terminator {
+        Terminator::Branch { condition, .. } => {
+            lines.push(format!["IF {}", expr_source(condition, program)])
+        }
+        Terminator::Return => lines.push(String::from("END")),
+        Terminator::Jump(_) => {}
+    }
+
+    lines
+}
@@ -0,0 +1,138 @@
+// the python module, with the python feature. `maturin develop --features python` builds it
+// and installs it into the python that runs it:
+//
+//   import haneul
+//   print(haneul.compile("LET a = 2\nPRINT a * 21\n", "python"))
+//   result = haneul.run("INPUT a\nPRINT a * 2\n", "21\n")
+//   assert result.stdout == "42\n" and result.exit_code == 0
+//   haneul.run("WHILE 1 < 2\nENDWHILE\n", max_steps=10000, allow_fs=False)  # stops it
+//   haneul.tokenize("LET a = 1")  # [("Let", "LET", 1, 1), ("Ident", "a", 1, 5), ...]
+//
+// a program with errors raises a ValueError with them, rendered like the command line shows
+// them
+
+use std::time::Duration;
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    diagnostics::{Diagnostic, Diagnostics, Verbosity},
+    i18n::Lang,
+    lexer::Lexer,
+    parser::ParseError,
+    runtime::{ExecutionPolicy, Limits},
+    source::SourceMap,
+};
+
+// what `haneul run` printed and exited with, see crate::RunResult
+#[pyclass(frozen, get_all)]
+struct RunResult {
+    stdout: String,
+    stderr: String,
+    exit_code: i32,
+}
+
+#[pymethods]
+impl RunResult {
+    fn __repr__(&self) -> String {
+        format![
+            "RunResult(stdout={:?}, stderr={:?}, exit_code={})",
+            self.stdout, self.stderr, self.exit_code
+        ]
+    }
+}
+
+// the program in another language, a target like --target names it
+#[pyfunction]
+#[pyo3(signature = (source, target = "c"))]
+fn compile(source: &str, target: &str) -> PyResult<String> {
+    crate::compile_text(source, target).map_err(PyValueError::new_err)
+}
+
+// runs the program on the vm with `stdin` as what its INPUTs read. a program with errors gives
+// them in stderr, with exit code 1, like `haneul run`, and so does one that went wrong. the rest is what the program may do (see
+// ExecutionPolicy), the timeout is in seconds and None is no limit
+#[pyfunction]
+#[pyo3(signature = (
+    source,
+    stdin = "",
+    *,
+    max_steps = None,
+    timeout = None,
+    max_memory = None,
+    allow_fs = true,
+    allow_net = true,
+    allow_exec = true,
+))]
+#[allow(clippy::too_many_arguments)]
+fn run(
+    source: &str,
+    stdin: &str,
+    max_steps: Option<u64>,
+    timeout: Option<f64>,
+    max_memory: Option<usize>,
+    allow_fs: bool,
+    allow_net: bool,
+    allow_exec: bool,
+) -> PyResult<RunResult> {
+    let timeout = timeout
+        .map(Duration::try_from_secs_f64)
+        .transpose()
+        .map_err(|error| PyValueError::new_err(format!["timeout: {}", error]))?;
+    let policy = ExecutionPolicy {
+        allow_fs,
+        allow_net,
+        allow_exec,
+        limits: Limits {
+            steps: max_steps,
+            timeout,
+        },
+        memory: max_memory,
+    };
+    let result = crate::run_source_with_policy(source, stdin, &policy);
+    Ok(RunResult {
+        stdout: result.stdout,
+        stderr: result.stderr,
+        exit_code: result.exit_code,
+    })
+}
+
+// the tokens of the program like --emit-tokens shows them: the type, the text, and the line
+// and the column they start at
+#[pyfunction]
+fn tokenize(source: &str) -> PyResult<Vec<(String, String, usize, usize)>> {
+    let mut sources = SourceMap::new();
+    let file = sources.add(String::from("main.han"), source.to_owned());
+    let mut tokens = vec![];
+    let mut errors = vec![];
+    for token in Lexer::new(file, sources.get(file).text()) {
+        match token {
+            Ok(token) => {
+                let span = token.span();
+                let kind = format!["{:?}", token.kind()];
+                tokens.push((kind, token.text().to_owned(), span.line, span.column));
+            }
+            Err(error) => errors.push(Diagnostic::from(&ParseError::from(error))),
+        }
+    }
+    if !errors.is_empty() {
+        let errors = Diagnostics {
+            list: errors,
+            sources,
+        };
+        return Err(PyValueError::new_err(
+            errors.render(Lang::English, Verbosity::Normal),
+        ));
+    }
+
+    Ok(tokens)
+}
+
+#[pymodule]
+fn haneul(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<RunResult>()?;
+    module.add_function(wrap_pyfunction!(compile, module)?)?;
+    module.add_function(wrap_pyfunction!(run, module)?)?;
+    module.add_function(wrap_pyfunction!(tokenize, module)?)?;
+    Ok(())
+}
@@ -0,0 +1,419 @@
+// --target=python, the program as an out.py for python 3. it works like the javascript
+// backend: the control flow graph in a loop, with an if for every block, and the types c gives
+// the numbers (see ctype.rs) made by hand. a float calculation is rounded to a float, an int
+// one wraps around like in c. an int divided by zero stops the program with an error, a float
+// one gives inf or nan like in c unless the division is checked (--float-division=error)
+
+use std::fmt::Write;
+
+use crate::{
+    ast::{BinaryOp, ExprId, ExprKind, Program, Statement, StatementKind, UnaryOp},
+    builtins::{Platform, Registry},
+    cfg::{Cfg, Terminator},
+    ctype::{self, CType, FloatDivision},
+    intern::Symbol,
+};
+
+// gives the name of a builtin the program uses that has no python implementation
+pub fn generate(
+    program: &Program,
+    builtins: &Registry,
+    float_division: FloatDivision,
+) -> Result<String, String> {
+    let cfg = Cfg::new(program);
+    let reachable = cfg.reachable();
+    let mut generator = Generator {
+        program,
+        builtins,
+        float_division,
+        helpers_used: vec![],
+        unsupported: None,
+    };
+
+    let mut code = String::new();
+    code.push_str("# made by haneul\n");
+    code.push_str(RUNTIME);
+    code.push_str("\n\ndef haneul_main():\n");
+    for variable in variables(&program.statements) {
+        let zero = match ctype::variable(program, variable) {
+            CType::Int | CType::Long => "0",
+            _ => "0.0",
+        };
+        let _ = writeln!(code, "    {} = {}", generator.name(variable), zero);
+    }
+    code.push_str("    haneul_block = 0\n");
+    code.push_str("    while True:\n");
+    for (i, id) in cfg.ids().filter(|id| reachable[id.index()]).enumerate() {
+        let block = cfg.block(id);
+        let keyword = if i == 0 { "if" } else { "elif" };
+        let _ = writeln!(code, "        {} haneul_block == {}:", keyword, id.index());
+        for statement in &block.statements {
+            let statement = generator.statement(statement);
+            let _ = writeln!(code, "            {}", statement);
+        }
+        let next = match block.terminator {
+            Terminator::Jump(target) => format!["haneul_block = {}", target.index()],
+            Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            } => format![
+                "haneul_block = {} if {} else {}",
+                then.index(),
+                generator.condition(condition),
+                otherwise.index()
+            ],
+            Terminator::Return => String::from("return"),
+        };
+        let _ = writeln!(code, "            {}", next);
+    }
+    for helper in builtins.helpers(Platform::Python) {
+        if generator.helpers_used.contains(&helper.name) {
+            let _ = write!(code, "\n\n{}\n", helper.code);
+        }
+    }
+    code.push_str("\n\nhaneul_main()\n");
+
+    match generator.unsupported {
+        Some(builtin) => Err(builtin),
+        None => Ok(code),
+    }
+}
+
+struct Generator<'a> {
+    program: &'a Program,
+    builtins: &'a Registry,
+    float_division: FloatDivision,
+    helpers_used: Vec<&'static str>,
+    unsupported: Option<String>,
+}
+
+impl Generator<'_> {
+    // only the statements that stay in a block of the control flow graph
+    fn statement(&mut self, statement: &Statement) -> String {
+        match &statement.kind {
+            StatementKind::Print(value) => match ctype::of(self.program, *value) {
+                CType::Int | CType::Long => {
+                    format!["haneul_print_integer({})", self.expression(*value)]
+                }
+                _ => format![
+                    "haneul_print_number({})",
+                    self.convert(*value, CType::Float)
+                ],
+            },
+            StatementKind::PrintString(string) => {
+                format!["haneul_print_string({})", string_literal(string)]
+            }
+            StatementKind::Let { variable, value } => format![
+                "{} = {}",
+                self.name(*variable),
+                self.convert(*value, ctype::variable(self.program, *variable))
+            ],
+            StatementKind::Input(variable) => {
+                let name = self.name(*variable);
+                format!["{} = haneul_input({})", name, name]
+            }
+            StatementKind::Call { name, arguments } => self.call(name, arguments),
+            StatementKind::If { .. }
+            | StatementKind::While { .. }
+            | StatementKind::Label(_)
+            | StatementKind::Goto(_) => unreachable!("these are the edges of the graph"),
+        }
+    }
+
+    // a bool, where c checks if the number isn't 0
+    fn condition(&mut self, condition: ExprId) -> String {
+        let exprs = &self.program.exprs;
+        match exprs[condition].kind {
+            ExprKind::Binary { op, left, right } if op.is_comparison() => {
+                let common = ctype::common(self.program, left, right);
+                format![
+                    "({} {} {})",
+                    self.convert(left, common),
+                    op.text(),
+                    self.convert(right, common)
+                ]
+            }
+            _ => format!["({} != 0)", self.expression(condition)],
+        }
+    }
+
+    fn expression(&mut self, expression: ExprId) -> String {
+        let exprs = &self.program.exprs;
+        match &exprs[expression].kind {
+            // written again, a leading 0 is an error in python
+            ExprKind::Number(text) => match (text.parse::<i64>(), ctype::number(text)) {
+                (Ok(value), CType::Int | CType::Long) => value.to_string(),
+                _ => format!["{:?}", text.parse::<f64>().unwrap_or_default()],
+            },
+            ExprKind::Variable(variable) => self.name(*variable),
+            ExprKind::Call { name, arguments } => self.call(name, arguments),
+            ExprKind::Unary { op, operand } => {
+                let ty = ctype::of(self.program, *operand);
+                let operand = self.expression(*operand);
+                match op {
+                    UnaryOp::Plus => operand,
+                    UnaryOp::Minus => wrap(ty, format!["-{}", operand]),
+                }
+            }
+            ExprKind::Binary { op, left, right } => {
+                let (op, left, right) = (*op, *left, *right);
+                let common = ctype::common(self.program, left, right);
+                let left = self.convert(left, common);
+                let right = self.convert(right, common);
+                match op {
+                    _ if op.is_comparison() => {
+                        format!["(1 if {} {} {} else 0)", left, op.text(), right]
+                    }
+                    BinaryOp::Divide if common.is_integer() => {
+                        let line = self.program.exprs[expression].span.line;
+                        wrap(
+                            common,
+                            format!["haneul_idiv({}, {}, {})", left, right, line],
+                        )
+                    }
+                    BinaryOp::Divide if self.float_division == FloatDivision::Error => {
+                        let line = self.program.exprs[expression].span.line;
+                        wrap(
+                            common,
+                            format!["haneul_fdiv({}, {}, {})", left, right, line],
+                        )
+                    }
+                    BinaryOp::Divide => wrap(common, format!["haneul_fdiv({}, {})", left, right]),
+                    BinaryOp::Remainder if common.is_integer() => {
+                        let line = self.program.exprs[expression].span.line;
+                        wrap(
+                            common,
+                            format!["haneul_irem({}, {}, {})", left, right, line],
+                        )
+                    }
+                    BinaryOp::Remainder if self.float_division == FloatDivision::Error => {
+                        let line = self.program.exprs[expression].span.line;
+                        wrap(
+                            common,
+                            format!["haneul_fmod({}, {}, {})", left, right, line],
+                        )
+                    }
+                    BinaryOp::Remainder => {
+                        wrap(common, format!["haneul_fmod({}, {})", left, right])
+                    }
+                    _ => wrap(common, format!["{} {} {}", left, op.text(), right]),
+                }
+            }
+        }
+    }
+
+    // the function of the builtin with the arguments as floats, its helper comes after main
+    fn call(&mut self, name: &str, arguments: &[ExprId]) -> String {
+        let builtin = self
+            .builtins
+            .get(name)
+            .expect("the parser only accepts known builtins");
+        let Some(implementation) = builtin.implementation(Platform::Python) else {
+            self.unsupported.get_or_insert_with(|| name.to_owned());
+            return String::new();
+        };
+        if !self.helpers_used.contains(&implementation.helper) {
+            self.helpers_used.push(implementation.helper);
+        }
+        let arguments: Vec<String> = arguments
+            .iter()
+            .map(|argument| self.convert(*argument, CType::Float))
+            .collect();
+        format!["{}({})", implementation.function, arguments.join(", ")]
+    }
+
+    // the expression as another type, like c converts it for a calculation or a LET. only
+    // making something a float changes the number
+    fn convert(&mut self, expression: ExprId, to: CType) -> String {
+        let from = ctype::of(self.program, expression);
+        let code = self.expression(expression);
+        if from != to && to == CType::Float {
+            format!["haneul_fround({})", code]
+        } else {
+            code
+        }
+    }
+
+    // a name python already uses gets a _ after it, no name in a program can end in one
+    fn name(&self, symbol: Symbol) -> String {
+        let name = self.program.names.resolve(symbol);
+        if RESERVED.contains(&name) {
+            format!["{}_", name]
+        } else {
+            name.to_owned()
+        }
+    }
+}
+
+// a calculation made into the type c gives it
+fn wrap(ty: CType, calculation: String) -> String {
+    match ty {
+        CType::Int => format!["haneul_int({})", calculation],
+        CType::Long => format!["haneul_long({})", calculation],
+        CType::Float => format!["haneul_fround({})", calculation],
+        CType::Double => format!["({})", calculation],
+    }
+}
+
+fn string_literal(string: &str) -> String {
+    let mut literal = String::from("\"");
+    for c in string.chars() {
+        match c {
+            '"' | '\\' => {
+                literal.push('\\');
+                literal.push(c);
+            }
+            c if c < ' ' => {
+                let _ = write!(literal, "\\x{:02x}", c as u32);
+            }
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+// every variable, in the order they first get a value
+fn variables(statements: &[Statement]) -> Vec<Symbol> {
+    fn collect(statements: &[Statement], variables: &mut Vec<Symbol>) {
+        for statement in statements {
+            match &statement.kind {
+                StatementKind::Let { variable, .. } | StatementKind::Input(variable)
+                    if !variables.contains(variable) =>
+                {
+                    variables.push(*variable)
+                }
+                StatementKind::If { body, .. } | StatementKind::While { body, .. } => {
+                    collect(body, variables)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut variables = vec![];
+    collect(statements, &mut variables);
+    variables
+}
+
+const RESERVED: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
+// the program only uses these names, so a variable called print can't get in the way
+const RUNTIME: &str = r#"
+import ctypes
+import math
+import re
+import sys
+
+
+def haneul_fround(value):
+    return ctypes.c_float(value).value
+
+
+def haneul_int(value):
+    return (value + 0x80000000) % 0x100000000 - 0x80000000
+
+
+def haneul_long(value):
+    return (value + 0x8000000000000000) % 0x10000000000000000 - 0x8000000000000000
+
+
+def haneul_fail(message, line):
+    sys.stdout.flush()
+    print("Error: %s on line %d" % (message, line), file=sys.stderr)
+    sys.exit(1)
+
+
+# c rounds towards zero
+def haneul_idiv(left, right, line):
+    if right == 0:
+        haneul_fail("an int is divided by 0", line)
+    quotient = abs(left) // abs(right)
+    return quotient if (left < 0) == (right < 0) else -quotient
+
+
+# with a line the division is checked
+def haneul_fdiv(left, right, line=None):
+    if right == 0 and line is not None:
+        haneul_fail("a float is divided by 0", line)
+    if right != 0:
+        return left / right
+    if left == 0 or math.isnan(left):
+        return math.nan
+    return math.copysign(math.inf, left) * math.copysign(1.0, right)
+
+
+# the sign of the left side, like in c. python's % has the sign of the right side
+def haneul_irem(left, right, line):
+    if right == 0:
+        haneul_fail("an int is divided by 0", line)
+    remainder = abs(left) % abs(right)
+    return remainder if left >= 0 else -remainder
+
+
+# fmod in c, with a line the division is checked. math.fmod raises an error where c gives nan
+def haneul_fmod(left, right, line=None):
+    if right == 0 and line is not None:
+        haneul_fail("a float is divided by 0", line)
+    if right == 0 or math.isinf(left) or math.isnan(left) or math.isnan(right):
+        return math.nan
+    return math.fmod(left, right)
+
+
+def haneul_print_integer(value):
+    print(value)
+
+
+# the fewest digits that read back as the same float, without an exponent, like the c code:
+# the first of 1 to 9 digits in the e notation that reads back as the float, with its zeros
+def haneul_print_number(value):
+    if math.isnan(value):
+        print("nan")
+        return
+    if math.isinf(value):
+        print("inf" if value > 0 else "-inf")
+        return
+    for precision in range(1, 10):
+        text = "%.*e" % (precision - 1, value)
+        if haneul_fround(float(text)) == value:
+            break
+    decimals = precision - 1 - int(text[text.index("e") + 1 :])
+    if decimals > 0:
+        print("%.*f" % (decimals, value))
+        return
+    sign = 1 if text[0] == "-" else 0
+    print(text[: sign + 1] + text[sign + 2 : sign + 1 + precision] + "0" * -decimals)
+
+
+def haneul_print_string(string):
+    print(string)
+
+
+# like scanf("%f") in c: the number at the start of the next word, the rest of the word is
+# read by the next INPUT. a word that doesn't start with a number gives 0, at the end of the
+# input the variable keeps the value it had
+haneul_pending = ""
+haneul_number = re.compile(r"[+-]?(infinity|inf|nan|(\d+\.?\d*|\.\d+)(e[+-]?\d+)?)", re.I)
+
+
+def haneul_input(value):
+    global haneul_pending
+    sys.stdout.flush()
+    while haneul_pending.strip() == "":
+        haneul_pending = sys.stdin.readline()
+        if haneul_pending == "":
+            return value
+
+    haneul_pending = haneul_pending.lstrip()
+    match = haneul_number.match(haneul_pending)
+    if match is None:
+        haneul_pending = re.sub(r"^\S*", "", haneul_pending)
+        return 0.0
+    haneul_pending = haneul_pending[match.end():]
+    return haneul_fround(float(match.group()))"#;
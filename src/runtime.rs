@@ -0,0 +1,658 @@
+// what the vm (vm.rs) and the interpreter (interpreter.rs) both run a program with: the numbers
+// with the types c gives them (see ctype.rs), the calculations on them, and PRINT and INPUT.
+// it does what the program does when it's compiled to c: ints wrap around, floats are rounded
+// to floats, PRINT and INPUT work like printf and scanf. dividing by 0 is an error like in
+// every backend (see ctype::FloatDivision)
+
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, BufWriter, IsTerminal, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    ast::BinaryOp,
+    builtins::Capability,
+    ctype::{self, CType, FloatDivision},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub line: usize,
+}
+
+// why a program stopped before its end
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    // it went wrong, like an int divided by 0
+    Failed,
+    // it went past its Limits, or the memory of its ExecutionPolicy
+    Limit,
+    // it called a builtin that needs what its ExecutionPolicy doesn't allow
+    NotAllowed,
+    // its CancellationToken was cancelled
+    Cancelled,
+}
+
+// stops a running program from another thread, for --watch and for a program that runs haneul
+// programs, like a gui or a server. the program stops at the next place the Limits are checked, with an
+// ErrorKind::Cancelled error. a clone cancels the same program
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// what a program may use while it runs, for running programs that aren't trusted, like the
+// ones of students. None has no limit
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Limits {
+    // instructions on the vm, statements in the interpreter
+    pub steps: Option<u64>,
+    // how long it may run. the time it waits for INPUT counts, but the wait itself isn't cut off
+    pub timeout: Option<Duration>,
+}
+
+// what an embedded program may do, for a server that runs programs it doesn't trust, like the
+// ones of students. a builtin that needs what isn't allowed is an error when the program is
+// parsed with a Registry restricted to the policy (see Registry::restrict), and when it's
+// called on a vm or an interpreter the policy was given to (see Vm::set_policy), also for a
+// program that was parsed without it. the limits and the memory stop the program while it runs
+#[derive(Debug, Clone, Copy)]
+pub struct ExecutionPolicy {
+    pub allow_fs: bool,
+    pub allow_net: bool,
+    pub allow_exec: bool,
+    pub limits: Limits,
+    // about how many bytes the program may take up while it runs: its code, its variables, its
+    // stack and the line of input it's reading. None has no limit
+    pub memory: Option<usize>,
+}
+
+impl ExecutionPolicy {
+    // nothing outside of the program is allowed. the limits are up to the server
+    pub fn sandbox() -> Self {
+        Self {
+            allow_fs: false,
+            allow_net: false,
+            allow_exec: false,
+            ..Self::default()
+        }
+    }
+
+    pub fn allows(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::Fs => self.allow_fs,
+            Capability::Net => self.allow_net,
+            Capability::Exec => self.allow_exec,
+        }
+    }
+
+    // `memory` is about how many bytes the program takes up now, at `line`
+    pub fn check_memory(&self, memory: usize, line: usize) -> Result<(), RuntimeError> {
+        match self.memory {
+            Some(limit) if memory > limit => Err(RuntimeError {
+                kind: ErrorKind::Limit,
+                message: format![
+                    "the program takes up about {} bytes, more than the {} it may use",
+                    memory, limit
+                ],
+                line,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    // before the builtin `name` runs, with what it needs (see Builtin::needs)
+    pub fn check_builtin(
+        &self,
+        name: &str,
+        needs: &[Capability],
+        line: usize,
+    ) -> Result<(), RuntimeError> {
+        match needs.iter().find(|capability| !self.allows(**capability)) {
+            Some(capability) => Err(RuntimeError {
+                kind: ErrorKind::NotAllowed,
+                message: format![
+                    "{} isn't allowed here, it needs allow_{}",
+                    name,
+                    capability.name()
+                ],
+                line,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+// how `haneul run` runs a program: everything is allowed and there are no limits
+impl Default for ExecutionPolicy {
+    fn default() -> Self {
+        Self {
+            allow_fs: true,
+            allow_net: true,
+            allow_exec: true,
+            limits: Limits::default(),
+            memory: None,
+        }
+    }
+}
+
+// checks a running program against its Limits and its CancellationToken: it counts the steps,
+// and a watchdog thread sets `expired` when the time is up. the clock starts when the guard is
+// made
+pub struct Guard {
+    left: u64,
+    limits: Limits,
+    expired: Arc<AtomicBool>,
+    cancellation: CancellationToken,
+    // dropping it stops the watchdog
+    _watchdog: Option<Sender<()>>,
+}
+
+impl Guard {
+    pub fn new(limits: &Limits, cancellation: &CancellationToken) -> Self {
+        let expired = Arc::new(AtomicBool::new(false));
+        let watchdog = limits.timeout.map(|timeout| {
+            let (sender, receiver) = mpsc::channel();
+            let expired = Arc::clone(&expired);
+            thread::spawn(move || {
+                if receiver.recv_timeout(timeout) == Err(RecvTimeoutError::Timeout) {
+                    expired.store(true, Ordering::Relaxed);
+                }
+            });
+            sender
+        });
+        Self {
+            left: limits.steps.unwrap_or(u64::MAX),
+            limits: *limits,
+            expired,
+            cancellation: cancellation.clone(),
+            _watchdog: watchdog,
+        }
+    }
+
+    // false when the program took more steps than it may, ran out of time or was cancelled
+    #[inline(always)]
+    pub fn take(&mut self, steps: u64) -> bool {
+        if steps > self.left
+            || self.expired.load(Ordering::Relaxed)
+            || self.cancellation.is_cancelled()
+        {
+            self.left = self.left.saturating_sub(steps);
+            return false;
+        }
+        self.left -= steps;
+        true
+    }
+
+    // after take gave false
+    #[cold]
+    pub fn exceeded(&self, line: usize) -> RuntimeError {
+        let (kind, message) = match (self.expired.load(Ordering::Relaxed), self.limits) {
+            _ if self.cancellation.is_cancelled() => (
+                ErrorKind::Cancelled,
+                String::from("the program was cancelled"),
+            ),
+            (
+                true,
+                Limits {
+                    timeout: Some(timeout),
+                    ..
+                },
+            ) => (
+                ErrorKind::Limit,
+                format!["the program ran for more than {:?}", timeout],
+            ),
+            (_, limits) => (
+                ErrorKind::Limit,
+                format![
+                    "possible infinite loop, the program took more than {} steps",
+                    limits.steps.unwrap_or(u64::MAX)
+                ],
+            ),
+        };
+        RuntimeError {
+            kind,
+            message,
+            line,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+}
+
+impl Value {
+    // a literal, with the type c gives it. the lexer doesn't let through an int that doesn't fit
+    // in a long
+    pub fn number(text: &str) -> Self {
+        match ctype::number(text) {
+            CType::Int => Value::Int(text.parse().unwrap_or_default()),
+            CType::Long => Value::Long(text.parse().expect("the lexer checks the size of ints")),
+            _ => Value::Double(text.parse().unwrap_or_default()),
+        }
+    }
+
+    // an IF or a WHILE is skipped when its condition is 0
+    pub fn is_zero(self) -> bool {
+        match self {
+            Value::Int(value) => value == 0,
+            Value::Long(value) => value == 0,
+            Value::Float(value) => value == 0.0,
+            Value::Double(value) => value == 0.0,
+        }
+    }
+
+    pub fn negate(self) -> Self {
+        match self {
+            Value::Int(value) => Value::Int(value.wrapping_neg()),
+            Value::Long(value) => Value::Long(value.wrapping_neg()),
+            Value::Float(value) => Value::Float(-value),
+            Value::Double(value) => Value::Double(-value),
+        }
+    }
+
+    pub fn convert(self, to: CType) -> Self {
+        match (self, to) {
+            (value, to) if value.ctype() == to => value,
+            (Value::Int(value), CType::Long) => Value::Long(value as i64),
+            (Value::Int(value), CType::Float) => Value::Float(value as f32),
+            (Value::Int(value), CType::Double) => Value::Double(value as f64),
+            (Value::Long(value), CType::Float) => Value::Float(value as f32),
+            (Value::Long(value), CType::Double) => Value::Double(value as f64),
+            (Value::Float(value), CType::Double) => Value::Double(value as f64),
+            (Value::Double(value), CType::Float) => Value::Float(value as f32),
+            _ => unreachable!("nothing is made into a smaller type but a float"),
+        }
+    }
+
+    pub fn ctype(self) -> CType {
+        match self {
+            Value::Int(_) => CType::Int,
+            Value::Long(_) => CType::Long,
+            Value::Float(_) => CType::Float,
+            Value::Double(_) => CType::Double,
+        }
+    }
+
+    // an INPUT reads into a float variable
+    pub fn float(self) -> f32 {
+        match self {
+            Value::Float(value) => value,
+            value => unreachable!("{:?} is converted to a float first", value),
+        }
+    }
+}
+
+// a value without its type, 8 bytes where a Value is 16. the vm keeps these on its stack, the
+// instructions know what type they are (see bytecode.rs)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Raw(u64);
+
+impl Raw {
+    pub fn new(value: Value) -> Self {
+        Raw(match value {
+            Value::Int(value) => value as i64 as u64,
+            Value::Long(value) => value as u64,
+            Value::Float(value) => value.to_bits() as u64,
+            Value::Double(value) => value.to_bits(),
+        })
+    }
+
+    pub fn get(self, ctype: CType) -> Value {
+        match ctype {
+            CType::Int => Value::Int(self.0 as i32),
+            CType::Long => Value::Long(self.0 as i64),
+            CType::Float => Value::Float(f32::from_bits(self.0 as u32)),
+            CType::Double => Value::Double(f64::from_bits(self.0)),
+        }
+    }
+
+    // for a snapshot of the vm's stack
+    pub fn bits(self) -> u64 {
+        self.0
+    }
+
+    pub fn from_bits(bits: u64) -> Self {
+        Raw(bits)
+    }
+}
+
+// both are the same type, they're converted to the common one first. a comparison gives an int,
+// % of floats is fmod in c. None when an int is divided by 0 with / or %, or a float with
+// FloatDivision::Error (see ctype::division_by_zero). inlined, so the vm gets a version of it
+// for every op
+#[inline(always)]
+pub fn binary(op: BinaryOp, left: Value, right: Value, division: FloatDivision) -> Option<Value> {
+    if op.is_comparison() {
+        return Some(Value::Int(compare(op, left, right) as i32));
+    }
+
+    let value = match (left, right) {
+        (Value::Int(left), Value::Int(right)) => Value::Int(match op {
+            BinaryOp::Add => left.wrapping_add(right),
+            BinaryOp::Subtract => left.wrapping_sub(right),
+            BinaryOp::Multiply => left.wrapping_mul(right),
+            _ if right == 0 => return None,
+            BinaryOp::Remainder => left.wrapping_rem(right),
+            _ => left.wrapping_div(right),
+        }),
+        (Value::Long(left), Value::Long(right)) => Value::Long(match op {
+            BinaryOp::Add => left.wrapping_add(right),
+            BinaryOp::Subtract => left.wrapping_sub(right),
+            BinaryOp::Multiply => left.wrapping_mul(right),
+            _ if right == 0 => return None,
+            BinaryOp::Remainder => left.wrapping_rem(right),
+            _ => left.wrapping_div(right),
+        }),
+        (Value::Float(left), Value::Float(right)) => Value::Float(match op {
+            BinaryOp::Add => left + right,
+            BinaryOp::Subtract => left - right,
+            BinaryOp::Multiply => left * right,
+            _ if right == 0.0 && division == FloatDivision::Error => return None,
+            BinaryOp::Remainder => left % right,
+            _ => left / right,
+        }),
+        (Value::Double(left), Value::Double(right)) => Value::Double(match op {
+            BinaryOp::Add => left + right,
+            BinaryOp::Subtract => left - right,
+            BinaryOp::Multiply => left * right,
+            _ if right == 0.0 && division == FloatDivision::Error => return None,
+            BinaryOp::Remainder => left % right,
+            _ => left / right,
+        }),
+        _ => unreachable!("{:?} and {:?} aren't the same type", left, right),
+    };
+    Some(value)
+}
+
+#[inline(always)]
+fn compare(op: BinaryOp, left: Value, right: Value) -> bool {
+    fn compare<T: PartialOrd>(op: BinaryOp, left: T, right: T) -> bool {
+        match op {
+            BinaryOp::Equal => left == right,
+            BinaryOp::NotEqual => left != right,
+            BinaryOp::Less => left < right,
+            BinaryOp::LessEqual => left <= right,
+            BinaryOp::Greater => left > right,
+            _ => left >= right,
+        }
+    }
+
+    match (left, right) {
+        (Value::Int(left), Value::Int(right)) => compare(op, left, right),
+        (Value::Long(left), Value::Long(right)) => compare(op, left, right),
+        (Value::Float(left), Value::Float(right)) => compare(op, left, right),
+        (Value::Double(left), Value::Double(right)) => compare(op, left, right),
+        _ => unreachable!("{:?} and {:?} aren't the same type", left, right),
+    }
+}
+
+// what PRINT writes: an int as it is, a float with format_number. a double is made a float
+// first, like the c code does
+pub fn format_value(value: Value) -> String {
+    match value {
+        Value::Int(value) => value.to_string(),
+        Value::Long(value) => value.to_string(),
+        Value::Float(value) => format_number(value),
+        Value::Double(value) => format_number(value as f32),
+    }
+}
+
+// the fewest digits that read back as the same float, without an exponent. every backend
+// does it the same way: the number with 1 to 9 digits in the e notation, the first one that
+// reads back as the float is written out with its zeros
+pub fn format_number(value: f32) -> String {
+    if value.is_nan() {
+        return String::from("nan");
+    }
+    if value.is_infinite() {
+        return String::from(if value > 0.0 { "inf" } else { "-inf" });
+    }
+    let (precision, text) = (1..=9)
+        .map(|precision| (precision, format!["{:.*e}", precision - 1, value]))
+        .find(|(precision, text)| *precision == 9 || text.parse::<f32>() == Ok(value))
+        .expect("9 digits are always enough for a float");
+    let (digits, exponent) = text.split_once('e').expect("the e notation has an e");
+    let exponent: i32 = exponent.parse().expect("the exponent is a number");
+    // a number with a fraction, or a whole one that ends in zeros
+    let decimals = precision as i32 - 1 - exponent;
+    if decimals > 0 {
+        return format!["{:.*}", decimals as usize, value];
+    }
+    format![
+        "{}{}",
+        digits.replace('.', ""),
+        "0".repeat(-decimals as usize)
+    ]
+}
+
+// where PRINT writes to, stdout unless the vm or the interpreter is given another writer with
+// set_output. stdout is buffered, an INPUT flushes it so the question is there before it waits
+pub type Output<'a> = BufWriter<Box<dyn Write + Send + 'a>>;
+
+pub fn stdout<'a>() -> Output<'a> {
+    BufWriter::new(Box::new(io::stdout()))
+}
+
+// like scanf("%f") in c: the number at the start of the next word, the rest of the word is read
+// by the next INPUT. a word that doesn't start with a number gives 0, at the end of the input the
+// variable keeps the value it had
+#[derive(Default)]
+pub struct Input {
+    // stdin without one
+    reader: Option<Box<dyn BufRead + Send>>,
+    pending: String,
+    // --replay, what the INPUTs give instead of reading the input
+    replay: Option<VecDeque<Option<f32>>>,
+    // --record, what every INPUT gave, None at the end of the input
+    recorded: Option<Vec<Option<f32>>>,
+}
+
+impl Input {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // reads the lines from `reader` instead of stdin, for a test or a host that gives the
+    // program its input, like Input::from_reader("3 4\n".as_bytes())
+    pub fn from_reader(reader: impl BufRead + Send + 'static) -> Self {
+        Self {
+            reader: Some(Box::new(reader)),
+            ..Self::default()
+        }
+    }
+
+    // when the recording runs out the input has ended
+    pub fn replay(&mut self, inputs: Vec<Option<f32>>) {
+        self.replay = Some(inputs.into());
+    }
+
+    pub fn record(&mut self) {
+        self.recorded = Some(vec![]);
+    }
+
+    pub fn recorded(&self) -> Option<&[Option<f32>]> {
+        self.recorded.as_deref()
+    }
+
+    // what was read but not used by an INPUT yet, for a snapshot
+    pub fn pending(&self) -> &str {
+        &self.pending
+    }
+
+    pub fn set_pending(&mut self, pending: String) {
+        self.pending = pending;
+    }
+
+    pub fn read(&mut self, variable: &mut f32) {
+        let value = match &mut self.replay {
+            Some(replay) => replay.pop_front().flatten(),
+            None => self.next(),
+        };
+        if let Some(recorded) = &mut self.recorded {
+            recorded.push(value);
+        }
+        if let Some(value) = value {
+            *variable = value;
+        }
+    }
+
+    // INKEY: the code of the key that was pressed, 0 when none was. what an INPUT read but
+    // didn't use comes first, like in c. the keys of a reader given with from_reader and of
+    // stdin that isn't a terminal were all pressed already, a terminal is looked at without
+    // waiting for a key
+    pub fn key(&mut self) -> f32 {
+        if self.pending.is_empty() {
+            let _ = match &mut self.reader {
+                Some(reader) => reader.read_line(&mut self.pending),
+                None if io::stdin().is_terminal() => return pressed(),
+                None => io::stdin().lock().read_line(&mut self.pending),
+            };
+        }
+        let Some(key) = self.pending.chars().next() else {
+            return 0.0;
+        };
+        self.pending.drain(..key.len_utf8());
+        key as u32 as f32
+    }
+
+    // None at the end of the input
+    fn next(&mut self) -> Option<f32> {
+        while self.pending.trim_start().is_empty() {
+            self.pending.clear();
+            let read = match &mut self.reader {
+                Some(reader) => reader.read_line(&mut self.pending),
+                None => io::stdin().lock().read_line(&mut self.pending),
+            };
+            match read {
+                Ok(0) | Err(_) => return None,
+                Ok(_) => {}
+            }
+        }
+
+        let pending = self.pending.trim_start().to_owned();
+        let end = pending.find(char::is_whitespace).unwrap_or(pending.len());
+        let word = &pending[..end];
+        let number = (1..=word.len())
+            .rev()
+            .filter(|length| word.is_char_boundary(*length))
+            .find_map(|length| Some((length, word[..length].parse::<f32>().ok()?)));
+        match number {
+            Some((length, value)) => {
+                self.pending = pending[length..].to_owned();
+                Some(value)
+            }
+            None => {
+                self.pending = pending[end..].to_owned();
+                Some(0.0)
+            }
+        }
+    }
+}
+
+// the terminal is only put in raw mode for the duration of the call, so INPUT keeps working.
+// like the c helper it reads a byte, a key that isn't ascii comes in several
+#[cfg(unix)]
+fn pressed() -> f32 {
+    let mut byte = 0_u8;
+    // SAFETY: the termios is filled in by tcgetattr before it's used, read writes one byte
+    // into `byte`
+    let read = unsafe {
+        let mut old: libc::termios = core::mem::zeroed();
+        let terminal = libc::tcgetattr(libc::STDIN_FILENO, &mut old) == 0;
+        if terminal {
+            let mut raw = old;
+            raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw);
+        }
+        let flags = libc::fcntl(libc::STDIN_FILENO, libc::F_GETFL, 0);
+        libc::fcntl(libc::STDIN_FILENO, libc::F_SETFL, flags | libc::O_NONBLOCK);
+        let read = libc::read(libc::STDIN_FILENO, (&mut byte as *mut u8).cast(), 1);
+        libc::fcntl(libc::STDIN_FILENO, libc::F_SETFL, flags);
+        if terminal {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &old);
+        }
+        read
+    };
+    match read {
+        1 => byte as f32,
+        _ => 0.0,
+    }
+}
+
+// there's no way to read a key without waiting for it in the standard library
+#[cfg(not(unix))]
+fn pressed() -> f32 {
+    0.0
+}
+
+// the builtins the vm and the tree interpreter run themselves, named by the function of their
+// Platform::Run implementation (see builtins.rs). none of them reach outside of the program, so
+// there's nothing for an ExecutionPolicy to check, and none of them take arguments
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Native {
+    Inkey,
+    Beep,
+}
+
+impl Native {
+    pub const ALL: [Native; 2] = [Native::Inkey, Native::Beep];
+
+    pub fn named(function: &str) -> Option<Native> {
+        Self::ALL
+            .into_iter()
+            .find(|native| native.name() == function)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Native::Inkey => "inkey",
+            Native::Beep => "beep",
+        }
+    }
+
+    // a function gives a number, a statement nothing
+    pub fn returns(self) -> bool {
+        self == Native::Inkey
+    }
+
+    // what a statement gives back is 0. the output is flushed first, so what the program printed
+    // is there before it waits for a key or beeps
+    pub fn call(self, input: &mut Input, output: &mut Output) -> f32 {
+        let _ = output.flush();
+        match self {
+            Native::Inkey => input.key(),
+            Native::Beep => {
+                let _ = output.write_all(b"\x07");
+                let _ = output.flush();
+                0.0
+            }
+        }
+    }
+}
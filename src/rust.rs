@@ -0,0 +1,474 @@
+// --target=rust, the program as a main.rs that builds on its own with rustc, and the Cargo.toml
+// that `cargo run` builds it with.
+// rust has no goto, so the program is its control flow graph: a loop with a match on the
+// block that runs next, every block says which one comes after it. the numbers follow the
+// types c gives them (see ctype.rs), so the program prints the same as the c one does
+//
+// a builtin is a call to its rust helper (see builtins::Platform), which comes after the runtime
+
+use std::fmt::Write;
+
+use crate::{
+    ast::{BinaryOp, ExprId, ExprKind, Program, Statement, StatementKind, UnaryOp},
+    builtins::{Platform, Registry},
+    cfg::{Cfg, Terminator},
+    ctype::{self, CType, FloatDivision},
+    intern::Symbol,
+};
+
+// gives the name of a builtin the program uses that has no rust implementation
+pub fn generate(
+    program: &Program,
+    builtins: &Registry,
+    float_division: FloatDivision,
+) -> Result<String, String> {
+    let cfg = Cfg::new(program);
+    let reachable = cfg.reachable();
+    let mut generator = Generator {
+        program,
+        builtins,
+        float_division,
+        helpers_used: vec![],
+        unsupported: None,
+    };
+
+    let mut code = String::new();
+    code.push_str(PRELUDE);
+    code.push_str("fn main() {\n");
+    code.push_str("    let mut haneul_input = runtime::Input::new();\n");
+    for variable in variables(&program.statements) {
+        let ty = ctype::variable(program, variable);
+        let _ = writeln!(
+            code,
+            "    let mut {}: {} = {};",
+            generator.name(variable),
+            rust_type(ty),
+            zero(ty)
+        );
+    }
+    code.push_str("    let mut haneul_block = 0;\n");
+    code.push_str("    loop {\n");
+    code.push_str("        match haneul_block {\n");
+    for id in cfg.ids().filter(|id| reachable[id.index()]) {
+        let block = cfg.block(id);
+        let _ = writeln!(code, "            {} => {{", id.index());
+        for statement in &block.statements {
+            let statement = generator.statement(statement);
+            let _ = writeln!(code, "                {}", statement);
+        }
+        let next = match block.terminator {
+            Terminator::Jump(target) => format!["haneul_block = {};", target.index()],
+            Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            } => format![
+                "haneul_block = if {} {{ {} }} else {{ {} }};",
+                generator.condition(condition),
+                then.index(),
+                otherwise.index()
+            ],
+            Terminator::Return => String::from("return;"),
+        };
+        let _ = writeln!(code, "                {}", next);
+        code.push_str("            }\n");
+    }
+    code.push_str("            _ => unreachable!(),\n");
+    code.push_str("        }\n");
+    code.push_str("    }\n");
+    code.push_str("}\n");
+    code.push_str(RUNTIME);
+    for helper in builtins.helpers(Platform::Rust) {
+        if generator.helpers_used.contains(&helper.name) {
+            let _ = write!(code, "\n{}\n", helper.code);
+        }
+    }
+
+    match generator.unsupported {
+        Some(builtin) => Err(builtin),
+        None => Ok(code),
+    }
+}
+
+struct Generator<'a> {
+    program: &'a Program,
+    builtins: &'a Registry,
+    float_division: FloatDivision,
+    helpers_used: Vec<&'static str>,
+    unsupported: Option<String>,
+}
+
+impl Generator<'_> {
+    // only the statements that stay in a block of the control flow graph
+    fn statement(&mut self, statement: &Statement) -> String {
+        match &statement.kind {
+            StatementKind::Print(value) => match ctype::of(self.program, *value) {
+                CType::Int | CType::Long => {
+                    format!["println!(\"{{}}\", {});", self.expression(*value)]
+                }
+                _ => format![
+                    "runtime::print_number({});",
+                    self.convert(*value, CType::Float)
+                ],
+            },
+            StatementKind::PrintString(string) => format!["println!(\"{{}}\", {:?});", string],
+            StatementKind::Let { variable, value } => format![
+                "{} = {};",
+                self.name(*variable),
+                self.convert(*value, ctype::variable(self.program, *variable))
+            ],
+            StatementKind::Input(variable) => {
+                format!["haneul_input.read(&mut {});", self.name(*variable)]
+            }
+            StatementKind::Call { name, arguments } => format!["{};", self.call(name, arguments)],
+            StatementKind::If { .. }
+            | StatementKind::While { .. }
+            | StatementKind::Label(_)
+            | StatementKind::Goto(_) => unreachable!("these are the edges of the graph"),
+        }
+    }
+
+    // a bool, where c checks if the number isn't 0
+    fn condition(&mut self, condition: ExprId) -> String {
+        let exprs = &self.program.exprs;
+        match exprs[condition].kind {
+            ExprKind::Binary { op, left, right } if op.is_comparison() => {
+                let common = ctype::common(self.program, left, right);
+                format![
+                    "({} {} {})",
+                    self.convert(left, common),
+                    op.text(),
+                    self.convert(right, common)
+                ]
+            }
+            _ => {
+                let zero = zero(ctype::of(self.program, condition));
+                format!["({} != {})", self.expression(condition), zero]
+            }
+        }
+    }
+
+    // ints wrap around like they do in the c code, instead of stopping the program. a division
+    // that's checked calls the runtime with its line
+    fn expression(&mut self, expression: ExprId) -> String {
+        let exprs = &self.program.exprs;
+        match &exprs[expression].kind {
+            ExprKind::Number(text) => match ctype::number(text) {
+                CType::Double => format!["{:?}f64", text.parse::<f64>().unwrap_or_default()],
+                ty => format!["{}{}", text, rust_type(ty)],
+            },
+            ExprKind::Variable(variable) => self.name(*variable),
+            ExprKind::Call { name, arguments } => self.call(name, arguments),
+            ExprKind::Unary { op, operand } => {
+                let integer = ctype::of(self.program, *operand).is_integer();
+                let operand = self.expression(*operand);
+                match op {
+                    UnaryOp::Plus => operand,
+                    UnaryOp::Minus if integer => format!["{}.wrapping_neg()", operand],
+                    UnaryOp::Minus => format!["(-{})", operand],
+                }
+            }
+            ExprKind::Binary { op, left, right } => {
+                let (op, left, right) = (*op, *left, *right);
+                let common = ctype::common(self.program, left, right);
+                let left = self.convert(left, common);
+                let right = self.convert(right, common);
+                match op {
+                    _ if op.is_comparison() => {
+                        format!["(({} {} {}) as i32)", left, op.text(), right]
+                    }
+                    BinaryOp::Divide | BinaryOp::Remainder
+                        if common.is_integer() || self.float_division == FloatDivision::Error =>
+                    {
+                        let line = self.program.exprs[expression].span.line;
+                        let name = match op {
+                            BinaryOp::Divide => "divide",
+                            _ => "remainder",
+                        };
+                        format![
+                            "runtime::{}_{}({}, {}, {})",
+                            name,
+                            rust_type(common),
+                            left,
+                            right,
+                            line
+                        ]
+                    }
+                    _ if common.is_integer() => {
+                        format!["{}.{}({})", left, wrapping(op), right]
+                    }
+                    _ => format!["({} {} {})", left, op.text(), right],
+                }
+            }
+        }
+    }
+
+    // the function of the builtin with the arguments as floats, its helper comes after main
+    fn call(&mut self, name: &str, arguments: &[ExprId]) -> String {
+        let builtin = self
+            .builtins
+            .get(name)
+            .expect("the parser only accepts known builtins");
+        let Some(implementation) = builtin.implementation(Platform::Rust) else {
+            self.unsupported.get_or_insert_with(|| name.to_owned());
+            return String::new();
+        };
+        if !self.helpers_used.contains(&implementation.helper) {
+            self.helpers_used.push(implementation.helper);
+        }
+        let arguments: Vec<String> = arguments
+            .iter()
+            .map(|argument| self.convert(*argument, CType::Float))
+            .collect();
+        format!["{}({})", implementation.function, arguments.join(", ")]
+    }
+
+    // the expression as another type, like c converts it for a calculation or a LET
+    fn convert(&mut self, expression: ExprId, to: CType) -> String {
+        let from = ctype::of(self.program, expression);
+        let code = self.expression(expression);
+        if from == to {
+            code
+        } else {
+            format!["({} as {})", code, rust_type(to)]
+        }
+    }
+
+    // a name rust already uses gets a _ after it, no name in a program can end in one
+    fn name(&self, symbol: Symbol) -> String {
+        let name = self.program.names.resolve(symbol);
+        if RESERVED.contains(&name) {
+            format!["{}_", name]
+        } else {
+            name.to_owned()
+        }
+    }
+}
+
+fn rust_type(ty: CType) -> &'static str {
+    match ty {
+        CType::Int => "i32",
+        CType::Long => "i64",
+        CType::Float => "f32",
+        CType::Double => "f64",
+    }
+}
+
+fn zero(ty: CType) -> String {
+    match ty {
+        CType::Int | CType::Long => format!["0{}", rust_type(ty)],
+        CType::Float | CType::Double => format!["0.0{}", rust_type(ty)],
+    }
+}
+
+fn wrapping(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "wrapping_add",
+        BinaryOp::Subtract => "wrapping_sub",
+        _ => "wrapping_mul",
+    }
+}
+
+// every variable, in the order they first get a value
+fn variables(statements: &[Statement]) -> Vec<Symbol> {
+    fn collect(statements: &[Statement], variables: &mut Vec<Symbol>) {
+        for statement in statements {
+            match &statement.kind {
+                StatementKind::Let { variable, .. } | StatementKind::Input(variable)
+                    if !variables.contains(variable) =>
+                {
+                    variables.push(*variable)
+                }
+                StatementKind::If { body, .. } | StatementKind::While { body, .. } => {
+                    collect(body, variables)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut variables = vec![];
+    collect(statements, &mut variables);
+    variables
+}
+
+// the Cargo.toml next to main.rs, the package is named after the program. it has no
+// dependencies and is a workspace of its own, so it also builds in the directory of another one
+pub fn manifest(source: &str) -> String {
+    let stem = source.rsplit(['/', '\\']).next().unwrap_or(source);
+    let stem = stem.split('.').next().unwrap_or(stem);
+    // cargo takes letters, digits, - and _, and no digit at the start. <stdin> is stdin
+    let name: String = stem
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() || c == '-' {
+            true => c,
+            false => '_',
+        })
+        .collect();
+    let name = match name.trim_matches(['_', '-']) {
+        "" => String::from("haneul"),
+        name if name.starts_with(|c: char| c.is_ascii_digit()) => format!["haneul_{}", name],
+        name => name.to_owned(),
+    };
+    format![
+        "# made by haneul\n\
+         [package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+         [[bin]]\nname = \"{name}\"\npath = \"main.rs\"\n\n\
+         [workspace]\n"
+    ]
+}
+
+// the keywords, `_` is one too
+const RESERVED: &[&str] = &[
+    "_", "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true",
+    "type", "unsafe", "use", "where", "while", "abstract", "become", "box", "do", "final", "gen",
+    "macro", "override", "priv", "try", "typeof", "unsized", "virtual", "yield", "main",
+];
+
+// a variable can be given a value and never read, or read only in the next block
+const PRELUDE: &str = "// made by haneul
+#![allow(unused_mut, unused_assignments, unused_variables, unused_parens, unreachable_code)]
+#![allow(dead_code, non_snake_case)]
+
+";
+
+const RUNTIME: &str = r#"
+mod runtime {
+    use std::io::{self, BufRead, Write};
+
+    // the fewest digits that read back as the same float, without an exponent, like the c
+    // code: the first of 1 to 9 digits in the e notation that reads back as the float
+    pub fn print_number(value: f32) {
+        if value.is_nan() {
+            println!("nan");
+            return;
+        }
+        if value.is_infinite() {
+            println!("{}", if value > 0.0 { "inf" } else { "-inf" });
+            return;
+        }
+        let (precision, text) = (1..=9)
+            .map(|precision| (precision, format!("{:.*e}", precision - 1, value)))
+            .find(|(precision, text)| *precision == 9 || text.parse::<f32>() == Ok(value))
+            .unwrap();
+        let (digits, exponent) = text.split_once('e').unwrap();
+        let decimals = precision as i32 - 1 - exponent.parse::<i32>().unwrap();
+        if decimals > 0 {
+            println!("{:.*}", decimals as usize, value);
+        } else {
+            println!("{}{}", digits.replace('.', ""), "0".repeat(-decimals as usize));
+        }
+    }
+
+    fn fail(message: &str, line: u32) -> ! {
+        let _ = io::stdout().flush();
+        eprintln!("Error: {} on line {}", message, line);
+        std::process::exit(1)
+    }
+
+    // the smallest int divided by -1 wraps around like in the c code
+    pub fn divide_i32(left: i32, right: i32, line: u32) -> i32 {
+        if right == 0 {
+            fail("an int is divided by 0", line);
+        }
+        left.wrapping_div(right)
+    }
+
+    pub fn divide_i64(left: i64, right: i64, line: u32) -> i64 {
+        if right == 0 {
+            fail("an int is divided by 0", line);
+        }
+        left.wrapping_div(right)
+    }
+
+    // the sign of the left side like in c, the smallest int % -1 is 0
+    pub fn remainder_i32(left: i32, right: i32, line: u32) -> i32 {
+        if right == 0 {
+            fail("an int is divided by 0", line);
+        }
+        left.wrapping_rem(right)
+    }
+
+    pub fn remainder_i64(left: i64, right: i64, line: u32) -> i64 {
+        if right == 0 {
+            fail("an int is divided by 0", line);
+        }
+        left.wrapping_rem(right)
+    }
+
+    // only with --float-division=error
+    pub fn divide_f32(left: f32, right: f32, line: u32) -> f32 {
+        if right == 0.0 {
+            fail("a float is divided by 0", line);
+        }
+        left / right
+    }
+
+    pub fn divide_f64(left: f64, right: f64, line: u32) -> f64 {
+        if right == 0.0 {
+            fail("a float is divided by 0", line);
+        }
+        left / right
+    }
+
+    pub fn remainder_f32(left: f32, right: f32, line: u32) -> f32 {
+        if right == 0.0 {
+            fail("a float is divided by 0", line);
+        }
+        left % right
+    }
+
+    pub fn remainder_f64(left: f64, right: f64, line: u32) -> f64 {
+        if right == 0.0 {
+            fail("a float is divided by 0", line);
+        }
+        left % right
+    }
+
+    // like scanf("%f") in c: the number at the start of the next word, the rest of the word
+    // is read by the next INPUT. a word that doesn't start with a number gives 0, at the end
+    // of the input the variable keeps the value it had
+    pub struct Input {
+        pending: String,
+    }
+
+    impl Input {
+        pub fn new() -> Self {
+            Self {
+                pending: String::new(),
+            }
+        }
+
+        pub fn read(&mut self, variable: &mut f32) {
+            let _ = io::stdout().flush();
+            while self.pending.trim_start().is_empty() {
+                self.pending.clear();
+                match io::stdin().lock().read_line(&mut self.pending) {
+                    Ok(0) | Err(_) => return,
+                    Ok(_) => {}
+                }
+            }
+
+            let pending = self.pending.trim_start().to_owned();
+            let end = pending.find(char::is_whitespace).unwrap_or(pending.len());
+            let word = &pending[..end];
+            let number = (1..=word.len())
+                .rev()
+                .filter(|length| word.is_char_boundary(*length))
+                .find_map(|length| Some((length, word[..length].parse::<f32>().ok()?)));
+            match number {
+                Some((length, value)) => {
+                    *variable = value;
+                    self.pending = pending[length..].to_owned();
+                }
+                None => {
+                    *variable = 0.0;
+                    self.pending = pending[end..].to_owned();
+                }
+            }
+        }
+    }
+}
+"#;
@@ -0,0 +1,74 @@
+// --record and --replay of `haneul run`: what every INPUT gave, in a json file, so a run can be
+// done again exactly, like the one a bug was found in. --record writes it when the program
+// stops, also when it went wrong. with --replay the INPUTs give the recorded values instead of
+// reading the input
+//
+//   {"inputs":[1.5,0,null,"nan"]}
+//
+// null is an INPUT at the end of the input, which leaves the variable as it was
+
+use std::fs;
+
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    #[serde(with = "inputs")]
+    pub inputs: Vec<Option<f32>>,
+}
+
+impl Session {
+    pub fn load(file: &str) -> Result<Self, String> {
+        let text =
+            fs::read_to_string(file).map_err(|e| format!["couldn't read {}: {}", file, e])?;
+        serde_json::from_str(&text).map_err(|e| format!["{} isn't a session: {}", file, e])
+    }
+
+    pub fn save(&self, file: &str) -> Result<(), String> {
+        let json = serde_json::to_string(self).expect("a session is always json");
+        fs::write(file, format!["{}\n", json])
+            .map_err(|e| format!["couldn't write {}: {}", file, e])
+    }
+}
+
+// a float is exact as a double, so it reads back as the same float. inf and nan aren't json
+// numbers, they're written as strings
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Input {
+    Number(f32),
+    Text(String),
+}
+
+mod inputs {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        inputs: &[Option<f32>],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(inputs.iter().map(|input| {
+            input.map(|value| match value.is_finite() {
+                true => Input::Number(value),
+                false => Input::Text(value.to_string().to_lowercase()),
+            })
+        }))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Option<f32>>, D::Error> {
+        let inputs = Vec::<Option<Input>>::deserialize(deserializer)?;
+        inputs
+            .into_iter()
+            .map(|input| match input {
+                None => Ok(None),
+                Some(Input::Number(value)) => Ok(Some(value)),
+                Some(Input::Text(text)) => text
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| D::Error::custom(format!["not a number: {}", text])),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,110 @@
+// --snapshot and --resume of `haneul run`: the state of the vm in a json file. a program that
+// --max-steps or --timeout stops is saved with --snapshot, and --resume runs it on from there
+// with the same variables, stack and input, so a long program can go on after a restart
+//
+//   {"program":"9c0e5f2d1b7a4e83","pc":12,"stack":["0000000000000001"],
+//    "variables":{"a":"000000003fc00000"},"pending":" 3\n"}
+//
+// a snapshot is of the bytecode of one program, `program` is its checksum and it only resumes
+// that one. there's no call stack, a program has no functions. the tree interpreter has no
+// place it could resume from, so it's vm only
+
+use std::{collections::BTreeMap, fs};
+
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+// the 64 bit numbers are hex strings, a json number is a double
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    // hbc::checksum of the bytecode
+    #[serde(with = "hex")]
+    pub program: u64,
+    // the instruction that runs next
+    pub pc: usize,
+    // the values on the stack as bits, they don't know their type
+    #[serde(with = "hexes")]
+    pub stack: Vec<u64>,
+    // as bits like the stack, the program has the type of every variable. an object of the
+    // names in the json
+    #[serde(with = "hex_variables")]
+    pub variables: Vec<(String, u64)>,
+    // what was read but not used by an INPUT yet
+    pub pending: String,
+}
+
+impl Snapshot {
+    pub fn load(file: &str) -> Result<Self, String> {
+        let text =
+            fs::read_to_string(file).map_err(|e| format!["couldn't read {}: {}", file, e])?;
+        serde_json::from_str(&text).map_err(|e| format!["{} isn't a snapshot: {}", file, e])
+    }
+
+    pub fn save(&self, file: &str) -> Result<(), String> {
+        let json = serde_json::to_string(self).expect("a snapshot is always json");
+        fs::write(file, format!["{}\n", json])
+            .map_err(|e| format!["couldn't write {}: {}", file, e])
+    }
+}
+
+struct Hex(u64);
+
+impl Serialize for Hex {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&format_args!["{:016x}", self.0])
+    }
+}
+
+impl<'de> Deserialize<'de> for Hex {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        u64::from_str_radix(&text, 16)
+            .map(Hex)
+            .map_err(|_| D::Error::custom(format!["not a hex number: {}", text]))
+    }
+}
+
+mod hex {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(bits: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        Hex(*bits).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        Ok(Hex::deserialize(deserializer)?.0)
+    }
+}
+
+mod hexes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(stack: &[u64], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(stack.iter().map(|bits| Hex(*bits)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u64>, D::Error> {
+        let stack = Vec::<Hex>::deserialize(deserializer)?;
+        Ok(stack.into_iter().map(|bits| bits.0).collect())
+    }
+}
+
+mod hex_variables {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        variables: &[(String, u64)],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(variables.iter().map(|(name, bits)| (name, Hex(*bits))))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<(String, u64)>, D::Error> {
+        let variables = BTreeMap::<String, Hex>::deserialize(deserializer)?;
+        Ok(variables
+            .into_iter()
+            .map(|(name, bits)| (name, bits.0))
+            .collect())
+    }
+}
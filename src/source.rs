@@ -0,0 +1,60 @@
+// every file that takes part in a compilation lives in the SourceMap, spans point back
+// into it with a FileId so a diagnostic always knows which file it came from
+
+use crate::prelude::*;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FileId(usize);
+
+#[derive(Debug)]
+pub struct SourceFile {
+    name: String,
+    // None when the file was lexed while it was read, then there are no lines to show
+    text: Option<String>,
+}
+
+impl SourceFile {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn text(&self) -> &str {
+        self.text.as_deref().unwrap_or("")
+    }
+
+    // lines start at 1, like in spans
+    // lines() already leaves out the \r of a \r\n
+    pub fn line(&self, line: usize) -> Option<&str> {
+        let text = self.text.as_deref()?;
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        Some(text.lines().nth(line.saturating_sub(1)).unwrap_or(""))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, name: String, text: String) -> FileId {
+        self.files.push(SourceFile {
+            name,
+            text: Some(text),
+        });
+        FileId(self.files.len() - 1)
+    }
+
+    // for a file that is streamed instead of read up front
+    pub fn add_unread(&mut self, name: String) -> FileId {
+        self.files.push(SourceFile { name, text: None });
+        FileId(self.files.len() - 1)
+    }
+
+    pub fn get(&self, file: FileId) -> &SourceFile {
+        &self.files[file.0]
+    }
+}
@@ -0,0 +1,75 @@
+// source maps (version 3), so the browser's debugger and node's stack traces show the line of
+// the program a line of out.js comes from. only lines are mapped, every mapping is at the
+// start of a line in both files
+
+use serde::Serialize;
+
+// `positions` are the byte offset in `code` where a statement starts, with its line in the
+// program, in the order they are in `code`
+pub fn encode(code: &str, positions: &[(usize, usize)], file: &str, source: &str) -> String {
+    let mut mappings = String::new();
+    let mut positions = positions.iter().peekable();
+    // counted from 0 in a source map
+    let mut previous_line = 0;
+    let mut start = 0;
+    for (index, line) in code.split_inclusive('\n').enumerate() {
+        if index > 0 {
+            mappings.push(';');
+        }
+        let end = start + line.len();
+        // a line with more than one statement on it is mapped to the first one
+        let mut mapped = None;
+        while let Some((_, source_line)) = positions.next_if(|(offset, _)| *offset < end) {
+            mapped.get_or_insert(*source_line as i64 - 1);
+        }
+        if let Some(source_line) = mapped {
+            // the column in out.js, the source, the line and the column in the program, all
+            // but the column in out.js are relative to the segment before
+            mappings.push('A');
+            mappings.push('A');
+            vlq(source_line - previous_line, &mut mappings);
+            mappings.push('A');
+            previous_line = source_line;
+        }
+        start = end;
+    }
+
+    #[derive(Serialize)]
+    struct SourceMap<'a> {
+        version: u32,
+        file: &'a str,
+        sources: [&'a str; 1],
+        names: [&'a str; 0],
+        mappings: String,
+    }
+
+    let map = SourceMap {
+        version: 3,
+        file,
+        sources: [source],
+        names: [],
+        mappings,
+    };
+    serde_json::to_string(&map).expect("a source map is always json")
+}
+
+// base64 digits of 5 bits, the lowest first, with the sign in the lowest bit of the number
+fn vlq(value: i64, output: &mut String) {
+    const DIGITS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut rest = if value < 0 {
+        (-value as u64) << 1 | 1
+    } else {
+        (value as u64) << 1
+    };
+    loop {
+        let mut digit = rest & 0b11111;
+        rest >>= 5;
+        if rest > 0 {
+            digit |= 0b100000;
+        }
+        output.push(DIGITS[digit as usize] as char);
+        if rest == 0 {
+            break;
+        }
+    }
+}
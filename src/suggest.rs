@@ -0,0 +1,48 @@
+// "did you mean" suggestions for misspelled names
+
+use crate::prelude::*;
+
+// the closest candidate to `name`, if it is close enough to be a likely typo.
+// case is ignored, so `endwile` still finds `ENDWHILE`
+pub fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let name = name.to_uppercase();
+    // every one letter name is one edit away from every other one, so those are never suggested
+    let length = name.chars().count();
+    let max_distance = (length / 3).max(1).min(length.saturating_sub(1));
+
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(&name, &candidate.to_uppercase()), candidate))
+        .filter(|(distance, candidate)| *distance <= max_distance && *candidate != name)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+// levenshtein distance where swapping two neighbouring characters also counts as one edit,
+// since that is one of the most common typos
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in table.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in table[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            table[i][j] = (table[i - 1][j] + 1)
+                .min(table[i][j - 1] + 1)
+                .min(table[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                table[i][j] = table[i][j].min(table[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    table[a.len()][b.len()]
+}
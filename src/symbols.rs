@@ -0,0 +1,230 @@
+// the symbol table: every variable, label and builtin a program uses, with where it is used.
+// the analysis checks the program with it, and tools like an editor or a debugger can look
+// names up in it instead of walking the tree themselves
+
+use crate::{
+    ast::{ExprId, ExprKind, Exprs, Program, Statement, StatementKind},
+    builtins::{Registry, Type},
+    intern::Symbol,
+    prelude::*,
+    token::Span,
+    visit::{self, Visitor},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variable {
+    pub symbol: Symbol,
+    pub name: String,
+    pub ty: Type,
+    // the LET and INPUT statements that give it a value
+    pub assignments: Vec<Span>,
+    pub reads: Vec<Span>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub symbol: Symbol,
+    pub name: String,
+    // more than one is an error, but they are all kept so every one can be shown
+    pub declarations: Vec<Span>,
+    pub gotos: Vec<Span>,
+}
+
+// a builtin statement or function the program uses
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub params: &'static [Type],
+    pub returns: Option<Type>,
+    pub calls: Vec<Span>,
+}
+
+// what a name at some place in the program stands for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reference<'a> {
+    Variable(&'a Variable),
+    Label(&'a Label),
+    Function(&'a Function),
+}
+
+// everything is in the order it first comes up in the program, and all spans are too
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SymbolTable {
+    variables: Vec<Variable>,
+    labels: Vec<Label>,
+    functions: Vec<Function>,
+}
+
+impl SymbolTable {
+    pub fn new(program: &Program, builtins: &Registry) -> Self {
+        let mut builder = Builder {
+            program,
+            builtins,
+            table: SymbolTable::default(),
+        };
+        visit::walk_program(&mut builder, program);
+
+        builder.table
+    }
+
+    pub fn variables(&self) -> &[Variable] {
+        &self.variables
+    }
+
+    pub fn labels(&self) -> &[Label] {
+        &self.labels
+    }
+
+    pub fn functions(&self) -> &[Function] {
+        &self.functions
+    }
+
+    pub fn variable(&self, name: &str) -> Option<&Variable> {
+        self.variables.iter().find(|variable| variable.name == name)
+    }
+
+    pub fn label(&self, name: &str) -> Option<&Label> {
+        self.labels.iter().find(|label| label.name == name)
+    }
+
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+
+    // the name used at a byte offset in the source, for hovering over it or jumping to where
+    // it is declared. a read or a label is found by its name, an assignment or a call by
+    // the whole statement or call, so the innermost one wins
+    pub fn at(&self, offset: usize) -> Option<Reference<'_>> {
+        let mut uses = vec![];
+        for variable in &self.variables {
+            let spans = variable.reads.iter().chain(&variable.assignments);
+            uses.extend(spans.map(|span| (*span, Reference::Variable(variable))));
+        }
+        for label in &self.labels {
+            let spans = label.declarations.iter().chain(&label.gotos);
+            uses.extend(spans.map(|span| (*span, Reference::Label(label))));
+        }
+        for function in &self.functions {
+            uses.extend(
+                function
+                    .calls
+                    .iter()
+                    .map(|span| (*span, Reference::Function(function))),
+            );
+        }
+
+        uses.into_iter()
+            .filter(|(span, _)| (span.offset..span.offset + span.byte_length).contains(&offset))
+            .min_by_key(|(span, _)| span.byte_length)
+            .map(|(_, reference)| reference)
+    }
+}
+
+struct Builder<'a> {
+    program: &'a Program,
+    builtins: &'a Registry,
+    table: SymbolTable,
+}
+
+impl Builder<'_> {
+    fn variable(&mut self, symbol: Symbol) -> &mut Variable {
+        let index = match self.table.variables.iter().position(|v| v.symbol == symbol) {
+            Some(index) => index,
+            None => {
+                self.table.variables.push(Variable {
+                    symbol,
+                    name: self.program.names.resolve(symbol).to_owned(),
+                    // the only type a variable can have
+                    ty: Type::Number,
+                    assignments: vec![],
+                    reads: vec![],
+                });
+                self.table.variables.len() - 1
+            }
+        };
+
+        &mut self.table.variables[index]
+    }
+
+    fn label(&mut self, symbol: Symbol) -> &mut Label {
+        let index = match self.table.labels.iter().position(|l| l.symbol == symbol) {
+            Some(index) => index,
+            None => {
+                self.table.labels.push(Label {
+                    symbol,
+                    name: self.program.names.resolve(symbol).to_owned(),
+                    declarations: vec![],
+                    gotos: vec![],
+                });
+                self.table.labels.len() - 1
+            }
+        };
+
+        &mut self.table.labels[index]
+    }
+
+    // unknown builtins are already reported by the parser, they aren't in the table
+    fn call(&mut self, name: &str, span: Span) {
+        if let Some(function) = self.table.functions.iter_mut().find(|f| f.name == name) {
+            function.calls.push(span);
+            return;
+        }
+        if let Some(builtin) = self.builtins.get(name) {
+            self.table.functions.push(Function {
+                name: name.to_owned(),
+                params: builtin.params,
+                returns: builtin.returns,
+                calls: vec![span],
+            });
+        }
+    }
+}
+
+impl Visitor for Builder<'_> {
+    fn visit_stmt(&mut self, exprs: &Exprs, statement: &Statement) {
+        match &statement.kind {
+            StatementKind::Let { variable, value } => {
+                // the value is read before the variable gets it
+                self.visit_expr(exprs, *value);
+                self.variable(*variable).assignments.push(statement.span);
+                return;
+            }
+            StatementKind::Input(variable) => {
+                self.variable(*variable).assignments.push(statement.span)
+            }
+            StatementKind::Label(label) => {
+                let span = name_span(statement.span, self.program.names.resolve(*label));
+                self.label(*label).declarations.push(span);
+            }
+            StatementKind::Goto(label) => {
+                let span = name_span(statement.span, self.program.names.resolve(*label));
+                self.label(*label).gotos.push(span);
+            }
+            StatementKind::Call { name, .. } => self.call(name, statement.span),
+            _ => {}
+        }
+        visit::walk_stmt(self, exprs, statement);
+    }
+
+    fn visit_expr(&mut self, exprs: &Exprs, expression: ExprId) {
+        let span = exprs[expression].span;
+        match &exprs[expression].kind {
+            ExprKind::Variable(variable) => self.variable(*variable).reads.push(span),
+            ExprKind::Call { name, .. } => self.call(name, span),
+            _ => {}
+        }
+        visit::walk_expr(self, exprs, expression);
+    }
+}
+
+// the name at the end of a LABEL or GOTO
+fn name_span(statement: Span, name: &str) -> Span {
+    let length = name.chars().count();
+    Span {
+        column: (statement.column + statement.length).saturating_sub(length),
+        length,
+        offset: (statement.offset + statement.byte_length).saturating_sub(name.len()),
+        byte_length: name.len(),
+        ..statement
+    }
+}
@@ -0,0 +1,148 @@
+// --timings, how long every phase of compiling took and how much memory it needed, printed
+// when compiling is done:
+//
+// phase          time    peak memory
+// lex          0.06ms       15.8 KiB
+// parse        0.11ms       21.1 KiB
+// ...
+// gcc         36.05ms       13.7 KiB
+//
+// the peak memory is everything the compiler had allocated at the worst moment of the phase,
+// not only what the phase allocated itself. it's counted from when --timings is read, without
+// it the allocator only looks at a flag
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+// counts the bytes that are allocated, for the peak memory
+struct Counting;
+
+static COUNTING: AtomicBool = AtomicBool::new(false);
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+// for --timings, the allocations before it aren't counted
+pub fn count_memory() {
+    COUNTING.store(true, Ordering::Relaxed);
+}
+
+fn allocated(size: usize) {
+    if !COUNTING.load(Ordering::Relaxed) {
+        return;
+    }
+    let current = CURRENT.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(current, Ordering::Relaxed);
+}
+
+fn freed(size: usize) {
+    if !COUNTING.load(Ordering::Relaxed) {
+        return;
+    }
+    // what was allocated before counting started can be freed after it
+    let _ = CURRENT.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+        Some(current.saturating_sub(size))
+    });
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let pointer = System.alloc(layout);
+        if !pointer.is_null() {
+            allocated(layout.size());
+        }
+        pointer
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let pointer = System.alloc_zeroed(layout);
+        if !pointer.is_null() {
+            allocated(layout.size());
+        }
+        pointer
+    }
+
+    unsafe fn realloc(&self, pointer: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_pointer = System.realloc(pointer, layout, new_size);
+        if !new_pointer.is_null() {
+            freed(layout.size());
+            allocated(new_size);
+        }
+        new_pointer
+    }
+
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        System.dealloc(pointer, layout);
+        freed(layout.size());
+    }
+}
+
+struct Phase {
+    name: &'static str,
+    time: Duration,
+    peak: usize,
+}
+
+pub struct Timings {
+    enabled: bool,
+    phases: Vec<Phase>,
+}
+
+impl Timings {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            phases: vec![],
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    // runs a phase, and remembers how long it took when the timings are shown
+    pub fn time<T>(&mut self, name: &'static str, phase: impl FnOnce() -> T) -> T {
+        if !self.enabled {
+            return phase();
+        }
+
+        PEAK.store(CURRENT.load(Ordering::Relaxed), Ordering::Relaxed);
+        let start = Instant::now();
+        let result = phase();
+        self.phases.push(Phase {
+            name,
+            time: start.elapsed(),
+            peak: PEAK.load(Ordering::Relaxed),
+        });
+
+        result
+    }
+
+    pub fn report(&self) {
+        if !self.enabled {
+            return;
+        }
+
+        let width = self
+            .phases
+            .iter()
+            .map(|phase| phase.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("phase".len());
+        eprintln!("{:width$} {:>10} {:>14}", "phase", "time", "peak memory");
+        for phase in &self.phases {
+            eprintln!(
+                "{:width$} {:>8.2}ms {:>10.1} KiB",
+                phase.name,
+                phase.time.as_secs_f64() * 1000.0,
+                phase.peak as f64 / 1024.0
+            );
+        }
+    }
+}
@@ -1,12 +1,89 @@
+use alloc::borrow::Cow;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{keywords::KEYWORDS, prelude::*, source::FileId};
+
+// the text is borrowed from the source when there is one, so lexing doesn't allocate. a token
+// that's deserialized owns its text
 #[derive(Debug, Default, Clone)]
-pub struct Token {
-    text: String,
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Token<'src> {
+    text: Cow<'src, str>,
     kind: TokenType,
+    span: Span,
+    // the whitespace, comments and unreadable text in front of the token, only kept by a
+    // lossless lexer
+    trivia: Cow<'src, str>,
 }
 
-impl Token {
-    pub fn new(text: String, kind: TokenType) -> Self {
-        Self { text, kind }
+// lines and columns start at 1, the length is in characters
+// offset and byte_length index into the original source string, for editors that want byte ranges
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Span {
+    // only an index into the SourceMap of the compiler that made it, so it isn't serialized. a
+    // deserialized span points into the first file
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub file: FileId,
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+    pub offset: usize,
+    pub byte_length: usize,
+}
+
+impl Span {
+    // from the start of this span to the end of `end`. only the part on the first line counts
+    // for the length, that's the part a diagnostic underlines
+    pub fn to(self, end: Span) -> Span {
+        let length = if end.line == self.line {
+            (end.column + end.length).saturating_sub(self.column)
+        } else {
+            self.length
+        };
+        Span {
+            length,
+            byte_length: (end.offset + end.byte_length).saturating_sub(self.offset),
+            ..self
+        }
+    }
+}
+
+impl<'src> Token<'src> {
+    pub fn new(text: impl Into<Cow<'src, str>>, kind: TokenType) -> Self {
+        Self {
+            text: text.into(),
+            kind,
+            span: Span::default(),
+            trivia: Cow::Borrowed(""),
+        }
+    }
+
+    pub fn with_span(self, span: Span) -> Self {
+        Self { span, ..self }
+    }
+
+    pub fn with_trivia(self, trivia: impl Into<Cow<'src, str>>) -> Self {
+        Self {
+            trivia: trivia.into(),
+            ..self
+        }
+    }
+
+    // tokens that are always written the same way don't need their own copy of the text
+    pub fn into_owned(self) -> Token<'static> {
+        let text = match self.kind.fixed_text() {
+            Some(text) => Cow::Borrowed(text),
+            None => Cow::Owned(self.text.into_owned()),
+        };
+        Token {
+            text,
+            kind: self.kind,
+            span: self.span,
+            trivia: Cow::Owned(self.trivia.into_owned()),
+        }
     }
 
     pub fn kind(&self) -> TokenType {
@@ -15,17 +92,73 @@ impl Token {
     pub fn text(&self) -> &str {
         &self.text
     }
+    pub fn span(&self) -> Span {
+        self.span
+    }
+    pub fn trivia(&self) -> &str {
+        &self.trivia
+    }
+
+    // the token like it is written in the source, the trivia and source text of all tokens
+    // of a lossless lexer together are the whole source again
+    pub fn source_text(&self) -> Cow<'_, str> {
+        match self.kind {
+            // the newline the lexer adds has no text, the others are \n or \r\n
+            TokenType::Newline => Cow::Borrowed(match self.span.byte_length {
+                0 => "",
+                1 => "\n",
+                _ => "\r\n",
+            }),
+            TokenType::Eof => Cow::Borrowed(""),
+            TokenType::String => Cow::Owned(format!["\"{}\"", self.text]),
+            _ => Cow::Borrowed(&self.text),
+        }
+    }
 }
 
+// serialized as its name, like "EndWhile"
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[rustfmt::skip]
 pub enum TokenType {
     Eof, Newline, Number, Ident, String, 
     // keywords
     Label, Goto, Print, Input, Let, If, Then, Endif, While, Repeat, EndWhile,
     // operators
-    Eq, Plus, Minus, Asterisk, Slash, EqEq, NotEq, Lt, LtEq, Gt, GtEq,
+    Eq, Plus, Minus, Asterisk, Slash, Percent, EqEq, NotEq, Lt, LtEq, Gt, GtEq, LParen, RParen, Comma,
     // so I don't need options everywhere 
     #[default]
     None
 }
+
+impl TokenType {
+    // the text of keywords, operators, newlines and the end of the file
+    pub fn fixed_text(self) -> Option<&'static str> {
+        let text = match self {
+            TokenType::Eof => "\0",
+            TokenType::Newline => "\n",
+            TokenType::Eq => "=",
+            TokenType::Plus => "+",
+            TokenType::Minus => "-",
+            TokenType::Asterisk => "*",
+            TokenType::Slash => "/",
+            TokenType::Percent => "%",
+            TokenType::EqEq => "==",
+            TokenType::NotEq => "!=",
+            TokenType::Lt => "<",
+            TokenType::LtEq => "<=",
+            TokenType::Gt => ">",
+            TokenType::GtEq => ">=",
+            TokenType::LParen => "(",
+            TokenType::RParen => ")",
+            TokenType::Comma => ",",
+            kind => {
+                return KEYWORDS
+                    .iter()
+                    .find(|(_, keyword)| *keyword == kind)
+                    .map(|(text, _)| *text)
+            }
+        };
+        Some(text)
+    }
+}
@@ -0,0 +1,112 @@
+// the type checker. every value in haneul is a number, except the comparisons in the
+// condition of an IF or a WHILE. the grammar keeps most mixups out already, but a chain
+// like a < b < c still compares the result of a comparison, and a tree from a tool can
+// have anything in it, so the types are checked before any c is made
+
+use crate::{
+    ast::{ExprId, ExprKind, Exprs, Program, Statement, StatementKind},
+    builtins::{Registry, Type},
+    codes,
+    diagnostics::Diagnostic,
+    prelude::*,
+};
+
+pub fn check(program: &Program, builtins: &Registry) -> Vec<Diagnostic> {
+    let mut checker = Checker {
+        exprs: &program.exprs,
+        builtins,
+        errors: vec![],
+    };
+    checker.statements(&program.statements);
+
+    checker.errors
+}
+
+struct Checker<'a> {
+    exprs: &'a Exprs,
+    builtins: &'a Registry,
+    errors: Vec<Diagnostic>,
+}
+
+impl Checker<'_> {
+    fn statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match &statement.kind {
+            StatementKind::Print(value) | StatementKind::Let { value, .. } => {
+                self.expect(*value, Type::Number)
+            }
+            StatementKind::If { condition, body } | StatementKind::While { condition, body } => {
+                self.expect(*condition, Type::Boolean);
+                self.statements(body);
+            }
+            StatementKind::Call { name, arguments } => self.arguments(name, arguments),
+            StatementKind::PrintString(_)
+            | StatementKind::Label(_)
+            | StatementKind::Goto(_)
+            | StatementKind::Input(_) => {}
+        }
+    }
+
+    // the type of an expression. a part with the wrong type is reported and counted as the
+    // type it should have had, so one mistake isn't reported again by everything around it
+    fn infer(&mut self, expression: ExprId) -> Type {
+        match &self.exprs[expression].kind {
+            ExprKind::Number(_) | ExprKind::Variable(_) => Type::Number,
+            ExprKind::Call { name, arguments } => {
+                self.arguments(name, arguments);
+                self.builtins
+                    .get(name)
+                    .and_then(|builtin| builtin.returns)
+                    .unwrap_or(Type::Number)
+            }
+            ExprKind::Unary { operand, .. } => {
+                self.expect(*operand, Type::Number);
+                Type::Number
+            }
+            ExprKind::Binary { op, left, right } => {
+                self.expect(*left, Type::Number);
+                self.expect(*right, Type::Number);
+                if op.is_comparison() {
+                    Type::Boolean
+                } else {
+                    Type::Number
+                }
+            }
+        }
+    }
+
+    fn expect(&mut self, expression: ExprId, expected: Type) {
+        let found = self.infer(expression);
+        if found == expected {
+            return;
+        }
+
+        let code = match expected {
+            Type::Number => codes::COMPARISON_AS_NUMBER,
+            Type::Boolean => codes::NUMBER_AS_CONDITION,
+        };
+        self.errors
+            .push(Diagnostic::error(code, vec![], self.exprs[expression].span));
+    }
+
+    // unknown builtins are already reported by the parser
+    fn arguments(&mut self, name: &str, arguments: &[ExprId]) {
+        let params = self
+            .builtins
+            .get(name)
+            .map_or(&[][..], |builtin| builtin.params);
+        for (i, argument) in arguments.iter().enumerate() {
+            match params.get(i) {
+                Some(param) => self.expect(*argument, *param),
+                None => {
+                    self.infer(*argument);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,128 @@
+// walking the tree. a pass implements Visitor (or VisitorMut if it changes the tree) and only
+// overrides the methods for the nodes it cares about, the walk_ functions do the rest.
+// an overridden method that still wants to look at the children calls the walk_ function itself.
+// the expressions are looked up in `exprs`, the arena of the program they're in
+
+use crate::ast::{ExprId, ExprKind, Exprs, Program, Statement, StatementKind};
+
+pub trait Visitor {
+    fn visit_stmt(&mut self, exprs: &Exprs, statement: &Statement) {
+        walk_stmt(self, exprs, statement);
+    }
+
+    fn visit_expr(&mut self, exprs: &Exprs, expression: ExprId) {
+        walk_expr(self, exprs, expression);
+    }
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(visitor: &mut V, program: &Program) {
+    for statement in &program.statements {
+        visitor.visit_stmt(&program.exprs, statement);
+    }
+}
+
+pub fn walk_stmt<V: Visitor + ?Sized>(visitor: &mut V, exprs: &Exprs, statement: &Statement) {
+    match &statement.kind {
+        StatementKind::Print(value) => visitor.visit_expr(exprs, *value),
+        StatementKind::If { condition, body } | StatementKind::While { condition, body } => {
+            visitor.visit_expr(exprs, *condition);
+            for statement in body {
+                visitor.visit_stmt(exprs, statement);
+            }
+        }
+        StatementKind::Let { value, .. } => visitor.visit_expr(exprs, *value),
+        StatementKind::Call { arguments, .. } => {
+            for argument in arguments {
+                visitor.visit_expr(exprs, *argument);
+            }
+        }
+        StatementKind::PrintString(_)
+        | StatementKind::Label(_)
+        | StatementKind::Goto(_)
+        | StatementKind::Input(_) => {}
+    }
+}
+
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, exprs: &Exprs, expression: ExprId) {
+    match &exprs[expression].kind {
+        ExprKind::Call { arguments, .. } => {
+            for argument in arguments {
+                visitor.visit_expr(exprs, *argument);
+            }
+        }
+        ExprKind::Unary { operand, .. } => visitor.visit_expr(exprs, *operand),
+        ExprKind::Binary { left, right, .. } => {
+            visitor.visit_expr(exprs, *left);
+            visitor.visit_expr(exprs, *right);
+        }
+        ExprKind::Number(_) | ExprKind::Variable(_) => {}
+    }
+}
+
+// the same, for passes that rewrite the tree in place
+pub trait VisitorMut {
+    fn visit_stmt_mut(&mut self, exprs: &mut Exprs, statement: &mut Statement) {
+        walk_stmt_mut(self, exprs, statement);
+    }
+
+    fn visit_expr_mut(&mut self, exprs: &mut Exprs, expression: ExprId) {
+        walk_expr_mut(self, exprs, expression);
+    }
+}
+
+pub fn walk_program_mut<V: VisitorMut + ?Sized>(visitor: &mut V, program: &mut Program) {
+    for statement in &mut program.statements {
+        visitor.visit_stmt_mut(&mut program.exprs, statement);
+    }
+}
+
+pub fn walk_stmt_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    exprs: &mut Exprs,
+    statement: &mut Statement,
+) {
+    match &mut statement.kind {
+        StatementKind::Print(value) => visitor.visit_expr_mut(exprs, *value),
+        StatementKind::If { condition, body } | StatementKind::While { condition, body } => {
+            visitor.visit_expr_mut(exprs, *condition);
+            for statement in body {
+                visitor.visit_stmt_mut(exprs, statement);
+            }
+        }
+        StatementKind::Let { value, .. } => visitor.visit_expr_mut(exprs, *value),
+        StatementKind::Call { arguments, .. } => {
+            for argument in arguments {
+                visitor.visit_expr_mut(exprs, *argument);
+            }
+        }
+        StatementKind::PrintString(_)
+        | StatementKind::Label(_)
+        | StatementKind::Goto(_)
+        | StatementKind::Input(_) => {}
+    }
+}
+
+// the children are copied out first, the visitor gets the arena to change them
+pub fn walk_expr_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    exprs: &mut Exprs,
+    expression: ExprId,
+) {
+    match &exprs[expression].kind {
+        ExprKind::Call { arguments, .. } => {
+            for argument in arguments.clone() {
+                visitor.visit_expr_mut(exprs, argument);
+            }
+        }
+        ExprKind::Unary { operand, .. } => {
+            let operand = *operand;
+            visitor.visit_expr_mut(exprs, operand);
+        }
+        ExprKind::Binary { left, right, .. } => {
+            let (left, right) = (*left, *right);
+            visitor.visit_expr_mut(exprs, left);
+            visitor.visit_expr_mut(exprs, right);
+        }
+        ExprKind::Number(_) | ExprKind::Variable(_) => {}
+    }
+}
@@ -0,0 +1,368 @@
+// runs the bytecode of a program (see bytecode.rs), for `haneul run`. the numbers and what is
+// done with them are in runtime.rs, the interpreter uses the same ones. the stack holds them
+// as Raw, without their type, the instruction says what it is
+//
+// the dispatch is one match with an arm for every calculation, so each one is made into its own
+// code. what doesn't happen in a loop (printing, INPUT, the error) is kept out of it. a table of
+// a function for every instruction and fusing a comparison with the jump after it were both
+// tried, they were slower than the match
+//
+// when the Limits stop the program it's left before the jump that stopped it, so it can be
+// saved as a Snapshot and run on from there later
+
+use std::io::{BufWriter, Write};
+
+use crate::{
+    ast::BinaryOp,
+    bytecode::{Chunk, Instruction},
+    ctype::{self, CType},
+    hbc,
+    runtime::{
+        self, CancellationToken, ErrorKind, ExecutionPolicy, Guard, Input, Limits, Native, Output,
+        Raw, RuntimeError, Value,
+    },
+    snapshot::Snapshot,
+};
+
+pub struct Vm<'a> {
+    chunk: &'a Chunk,
+    pc: usize,
+    // where the instructions that ran since the last jump start
+    entered: usize,
+    // the instructions before the conditional jumps since the last Jump, see count
+    skipped: u64,
+    constants: Vec<Raw>,
+    stack: Vec<Raw>,
+    // without their type like the stack, the chunk has it
+    variables: Vec<Raw>,
+    policy: ExecutionPolicy,
+    // about how many bytes the chunk takes up, see memory
+    footprint: usize,
+    cancellation: CancellationToken,
+    guard: Guard,
+    // the Limits stopped the last run
+    stopped: bool,
+    input: Input,
+    output: Output<'a>,
+}
+
+impl<'a> Vm<'a> {
+    pub fn new(chunk: &'a Chunk) -> Self {
+        Self {
+            chunk,
+            pc: 0,
+            entered: 0,
+            skipped: 0,
+            constants: chunk
+                .constants
+                .iter()
+                .map(|value| Raw::new(*value))
+                .collect(),
+            stack: vec![],
+            // 0 of every type
+            variables: vec![Raw::from_bits(0); chunk.variables.len()],
+            policy: ExecutionPolicy::default(),
+            footprint: footprint(chunk),
+            cancellation: CancellationToken::new(),
+            guard: Guard::new(&Limits::default(), &CancellationToken::new()),
+            stopped: false,
+            input: Input::new(),
+            output: runtime::stdout(),
+        }
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.policy.limits = limits;
+    }
+
+    // for a program that isn't trusted: its limits and how much memory it may take up. the
+    // builtins the vm runs (see runtime::Native) don't reach outside of the program, so a chunk
+    // never needs more than that
+    pub fn set_policy(&mut self, policy: ExecutionPolicy) {
+        self.policy = policy;
+    }
+
+    // cancelling it from another thread stops the run, see CancellationToken
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
+    pub fn set_input(&mut self, input: Input) {
+        self.input = input;
+    }
+
+    // what the program PRINTs goes to `output` instead of stdout, like a Vec<u8> that a test
+    // looks at after the run. it gets every write when it's made, it isn't buffered like stdout
+    pub fn set_output(&mut self, output: impl Write + Send + 'a) {
+        self.output = BufWriter::with_capacity(0, Box::new(output));
+    }
+
+    // after a run, what the INPUTs gave with --record
+    pub fn recorded(&self) -> Option<&[Option<f32>]> {
+        self.input.recorded()
+    }
+
+    pub fn stopped(&self) -> bool {
+        self.stopped
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            program: hbc::checksum(&hbc::encode(self.chunk)),
+            pc: self.pc,
+            stack: self.stack.iter().map(|raw| raw.bits()).collect(),
+            variables: self
+                .chunk
+                .variables
+                .iter()
+                .map(|(name, _)| name.clone())
+                .zip(self.variables.iter().map(|raw| raw.bits()))
+                .collect(),
+            pending: self.input.pending().to_owned(),
+        }
+    }
+
+    // every variable with its value, in the type it has
+    pub fn variables(&self) -> Vec<(String, Value)> {
+        self.chunk
+            .variables
+            .iter()
+            .zip(&self.variables)
+            .map(|((name, ctype), raw)| (name.clone(), raw.get(*ctype)))
+            .collect()
+    }
+
+    // the value a variable has after a run, like Interpreter::get
+    pub fn get(&self, name: &str) -> Option<Value> {
+        let slot = self.chunk.variables.iter().position(|(v, _)| v == name)?;
+        Some(self.variables[slot].get(self.chunk.variables[slot].1))
+    }
+
+    // the next run goes on where the snapshot was made
+    pub fn resume(&mut self, snapshot: &Snapshot) -> Result<(), String> {
+        if snapshot.program != hbc::checksum(&hbc::encode(self.chunk)) {
+            return Err(String::from(
+                "it's of another program, or of another version of it",
+            ));
+        }
+        if snapshot.pc >= self.chunk.code.len() {
+            return Err(format!["there is no instruction {}", snapshot.pc]);
+        }
+        // the instructions after it pop what's on the stack
+        let depth = hbc::depths(self.chunk)?[snapshot.pc];
+        if depth != Some(snapshot.stack.len()) {
+            return Err(format![
+                "it has {} values on the stack at instruction {}, which the program doesn't",
+                snapshot.stack.len(),
+                snapshot.pc
+            ]);
+        }
+        for (name, bits) in &snapshot.variables {
+            let slot = self
+                .chunk
+                .variables
+                .iter()
+                .position(|(variable, _)| variable == name)
+                .ok_or_else(|| format!["the program has no variable {}", name])?;
+            self.variables[slot] = Raw::from_bits(*bits);
+        }
+        self.pc = snapshot.pc;
+        self.entered = self.pc;
+        self.skipped = 0;
+        self.stack = snapshot
+            .stack
+            .iter()
+            .map(|bits| Raw::from_bits(*bits))
+            .collect();
+        self.input.set_pending(snapshot.pending.clone());
+        Ok(())
+    }
+
+    // for --watch, the variables and the input of the version of the program before this one.
+    // the variables this one has too keep their value, when it fits in the type they have now:
+    // an int can become a float, a float that became an int starts at 0. the next run starts at
+    // the first instruction of the line, or at the top without one
+    pub fn reload(&mut self, variables: &[(String, Value)], pending: String, line: Option<usize>) {
+        for (name, value) in variables {
+            let slot = self.chunk.variables.iter().position(|(v, _)| v == name);
+            if let Some(slot) = slot {
+                let ctype = self.chunk.variables[slot].1;
+                if value.ctype() <= ctype {
+                    self.variables[slot] = Raw::new(value.convert(ctype));
+                }
+            }
+        }
+        self.pc = line
+            .and_then(|line| self.chunk.lines.iter().position(|l| *l == line))
+            .unwrap_or(0);
+        self.entered = self.pc;
+        self.skipped = 0;
+        self.stack.clear();
+        self.input.set_pending(pending);
+    }
+
+    pub fn run(&mut self) -> Result<(), RuntimeError> {
+        // the clock of the timeout starts here
+        self.guard = Guard::new(&self.policy.limits, &self.cancellation);
+        self.stopped = false;
+        let line = self.chunk.lines.get(self.pc).copied().unwrap_or(1);
+        self.policy.check_memory(self.memory(), line)?;
+        let result = self.dispatch();
+        let _ = self.output.flush();
+        result
+    }
+
+    fn dispatch(&mut self) -> Result<(), RuntimeError> {
+        loop {
+            let instruction = self.chunk.code[self.pc];
+            self.pc += 1;
+            match instruction {
+                Instruction::Constant(index) => {
+                    self.stack.push(self.constants[index as usize]);
+                }
+                Instruction::Load(slot) => {
+                    self.stack.push(self.variables[slot as usize]);
+                }
+                Instruction::Store(slot) => {
+                    self.variables[slot as usize] = self
+                        .stack
+                        .pop()
+                        .expect("the compiler only pops what it pushed");
+                }
+                Instruction::Convert(from, to) => {
+                    let value = self.pop(from);
+                    self.stack.push(Raw::new(value.convert(to)));
+                }
+                Instruction::Negate(ctype) => {
+                    let value = self.pop(ctype);
+                    self.stack.push(Raw::new(value.negate()));
+                }
+                Instruction::Jump(target) => {
+                    self.count()?;
+                    self.pc = target as usize;
+                    self.entered = self.pc;
+                }
+                Instruction::JumpIfZero(target) => {
+                    self.skipped += (self.pc - self.entered) as u64;
+                    if self.pop(CType::Int).is_zero() {
+                        self.pc = target as usize;
+                    }
+                    self.entered = self.pc;
+                }
+                Instruction::Print(ctype) => self.print(ctype),
+                Instruction::PrintString(index) => self.print_string(index),
+                Instruction::Input(slot) => {
+                    self.input(slot);
+                    let line = self.chunk.lines[self.pc - 1];
+                    self.policy.check_memory(self.memory(), line)?;
+                }
+                Instruction::Call(native) => self.call(native),
+                Instruction::Halt => return Ok(()),
+                Instruction::Add(ctype) => self.binary(BinaryOp::Add, ctype)?,
+                Instruction::Subtract(ctype) => self.binary(BinaryOp::Subtract, ctype)?,
+                Instruction::Multiply(ctype) => self.binary(BinaryOp::Multiply, ctype)?,
+                Instruction::Divide(ctype) => self.binary(BinaryOp::Divide, ctype)?,
+                Instruction::Remainder(ctype) => self.binary(BinaryOp::Remainder, ctype)?,
+                Instruction::Equal(ctype) => self.binary(BinaryOp::Equal, ctype)?,
+                Instruction::NotEqual(ctype) => self.binary(BinaryOp::NotEqual, ctype)?,
+                Instruction::Less(ctype) => self.binary(BinaryOp::Less, ctype)?,
+                Instruction::LessEqual(ctype) => self.binary(BinaryOp::LessEqual, ctype)?,
+                Instruction::Greater(ctype) => self.binary(BinaryOp::Greater, ctype)?,
+                Instruction::GreaterEqual(ctype) => self.binary(BinaryOp::GreaterEqual, ctype)?,
+            }
+        }
+    }
+
+    // about how many bytes the program takes up now: the chunk, the stack and the line of input
+    // it's reading
+    fn memory(&self) -> usize {
+        self.footprint + self.stack.len() * size_of::<Raw>() + self.input.pending().len()
+    }
+
+    // every instruction calls this with its own op, so it's made into a version for each
+    #[inline(always)]
+    fn binary(&mut self, op: BinaryOp, ctype: CType) -> Result<(), RuntimeError> {
+        let right = self.pop(ctype);
+        let left = self.pop(ctype);
+        let value = runtime::binary(op, left, right, self.chunk.float_division)
+            .ok_or_else(|| self.divided_by_zero(ctype))?;
+        self.stack.push(Raw::new(value));
+        Ok(())
+    }
+
+    // the steps are counted at a Jump, the end of a WHILE or a GOTO, every loop has one, so the
+    // other instructions don't have to count themselves. the interpreter counts at the same
+    // statements, so the two stop a program that runs too long on the same line
+    fn count(&mut self) -> Result<(), RuntimeError> {
+        let steps = core::mem::take(&mut self.skipped) + (self.pc - self.entered) as u64;
+        if self.guard.take(steps) {
+            let line = self.chunk.lines[self.pc - 1];
+            return self.policy.check_memory(self.memory(), line);
+        }
+        let error = self.guard.exceeded(self.chunk.lines[self.pc - 1]);
+        // the jump runs again when the program goes on
+        self.pc -= 1;
+        self.entered = self.pc;
+        self.stopped = true;
+        Err(error)
+    }
+
+    #[inline(never)]
+    fn print(&mut self, ctype: CType) {
+        let value = self.pop(ctype);
+        let _ = writeln!(self.output, "{}", runtime::format_value(value));
+    }
+
+    #[inline(never)]
+    fn print_string(&mut self, index: u32) {
+        let _ = writeln!(self.output, "{}", self.chunk.strings[index as usize]);
+    }
+
+    #[inline(never)]
+    fn input(&mut self, slot: u32) {
+        // so the program's question is there before it waits
+        let _ = self.output.flush();
+        // an INPUT makes a variable a float, see ctype::infer
+        let variable = &mut self.variables[slot as usize];
+        let mut value = variable.get(CType::Float).float();
+        self.input.read(&mut value);
+        *variable = Raw::new(Value::Float(value));
+    }
+
+    #[inline(never)]
+    fn call(&mut self, native: Native) {
+        let value = native.call(&mut self.input, &mut self.output);
+        if native.returns() {
+            self.stack.push(Raw::new(Value::Float(value)));
+        }
+    }
+
+    #[cold]
+    fn divided_by_zero(&self, ctype: CType) -> RuntimeError {
+        self.error(String::from(ctype::division_by_zero(ctype)))
+    }
+
+    // the value on top, which has the type the instruction says
+    fn pop(&mut self, ctype: CType) -> Value {
+        self.stack
+            .pop()
+            .expect("the compiler only pops what it pushed")
+            .get(ctype)
+    }
+
+    // the instruction that just ran went wrong
+    fn error(&self, message: String) -> RuntimeError {
+        RuntimeError {
+            kind: ErrorKind::Failed,
+            message,
+            line: self.chunk.lines[self.pc - 1],
+        }
+    }
+}
+
+// its instructions with their lines, its constants, strings and variables
+fn footprint(chunk: &Chunk) -> usize {
+    chunk.code.len() * (size_of::<Instruction>() + size_of::<usize>())
+        + (chunk.constants.len() + chunk.variables.len()) * size_of::<Raw>()
+        + chunk.strings.iter().map(String::len).sum::<usize>()
+}
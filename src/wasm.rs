@@ -0,0 +1,102 @@
+// the way in for a playground in the browser, with the wasm feature. wasm-bindgen makes these
+// into javascript functions that take and give strings:
+//
+//   import init, { compile, run, format } from "./haneul.js";
+//   await init();
+//   const exitCode = run(editor.value, "3 4\n", line => output.append(line + "\n"),
+//       diagnostics => errors.textContent = diagnostics, 10_000_000, 64 << 20);
+//
+// it all runs in the browser, the programs on the vm like `haneul run` runs them
+
+use std::io::{self, Write};
+
+use js_sys::Function;
+use wasm_bindgen::prelude::*;
+
+use crate::runtime::{ExecutionPolicy, Limits};
+
+// the program in another language, a target like --target names it. a program with errors
+// throws them, rendered like the command line shows them
+#[wasm_bindgen]
+pub fn compile(source: &str, target: &str) -> Result<String, String> {
+    crate::compile_text(source, target)
+}
+
+// runs the program with `stdin` as its input and gives the exit code of `haneul run`. `print`
+// is called with every line the program prints while it runs, and `diagnostics` with its
+// warnings and errors and the error it stopped with when it has any, after the run. an
+// infinite loop is stopped after `max_steps` instructions, a timeout needs a thread the
+// browser doesn't give.
+// `max_memory` is about how many bytes the program may use. undefined is no limit. nothing
+// outside of the program is allowed, a page has no files or commands
+#[wasm_bindgen]
+pub fn run(
+    source: &str,
+    stdin: &str,
+    print: &Function,
+    diagnostics: &Function,
+    max_steps: Option<u32>,
+    max_memory: Option<u32>,
+) -> i32 {
+    let policy = ExecutionPolicy {
+        limits: Limits {
+            steps: max_steps.map(u64::from),
+            timeout: None,
+        },
+        memory: max_memory.map(|memory| memory as usize),
+        ..ExecutionPolicy::sandbox()
+    };
+    let mut lines = Lines {
+        print,
+        line: vec![],
+    };
+    let result = crate::run_source_to(source, stdin, &policy, &mut lines);
+    // what's left after the last newline, like a BEEP at the end
+    if !lines.line.is_empty() {
+        lines.print();
+    }
+    if !result.stderr.is_empty() {
+        let _ = diagnostics.call1(&JsValue::NULL, &JsValue::from_str(&result.stderr));
+    }
+    result.exit_code
+}
+
+// gives `print` what the program prints a line at a time, without the newline
+struct Lines<'a> {
+    print: &'a Function,
+    line: Vec<u8>,
+}
+
+// the vm wants an output it could send to another thread. the page runs the program on its one
+// thread and the vm is never sent anywhere
+unsafe impl Send for Lines<'_> {}
+
+impl Lines<'_> {
+    fn print(&mut self) {
+        let line = String::from_utf8_lossy(&self.line);
+        let _ = self.print.call1(&JsValue::NULL, &JsValue::from_str(&line));
+        self.line.clear();
+    }
+}
+
+impl Write for Lines<'_> {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        for &byte in bytes {
+            match byte {
+                b'\n' => self.print(),
+                byte => self.line.push(byte),
+            }
+        }
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// see format.rs
+#[wasm_bindgen]
+pub fn format(source: &str) -> String {
+    crate::format::format(source)
+}
@@ -0,0 +1,457 @@
+// --target=wat, the program as a webassembly module in the text format, out.wat. make it into
+// out.wasm with `wat2wasm out.wat`, then out.glue.js runs it in node or a browser.
+// the module imports PRINT and INPUT from the glue and exports main and its memory, where the
+// strings of PRINT "..." are
+//
+// wasm has the same number types c has, so the types c gives the numbers (see ctype.rs) are
+// just the types of the instructions. like the other backends without goto the program is its
+// control flow graph, a loop around a br_table that jumps to the block that runs next
+//
+// div_s traps on a 0 and on the smallest int divided by -1, so a division that's checked calls
+// a function of the module that stops the program through the glue, or wraps around. a % is a
+// function too, rem_s traps on a 0 and wasm has no % of floats, the glue's fmod does that one
+
+use std::fmt::Write;
+
+use crate::{
+    ast::{BinaryOp, ExprId, ExprKind, Program, Statement, StatementKind, UnaryOp},
+    cfg::{BlockId, Cfg, Terminator},
+    ctype::{self, CType, FloatDivision},
+    intern::Symbol,
+    js,
+};
+
+// gives the name of the builtin when the program uses one
+pub fn generate(program: &Program, float_division: FloatDivision) -> Result<String, String> {
+    let cfg = Cfg::new(program);
+    let reachable = cfg.reachable();
+    // the br_table needs them numbered without gaps
+    let cases: Vec<BlockId> = cfg.ids().filter(|id| reachable[id.index()]).collect();
+    let mut generator = Generator {
+        program,
+        float_division,
+        cases: &cases,
+        code: vec![],
+        data: vec![],
+        divisions: vec![],
+        unsupported: None,
+    };
+    let checked_floats = float_division == FloatDivision::Error;
+
+    for case in (0..cases.len()).rev() {
+        generator.emit(format!["block $case{}", case]);
+    }
+    let labels: Vec<String> = (0..cases.len())
+        .map(|case| format!["$case{}", case])
+        .collect();
+    generator.emit(String::from("local.get $haneul_block"));
+    generator.emit(format![
+        "br_table {} {}",
+        labels.join(" "),
+        labels.last().expect("there is always an entry block")
+    ]);
+    for (case, id) in cases.iter().enumerate() {
+        generator.emit(format!["end ;; $case{}", case]);
+        generator.block(*id, &cfg);
+    }
+
+    let mut module = String::new();
+    module.push_str(";; made by haneul\n(module\n");
+    module.push_str("  (import \"haneul\" \"print_number\" (func $print_number (param f32)))\n");
+    module.push_str("  (import \"haneul\" \"print_int\" (func $print_int (param i32)))\n");
+    module.push_str("  (import \"haneul\" \"print_long\" (func $print_long (param i64)))\n");
+    module
+        .push_str("  (import \"haneul\" \"print_string\" (func $print_string (param i32 i32)))\n");
+    module.push_str("  (import \"haneul\" \"input\" (func $input (param f32) (result f32)))\n");
+    module.push_str(
+        "  (import \"haneul\" \"divided_by_zero\" (func $divided_by_zero (param i32 i32)))\n",
+    );
+    module.push_str("  (import \"haneul\" \"fmod\" (func $fmod (param f64 f64) (result f64)))\n");
+    let pages = generator.data.len().div_ceil(65536).max(1);
+    let _ = writeln!(module, "  (memory (export \"memory\") {})", pages);
+    if !generator.data.is_empty() {
+        let _ = writeln!(
+            module,
+            "  (data (i32.const 0) \"{}\")",
+            escape(&generator.data)
+        );
+    }
+    module.push_str("  (func (export \"main\")\n");
+    for variable in variables(&program.statements) {
+        let ty = wasm_type(ctype::variable(program, variable));
+        let _ = writeln!(module, "    (local {} {})", local(program, variable), ty);
+    }
+    module.push_str("    (local $haneul_block i32)\n");
+    module.push_str("    loop $dispatch\n");
+    for instruction in &generator.code {
+        let _ = writeln!(module, "      {}", instruction);
+    }
+    module.push_str("    end\n  )\n");
+    for (op, ty) in &generator.divisions {
+        module.push_str(&division(*op, *ty, ty.is_integer() || checked_floats));
+    }
+    module.push_str(")\n");
+
+    match generator.unsupported {
+        Some(builtin) => Err(builtin),
+        None => Ok(module),
+    }
+}
+
+// runs out.wasm with the javascript backend's PRINT and INPUT
+pub fn glue() -> String {
+    format![
+        "// made by haneul, runs out.wasm. make it from out.wat with `wat2wasm out.wat`\n\
+         \"use strict\";\n{}{}",
+        js::RUNTIME,
+        GLUE
+    ]
+}
+
+struct Generator<'a> {
+    program: &'a Program,
+    float_division: FloatDivision,
+    cases: &'a [BlockId],
+    code: Vec<String>,
+    // the strings of PRINT "...", one after the other
+    data: Vec<u8>,
+    // the checked divisions and the %s, by their type. their functions are put after main
+    divisions: Vec<(BinaryOp, CType)>,
+    unsupported: Option<String>,
+}
+
+impl Generator<'_> {
+    fn emit(&mut self, instruction: String) {
+        self.code.push(instruction);
+    }
+
+    fn block(&mut self, id: BlockId, cfg: &Cfg) {
+        let block = cfg.block(id);
+        for statement in &block.statements {
+            self.statement(statement);
+        }
+        match block.terminator {
+            Terminator::Jump(target) => {
+                self.emit(format!["i32.const {}", self.case(target)]);
+                self.emit(String::from("local.set $haneul_block"));
+                self.emit(String::from("br $dispatch"));
+            }
+            // select gives the first one when the condition isn't 0
+            Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            } => {
+                self.emit(format!["i32.const {}", self.case(then)]);
+                self.emit(format!["i32.const {}", self.case(otherwise)]);
+                self.condition(condition);
+                self.emit(String::from("select"));
+                self.emit(String::from("local.set $haneul_block"));
+                self.emit(String::from("br $dispatch"));
+            }
+            Terminator::Return => self.emit(String::from("return")),
+        }
+    }
+
+    fn case(&self, id: BlockId) -> usize {
+        self.cases
+            .iter()
+            .position(|case| *case == id)
+            .expect("a reachable block only jumps to reachable blocks")
+    }
+
+    // only the statements that stay in a block of the control flow graph
+    fn statement(&mut self, statement: &Statement) {
+        match &statement.kind {
+            StatementKind::Print(value) => match ctype::of(self.program, *value) {
+                CType::Int => {
+                    self.expression(*value);
+                    self.emit(String::from("call $print_int"));
+                }
+                CType::Long => {
+                    self.expression(*value);
+                    self.emit(String::from("call $print_long"));
+                }
+                _ => {
+                    self.convert(*value, CType::Float);
+                    self.emit(String::from("call $print_number"));
+                }
+            },
+            StatementKind::PrintString(string) => {
+                let offset = self.data.len();
+                self.data.extend(string.as_bytes());
+                self.emit(format!["i32.const {}", offset]);
+                self.emit(format!["i32.const {}", string.len()]);
+                self.emit(String::from("call $print_string"));
+            }
+            StatementKind::Let { variable, value } => {
+                self.convert(*value, ctype::variable(self.program, *variable));
+                self.emit(format!["local.set {}", local(self.program, *variable)]);
+            }
+            StatementKind::Input(variable) => {
+                let local = local(self.program, *variable);
+                self.emit(format!["local.get {}", local]);
+                self.emit(String::from("call $input"));
+                self.emit(format!["local.set {}", local]);
+            }
+            StatementKind::Call { name, .. } => {
+                self.unsupported.get_or_insert_with(|| name.clone());
+            }
+            StatementKind::If { .. }
+            | StatementKind::While { .. }
+            | StatementKind::Label(_)
+            | StatementKind::Goto(_) => unreachable!("these are the edges of the graph"),
+        }
+    }
+
+    // an i32 that isn't 0 when c would take the IF or the WHILE
+    fn condition(&mut self, condition: ExprId) {
+        self.expression(condition);
+        let ty = ctype::of(self.program, condition);
+        if ty != CType::Int {
+            self.emit(format!["{}.const 0", wasm_type(ty)]);
+            self.emit(format!["{}.ne", wasm_type(ty)]);
+        }
+    }
+
+    fn expression(&mut self, expression: ExprId) {
+        let exprs = &self.program.exprs;
+        match &exprs[expression].kind {
+            ExprKind::Number(text) => match ctype::number(text) {
+                CType::Int => self.emit(format!["i32.const {}", text.parse::<i32>().unwrap()]),
+                CType::Long => self.emit(format!["i64.const {}", text.parse::<i64>().unwrap()]),
+                _ => self.emit(format![
+                    "f64.const {:?}",
+                    text.parse::<f64>().unwrap_or_default()
+                ]),
+            },
+            ExprKind::Variable(variable) => {
+                self.emit(format!["local.get {}", local(self.program, *variable)])
+            }
+            ExprKind::Call { name, .. } => {
+                self.unsupported.get_or_insert_with(|| name.clone());
+                self.emit(String::from("f32.const 0"));
+            }
+            ExprKind::Unary { op, operand } => {
+                let (op, operand) = (*op, *operand);
+                let ty = ctype::of(self.program, operand);
+                match op {
+                    UnaryOp::Plus => self.expression(operand),
+                    UnaryOp::Minus if ty.is_integer() => {
+                        self.emit(format!["{}.const 0", wasm_type(ty)]);
+                        self.expression(operand);
+                        self.emit(format!["{}.sub", wasm_type(ty)]);
+                    }
+                    UnaryOp::Minus => {
+                        self.expression(operand);
+                        self.emit(format!["{}.neg", wasm_type(ty)]);
+                    }
+                }
+            }
+            ExprKind::Binary { op, left, right } => {
+                let (op, left, right) = (*op, *left, *right);
+                let common = ctype::common(self.program, left, right);
+                self.convert(left, common);
+                self.convert(right, common);
+                let checked = common.is_integer() || self.float_division == FloatDivision::Error;
+                if (op == BinaryOp::Divide && checked) || op == BinaryOp::Remainder {
+                    let line = self.program.exprs[expression].span.line;
+                    self.emit(format!["i32.const {}", line]);
+                    self.emit(format!["call ${}", function(op, common)]);
+                    if !self.divisions.contains(&(op, common)) {
+                        self.divisions.push((op, common));
+                    }
+                } else {
+                    self.emit(format!["{}.{}", wasm_type(common), instruction(op, common)]);
+                }
+            }
+        }
+    }
+
+    // the expression as another type, like c converts it for a calculation or a LET
+    fn convert(&mut self, expression: ExprId, to: CType) {
+        self.expression(expression);
+        let from = ctype::of(self.program, expression);
+        let conversion = match (from, to) {
+            _ if from == to => return,
+            (CType::Int, CType::Long) => "i64.extend_i32_s",
+            (CType::Int, CType::Float) => "f32.convert_i32_s",
+            (CType::Int, CType::Double) => "f64.convert_i32_s",
+            (CType::Long, CType::Float) => "f32.convert_i64_s",
+            (CType::Long, CType::Double) => "f64.convert_i64_s",
+            (CType::Float, CType::Double) => "f64.promote_f32",
+            (CType::Double, CType::Float) => "f32.demote_f64",
+            _ => unreachable!("nothing is made into a smaller type but a float"),
+        };
+        self.emit(String::from(conversion));
+    }
+}
+
+fn wasm_type(ty: CType) -> &'static str {
+    match ty {
+        CType::Int => "i32",
+        CType::Long => "i64",
+        CType::Float => "f32",
+        CType::Double => "f64",
+    }
+}
+
+fn function(op: BinaryOp, ty: CType) -> String {
+    match op {
+        BinaryOp::Divide => format!["divide_{}", wasm_type(ty)],
+        _ => format!["remainder_{}", wasm_type(ty)],
+    }
+}
+
+// the / or % of a type, a checked one stops the program when it divides by 0, the glue gets 1
+// for a float. the smallest int divided by -1 wraps around like in c, its % is 0 like rem_s
+// gives. the % of floats is fmod of doubles, which has the exact float in it
+fn division(op: BinaryOp, ty: CType, checked: bool) -> String {
+    let name = wasm_type(ty);
+    let zero = match ty.is_integer() {
+        _ if !checked => String::new(),
+        true => format![
+            "    local.get $right\n    {name}.eqz\n    if\n      i32.const 0\n      \
+             local.get $line\n      call $divided_by_zero\n      unreachable\n    end\n"
+        ],
+        false => format![
+            "    local.get $right\n    {name}.const 0\n    {name}.eq\n    if\n      \
+             i32.const 1\n      local.get $line\n      call $divided_by_zero\n      \
+             unreachable\n    end\n"
+        ],
+    };
+    let calculation = match (op, ty) {
+        (BinaryOp::Divide, CType::Int | CType::Long) => format![
+            "    local.get $right\n    {name}.const -1\n    {name}.eq\n    if\n      \
+             {name}.const 0\n      local.get $left\n      {name}.sub\n      return\n    end\n    \
+             local.get $left\n    local.get $right\n    {name}.div_s\n"
+        ],
+        (BinaryOp::Divide, _) => {
+            format!["    local.get $left\n    local.get $right\n    {name}.div\n"]
+        }
+        (_, CType::Int | CType::Long) => {
+            format!["    local.get $left\n    local.get $right\n    {name}.rem_s\n"]
+        }
+        (_, CType::Float) => String::from(
+            "    local.get $left\n    f64.promote_f32\n    local.get $right\n    \
+             f64.promote_f32\n    call $fmod\n    f32.demote_f64\n",
+        ),
+        (_, CType::Double) => {
+            String::from("    local.get $left\n    local.get $right\n    call $fmod\n")
+        }
+    };
+    format![
+        "  (func ${} (param $left {name}) (param $right {name}) (param $line i32) \
+         (result {name})\n{zero}{calculation}  )\n",
+        function(op, ty)
+    ]
+}
+
+// a comparison gives an i32, like it gives an int in c
+fn instruction(op: BinaryOp, ty: CType) -> &'static str {
+    let integer = ty.is_integer();
+    match op {
+        BinaryOp::Add => "add",
+        BinaryOp::Subtract => "sub",
+        BinaryOp::Multiply => "mul",
+        BinaryOp::Divide if integer => "div_s",
+        BinaryOp::Divide => "div",
+        BinaryOp::Remainder => unreachable!("a % is a function, see division"),
+        BinaryOp::Equal => "eq",
+        BinaryOp::NotEqual => "ne",
+        BinaryOp::Less if integer => "lt_s",
+        BinaryOp::Less => "lt",
+        BinaryOp::LessEqual if integer => "le_s",
+        BinaryOp::LessEqual => "le",
+        BinaryOp::Greater if integer => "gt_s",
+        BinaryOp::Greater => "gt",
+        BinaryOp::GreaterEqual if integer => "ge_s",
+        BinaryOp::GreaterEqual => "ge",
+    }
+}
+
+// a wasm name can only have ascii in it, a name with something else in it is written in hex
+fn local(program: &Program, variable: Symbol) -> String {
+    let name = program.names.resolve(variable);
+    if name.is_ascii() {
+        format!["${}", name]
+    } else {
+        let hex: String = name.bytes().map(|byte| format!["{:02x}", byte]).collect();
+        format!["$haneul_{}", hex]
+    }
+}
+
+fn escape(bytes: &[u8]) -> String {
+    let mut escaped = String::new();
+    for byte in bytes {
+        match byte {
+            b'"' | b'\\' => {
+                let _ = write!(escaped, "\\{:02x}", byte);
+            }
+            b' '..=b'~' => escaped.push(*byte as char),
+            _ => {
+                let _ = write!(escaped, "\\{:02x}", byte);
+            }
+        }
+    }
+    escaped
+}
+
+// every variable, in the order they first get a value
+fn variables(statements: &[Statement]) -> Vec<Symbol> {
+    fn collect(statements: &[Statement], variables: &mut Vec<Symbol>) {
+        for statement in statements {
+            match &statement.kind {
+                StatementKind::Let { variable, .. } | StatementKind::Input(variable)
+                    if !variables.contains(variable) =>
+                {
+                    variables.push(*variable)
+                }
+                StatementKind::If { body, .. } | StatementKind::While { body, .. } => {
+                    collect(body, variables)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut variables = vec![];
+    collect(statements, &mut variables);
+    variables
+}
+
+const GLUE: &str = r#"
+let haneul_memory;
+
+const haneul_imports = {
+    haneul: {
+        print_number: haneul_print_number,
+        print_int: haneul_print_integer,
+        print_long: haneul_print_integer,
+        print_string: (offset, length) => {
+            const bytes = new Uint8Array(haneul_memory.buffer, offset, length);
+            haneul_print_string(new TextDecoder().decode(bytes));
+        },
+        input: haneul_input,
+        fmod: (left, right) => left % right,
+        divided_by_zero: (float, line) => {
+            haneul_fail(float ? "a float is divided by 0" : "an int is divided by 0", line);
+        },
+    },
+};
+
+async function haneul_run() {
+    let bytes;
+    if (typeof window === "undefined") {
+        const path = require("path");
+        bytes = require("fs").readFileSync(path.join(__dirname, "out.wasm"));
+    } else {
+        bytes = await (await fetch("out.wasm")).arrayBuffer();
+    }
+    const { instance } = await WebAssembly.instantiate(bytes, haneul_imports);
+    haneul_memory = instance.exports.memory;
+    instance.exports.main();
+}
+
+haneul_run();
+"#;
@@ -0,0 +1,185 @@
+// `haneul run --watch`: runs the program, and when its file changes compiles it again and swaps
+// the new version in, for working on a game loop without starting it over every time
+//
+// a thread looks at the file while the program runs. once the new version compiled, the old
+// one is cancelled at its next jump (see CancellationToken) and the new one goes on at the
+// statement the old one was at, with the variables they both have (see Vm::reload). that line is found again
+// when lines were added or removed above it, when it was changed itself the new version starts
+// at the top, still with the variables. a version with errors isn't swapped in, the old one
+// runs on
+//
+// a program that ended or went wrong runs again from the top at the next change. one that
+// waits for an INPUT is swapped once it has read it
+//
+// the program is compiled with -O1 at most, what the loop pass and cse move to another line
+// can't be carried over (see main.rs)
+
+use std::{
+    fs, thread,
+    time::{Duration, SystemTime},
+};
+
+use haneul::{
+    bytecode::Chunk,
+    runtime::{ErrorKind, Input, Limits, Value},
+    vm::Vm,
+};
+
+// how often the file is looked at
+const POLL: Duration = Duration::from_millis(200);
+// how many lines of the old version times lines of the new one are compared to find the line
+// the new version goes on at
+const MAX_COMPARED: usize = 4_000_000;
+
+// `text` is what `chunk` was compiled from. `recompile` reports the errors of a new version and
+// gives None when it has them. it only stops with ctrl-c
+pub fn run(
+    file: &str,
+    mut text: String,
+    mut chunk: Chunk,
+    limits: Limits,
+    recompile: &(dyn Fn(&str) -> Option<Chunk> + Sync),
+) -> ! {
+    let mut modified = modified(file);
+    let mut carried: Option<Carried> = None;
+    loop {
+        let mut vm = Vm::new(&chunk);
+        vm.set_limits(limits);
+        vm.set_input(Input::new());
+        if let Some(carried) = carried.take() {
+            vm.reload(&carried.variables, carried.pending, carried.line);
+        }
+        let cancellation = vm.cancellation_token();
+        let (result, (new_text, new_chunk)) = thread::scope(|scope| {
+            let watcher = scope.spawn(|| {
+                let changed = changed(file, &text, &mut modified, recompile);
+                cancellation.cancel();
+                changed
+            });
+            let result = vm.run();
+            match &result {
+                Ok(()) => eprintln!("the program ended, it runs again when {} changes", file),
+                Err(error) if error.kind == ErrorKind::Cancelled => {}
+                Err(error) => eprintln!("Error: {} on line {}", error.message, error.line),
+            }
+            (result, watcher.join().expect("the watcher doesn't panic"))
+        });
+
+        carried = match result {
+            Err(error) if error.kind == ErrorKind::Cancelled => {
+                let snapshot = vm.snapshot();
+                let line = moved(&text, &new_text, chunk.lines[snapshot.pc]);
+                match line {
+                    Some(line) => eprintln!("{} changed, it goes on at line {}", file, line),
+                    None => eprintln!("{} changed, it starts again at the top", file),
+                }
+                Some(Carried {
+                    variables: vm.variables(),
+                    pending: snapshot.pending,
+                    line,
+                })
+            }
+            _ => {
+                eprintln!("{} changed, it runs again", file);
+                None
+            }
+        };
+        drop(vm);
+        text = new_text;
+        chunk = new_chunk;
+    }
+}
+
+// the variables and the input of the old version, and where the new one goes on
+struct Carried {
+    variables: Vec<(String, Value)>,
+    pending: String,
+    line: Option<usize>,
+}
+
+// waits for a version of the file that is different and compiles
+fn changed(
+    file: &str,
+    text: &str,
+    modified: &mut Option<SystemTime>,
+    recompile: &(dyn Fn(&str) -> Option<Chunk> + Sync),
+) -> (String, Chunk) {
+    loop {
+        thread::sleep(POLL);
+        let now = self::modified(file);
+        if now == *modified {
+            continue;
+        }
+        *modified = now;
+        // an editor can save it without changing it, or remove it for a moment while it does
+        let new = match fs::read_to_string(file) {
+            Ok(new) if new != text => new,
+            _ => continue,
+        };
+        if let Some(chunk) = recompile(&new) {
+            return (new, chunk);
+        }
+    }
+}
+
+fn modified(file: &str) -> Option<SystemTime> {
+    fs::metadata(file)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+// where a line of the old text is in the new one: the lines both have, in the same order, are
+// matched up (the longest common subsequence of them, like diff does). a line that was changed
+// or removed has no place
+fn moved(old: &str, new: &str, line: usize) -> Option<usize> {
+    let old: Vec<_> = old.lines().collect();
+    let new: Vec<_> = new.lines().collect();
+    // lines start at 1
+    let index = line.checked_sub(1).filter(|index| *index < old.len())?;
+    // most of the file stays the same, only what's between the first and the last change is
+    // compared line by line
+    let front = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let back = old[front..]
+        .iter()
+        .rev()
+        .zip(new[front..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    if index < front {
+        return Some(line);
+    }
+    if index >= old.len() - back {
+        return Some(index + new.len() - old.len() + 1);
+    }
+    let (old, new) = (&old[front..old.len() - back], &new[front..new.len() - back]);
+    if old.len() * new.len() > MAX_COMPARED {
+        return None;
+    }
+
+    // longest[i][j] is the length of the subsequence of old[i..] and new[j..]
+    let mut longest = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            longest[i][j] = if old[i] == new[j] {
+                longest[i + 1][j + 1] + 1
+            } else {
+                longest[i + 1][j].max(longest[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            if i == index - front {
+                return Some(front + j + 1);
+            }
+            i += 1;
+            j += 1;
+        } else if longest[i + 1][j] >= longest[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    None
+}
@@ -0,0 +1,93 @@
+// every backend compiles the builtins it has an implementation of (see builtins::Platform) and
+// the vm and the tree interpreter run theirs. the others are turned away before anything is
+// made or run
+
+mod common;
+
+use common::{run, Engine, LEVELS};
+use haneul::{
+    builtins::Registry, interpreter::Interpreter, lexer::Lexer, parse, parser, passes::OptLevel,
+    source::SourceMap, Error, Options, Target,
+};
+
+fn compile(source: &str, target: Target) -> Result<String, Error> {
+    let options = Options {
+        target,
+        ..Options::default()
+    };
+    let output = haneul::compile(source, &options)?;
+    Ok(String::from_utf8(output.files[0].1.clone()).unwrap())
+}
+
+#[test]
+fn a_builtin_calls_the_helper_of_the_target() {
+    let source = "BEEP\nLET a = 2\nSOUND a, 10\n";
+    for (target, helper) in [
+        (
+            Target::C,
+            "static void haneul_sound(float freq, float duration)",
+        ),
+        (Target::Js, "function haneul_sound(freq, duration)"),
+        (Target::Python, "def haneul_sound(freq, duration):"),
+        (Target::Rust, "fn haneul_sound(_freq: f32, duration: f32)"),
+    ] {
+        let code = compile(source, target).unwrap();
+        assert!(code.contains("haneul_beep()"), "{:?}:\n{}", target, code);
+        assert!(code.contains(helper), "{:?}:\n{}", target, code);
+        // the helpers of a builtin the program doesn't call aren't in it
+        assert!(!code.contains("inkey"), "{:?}:\n{}", target, code);
+    }
+}
+
+#[test]
+fn a_builtin_without_an_implementation_is_turned_away() {
+    let error = compile("PRINT 1\nFORWARD 10\n", Target::Js).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "FORWARD can't be compiled to javascript yet"
+    );
+    let error = compile("BEEP\n", Target::Wat).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "BEEP can't be compiled to webassembly yet"
+    );
+    assert!(compile("PRINT 1\nFORWARD 10\n", Target::C).is_ok());
+}
+
+#[test]
+fn the_interpreter_checks_the_builtins_before_it_runs() {
+    let builtins = Registry::new();
+    let mut sources = SourceMap::new();
+    let file = sources.add(
+        String::from("main.han"),
+        String::from("PRINT 1\nFORWARD 10\n"),
+    );
+    let mut lexer = Lexer::new(file, sources.get(file).text());
+    let (result, _) = parse(&mut lexer, &builtins, parser::DEFAULT_MAX_DEPTH);
+    let program = result.unwrap();
+
+    let interpreter = Interpreter::new(&program);
+    let error = interpreter.check().unwrap_err();
+    assert_eq!(error.message, "FORWARD can't be run yet");
+    assert_eq!(error.line, 2);
+
+    let mut interpreter = Interpreter::new(&program);
+    interpreter.register("FORWARD", |_: f32| {});
+    assert!(interpreter.check().is_ok());
+}
+
+#[test]
+fn inkey_gives_the_next_key_on_the_vm_and_the_tree() {
+    let source = "INPUT a\nPRINT INKEY()\nPRINT INKEY()\nPRINT a\n";
+    for engine in [Engine::Vm, Engine::Tree] {
+        for level in LEVELS {
+            // like getchar after scanf in c, what the INPUT didn't use comes first
+            assert_eq!(run(source, "3 x\n", level, engine), "32\n120\n3\n");
+            assert_eq!(run(source, "", level, engine), "0\n0\n0\n");
+        }
+        assert_eq!(
+            run("PRINT 1\nBEEP\nPRINT 2\n", "", OptLevel::O0, engine),
+            "1\n\x072\n"
+        );
+    }
+}
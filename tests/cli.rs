@@ -0,0 +1,186 @@
+// `haneul run` with the flags that keep files between runs: the cache in .haneul-cache,
+// --record and --replay, and --snapshot and --resume. every test runs in a directory of its own
+
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+};
+
+const PROGRAM: &str = "INPUT a
+LET i = 0
+WHILE i < 5 REPEAT
+PRINT i * a
+LET i = i + 1
+ENDWHILE
+INPUT b
+PRINT b
+";
+
+// a directory with the program in main.han
+fn directory(name: &str) -> PathBuf {
+    let directory =
+        std::env::temp_dir().join(format!["haneul-cli-{}-{}", std::process::id(), name]);
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("main.han"), PROGRAM).unwrap();
+    directory
+}
+
+fn haneul(directory: &Path, args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_haneul"))
+        .arg("run")
+        .args(args)
+        .arg("main.han")
+        .current_dir(directory)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // a program that doesn't read all of it can be done before it's written
+    let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+    child.wait_with_output().unwrap()
+}
+
+// what `haneul run` printed on stdout, and if it exited with 0
+fn run(directory: &Path, args: &[&str], stdin: &str) -> (String, bool) {
+    let ran = haneul(directory, args, stdin);
+    (String::from_utf8(ran.stdout).unwrap(), ran.status.success())
+}
+
+// what it printed on stderr when it went wrong
+fn error(directory: &Path, args: &[&str], stdin: &str) -> String {
+    let ran = haneul(directory, args, stdin);
+    assert!(!ran.status.success());
+    String::from_utf8(ran.stderr).unwrap()
+}
+
+fn cached(directory: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<_> = fs::read_dir(directory.join(".haneul-cache"))
+        .map(|entries| entries.map(|entry| entry.unwrap().path()).collect())
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+#[test]
+fn an_unchanged_program_runs_from_the_cache() {
+    let directory = directory("cache");
+    let expected = "0\n3\n6\n9\n12\n4\n";
+
+    // the first run compiles it and saves it
+    assert_eq!(
+        run(&directory, &[], "3 4\n"),
+        (String::from(expected), true)
+    );
+    let files = cached(&directory);
+    assert_eq!(files.len(), 1);
+
+    // the next one runs what's saved, even when it's of another program
+    let options = haneul::Options {
+        target: haneul::Target::Bytecode,
+        ..haneul::Options::default()
+    };
+    let other = haneul::compile("PRINT 42\n", &options).unwrap();
+    fs::write(&files[0], &other.files[0].1).unwrap();
+    assert_eq!(run(&directory, &[], "3 4\n"), (String::from("42\n"), true));
+
+    // --no-cache doesn't look at it
+    assert_eq!(
+        run(&directory, &["--no-cache"], "3 4\n"),
+        (String::from(expected), true)
+    );
+
+    // a file that can't be read is compiled again, and saved over
+    fs::write(&files[0], b"not bytecode").unwrap();
+    assert_eq!(
+        run(&directory, &[], "3 4\n"),
+        (String::from(expected), true)
+    );
+    assert_ne!(fs::read(&files[0]).unwrap(), b"not bytecode");
+
+    // a changed program or other options miss it
+    fs::write(
+        directory.join("main.han"),
+        PROGRAM.replace("i * a", "i + a"),
+    )
+    .unwrap();
+    assert_eq!(
+        run(&directory, &[], "3 4\n"),
+        (String::from("3\n4\n5\n6\n7\n4\n"), true)
+    );
+    assert_eq!(cached(&directory).len(), 2);
+    run(&directory, &["-O0"], "3 4\n");
+    assert_eq!(cached(&directory).len(), 3);
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn a_replayed_run_prints_what_the_recorded_one_did() {
+    let directory = directory("replay");
+    let recorded = run(&directory, &["--record=session.json"], "2 7\n");
+    assert_eq!(recorded, (String::from("0\n2\n4\n6\n8\n7\n"), true));
+    assert_eq!(
+        fs::read_to_string(directory.join("session.json")).unwrap(),
+        "{\"inputs\":[2.0,7.0]}\n"
+    );
+    // the input isn't read anymore
+    assert_eq!(
+        run(&directory, &["--replay=session.json"], "5 5\n"),
+        recorded
+    );
+    assert_eq!(
+        run(&directory, &["--replay=session.json", "--engine=tree"], ""),
+        recorded
+    );
+
+    // an INPUT at the end of the input is recorded too
+    let recorded = run(&directory, &["--record=session.json"], "2\n");
+    assert_eq!(
+        fs::read_to_string(directory.join("session.json")).unwrap(),
+        "{\"inputs\":[2.0,null]}\n"
+    );
+    assert_eq!(run(&directory, &["--replay=session.json"], ""), recorded);
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn a_resumed_run_ends_like_one_that_wasnt_stopped() {
+    let directory = directory("resume");
+    let (whole, _) = run(&directory, &[], "3 4\n");
+
+    // stopped in the loop, with the 4 not read yet
+    let (stopped, done) = run(
+        &directory,
+        &["--max-steps=20", "--snapshot=snapshot.json"],
+        "3 4\n",
+    );
+    assert!(!done);
+    assert!(whole.starts_with(&stopped) && stopped != whole);
+    let (resumed, done) = run(&directory, &["--resume=snapshot.json"], "");
+    assert!(done);
+    assert_eq!(stopped + &resumed, whole);
+
+    // a snapshot only resumes the program it's of
+    let snapshot = fs::read_to_string(directory.join("snapshot.json")).unwrap();
+    let start = snapshot.find("\"program\":\"").unwrap() + 11;
+    let mut other = snapshot.clone();
+    other.replace_range(start..start + 16, "0123456789abcdef");
+    fs::write(directory.join("other.json"), other).unwrap();
+    assert!(error(&directory, &["--resume=other.json"], "")
+        .contains("other.json can't be resumed: it's of another program"));
+
+    fs::write(
+        directory.join("main.han"),
+        PROGRAM.replace("i * a", "i + a"),
+    )
+    .unwrap();
+    assert!(error(&directory, &["--resume=snapshot.json"], "")
+        .contains("snapshot.json can't be resumed: it's of another program"));
+
+    fs::remove_dir_all(&directory).unwrap();
+}
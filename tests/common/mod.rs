@@ -0,0 +1,104 @@
+// running a program the way `haneul run` does, for the tests. every test file uses some of it
+#![allow(dead_code)]
+
+use haneul::{
+    analyze,
+    ast::Program,
+    builtins::Registry,
+    bytecode,
+    ctype::FloatDivision,
+    diagnostics::Lints,
+    interpreter::Interpreter,
+    lexer::Lexer,
+    parse, parser,
+    passes::{self, OptLevel},
+    runtime::{ExecutionPolicy, Input},
+    source::SourceMap,
+    vm::Vm,
+};
+
+pub const LEVELS: [OptLevel; 3] = [OptLevel::O0, OptLevel::O1, OptLevel::O2];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Engine {
+    Vm,
+    Tree,
+}
+
+// what the program printed, with "Error: ... on line N" at the end when it went wrong
+pub fn run(source: &str, stdin: &str, level: OptLevel, engine: Engine) -> String {
+    run_with(source, stdin, level, engine, ExecutionPolicy::default())
+}
+
+// the program after the passes of the level
+pub fn optimized(source: &str, level: OptLevel) -> Program {
+    let builtins = Registry::new();
+    let mut sources = SourceMap::new();
+    let file = sources.add(String::from("main.han"), source.to_owned());
+    let mut lexer = Lexer::new(file, sources.get(file).text());
+    let (result, warnings) = parse(&mut lexer, &builtins, parser::DEFAULT_MAX_DEPTH);
+    let (_, errors) = analyze(&result, warnings, &builtins, &Lints::default());
+    assert!(errors.is_empty(), "the program has errors: {:?}", errors);
+    let mut program = result.unwrap();
+    passes::optimize(&mut program, level);
+    program
+}
+
+pub fn run_with(
+    source: &str,
+    stdin: &str,
+    level: OptLevel,
+    engine: Engine,
+    policy: ExecutionPolicy,
+) -> String {
+    let builtins = Registry::new();
+    let program = optimized(source, level);
+
+    let mut output = vec![];
+    let input = Input::from_reader(std::io::Cursor::new(stdin.to_owned()));
+    let result = match engine {
+        Engine::Vm => {
+            let mut chunk =
+                bytecode::compile(&program, &builtins, FloatDivision::default()).unwrap();
+            passes::optimize_chunk(&mut chunk, level);
+            let mut vm = Vm::new(&chunk);
+            vm.set_policy(policy);
+            vm.set_input(input);
+            vm.set_output(&mut output);
+            vm.run()
+        }
+        Engine::Tree => {
+            let mut interpreter = Interpreter::new(&program);
+            interpreter.set_policy(policy);
+            interpreter.set_input(input);
+            interpreter.set_output(&mut output);
+            interpreter.run()
+        }
+    };
+    let mut output = String::from_utf8(output).unwrap();
+    if let Err(error) = result {
+        output.push_str(&format![
+            "Error: {} on line {}\n",
+            error.message, error.line
+        ]);
+    }
+    output
+}
+
+// what the program prints at -O0 on the vm, after checking every level prints the same on both
+// engines, for the tests of the optimizer
+pub fn same_as_o0(source: &str, stdin: &str) -> String {
+    let expected = run(source, stdin, OptLevel::O0, Engine::Vm);
+    for level in LEVELS {
+        for engine in [Engine::Vm, Engine::Tree] {
+            assert_eq!(
+                run(source, stdin, level, engine),
+                expected,
+                "{:?} at {:?}",
+                engine,
+                level
+            );
+        }
+    }
+    expected
+}
@@ -0,0 +1,224 @@
+// every program in tests/samples prints the same on every backend at every -O level. the vm and
+// the tree interpreter run it here, the c, javascript, python and rust it's compiled to are run
+// with cc, node, python3 and rustc. a backend whose tool isn't installed is skipped
+//
+// a sample reads its INPUTs from the .in file next to it, when it has one. what a program
+// prints when it goes wrong is compared too, it's on stderr for the compiled ones
+
+mod common;
+
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use common::{run, Engine, LEVELS};
+use haneul::{passes::OptLevel, Options, Target};
+
+// the target, and how its files are made into a program that runs: the tool, the arguments to
+// build it with (None runs the file with the tool) and the file
+const COMPILED: &[(Target, &str, Option<&[&str]>, &str)] = &[
+    (
+        Target::C,
+        "cc",
+        Some(&["out.c", "-fwrapv", "-o", "program", "-lm"]),
+        "program",
+    ),
+    (Target::Js, "node", None, "out.js"),
+    (Target::Python, "python3", None, "out.py"),
+    (
+        Target::Rust,
+        "rustc",
+        Some(&["main.rs", "-o", "program"]),
+        "program",
+    ),
+];
+
+#[test]
+fn every_backend_prints_the_same() {
+    let mut samples: Vec<PathBuf> = fs::read_dir("tests/samples")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "han"))
+        .collect();
+    samples.sort();
+    assert!(!samples.is_empty());
+
+    let installed: Vec<_> = COMPILED
+        .iter()
+        .filter(|(target, tool, ..)| {
+            let found = Command::new(tool)
+                .arg("--version")
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .is_ok_and(|status| status.success());
+            if !found {
+                eprintln!("{} isn't installed, {:?} is skipped", tool, target);
+            }
+            found
+        })
+        .collect();
+
+    let mut different = vec![];
+    for sample in &samples {
+        let source = fs::read_to_string(sample).unwrap();
+        let stdin = fs::read_to_string(sample.with_extension("in")).unwrap_or_default();
+        let expected = run(&source, &stdin, OptLevel::O0, Engine::Vm);
+        for level in LEVELS {
+            let mut outputs = vec![
+                (String::from("vm"), run(&source, &stdin, level, Engine::Vm)),
+                (
+                    String::from("tree"),
+                    run(&source, &stdin, level, Engine::Tree),
+                ),
+            ];
+            for (target, tool, build, program) in &installed {
+                let output = compiled(&source, &stdin, level, *target, tool, *build, program);
+                outputs.push((format!["{:?}", target], output));
+            }
+            for (backend, output) in outputs {
+                if output != expected {
+                    different.push(format![
+                        "{} on {} at {:?}:\n{}\ninstead of:\n{}",
+                        sample.display(),
+                        backend,
+                        level,
+                        output,
+                        expected
+                    ]);
+                }
+            }
+        }
+    }
+    assert!(different.is_empty(), "{}", different.join("\n"));
+}
+
+// what the program printed on stdout, and then on stderr
+fn compiled(
+    source: &str,
+    stdin: &str,
+    level: OptLevel,
+    target: Target,
+    tool: &str,
+    build: Option<&[&str]>,
+    program: &str,
+) -> String {
+    let options = Options {
+        target,
+        opt_level: level,
+        ..Options::default()
+    };
+    let output = haneul::compile(source, &options).unwrap();
+    let directory = std::env::temp_dir().join(format![
+        "haneul-conformance-{}-{:?}-{:?}",
+        std::process::id(),
+        target,
+        level
+    ]);
+    fs::create_dir_all(&directory).unwrap();
+    for (file, bytes) in &output.files {
+        fs::write(directory.join(file), bytes).unwrap();
+    }
+
+    let mut command = match build {
+        Some(args) => {
+            let built = Command::new(tool)
+                .args(args)
+                .current_dir(&directory)
+                .output()
+                .unwrap();
+            assert!(
+                built.status.success(),
+                "{} failed:\n{}",
+                tool,
+                String::from_utf8_lossy(&built.stderr)
+            );
+            Command::new(Path::new(&directory).join(program))
+        }
+        None => {
+            let mut command = Command::new(tool);
+            command.arg(program);
+            command
+        }
+    };
+    let mut child = command
+        .current_dir(&directory)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    let ran = child.wait_with_output().unwrap();
+    fs::remove_dir_all(&directory).unwrap();
+
+    let mut printed = String::from_utf8(ran.stdout).unwrap();
+    printed.push_str(&String::from_utf8(ran.stderr).unwrap());
+    // a program that went wrong exits with 1, like `haneul run`
+    assert_eq!(
+        ran.status.success(),
+        !printed.contains("Error: "),
+        "{:?} exited with {}",
+        target,
+        ran.status
+    );
+    printed
+}
+
+#[test]
+fn the_rust_target_builds_with_cargo() {
+    let source = "INPUT a\nPRINT a * 2\nPRINT \"done\"\n";
+    let options = Options {
+        target: Target::Rust,
+        name: String::from("games/2 player.han"),
+        ..Options::default()
+    };
+    let output = haneul::compile(source, &options).unwrap();
+    let names: Vec<_> = output.files.iter().map(|(file, _)| file.as_str()).collect();
+    assert_eq!(names, ["main.rs", "Cargo.toml"]);
+    let manifest = String::from_utf8(output.files[1].1.clone()).unwrap();
+    assert!(
+        manifest.contains("name = \"haneul_2_player\""),
+        "{}",
+        manifest
+    );
+
+    let found = Command::new("cargo")
+        .arg("--version")
+        .output()
+        .is_ok_and(|output| output.status.success());
+    if !found {
+        eprintln!("cargo isn't installed, the rust target isn't built with it");
+        return;
+    }
+    let directory = std::env::temp_dir().join(format!["haneul-cargo-{}", std::process::id()]);
+    fs::create_dir_all(&directory).unwrap();
+    for (file, bytes) in &output.files {
+        fs::write(directory.join(file), bytes).unwrap();
+    }
+    let mut child = Command::new("cargo")
+        .args(["run", "--quiet", "--offline"])
+        .current_dir(&directory)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"21\n").unwrap();
+    let ran = child.wait_with_output().unwrap();
+    fs::remove_dir_all(&directory).unwrap();
+    assert!(
+        ran.status.success(),
+        "{}",
+        String::from_utf8_lossy(&ran.stderr)
+    );
+    assert_eq!(String::from_utf8(ran.stdout).unwrap(), "42\ndone\n");
+}
@@ -0,0 +1,61 @@
+// sharing a calculation (see cse.rs) can't change what a program prints: a calculation stops
+// being shared where one of its variables can get another value, by an INPUT or by a GOTO to a
+// LABEL from somewhere else
+
+mod common;
+
+use common::{optimized, same_as_o0};
+use haneul::{passes::OptLevel, pretty};
+
+#[test]
+fn a_calculation_is_shared_in_a_block() {
+    let source = "INPUT a
+LET x = a * a + 1
+LET y = a * a - 1
+PRINT x
+PRINT y
+";
+    assert_eq!(same_as_o0(source, "3\n"), "10\n8\n");
+    let tree = pretty::ast(&optimized(source, OptLevel::O2));
+    assert!(tree.contains("haneul_cse0"), "{}", tree);
+}
+
+#[test]
+fn an_input_ends_the_sharing() {
+    let source = "INPUT a
+LET x = a * 2 + 1
+INPUT a
+LET y = a * 2 + 1
+PRINT x
+PRINT y
+";
+    assert_eq!(same_as_o0(source, "3 4\n"), "7\n9\n");
+    // at the end of the input the variable keeps its value, so it's the same after all
+    assert_eq!(same_as_o0(source, "3\n"), "7\n7\n");
+}
+
+#[test]
+fn a_label_ends_the_sharing() {
+    // the second i * 7 is also reached by the GOTO, after i changed
+    let source = "LET i = 1
+LET x = i * 7
+PRINT x
+LABEL again
+PRINT i * 7
+IF i < 3 THEN
+    LET i = i + 1
+    GOTO again
+ENDIF
+";
+    assert_eq!(same_as_o0(source, ""), "7\n7\n14\n21\n");
+}
+
+#[test]
+fn a_call_is_never_shared() {
+    let source = "LET a = INKEY() + 1
+LET b = INKEY() + 1
+PRINT a
+PRINT b
+";
+    assert_eq!(same_as_o0(source, "xy\n"), "121\n122\n");
+}
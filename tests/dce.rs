@@ -0,0 +1,57 @@
+// leaving out code that never runs (see dce.rs) keeps a LABEL that only a GOTO gets to, also one
+// after a GOTO or inside an IF whose condition is never true
+
+mod common;
+
+use common::{optimized, same_as_o0};
+use haneul::{passes::OptLevel, pretty};
+
+#[test]
+fn code_after_a_goto_is_left_out() {
+    let source = "GOTO skip\nPRINT 111\nLABEL skip\nPRINT 2\n";
+    assert_eq!(same_as_o0(source, ""), "2\n");
+    let tree = pretty::ast(&optimized(source, OptLevel::O1));
+    assert!(!tree.contains("111"), "{}", tree);
+}
+
+#[test]
+fn a_label_only_a_goto_gets_to_is_kept() {
+    let source = "GOTO later
+LABEL back
+PRINT 3
+GOTO done
+LABEL later
+PRINT 4
+GOTO back
+LABEL done
+";
+    assert_eq!(same_as_o0(source, ""), "4\n3\n");
+}
+
+#[test]
+fn a_label_in_an_if_that_is_never_true_is_kept() {
+    let source = "GOTO inside
+IF 1 > 2 THEN
+    PRINT 444
+    LABEL inside
+    PRINT 5
+ENDIF
+PRINT 6
+";
+    assert_eq!(same_as_o0(source, ""), "5\n6\n");
+    let tree = pretty::ast(&optimized(source, OptLevel::O1));
+    assert!(!tree.contains("444"), "{}", tree);
+}
+
+#[test]
+fn a_loop_that_is_only_entered_by_a_goto_is_kept() {
+    // the body runs once, then the condition is checked
+    let source = "GOTO body
+WHILE 1 > 2 REPEAT
+    LABEL body
+    PRINT 7
+ENDWHILE
+PRINT 8
+";
+    assert_eq!(same_as_o0(source, ""), "7\n8\n");
+}
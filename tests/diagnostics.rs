@@ -0,0 +1,161 @@
+// what the diagnostics say: the json of --message-format=json, and the text of every code
+
+use haneul::{
+    codes::explain,
+    i18n::{self, Lang},
+    run_source,
+};
+use serde_json::Value;
+
+// one of every kind of json a diagnostic is written as. an error of the lexer stops the program
+// before it's analyzed, it's in a program of its own
+const SOURCES: [&str; 2] = [
+    "LET score = 1\nPRINT scroe\nPRINT x\nLABEL unused\nGOTO nowhere\n",
+    "PRINT 1\nPRINT $\n",
+];
+
+fn json(source: &str, lang: Lang) -> Vec<Value> {
+    let result = run_source(source, "");
+    let diagnostics = result.diagnostics;
+    assert!(!diagnostics.is_empty());
+    diagnostics
+        .list
+        .iter()
+        .map(|diagnostic| serde_json::from_str(&diagnostic.to_json(&diagnostics.sources, lang)))
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[test]
+fn a_diagnostic_is_json_with_every_field() {
+    for (source, lang) in SOURCES
+        .into_iter()
+        .flat_map(|source| [(source, Lang::English), (source, Lang::Korean)])
+    {
+        for diagnostic in json(source, lang) {
+            let Value::Object(fields) = &diagnostic else {
+                panic!["{} isn't an object", diagnostic];
+            };
+            let keys: Vec<_> = fields.keys().map(String::as_str).collect();
+            let mut expected = [
+                "code",
+                "severity",
+                "file",
+                "span",
+                "message",
+                "suggestion",
+                "hint",
+            ];
+            expected.sort();
+            assert_eq!(keys, expected, "{}", diagnostic);
+
+            let code = diagnostic["code"].as_str().unwrap();
+            let severity = diagnostic["severity"].as_str().unwrap();
+            assert!(code.len() == 5 && code[1..].bytes().all(|b| b.is_ascii_digit()));
+            match &code[..1] {
+                "E" => assert_eq!(severity, "error"),
+                "W" => assert_eq!(severity, "warning"),
+                _ => panic!["{} isn't a code", code],
+            }
+            assert_eq!(diagnostic["file"], "main.han");
+            for field in ["line", "column", "length", "offset", "byte_length"] {
+                assert!(diagnostic["span"][field].is_u64(), "{}", diagnostic);
+            }
+            assert_eq!(diagnostic["span"].as_object().unwrap().len(), 5);
+            assert!(!diagnostic["message"].as_str().unwrap().is_empty());
+            assert!(diagnostic["suggestion"].is_null() || diagnostic["suggestion"].is_string());
+            assert!(diagnostic["hint"].is_null() || diagnostic["hint"].is_string());
+        }
+    }
+}
+
+#[test]
+fn only_a_replacement_is_a_suggestion() {
+    let diagnostics = json(SOURCES[0], Lang::English);
+    let find = |line: u64| {
+        diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic["span"]["line"] == line && diagnostic["code"] != "W0001")
+            .unwrap_or_else(|| panic!["nothing on line {}", line])
+    };
+    // a typo of a variable
+    assert_eq!(find(2)["suggestion"], "score");
+    assert_eq!(find(2)["hint"], "did you mean `score`?");
+    // a variable that's nowhere has only the hint
+    assert!(find(3)["suggestion"].is_null());
+    assert!(find(3)["hint"].is_string());
+    // and a character that can't be lexed has neither
+    let unknown = &json(SOURCES[1], Lang::English)[0];
+    assert_eq!(unknown["code"], "E0001");
+    assert!(unknown["suggestion"].is_null());
+    assert!(unknown["hint"].is_null());
+}
+
+// every code in src/codes.rs
+fn codes() -> Vec<&'static str> {
+    include_str!("../src/codes.rs")
+        .lines()
+        .filter_map(|line| line.strip_prefix("pub const ")?.split_once(": &str = \""))
+        .map(|(_, code)| code.trim_end_matches("\";"))
+        .collect()
+}
+
+fn korean(text: &str) -> bool {
+    text.chars().any(|c| ('\u{ac00}'..='\u{d7a3}').contains(&c))
+}
+
+#[test]
+fn every_code_has_english_and_korean_text() {
+    let codes = codes();
+    let constants = include_str!("../src/codes.rs")
+        .matches("pub const ")
+        .count();
+    assert_eq!(codes.len(), constants, "{:?}", codes);
+    let args: Vec<String> = ["a", "b", "c", "d"].map(String::from).to_vec();
+    for code in codes {
+        assert!(explain(code).is_some(), "{} has no explanation", code);
+
+        // a message that isn't there is the code and the arguments
+        let english = i18n::message(Lang::English, code, &args);
+        let korean_message = i18n::message(Lang::Korean, code, &args);
+        assert!(
+            !english.starts_with(code),
+            "{} has no english message",
+            code
+        );
+        assert!(korean(&korean_message), "{} has no korean message", code);
+
+        // --teach, an english lesson would be used when there's no korean one
+        let lesson = i18n::lesson(Lang::English, code, &args);
+        let korean_lesson = i18n::lesson(Lang::Korean, code, &args);
+        assert!(lesson.is_some(), "{} has no english lesson", code);
+        assert!(
+            korean_lesson.as_deref().is_some_and(korean),
+            "{} has no korean lesson",
+            code
+        );
+
+        // the hints are the same in both, when there's one
+        let hint = i18n::hint(Lang::English, code, &args, None);
+        let korean_hint = i18n::hint(Lang::Korean, code, &args, None);
+        assert_eq!(hint.is_some(), korean_hint.is_some(), "{}", code);
+        assert!(korean_hint.as_deref().is_none_or(korean), "{}", code);
+
+        for text in [
+            english,
+            korean_message,
+            lesson.unwrap(),
+            korean_lesson.unwrap(),
+        ]
+        .into_iter()
+        .chain(hint)
+        {
+            assert!(
+                !text.contains("{0}"),
+                "{} leaves an argument out: {}",
+                code,
+                text
+            );
+        }
+    }
+}
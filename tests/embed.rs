@@ -0,0 +1,128 @@
+// haneul in another rust program: the functions the host gives it, and where its INPUTs read
+// from and its PRINTs write to
+
+use std::{
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+use haneul::{
+    analyze,
+    ast::Program,
+    builtins::{self, Registry},
+    bytecode,
+    ctype::FloatDivision,
+    diagnostics::Lints,
+    interpreter::Interpreter,
+    lexer::Lexer,
+    parse, parser,
+    runtime::Input,
+    source::SourceMap,
+    vm::Vm,
+};
+
+fn program(source: &str, builtins: &Registry) -> Program {
+    let mut sources = SourceMap::new();
+    let file = sources.add(String::from("main.han"), source.to_owned());
+    let mut lexer = Lexer::new(file, sources.get(file).text());
+    let (result, warnings) = parse(&mut lexer, builtins, parser::DEFAULT_MAX_DEPTH);
+    let (_, errors) = analyze(&result, warnings, builtins, &Lints::default());
+    assert!(errors.is_empty(), "the program has errors: {:?}", errors);
+    result.unwrap()
+}
+
+#[test]
+fn the_program_calls_the_functions_of_the_host() {
+    let mut builtins = Registry::new();
+    builtins.register(builtins::host("SPAWN", 2, false));
+    builtins.register(builtins::host("HEALTH", 0, true));
+    let program = program(
+        "SPAWN 1, 2\nLET a = 3\nSPAWN a, a * 2\nLET h = HEALTH()\nPRINT h - 1\n",
+        &builtins,
+    );
+
+    let mut enemies = vec![];
+    let health = 42.5;
+    let mut output = vec![];
+    let mut interpreter = Interpreter::new(&program);
+    interpreter.register("SPAWN", |x: f32, y: f32| enemies.push((x, y)));
+    interpreter.register("HEALTH", || health);
+    interpreter.set_output(&mut output);
+    interpreter.run().unwrap();
+    drop(interpreter);
+    assert_eq!(enemies, [(1.0, 2.0), (3.0, 6.0)]);
+    assert_eq!(String::from_utf8(output).unwrap(), "41.5\n");
+
+    // one that takes another number of arguments is turned away before the program runs
+    let spawned = Mutex::new(0);
+    let mut interpreter = Interpreter::new(&program);
+    interpreter.register("SPAWN", |_: f32| *spawned.lock().unwrap() += 1);
+    interpreter.register("HEALTH", || health);
+    let error = interpreter.run().unwrap_err();
+    assert_eq!(error.message, "SPAWN takes 1 number, not 2");
+    assert_eq!(error.line, 1);
+    assert_eq!(*spawned.lock().unwrap(), 0);
+}
+
+// a writer the test can look at while the program runs
+#[derive(Clone, Default)]
+struct Shared(Arc<Mutex<Vec<u8>>>);
+
+impl Shared {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl Write for Shared {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn the_host_gives_the_input_and_gets_the_output() {
+    let source = "INPUT a\nINPUT b\nPRINT a + b\nINPUT c\nPRINT c\n";
+    let builtins = Registry::new();
+    let program = program(source, &builtins);
+    let chunk = bytecode::compile(&program, &builtins, FloatDivision::default()).unwrap();
+
+    let output = Shared::default();
+    let mut vm = Vm::new(&chunk);
+    vm.set_input(Input::from_reader(&b"1.5 2\n"[..]));
+    vm.set_output(output.clone());
+    vm.run().unwrap();
+    drop(vm);
+    // the INPUT at the end of the input leaves c at 0
+    assert_eq!(output.text(), "3.5\n0\n");
+
+    let output = Shared::default();
+    let mut interpreter = Interpreter::new(&program);
+    interpreter.set_input(Input::from_reader(&b"1.5\n2\n7\n"[..]));
+    interpreter.set_output(output.clone());
+    interpreter.run().unwrap();
+    drop(interpreter);
+    assert_eq!(output.text(), "3.5\n7\n");
+}
+
+#[test]
+fn what_the_program_printed_is_there_when_the_host_is_called() {
+    let mut builtins = Registry::new();
+    builtins.register(builtins::host("CHECK", 0, false));
+    let program = program("PRINT 1\nCHECK\nPRINT 2\n", &builtins);
+
+    let output = Shared::default();
+    let seen = Mutex::new(String::new());
+    let mut interpreter = Interpreter::new(&program);
+    interpreter.register("CHECK", || *seen.lock().unwrap() = output.text());
+    interpreter.set_output(output.clone());
+    interpreter.run().unwrap();
+    drop(interpreter);
+    assert_eq!(*seen.lock().unwrap(), "1\n");
+    assert_eq!(output.text(), "1\n2\n");
+}
@@ -0,0 +1,86 @@
+// the c interface of src/ffi.rs, called the way a c program calls it. `cargo test --features
+// ffi` runs it
+#![cfg(feature = "ffi")]
+
+use std::{
+    ffi::{c_char, c_int, CStr, CString},
+    ptr,
+};
+
+// the crate has to be linked for the functions to be there
+use haneul as _;
+
+extern "C" {
+    fn haneul_compile(source: *const c_char, target: *const c_char) -> *mut c_char;
+    fn haneul_run(source: *const c_char, input: *const c_char, output: *mut *mut c_char) -> c_int;
+    fn haneul_last_error() -> *const c_char;
+    fn haneul_free(string: *mut c_char);
+}
+
+fn last_error() -> String {
+    unsafe { CStr::from_ptr(haneul_last_error()) }
+        .to_str()
+        .unwrap()
+        .to_owned()
+}
+
+// what haneul gave back as a String, freed like a c program frees it
+unsafe fn take(string: *mut c_char) -> String {
+    assert!(!string.is_null());
+    let text = CStr::from_ptr(string).to_str().unwrap().to_owned();
+    haneul_free(string);
+    text
+}
+
+#[test]
+fn compile_gives_the_code_or_the_error() {
+    let source = CString::new("LET a = 2\nPRINT a * 21\n").unwrap();
+    let python = CString::new("python").unwrap();
+    let code = unsafe { take(haneul_compile(source.as_ptr(), python.as_ptr())) };
+    assert!(code.contains("a * 21"), "{}", code);
+
+    let wrong = CString::new("PRINT b\n").unwrap();
+    assert!(unsafe { haneul_compile(wrong.as_ptr(), python.as_ptr()) }.is_null());
+    assert!(last_error().contains("b"), "{}", last_error());
+
+    let bytecode = CString::new("bytecode").unwrap();
+    assert!(unsafe { haneul_compile(source.as_ptr(), bytecode.as_ptr()) }.is_null());
+    assert_eq!(last_error(), "can't compile to bytecode");
+
+    assert!(unsafe { haneul_compile(ptr::null(), python.as_ptr()) }.is_null());
+    assert_eq!(last_error(), "source is NULL");
+}
+
+#[test]
+fn run_gives_the_output_and_the_exit_code() {
+    let source = CString::new("INPUT a\nPRINT a * 2\n").unwrap();
+    let input = CString::new("21\n").unwrap();
+    let mut output = ptr::null_mut();
+    let code = unsafe { haneul_run(source.as_ptr(), input.as_ptr(), &mut output) };
+    assert_eq!(code, 0);
+    assert_eq!(unsafe { take(output) }, "42\n");
+    assert_eq!(last_error(), "");
+
+    // what it printed before it went wrong is there too
+    let source = CString::new("PRINT 1\nPRINT 1 / 0\n").unwrap();
+    let mut output = ptr::null_mut();
+    let code = unsafe { haneul_run(source.as_ptr(), input.as_ptr(), &mut output) };
+    assert_eq!(code, 1);
+    assert_eq!(unsafe { take(output) }, "1\n");
+    assert!(
+        last_error().contains("an int is divided by 0"),
+        "{}",
+        last_error()
+    );
+
+    // without a place for the output
+    let code = unsafe { haneul_run(source.as_ptr(), input.as_ptr(), ptr::null_mut()) };
+    assert_eq!(code, 1);
+
+    let code = unsafe { haneul_run(source.as_ptr(), ptr::null(), ptr::null_mut()) };
+    assert_eq!(code, -1);
+    assert_eq!(last_error(), "input is NULL");
+
+    // freeing NULL does nothing
+    unsafe { haneul_free(ptr::null_mut()) };
+}
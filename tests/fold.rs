@@ -0,0 +1,61 @@
+// constant folding (see fold.rs) works the numbers out like c does when the program runs: an
+// int that overflows wraps around, a calculation with an int and a number with a dot is done in
+// doubles and printed as a float
+
+mod common;
+
+use common::{optimized, same_as_o0};
+use haneul::{passes::OptLevel, pretty};
+
+#[test]
+fn numbers_are_worked_out_while_compiling() {
+    let source = "LET seconds = 60 * 60 * 24\nPRINT seconds\n";
+    assert_eq!(same_as_o0(source, ""), "86400\n");
+    let tree = pretty::ast(&optimized(source, OptLevel::O1));
+    assert!(tree.contains("86400"), "{}", tree);
+    assert!(!tree.contains("60"), "{}", tree);
+}
+
+#[test]
+fn ints_wrap_around_like_when_the_program_runs() {
+    let source = "PRINT 2147483647 + 1
+PRINT 0 - 2147483647 - 2
+PRINT 46341 * 46341
+PRINT 9223372036854775807 + 1
+PRINT 0 - 7 / 2
+PRINT 0 - 7 % 3
+";
+    assert_eq!(
+        same_as_o0(source, ""),
+        "-2147483648\n2147483647\n-2147479015\n-9223372036854775808\n-3\n-1\n"
+    );
+}
+
+#[test]
+fn an_int_and_a_number_with_a_dot_are_worked_out_in_doubles() {
+    let source = "PRINT 7 / 2.0
+PRINT 1 + 0.1
+PRINT 16777217 + 0.0
+PRINT 3 * 0.1 - 0.3
+PRINT 2147483647 + 1.0
+IF 1 / 3 * 3 == 0 THEN
+    PRINT 1
+ENDIF
+IF 1 / 3.0 * 3 == 1 THEN
+    PRINT 2
+ENDIF
+";
+    assert_eq!(
+        same_as_o0(source, ""),
+        "3.5\n1.1\n16777216\n0.00000000000000005551115\n2147483600\n1\n2\n"
+    );
+}
+
+#[test]
+fn dividing_by_zero_is_left_for_when_it_runs() {
+    let source = "PRINT 1\nPRINT 1 / 0\nPRINT 2\n";
+    assert_eq!(
+        same_as_o0(source, ""),
+        "1\nError: an int is divided by 0 on line 2\n"
+    );
+}
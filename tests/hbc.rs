@@ -0,0 +1,84 @@
+// .hbc files that were changed by hand are turned away instead of making the vm panic
+
+use haneul::{
+    bytecode::{Chunk, Instruction},
+    ctype::CType,
+    hbc,
+    runtime::{Native, Value},
+};
+
+fn chunk(code: Vec<Instruction>) -> Chunk {
+    Chunk {
+        lines: vec![1; code.len()],
+        code,
+        constants: vec![Value::Int(1)],
+        variables: vec![(String::from("a"), CType::Int)],
+        ..Chunk::default()
+    }
+}
+
+fn decode(code: Vec<Instruction>) -> Result<Chunk, String> {
+    hbc::decode(&hbc::encode(&chunk(code)))
+}
+
+#[test]
+fn a_chunk_that_pushes_what_it_pops_is_read_back() {
+    let code = vec![
+        Instruction::Constant(0),
+        Instruction::Store(0),
+        Instruction::Load(0),
+        Instruction::Constant(0),
+        Instruction::Less(CType::Int),
+        Instruction::JumpIfZero(7),
+        Instruction::Jump(2),
+        Instruction::Halt,
+    ];
+    assert_eq!(decode(code.clone()), Ok(chunk(code)));
+}
+
+#[test]
+fn popping_an_empty_stack_is_turned_away() {
+    let error = decode(vec![Instruction::Print(CType::Int), Instruction::Halt]).unwrap_err();
+    assert!(error.contains("pops more than there is"), "{}", error);
+    let error = decode(vec![
+        Instruction::Constant(0),
+        Instruction::Add(CType::Int),
+        Instruction::Halt,
+    ])
+    .unwrap_err();
+    assert!(error.contains("pops more than there is"), "{}", error);
+}
+
+#[test]
+fn a_jump_has_to_leave_the_stack_like_the_other_ways_there() {
+    // the loop pushes one more every time round
+    let error = decode(vec![
+        Instruction::Constant(0),
+        Instruction::Jump(0),
+        Instruction::Halt,
+    ])
+    .unwrap_err();
+    assert!(error.contains("values on the stack"), "{}", error);
+}
+
+#[test]
+fn a_builtin_is_read_back_and_one_the_vm_doesnt_have_turned_away() {
+    let code = vec![
+        Instruction::Call(Native::Beep),
+        Instruction::Call(Native::Inkey),
+        Instruction::Print(CType::Float),
+        Instruction::Halt,
+    ];
+    assert_eq!(decode(code.clone()), Ok(chunk(code.clone())));
+
+    // the operand of the BEEP, given a new checksum
+    let mut bytes = hbc::encode(&chunk(code));
+    let at = bytes
+        .windows(5)
+        .position(|window| window == [22, 1, 0, 0, 0])
+        .unwrap();
+    bytes[at + 1] = 9;
+    let sum = hbc::checksum(&bytes[16..]);
+    bytes[8..16].copy_from_slice(&sum.to_le_bytes());
+    assert_eq!(hbc::decode(&bytes).unwrap_err(), "has an unknown builtin 9");
+}
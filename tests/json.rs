@@ -0,0 +1,63 @@
+// the tree as json, like --emit-ast=json writes it. a tree that's read back is checked, so one
+// that was changed by hand is turned away instead of making the compiler panic
+
+use haneul::{
+    ast::Program, builtins::Registry, lexer::Lexer, parse, parser, pretty, source::SourceMap,
+};
+
+const SOURCE: &str = "INPUT a\nLET b = a * 2 + -3\nIF b > 1 THEN\nPRINT b\nENDIF\n";
+
+fn json(source: &str) -> String {
+    let mut sources = SourceMap::new();
+    let file = sources.add(String::from("main.han"), source.to_owned());
+    let mut lexer = Lexer::new(file, sources.get(file).text());
+    let (result, _) = parse(&mut lexer, &Registry::new(), parser::DEFAULT_MAX_DEPTH);
+    serde_json::to_string(&result.unwrap()).unwrap()
+}
+
+fn read(json: &str) -> Result<Program, String> {
+    serde_json::from_str(json).map_err(|e| e.to_string())
+}
+
+#[test]
+fn a_tree_reads_back_the_same() {
+    let written = json(SOURCE);
+    let program = read(&written).unwrap();
+    assert_eq!(serde_json::to_string(&program).unwrap(), written);
+    assert_eq!(
+        pretty::ast(&program),
+        pretty::ast(&read(&json(SOURCE)).unwrap())
+    );
+    assert!(!program.types.is_empty());
+}
+
+#[test]
+fn a_number_the_lexer_wouldnt_read_is_turned_away() {
+    for number in ["2x", "", "1.2.3", "99999999999999999999", " 2", "--2"] {
+        let changed = json(SOURCE).replace(
+            r#"{"Number":"2"}"#,
+            &format![r#"{{"Number":"{}"}}"#, number],
+        );
+        let error = read(&changed).unwrap_err();
+        assert!(error.starts_with("not a number"), "{}: {}", number, error);
+    }
+    let negative = json(SOURCE).replace(r#"{"Number":"2"}"#, r#"{"Number":"-2.5"}"#);
+    assert!(read(&negative).is_ok());
+}
+
+#[test]
+fn an_index_that_points_nowhere_is_turned_away() {
+    // the first expression is `a`, the second `2`
+    let forward = json(SOURCE).replace(r#""left":0"#, r#""left":5"#);
+    assert!(read(&forward)
+        .unwrap_err()
+        .contains("points at expression 5"));
+    let missing = json(SOURCE).replace(r#"{"Print":9}"#, r#"{"Print":99}"#);
+    assert!(read(&missing)
+        .unwrap_err()
+        .contains("there's no expression 99"));
+    let name = json(SOURCE).replace(r#"{"Input":0}"#, r#"{"Input":7}"#);
+    assert!(read(&name).unwrap_err().contains("there's no name"));
+    let twice = json(SOURCE).replace(r#"["a","b"]"#, r#"["a","a"]"#);
+    assert!(read(&twice).unwrap_err().contains("in the names twice"));
+}
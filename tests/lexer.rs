@@ -0,0 +1,314 @@
+use haneul::{
+    incremental::{relex, TextEdit},
+    lexer::{LexError, Lexer, StreamLexer},
+    source::FileId,
+    token::{Span, Token, TokenType},
+};
+
+// the type and the text of every token, or the error instead of it
+fn lex(source: &str) -> Vec<Result<(TokenType, String), LexError>> {
+    Lexer::new(FileId::default(), source)
+        .map(|token| token.map(|token| (token.kind(), token.text().to_owned())))
+        .collect()
+}
+
+// with their text and where they are
+fn texts<'a>(
+    tokens: impl Iterator<Item = Result<Token<'a>, LexError>>,
+) -> Vec<Result<(TokenType, String, Span), LexError>> {
+    tokens
+        .map(|token| token.map(|token| (token.kind(), token.text().to_owned(), token.span())))
+        .collect()
+}
+
+// with where they are
+fn spans<'a>(
+    tokens: impl Iterator<Item = Result<Token<'a>, LexError>>,
+) -> Vec<Result<(TokenType, Span), LexError>> {
+    tokens
+        .map(|token| token.map(|token| (token.kind(), token.span())))
+        .collect()
+}
+
+// the tokens of `haneul -`, which lexes the program a line at a time while it reads it, are
+// the ones of the whole file
+fn same_streamed(source: &str) {
+    let whole = texts(Lexer::new(FileId::default(), source));
+    let streamed = texts(StreamLexer::new(FileId::default(), source.as_bytes()));
+    assert_eq!(streamed, whole, "{:?}", source);
+}
+
+fn too_large(source: &str) -> bool {
+    lex(source)
+        .iter()
+        .any(|token| matches!(token, Err(LexError::NumberTooLarge { .. })))
+}
+
+#[test]
+fn ints_have_to_fit_in_a_long() {
+    assert!(!too_large("PRINT 9223372036854775807\n"));
+    assert!(too_large("PRINT 9223372036854775808\n"));
+    assert!(too_large("PRINT 99999999999999999999\n"));
+}
+
+#[test]
+fn floats_have_to_fit_in_a_float() {
+    assert!(!too_large("PRINT 99999999999999999999.0\n"));
+    assert!(too_large(&format!["PRINT 1{}.0\n", "0".repeat(39)]));
+}
+
+#[test]
+fn crlf_is_one_newline() {
+    let source = "LET a = 1\r\nPRINT a\r\n";
+    assert_eq!(lex(source), lex("LET a = 1\nPRINT a\n"));
+    let tokens = spans(Lexer::new(FileId::default(), source));
+    let Ok((TokenType::Newline, newline)) = tokens[4] else {
+        panic!["{:?} isn't a newline", tokens[4]];
+    };
+    assert_eq!((newline.length, newline.byte_length), (2, 2));
+    assert!(matches!(
+        tokens[5],
+        Ok((TokenType::Print, Span { line: 2, .. }))
+    ));
+    same_streamed(source);
+}
+
+#[test]
+fn a_lone_cr_is_whitespace() {
+    let source = "LET a = 1\rPRINT a\n";
+    assert_eq!(lex(source), lex("LET a = 1 PRINT a\n"));
+    let tokens = spans(Lexer::new(FileId::default(), source));
+    assert!(matches!(
+        tokens[4],
+        Ok((
+            TokenType::Print,
+            Span {
+                line: 1,
+                column: 11,
+                ..
+            }
+        ))
+    ));
+    same_streamed(source);
+}
+
+#[test]
+fn a_byte_order_mark_only_starts_the_file() {
+    let source = "\u{feff}PRINT 1\n";
+    assert_eq!(lex(source), lex("PRINT 1\n"));
+    let tokens = spans(Lexer::new(FileId::default(), source));
+    assert!(matches!(
+        tokens[0],
+        Ok((
+            TokenType::Print,
+            Span {
+                column: 1,
+                offset: 3,
+                ..
+            }
+        ))
+    ));
+    same_streamed(source);
+
+    let source = "PRINT 1\n\u{feff}PRINT 2\n";
+    assert!(matches!(
+        lex(source)[3],
+        Err(LexError::UnknownCharacter {
+            found: '\u{feff}',
+            ..
+        })
+    ));
+    same_streamed(source);
+}
+
+#[test]
+fn strings_are_kept_as_theyre_written() {
+    let source = "PRINT \"100% \\ %d\"\n";
+    assert_eq!(
+        lex(source)[1],
+        Ok((TokenType::String, String::from("100% \\ %d")))
+    );
+}
+
+// the trivia and source text of every token of a lossless lexer are the source again
+fn round_trips(source: &str) {
+    let mut written = String::new();
+    for token in Lexer::new(FileId::default(), source).lossless() {
+        let token = token.unwrap();
+        written.push_str(token.trivia());
+        written.push_str(&token.source_text());
+    }
+    assert_eq!(written, source);
+}
+
+#[test]
+fn a_lossless_lexer_gives_the_source_back() {
+    round_trips("LET a = 1 # one\nPRINT  \"a b\"\n\n");
+    round_trips("LET a = 1\r\nPRINT a\r\n");
+    round_trips("\u{feff}PRINT 1\nPRINT 2");
+    round_trips("\u{feff}LET a = 1\r\n  # only a comment\r\nPRINT a");
+    round_trips("");
+}
+
+// relexing after an edit gives the tokens lexing the whole source again does
+fn same_relexed(before: &str, edit: TextEdit) {
+    let mut after = String::from(before);
+    after.replace_range(edit.offset..edit.offset + edit.length, &edit.text);
+    let tokens: Vec<_> = Lexer::new(FileId::default(), before).collect();
+    let relexed = relex(FileId::default(), &after, &tokens, &edit);
+    let whole = Lexer::new(FileId::default(), &after);
+    assert_eq!(
+        texts(relexed.into_iter()),
+        texts(whole),
+        "{:?} after {:?}",
+        after,
+        edit
+    );
+}
+
+fn edit(offset: usize, length: usize, text: &str) -> TextEdit {
+    TextEdit {
+        offset,
+        length,
+        text: String::from(text),
+    }
+}
+
+#[test]
+fn relexing_an_edit_is_lexing_it_all_again() {
+    let source = "LET a = 1\nPRINT a\nPRINT \"hi\"\nLET b = a + 2\n";
+    // in a line
+    same_relexed(source, edit(8, 1, "42"));
+    // a new line
+    same_relexed(source, edit(10, 0, "INPUT c\n"));
+    // lines taken out
+    same_relexed(source, edit(10, 19, ""));
+    // on the last line, and past it
+    same_relexed(source, edit(43, 0, "PRINT b"));
+    same_relexed(source, edit(39, 1, "- 7"));
+    // an error that goes away, and one that comes
+    same_relexed("LET a = 1 ! 2\nPRINT a\n", edit(10, 1, "!="));
+    same_relexed(source, edit(6, 1, "! "));
+    // with crlf
+    same_relexed("LET a = 1\r\nPRINT a\r\n", edit(11, 5, "INPUT"));
+}
+
+fn kinds(source: &str) -> Vec<TokenType> {
+    lex(source)
+        .into_iter()
+        .map(|token| token.map_or(TokenType::None, |(kind, _)| kind))
+        .collect()
+}
+
+#[test]
+fn the_lexer_gives_every_token_and_then_none() {
+    use TokenType::*;
+    let source = "LABEL top\nINPUT a\nLET b = a * 2.5 + -3 / 4 % 5\n\
+        IF b >= 1 THEN\nPRINT \"big\"\nENDIF\n\
+        WHILE b != 0 REPEAT\nLET b = b - 1 # down\nENDWHILE\n\
+        IF a == b THEN\nGOTO top\nENDIF\nPRINT a < b, a <= b, a > b\nPRINT INKEY()";
+    assert_eq!(
+        kinds(source),
+        [
+            Label, Ident, Newline, Input, Ident, Newline, Let, Ident, Eq, Ident, Asterisk, Number,
+            Plus, Minus, Number, Slash, Number, Percent, Number, Newline, If, Ident, GtEq, Number,
+            Then, Newline, Print, String, Newline, Endif, Newline, While, Ident, NotEq, Number,
+            Repeat, Newline, Let, Ident, Eq, Ident, Minus, Number, Newline, EndWhile, Newline, If,
+            Ident, EqEq, Ident, Then, Newline, Goto, Ident, Newline, Endif, Newline, Print, Ident,
+            Lt, Ident, Comma, Ident, LtEq, Ident, Comma, Ident, Gt, Ident, Newline, Print, Ident,
+            LParen, RParen, Newline, Eof,
+        ]
+    );
+    assert_eq!(kinds(""), [Newline, Eof]);
+
+    // the newline the lexer adds comes after the one of the file
+    let mut lexer = Lexer::new(FileId::default(), "PRINT 1\n");
+    assert_eq!(lexer.by_ref().count(), 5);
+    assert!(lexer.next().is_none());
+    assert!(lexer.next().is_none());
+}
+
+#[test]
+fn the_lexer_goes_on_after_an_error() {
+    let tokens = lex("LET a = 1 ! 2\nPRINT $\n");
+    assert!(matches!(
+        tokens[4],
+        Err(LexError::ExpectedNotEq { found: ' ', .. })
+    ));
+    assert_eq!(tokens[5], Ok((TokenType::Number, String::from("2"))));
+    assert!(matches!(
+        tokens[8],
+        Err(LexError::UnknownCharacter { found: '$', .. })
+    ));
+    assert_eq!(
+        tokens[9..],
+        [
+            Ok((TokenType::Newline, String::from("\n"))),
+            Ok((TokenType::Newline, String::from("\n"))),
+            Ok((TokenType::Eof, String::from("\0"))),
+        ]
+    );
+
+    // like any iterator of results, collecting it stops at the first error
+    let collected: Result<Vec<_>, _> = Lexer::new(FileId::default(), "LET a ! 1\n").collect();
+    assert!(matches!(collected, Err(LexError::ExpectedNotEq { .. })));
+}
+
+#[test]
+fn the_text_of_a_token_borrows_the_source() {
+    let source = String::from("LET name = \"some text\"\n");
+    let texts: Vec<_> = Lexer::new(FileId::default(), &source)
+        .map(|token| token.unwrap())
+        .filter(|token| matches!(token.kind(), TokenType::Ident | TokenType::String))
+        .map(|token| token.text().to_owned())
+        .collect();
+    assert_eq!(texts, ["name", "some text"]);
+}
+
+// the newline the lexer adds and the Eof, where the file ends
+fn end(source: &str) -> Vec<(usize, usize, usize)> {
+    let tokens = spans(Lexer::new(FileId::default(), source));
+    tokens[tokens.len() - 2..]
+        .iter()
+        .map(|token| {
+            let (_, span) = token.clone().unwrap();
+            (span.line, span.column, span.offset)
+        })
+        .collect()
+}
+
+#[test]
+fn the_end_of_the_file_is_on_its_last_line() {
+    // an error about the end of the file is reported after the last thing in it
+    assert_eq!(end("WHILE 1 < 2 REPEAT"), [(1, 19, 18); 2]);
+    assert_eq!(end("WHILE 1 < 2 REPEAT\n"), [(1, 19, 18); 2]);
+    assert_eq!(end("PRINT 1\r\nPRINT 2\r\n"), [(2, 8, 16); 2]);
+    assert_eq!(end("PRINT 1\n\n"), [(2, 1, 8); 2]);
+    assert_eq!(end("PRINT 1\n  "), [(2, 3, 10); 2]);
+    assert_eq!(end(""), [(1, 1, 0); 2]);
+    for source in ["WHILE 1 < 2 REPEAT\n", "PRINT 1\r\n# done\r\n"] {
+        let tokens = spans(Lexer::new(FileId::default(), source));
+        assert!(tokens.iter().all(|token| {
+            let (_, span) = token.clone().unwrap();
+            span.offset + span.byte_length <= source.len() && span.line <= source.lines().count()
+        }));
+    }
+}
+
+#[test]
+fn a_streamed_file_has_the_tokens_of_the_whole_one() {
+    for source in [
+        "LET a = 1\nPRINT a\n",
+        "LET a = 1\nPRINT a",
+        "PRINT \"a b\" # c\n\n\n",
+        "PRINT 1\r\nPRINT 2\r\n",
+        "PRINT 1 ! 2\nPRINT $\n",
+        "PRINT \"not closed\nPRINT 1\n",
+        "\u{feff}PRINT 1\n",
+        "PRINT 1\n   ",
+        "\n",
+        "",
+    ] {
+        same_streamed(source);
+    }
+}
@@ -0,0 +1,57 @@
+// the loop optimizations of -O2 can't make a program go wrong where it didn't
+
+mod common;
+
+use common::{run, Engine, LEVELS};
+
+fn same_on_every_level(source: &str, expected: &str) {
+    for level in LEVELS {
+        for engine in [Engine::Vm, Engine::Tree] {
+            assert_eq!(
+                run(source, "", level, engine),
+                expected,
+                "{:?} at {:?}",
+                engine,
+                level
+            );
+        }
+    }
+}
+
+#[test]
+fn division_guarded_by_an_if_stays_in_the_if() {
+    let source = "LET z = 0
+LET i = 0
+WHILE i < 3 REPEAT
+    IF z != 0 THEN
+        PRINT 10 / z
+    ENDIF
+    LET i = i + 1
+ENDWHILE
+";
+    same_on_every_level(source, "");
+}
+
+#[test]
+fn division_in_a_loop_that_never_runs_isnt_done() {
+    let source = "LET z = 0
+LET n = 0
+WHILE n > 5 REPEAT
+    PRINT 1 / z
+ENDWHILE
+PRINT 7
+";
+    same_on_every_level(source, "7\n");
+}
+
+#[test]
+fn invariant_is_still_moved() {
+    let source = "LET w = 3
+LET i = 0
+WHILE i < 2 REPEAT
+    PRINT w * 5 + w / 2 + i
+    LET i = i + 1
+ENDWHILE
+";
+    same_on_every_level(source, "16\n17\n");
+}
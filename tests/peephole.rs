@@ -0,0 +1,178 @@
+// the peephole pass on the bytecode leaves out instructions without changing what a program does
+
+mod common;
+
+use common::{run, Engine, LEVELS};
+use haneul::{
+    bytecode::{Chunk, Instruction},
+    ctype::CType,
+    hbc,
+    passes::{self, OptLevel},
+    runtime::Value,
+};
+
+fn optimized(code: Vec<Instruction>, constants: Vec<Value>) -> Chunk {
+    let mut chunk = Chunk {
+        lines: vec![1; code.len()],
+        code,
+        constants,
+        variables: vec![(String::from("a"), CType::Int)],
+        ..Chunk::default()
+    };
+    passes::optimize_chunk(&mut chunk, OptLevel::O2);
+    // what's left still passes the checks of an .hbc file
+    hbc::decode(&hbc::encode(&chunk)).unwrap();
+    chunk
+}
+
+#[test]
+fn adding_0_and_multiplying_by_1_is_left_out() {
+    let chunk = optimized(
+        vec![
+            Instruction::Load(0),
+            Instruction::Constant(0),
+            Instruction::Add(CType::Int),
+            Instruction::Constant(1),
+            Instruction::Multiply(CType::Int),
+            Instruction::Print(CType::Int),
+            Instruction::Halt,
+        ],
+        vec![Value::Int(0), Value::Int(1)],
+    );
+    assert_eq!(
+        chunk.code,
+        [
+            Instruction::Load(0),
+            Instruction::Print(CType::Int),
+            Instruction::Halt
+        ]
+    );
+}
+
+#[test]
+fn floats_keep_adding_0() {
+    // -0.0 + 0.0 is 0.0
+    let code = vec![
+        Instruction::Load(0),
+        Instruction::Constant(0),
+        Instruction::Add(CType::Float),
+        Instruction::Print(CType::Float),
+        Instruction::Halt,
+    ];
+    assert_eq!(optimized(code.clone(), vec![Value::Float(0.0)]).code, code);
+}
+
+#[test]
+fn a_calculation_of_constants_is_done_before() {
+    let chunk = optimized(
+        vec![
+            Instruction::Constant(0),
+            Instruction::Constant(1),
+            Instruction::Multiply(CType::Int),
+            Instruction::Print(CType::Int),
+            // it goes wrong when the program runs, that's left to the vm
+            Instruction::Constant(0),
+            Instruction::Constant(2),
+            Instruction::Divide(CType::Int),
+            Instruction::Print(CType::Int),
+            Instruction::Halt,
+        ],
+        vec![Value::Int(6), Value::Int(7), Value::Int(0)],
+    );
+    assert_eq!(
+        chunk.code[..2],
+        [Instruction::Constant(3), Instruction::Print(CType::Int)]
+    );
+    assert_eq!(chunk.constants[3], Value::Int(42));
+    assert_eq!(chunk.code.len(), 7);
+}
+
+#[test]
+fn a_condition_of_constants_becomes_a_jump_or_nothing() {
+    // IF 2 < 1 THEN PRINT 2 ENDIF, IF 1 < 2 THEN PRINT 1 ENDIF
+    let chunk = optimized(
+        vec![
+            Instruction::Constant(0),
+            Instruction::Constant(1),
+            Instruction::Less(CType::Int),
+            Instruction::JumpIfZero(6),
+            Instruction::Constant(0),
+            Instruction::Print(CType::Int),
+            Instruction::Constant(1),
+            Instruction::Constant(0),
+            Instruction::Less(CType::Int),
+            Instruction::JumpIfZero(12),
+            Instruction::Constant(1),
+            Instruction::Print(CType::Int),
+            Instruction::Halt,
+        ],
+        vec![Value::Int(2), Value::Int(1)],
+    );
+    assert_eq!(
+        chunk.code,
+        [
+            Instruction::Constant(1),
+            Instruction::Print(CType::Int),
+            Instruction::Halt
+        ]
+    );
+}
+
+#[test]
+fn a_jump_to_a_jump_goes_where_that_one_goes() {
+    let chunk = optimized(
+        vec![
+            Instruction::Load(0),
+            Instruction::JumpIfZero(4),
+            Instruction::Load(0),
+            Instruction::Print(CType::Int),
+            Instruction::Jump(0),
+            Instruction::Halt,
+        ],
+        vec![],
+    );
+    assert_eq!(chunk.code[1], Instruction::JumpIfZero(0));
+}
+
+#[test]
+fn an_instruction_that_is_jumped_to_starts_a_new_row() {
+    let code = vec![
+        Instruction::Load(0),
+        Instruction::Constant(0),
+        Instruction::Add(CType::Int),
+        Instruction::Load(0),
+        Instruction::Load(0),
+        Instruction::JumpIfZero(2),
+        Instruction::Print(CType::Int),
+        Instruction::Print(CType::Int),
+        Instruction::Halt,
+    ];
+    assert_eq!(optimized(code.clone(), vec![Value::Int(0)]).code, code);
+}
+
+#[test]
+fn programs_print_the_same() {
+    let source = "LET a = 0
+LET s = 0
+WHILE a < 10 REPEAT
+    IF a % 2 == 0 THEN
+        LET s = s + a * 1 + 0
+    ENDIF
+    LET a = a + 1
+ENDWHILE
+PRINT s
+WHILE 1 < 2 REPEAT
+    PRINT 7
+    GOTO done
+ENDWHILE
+LABEL done
+IF 2 < 1 THEN
+    PRINT 8
+ENDIF
+PRINT 1 / 1
+";
+    for level in LEVELS {
+        assert_eq!(run(source, "", level, Engine::Vm), "20\n7\n1\n");
+        assert_eq!(run(source, "", level, Engine::Tree), "20\n7\n1\n");
+    }
+}
@@ -0,0 +1,113 @@
+// what an ExecutionPolicy stops a program from doing while it runs
+
+mod common;
+
+use common::{run_with, Engine, LEVELS};
+use haneul::{
+    analyze,
+    builtins::Registry,
+    diagnostics::Lints,
+    interpreter::Interpreter,
+    lexer::Lexer,
+    parse, parser, run_source, run_source_with_policy,
+    runtime::{ErrorKind, ExecutionPolicy, Limits},
+    source::SourceMap,
+};
+
+#[test]
+fn run_source_runs_like_haneul_run() {
+    let result = run_source("INPUT a\nPRINT a * 2\nPRINT 1 / 0\n", "21\n");
+    assert_eq!(result.stdout, "42\n");
+    assert_eq!(result.stderr, "Error: an int is divided by 0 on line 3\n");
+    assert_eq!(result.exit_code, 1);
+}
+
+#[test]
+fn max_steps_stop_an_infinite_loop() {
+    let policy = ExecutionPolicy {
+        limits: Limits {
+            steps: Some(1000),
+            timeout: None,
+        },
+        ..ExecutionPolicy::sandbox()
+    };
+    let result = run_source_with_policy(
+        "LET a = 1\nWHILE a > 0 REPEAT\n    PRINT a\nENDWHILE\n",
+        "",
+        &policy,
+    );
+    assert_eq!(result.exit_code, 1);
+    assert!(result.stderr.contains("possible infinite loop"));
+}
+
+#[test]
+fn memory_is_counted_while_it_runs() {
+    // it only goes over when it reads the long line
+    let source = "PRINT 1\nINPUT a\nPRINT a\n";
+    let line = format!["{}\n", "1 ".repeat(50_000)];
+    let policy = ExecutionPolicy {
+        memory: Some(10_000),
+        ..ExecutionPolicy::default()
+    };
+    let result = run_source_with_policy(source, &line, &policy);
+    assert_eq!(result.exit_code, 1);
+    assert_eq!(result.stdout, "1\n");
+    assert!(result.stderr.contains("more than the 10000 it may use"));
+    assert_eq!(
+        run_source_with_policy(source, "2\n", &policy).stdout,
+        "1\n2\n"
+    );
+}
+
+#[test]
+fn a_builtin_is_checked_when_it_is_called() {
+    // parsed without the policy, so only the interpreter can stop it
+    let builtins = Registry::new();
+    let mut sources = SourceMap::new();
+    let file = sources.add(
+        String::from("main.han"),
+        String::from("PRINT 1\nFORWARD 10\n"),
+    );
+    let mut lexer = Lexer::new(file, sources.get(file).text());
+    let (result, warnings) = parse(&mut lexer, &builtins, parser::DEFAULT_MAX_DEPTH);
+    let (_, errors) = analyze(&result, warnings, &builtins, &Lints::default());
+    assert!(errors.is_empty(), "the program has errors: {:?}", errors);
+    let program = result.unwrap();
+
+    let mut output = vec![];
+    let mut interpreter = Interpreter::new(&program);
+    interpreter.register("FORWARD", |_: f32| -> () { panic!["FORWARD was called"] });
+    interpreter.set_policy(ExecutionPolicy::sandbox());
+    interpreter.set_output(&mut output);
+    let error = interpreter.run().unwrap_err();
+    drop(interpreter);
+    assert_eq!(error.kind, ErrorKind::NotAllowed);
+    assert_eq!(error.line, 2);
+    assert_eq!(String::from_utf8(output).unwrap(), "1\n");
+}
+
+#[test]
+fn the_vm_and_the_tree_stop_a_loop_on_the_same_line() {
+    let policy = ExecutionPolicy {
+        limits: Limits {
+            steps: Some(100),
+            timeout: None,
+        },
+        ..ExecutionPolicy::default()
+    };
+    let sources = [
+        "LET a = 0\nWHILE a < 1 REPEAT\n    LET b = a\nENDWHILE\n",
+        "LET a = 0\nWHILE a < 1 REPEAT\n    IF a < 1 THEN\n        LET b = 1\n    ENDIF\nENDWHILE\n",
+        "LET a = 0\nLABEL top\nLET a = a + 1\nGOTO top\n",
+    ];
+    for source in sources {
+        for level in LEVELS {
+            let vm = run_with(source, "", level, Engine::Vm, policy);
+            let tree = run_with(source, "", level, Engine::Tree, policy);
+            assert!(vm.contains("possible infinite loop"), "{:?}", vm);
+            assert!(tree.contains("possible infinite loop"), "{:?}", tree);
+            let line = |output: &str| output.rsplit(" on line ").next().unwrap().to_owned();
+            assert_eq!(line(&vm), line(&tree), "{:?} at {:?}", source, level);
+        }
+    }
+}